@@ -0,0 +1,23 @@
+use std::process::Command;
+
+/// Stellt den Git-Hash als `GIT_HASH` für `/api/health` bereit. In Builds ohne
+/// `.git` (z.B. Docker) kann er über die Umgebungsvariable `GIT_HASH` gesetzt werden.
+fn main() {
+    let hash = std::env::var("GIT_HASH")
+        .ok()
+        .filter(|hash| !hash.trim().is_empty())
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "--short=12", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=GIT_HASH={}", hash);
+    println!("cargo:rerun-if-env-changed=GIT_HASH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+}
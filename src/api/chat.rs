@@ -1,56 +1,270 @@
-use axum::{response::IntoResponse, routing::post, Json, Router, extract::State};
+use axum::{response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response}, routing::post, Json, Router, extract::{DefaultBodyLimit, State}, http::{header, HeaderMap, StatusCode}};
 use serde_json::{json, Value};
 use futures::StreamExt;
-use crate::client::{GlmClient, Message};
+use crate::api::presets::apply_preset;
+use crate::api::settings::SettingsStore;
+use crate::api::uploads::UploadStore;
+use crate::client::{CachePolicy, ChatOptions, GlmClient, Message, Role, StreamEvent, StreamingResponse};
+use crate::conversations::{ConversationError, ConversationStore, ConversationTurn};
+use crate::documents::DocumentStore;
+use crate::presets::{PresetError, PresetStore};
+use crate::shutdown::{Shutdown, StreamContext};
+use std::convert::Infallible;
 use std::sync::Arc;
+use tracing::warn;
+
+#[derive(Clone)]
+pub struct ChatState {
+    pub client: Arc<GlmClient>,
+    pub uploads: Arc<UploadStore>,
+    pub documents: Option<Arc<DocumentStore>>,
+    /// Gespeicherte Einstellungen des Benutzers als Standard jeder Anfrage
+    pub settings: Option<Arc<SettingsStore>>,
+    /// System-Prompt-Presets, wählbar mit `{"preset": "<id>"}`
+    pub presets: Option<Arc<PresetStore>>,
+    /// Gespeicherte Unterhaltungen, fortgesetzt mit `{"conversation_id": "<id>"}`
+    pub conversations: Option<Arc<ConversationStore>>,
+    /// Beendet laufende Streams beim Herunterfahren
+    pub shutdown: Option<Arc<Shutdown>>,
+}
+
+pub fn chat_routes(
+    client: GlmClient,
+    uploads: Arc<UploadStore>,
+    documents: Option<Arc<DocumentStore>>,
+    settings: Option<Arc<SettingsStore>>,
+    presets: Option<Arc<PresetStore>>,
+    conversations: Option<Arc<ConversationStore>>,
+    shutdown: Option<Arc<Shutdown>>,
+) -> Router {
+    // Nachrichten dürfen Base64-Bilder enthalten
+    let body_limit = uploads.body_limit();
 
-pub fn chat_routes(client: GlmClient) -> Router {
     Router::new()
         .route("/api/chat", post(chat_handler))
         .route("/api/chat/stream", post(chat_stream_handler))
-        .with_state(Arc::new(client))
+        .layer(DefaultBodyLimit::max(body_limit))
+        .with_state(ChatState { client: Arc::new(client), uploads, documents, settings, presets, conversations, shutdown })
+}
+
+/// Optionen pro Anfrage, z.B. `{"thinking": false}` oder `{"temperature": 0.2}`;
+/// Cache-Header und `X-User-Id` haben Vorrang vor `cache` und `user` im Body
+pub(crate) fn chat_options(headers: &HeaderMap, payload: &Value) -> ChatOptions {
+    let mut options: ChatOptions = serde_json::from_value(payload.clone()).unwrap_or_default();
+    if let Some(policy) = cache_policy(headers) {
+        options.cache = policy;
+    }
+    if let Some(user) = super::user_id(headers) {
+        options.user = Some(user);
+    }
+    options
+}
+
+/// Optionen der Anfrage, ergänzt um das gewählte Preset (dessen System-Prompt
+/// den Nachrichten vorangestellt wird) und danach die gespeicherten
+/// Einstellungen des Benutzers; Werte aus der Anfrage haben immer Vorrang
+async fn request_options(
+    state: &ChatState,
+    headers: &HeaderMap,
+    payload: &Value,
+    messages: &mut Vec<Message>,
+) -> Result<ChatOptions, PresetError> {
+    let mut options = chat_options(headers, payload);
+    let user_name = super::user_name(headers, options.user.as_deref());
+    apply_preset(state.presets.as_deref(), payload, user_name.as_deref(), messages, &mut options)?;
+    if let Some(settings) = &state.settings {
+        settings.apply_defaults(&mut options).await;
+    }
+    Ok(options)
+}
+
+/// Setzt eine gespeicherte Unterhaltung fort, wenn `conversation_id` eine
+/// solche bezeichnet: Der Kontext wird aus dem Zweig bis `parent_id` (sonst
+/// dem Ende des aktiven Zweigs) gebildet; die neuen Nachrichten werden erst
+/// mit der Antwort gespeichert. Andere Kennungen dienen weiterhin nur der
+/// Verbrauchserfassung.
+fn continue_conversation(
+    state: &ChatState,
+    headers: &HeaderMap,
+    payload: &Value,
+    messages: &mut Vec<Message>,
+) -> Result<Option<ConversationTurn>, ConversationError> {
+    let Some(conversations) = &state.conversations else { return Ok(None) };
+    let Some(id) = payload.get("conversation_id").and_then(Value::as_str) else { return Ok(None) };
+    let user = super::request_user(headers);
+    if conversations.get(&user, id).is_err() {
+        return Ok(None);
+    }
+    let parent = payload.get("parent_id").and_then(Value::as_str);
+    let (context, turn) = conversations.begin_turn(&user, id, parent, messages)?;
+    *messages = context;
+    Ok(Some(turn))
+}
+
+/// `X-Cache-Bypass: true` oder `Cache-Control: no-store` umgehen den Cache,
+/// `Cache-Control: no-cache` erzwingt eine neue Antwort, die gespeichert wird
+fn cache_policy(headers: &HeaderMap) -> Option<CachePolicy> {
+    let bypass = headers
+        .get("x-cache-bypass")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"));
+    if bypass {
+        return Some(CachePolicy::Bypass);
+    }
+
+    let cache_control = headers.get(header::CACHE_CONTROL)?.to_str().ok()?.to_ascii_lowercase();
+    let directives: Vec<&str> = cache_control.split(',').map(str::trim).collect();
+    if directives.contains(&"no-store") {
+        Some(CachePolicy::Bypass)
+    } else if directives.contains(&"no-cache") {
+        Some(CachePolicy::Refresh)
+    } else {
+        None
+    }
+}
+
+/// Fügt passende Dokumentabschnitte vor der letzten Benutzernachricht ein;
+/// steuerbar pro Anfrage mit `{"use_documents": bool}`
+async fn inject_document_context(documents: Option<&DocumentStore>, payload: &Value, messages: &mut Vec<Message>) {
+    let Some(documents) = documents else { return };
+    let enabled = payload.get("use_documents").and_then(|v| v.as_bool()).unwrap_or(documents.auto_context());
+    if !enabled {
+        return;
+    }
+
+    let Some(position) = messages.iter().rposition(|message| matches!(message.role, Role::User)) else { return };
+    let query = messages[position].content.as_ref().map(|content| content.text()).unwrap_or_default();
+    if query.trim().is_empty() {
+        return;
+    }
+    if let Some(context) = documents.context_message(&query).await {
+        messages.insert(position, context);
+    }
 }
 
 async fn chat_handler(
-    State(client): State<Arc<GlmClient>>,
+    State(state): State<ChatState>,
+    headers: HeaderMap,
     Json(payload): Json<Value>
 ) -> impl IntoResponse {
     // Extrahiere Nachrichten
-    let messages: Vec<Message> = match serde_json::from_value(payload["messages"].clone()) {
+    let mut messages: Vec<Message> = match serde_json::from_value(payload["messages"].clone()) {
         Ok(messages) => messages,
         Err(_) => return Json(json!({"error": "Ungültige Nachrichtendaten"})).into_response(),
     };
+    let turn = match continue_conversation(&state, &headers, &payload, &mut messages) {
+        Ok(turn) => turn,
+        Err(err) => return err.into_response(),
+    };
+    // Bilder prüfen und Dateiverweise auflösen
+    if let Err(err) = state.uploads.resolve_messages(&mut messages).await {
+        return err.into_response();
+    }
+    inject_document_context(state.documents.as_deref(), &payload, &mut messages).await;
+    let options = match request_options(&state, &headers, &payload, &mut messages).await {
+        Ok(options) => options,
+        Err(err) => return err.into_response(),
+    };
 
     // Sende Anfrage an GLM-Client
-    match client.chat_completions(messages).await {
-        Ok(res) => Json(json!({"response": res})).into_response(),
+    match state.client.chat_completions_with(messages, &options).await {
+        Ok(res) => {
+            let Some(turn) = turn else { return Json(json!({"response": res})).into_response() };
+            // Mehrere Kandidaten werden als Alternativen gespeichert
+            let candidates = res.choices.iter().map(|choice| choice.message.clone()).collect();
+            let message_ids = turn.complete_all(candidates).await.unwrap_or_else(|err| {
+                warn!("Antwort konnte nicht in Unterhaltung {} gespeichert werden: {}", turn.conversation_id(), err);
+                Vec::new()
+            });
+            Json(json!({
+                "response": res,
+                "conversation_id": turn.conversation_id(),
+                "message_id": message_ids.first(),
+                "message_ids": message_ids
+            })).into_response()
+        },
         Err(err) => Json(json!({"error": err.to_string()})).into_response(),
     }
 }
 
+/// Streamt die Antwort als Server-Sent Events: `thinking` und `content` mit
+/// `{"content": "..."}`, abschließend `done` mit der vollständigen Nachricht
+/// oder `error`. Beim Herunterfahren endet ein unvollständiger Stream mit
+/// `done` und `finish_reason: "shutdown"`.
 async fn chat_stream_handler(
-    State(client): State<Arc<GlmClient>>,
+    State(state): State<ChatState>,
+    headers: HeaderMap,
     Json(payload): Json<Value>
 ) -> impl IntoResponse {
     // Extrahiere Nachrichten
-    let messages: Vec<Message> = match serde_json::from_value(payload["messages"].clone()) {
+    let mut messages: Vec<Message> = match serde_json::from_value(payload["messages"].clone()) {
         Ok(messages) => messages,
         Err(_) => return Json(json!({"error": "Ungültige Nachrichtendaten"})).into_response(),
     };
+    if let Err(err) = GlmClient::check_stream_candidates(&chat_options(&headers, &payload)) {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": err.to_string(), "status": "error"}))).into_response();
+    }
+    let turn = match continue_conversation(&state, &headers, &payload, &mut messages) {
+        Ok(turn) => turn,
+        Err(err) => return err.into_response(),
+    };
+    if let Err(err) = state.uploads.resolve_messages(&mut messages).await {
+        return err.into_response();
+    }
+    inject_document_context(state.documents.as_deref(), &payload, &mut messages).await;
+    let options = match request_options(&state, &headers, &payload, &mut messages).await {
+        Ok(options) => options,
+        Err(err) => return err.into_response(),
+    };
 
     // Sende Anfrage an GLM-Client und streame die Antwort
-    match client.chat_completions_stream(messages).await {
-        Ok(stream) => {
-            let collected: Vec<_> = stream.collect().await;
-            // Konvertiere Results zu JSON-Objekten
-            let json_responses: Vec<serde_json::Value> = collected.into_iter().map(|result| {
-                match result {
-                    Ok(response) => serde_json::to_value(response).unwrap_or_else(|_| serde_json::json!({"error": "Serialization failed"})),
-                    Err(err) => serde_json::json!({"error": err.to_string()}),
-                }
-            }).collect();
-            Json(json!({"stream": json_responses})).into_response()
-        },
+    match state.client.chat_completions_stream_with(messages, &options).await {
+        Ok(stream) => sse_response(stream, state.shutdown.as_ref(), &options, turn),
         Err(err) => Json(json!({"error": err.to_string()})).into_response(),
     }
 }
+
+/// Antwort-Stream als Server-Sent Events (`thinking`, `content`, `done`, `error`);
+/// mit `turn` wird die vollständige Antwort vor `done` in der Unterhaltung gespeichert
+pub(crate) fn sse_response(
+    stream: StreamingResponse,
+    shutdown: Option<&Arc<Shutdown>>,
+    options: &ChatOptions,
+    turn: Option<ConversationTurn>,
+) -> Response {
+    let stream = match shutdown {
+        Some(shutdown) => shutdown.guard_stream(stream, StreamContext {
+            user: options.user.clone(),
+            conversation_id: options.conversation_id.clone(),
+        }),
+        None => stream,
+    };
+    let events = stream.events().then(move |result| {
+        let turn = turn.clone();
+        async move {
+            if let (Some(turn), Ok(StreamEvent::Done { message, .. })) = (turn, &result) {
+                if let Err(err) = turn.complete(message.clone()).await {
+                    warn!("Antwort konnte nicht in Unterhaltung {} gespeichert werden: {}", turn.conversation_id(), err);
+                }
+            }
+            result
+        }
+    });
+    let events = events.map(|result| {
+        let event = match result {
+            Ok(event @ StreamEvent::Thinking { .. }) => sse_event("thinking", &event),
+            Ok(event @ StreamEvent::Content { .. }) => sse_event("content", &event),
+            Ok(event @ StreamEvent::Done { .. }) => sse_event("done", &event),
+            Err(err) => sse_event("error", &json!({"error": err.to_string()})),
+        };
+        Ok::<_, Infallible>(event)
+    });
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+fn sse_event(name: &str, data: &impl serde::Serialize) -> Event {
+    Event::default()
+        .event(name)
+        .json_data(data)
+        .unwrap_or_else(|_| Event::default().event("error").data("Serialisierung fehlgeschlagen"))
+}
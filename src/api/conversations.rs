@@ -0,0 +1,320 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use crate::api::chat::{chat_options, sse_response};
+use crate::api::settings::SettingsStore;
+use crate::api::uploads::UploadStore;
+use crate::client::{GlmClient, GlmError, Message, Role};
+use crate::conversations::{Conversation, ConversationError, ConversationStore, ConversationTurn};
+use crate::shutdown::Shutdown;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+impl ConversationError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ConversationError::NotFound(_) | ConversationError::MessageNotFound(_) => StatusCode::NOT_FOUND,
+            ConversationError::Invalid(_) => StatusCode::BAD_REQUEST,
+            ConversationError::Io(_) | ConversationError::Json(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ConversationError {
+    fn into_response(self) -> Response {
+        (self.status_code(), Json(json!({
+            "error": self.to_string(),
+            "status": "error"
+        }))).into_response()
+    }
+}
+
+#[derive(Clone)]
+pub struct ConversationsState {
+    pub store: Arc<ConversationStore>,
+    pub client: Arc<GlmClient>,
+    /// Löst gespeicherte Dateiverweise vor der Anfrage an das Modell auf
+    pub uploads: Arc<UploadStore>,
+    /// Gespeicherte Einstellungen des Benutzers als Standard für neue Antworten
+    pub settings: Option<Arc<SettingsStore>>,
+    /// Beendet laufende Streams beim Herunterfahren
+    pub shutdown: Option<Arc<Shutdown>>,
+}
+
+pub fn conversations_routes(
+    store: Arc<ConversationStore>,
+    client: Arc<GlmClient>,
+    uploads: Arc<UploadStore>,
+    settings: Option<Arc<SettingsStore>>,
+    shutdown: Option<Arc<Shutdown>>,
+) -> Router {
+    Router::new()
+        .route("/api/conversations", get(list_conversations).post(create_conversation))
+        .route("/api/conversations/:id", get(get_conversation).delete(delete_conversation))
+        .route("/api/conversations/:id/regenerate", post(regenerate_last))
+        .route("/api/conversations/:id/messages/:message_id", put(edit_message))
+        .route("/api/conversations/:id/messages/:message_id/regenerate", post(regenerate_message))
+        .route("/api/conversations/:id/messages/:message_id/siblings", get(list_siblings))
+        .route("/api/conversations/:id/messages/:message_id/activate", post(activate_message))
+        .with_state(ConversationsState { store, client, uploads, settings, shutdown })
+}
+
+/// Unterhaltung mit den Nachrichten des aktiven Zweigs
+fn conversation_json(conversation: &Conversation) -> Value {
+    json!({
+        "id": conversation.id,
+        "title": conversation.title,
+        "active_id": conversation.active_id,
+        "created_at": conversation.created_at,
+        "updated_at": conversation.updated_at,
+        "messages": conversation.active_branch()
+    })
+}
+
+/// Erzeugt eine neue Antwort für den begonnenen Durchgang, mit `{"n": 3}`
+/// mehrere Kandidaten; mit `{"stream": true}` eine Antwort als Server-Sent
+/// Events wie bei `/api/chat/stream`. Gespeichert wird nur bei Erfolg.
+async fn generate(
+    state: &ConversationsState,
+    headers: &HeaderMap,
+    payload: &Value,
+    id: &str,
+    begun: Result<(Vec<Message>, ConversationTurn), ConversationError>,
+) -> Response {
+    let user = super::request_user(headers);
+    let mut options = chat_options(headers, payload);
+    let stream = payload.get("stream").and_then(Value::as_bool).unwrap_or(false);
+    if stream {
+        if let Err(err) = GlmClient::check_stream_candidates(&options) {
+            return upstream_error(err);
+        }
+    }
+    let (mut context, turn) = match begun {
+        Ok(begun) => begun,
+        Err(err) => return err.into_response(),
+    };
+    // Gespeichert sind Dateiverweise, die API erwartet die Bilder selbst
+    if let Err(err) = state.uploads.resolve_messages(&mut context).await {
+        return err.into_response();
+    }
+    options.conversation_id = Some(id.to_string());
+    if let Some(settings) = &state.settings {
+        settings.apply_defaults(&mut options).await;
+    }
+
+    if stream {
+        return match state.client.chat_completions_stream_with(context, &options).await {
+            Ok(stream) => sse_response(stream, state.shutdown.as_ref(), &options, Some(turn)),
+            Err(err) => upstream_error(err),
+        };
+    }
+    let response = match state.client.chat_completions_with(context, &options).await {
+        Ok(response) => response,
+        Err(err) => return upstream_error(err),
+    };
+    if response.choices.is_empty() {
+        return upstream_error(GlmError::ParsingError { message: "Antwort ohne Auswahl".to_string() });
+    }
+    // Mehrere Kandidaten (`n`) werden als Alternativen gespeichert
+    let candidates = response.choices.iter().map(|choice| choice.message.clone()).collect();
+    let message_ids = match turn.complete_all(candidates).await {
+        Ok(message_ids) => message_ids,
+        Err(err) => return err.into_response(),
+    };
+    match state.store.get(&user, id) {
+        Ok(conversation) => Json(json!({
+            "message_id": message_ids.first(),
+            "message_ids": message_ids,
+            "response": response,
+            "conversation": conversation_json(&conversation),
+            "status": "success"
+        })).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+fn upstream_error(err: GlmError) -> Response {
+    let status = StatusCode::from_u16(err.http_status()).unwrap_or(StatusCode::BAD_GATEWAY);
+    (status, Json(json!({
+        "error": err.to_string(),
+        "status": "error"
+    }))).into_response()
+}
+
+async fn list_conversations(State(state): State<ConversationsState>, headers: HeaderMap) -> impl IntoResponse {
+    let conversations = state.store.list(&super::request_user(&headers));
+    Json(json!({
+        "conversations": conversations,
+        "count": conversations.len(),
+        "status": "success"
+    }))
+}
+
+/// Legt eine leere Unterhaltung an, optional mit `{"title": "..."}`
+async fn create_conversation(
+    State(state): State<ConversationsState>,
+    headers: HeaderMap,
+    payload: Option<Json<Value>>,
+) -> Result<impl IntoResponse, ConversationError> {
+    let title = payload.and_then(|Json(payload)| payload.get("title").and_then(Value::as_str).map(str::to_string));
+    let conversation = state.store.create(&super::request_user(&headers), title).await?;
+    Ok((StatusCode::CREATED, Json(json!({
+        "conversation": conversation_json(&conversation),
+        "status": "success"
+    }))))
+}
+
+async fn get_conversation(
+    State(state): State<ConversationsState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ConversationError> {
+    let conversation = state.store.get(&super::request_user(&headers), &id)?;
+    Ok(Json(json!({
+        "conversation": conversation_json(&conversation),
+        "status": "success"
+    })))
+}
+
+async fn delete_conversation(
+    State(state): State<ConversationsState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ConversationError> {
+    state.store.delete(&super::request_user(&headers), &id).await?;
+    Ok(Json(json!({
+        "id": id,
+        "status": "deleted"
+    })))
+}
+
+/// Bearbeitet eine Nachricht mit `{"content": "..."}`: Die neue Fassung wird
+/// eine Alternative zur alten. Bei Benutzernachrichten folgt eine neue
+/// Antwort, außer mit `{"generate": false}`.
+async fn edit_message(
+    State(state): State<ConversationsState>,
+    Path((id, message_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> Response {
+    let Some(content) = payload.get("content").and_then(Value::as_str) else {
+        return ConversationError::Invalid("content fehlt".to_string()).into_response();
+    };
+    let user = super::request_user(&headers);
+    let conversation = match state.store.get(&user, &id) {
+        Ok(conversation) => conversation,
+        Err(err) => return err.into_response(),
+    };
+    let Some(node) = conversation.node(&message_id) else {
+        return ConversationError::MessageNotFound(message_id).into_response();
+    };
+
+    // Die bearbeitete Fassung wird erst mit der neuen Antwort gespeichert
+    if matches!(node.message.role, Role::User) && payload.get("generate").and_then(Value::as_bool).unwrap_or(true) {
+        let begun = state.store.begin_edit_turn(&user, &id, &message_id, content);
+        return generate(&state, &headers, &payload, &id, begun).await;
+    }
+    let (conversation, edited_id) = match state.store.update(&user, &id, |conversation| conversation.edit(&message_id, content)).await {
+        Ok(edited) => edited,
+        Err(err) => return err.into_response(),
+    };
+    Json(json!({
+        "message_id": edited_id,
+        "conversation": conversation_json(&conversation),
+        "status": "updated"
+    })).into_response()
+}
+
+/// Erzeugt eine weitere Antwort: für eine Antwort des Modells als deren
+/// Alternative, für eine Benutzernachricht als neue Antwort darauf
+async fn regenerate_message(
+    State(state): State<ConversationsState>,
+    Path((id, message_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    payload: Option<Json<Value>>,
+) -> Response {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let conversation = match state.store.get(&super::request_user(&headers), &id) {
+        Ok(conversation) => conversation,
+        Err(err) => return err.into_response(),
+    };
+    let Some(node) = conversation.node(&message_id) else {
+        return ConversationError::MessageNotFound(message_id).into_response();
+    };
+    let parent = match node.message.role {
+        Role::Assistant => match &node.parent_id {
+            Some(parent) => parent.clone(),
+            None => return ConversationError::Invalid("Antwort ohne vorherige Nachricht".to_string()).into_response(),
+        },
+        Role::User => node.id.clone(),
+        Role::System => {
+            return ConversationError::Invalid("Systemnachrichten können nicht neu generiert werden".to_string()).into_response()
+        }
+    };
+    let begun = state.store.begin_turn(&super::request_user(&headers), &id, Some(&parent), &[]);
+    generate(&state, &headers, &payload, &id, begun).await
+}
+
+/// Erzeugt Alternativen zur letzten Antwort des aktiven Zweigs
+async fn regenerate_last(
+    State(state): State<ConversationsState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    payload: Option<Json<Value>>,
+) -> Response {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let conversation = match state.store.get(&super::request_user(&headers), &id) {
+        Ok(conversation) => conversation,
+        Err(err) => return err.into_response(),
+    };
+    let Some(parent) = conversation.last_answer().and_then(|node| node.parent_id.clone()) else {
+        return ConversationError::Invalid("Die Unterhaltung enthält noch keine Antwort".to_string()).into_response();
+    };
+    let begun = state.store.begin_turn(&super::request_user(&headers), &id, Some(&parent), &[]);
+    generate(&state, &headers, &payload, &id, begun).await
+}
+
+async fn list_siblings(
+    State(state): State<ConversationsState>,
+    Path((id, message_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ConversationError> {
+    let conversation = state.store.get(&super::request_user(&headers), &id)?;
+    let active: Vec<String> = conversation.active_branch().into_iter().map(|message| message.id).collect();
+    let siblings: Vec<Value> = conversation
+        .siblings(&message_id)?
+        .into_iter()
+        .map(|node| json!({
+            "id": node.id,
+            "parent_id": node.parent_id,
+            "message": node.message,
+            "created_at": node.created_at,
+            "active": active.contains(&node.id)
+        }))
+        .collect();
+    Ok(Json(json!({
+        "siblings": siblings,
+        "count": siblings.len(),
+        "status": "success"
+    })))
+}
+
+/// Wechselt auf den Zweig durch die Nachricht
+async fn activate_message(
+    State(state): State<ConversationsState>,
+    Path((id, message_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ConversationError> {
+    let (conversation, _) = state
+        .store
+        .update(&super::request_user(&headers), &id, |conversation| conversation.activate(&message_id))
+        .await?;
+    Ok(Json(json!({
+        "conversation": conversation_json(&conversation),
+        "status": "updated"
+    })))
+}
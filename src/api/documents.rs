@@ -0,0 +1,204 @@
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use crate::documents::{extract_text, DocumentStore, DocumentSummary, ExtractError};
+
+#[derive(Debug, Deserialize)]
+struct CreateDocumentRequest {
+    title: String,
+    content: String,
+    /// `text/plain` (Standard) oder `text/markdown`
+    content_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+pub fn documents_routes(store: Arc<DocumentStore>) -> Router {
+    let body_limit = store.max_file_size() + 64 * 1024;
+
+    Router::new()
+        .route("/api/documents", get(list_documents).post(create_document))
+        .route("/api/documents/upload", post(upload_document))
+        .route("/api/documents/search", get(search_documents))
+        .route("/api/documents/:id", get(get_document).delete(delete_document))
+        .route("/api/documents/:id/chunks/:index", get(get_chunk))
+        .layer(DefaultBodyLimit::max(body_limit))
+        .with_state(store)
+}
+
+fn error_response(status: StatusCode, message: impl std::fmt::Display) -> axum::response::Response {
+    (status, Json(json!({
+        "error": message.to_string(),
+        "status": "error"
+    }))).into_response()
+}
+
+impl ExtractError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ExtractError::Unsupported => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ExtractError::InvalidPdf(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+fn created(document: DocumentSummary) -> axum::response::Response {
+    (StatusCode::CREATED, Json(json!({
+        "document": document,
+        "status": "success"
+    }))).into_response()
+}
+
+async fn list_documents(State(store): State<Arc<DocumentStore>>) -> impl IntoResponse {
+    let documents = store.list();
+    Json(json!({
+        "documents": documents,
+        "count": documents.len(),
+        "status": "success"
+    }))
+}
+
+async fn create_document(
+    State(store): State<Arc<DocumentStore>>,
+    Json(request): Json<CreateDocumentRequest>
+) -> impl IntoResponse {
+    let content_type = match request.content_type.as_deref() {
+        Some("text/markdown") => "text/markdown",
+        _ => "text/plain",
+    };
+
+    match store.add(&request.title, content_type, &request.content).await {
+        Ok(document) => created(document),
+        Err(err) => error_response(StatusCode::BAD_REQUEST, err),
+    }
+}
+
+/// Multipart-Upload mit dem Feld `file` (Text, Markdown oder PDF) und optional `title`
+async fn upload_document(
+    State(store): State<Arc<DocumentStore>>,
+    mut multipart: Multipart
+) -> impl IntoResponse {
+    let mut title = None;
+    let mut file = None;
+
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(err) => return error_response(StatusCode::BAD_REQUEST, err),
+        };
+
+        match field.name() {
+            Some("title") => title = field.text().await.ok(),
+            Some("file") => {
+                let filename = field.file_name().map(str::to_string);
+                let content_type = field.content_type().map(str::to_string);
+                match field.bytes().await {
+                    Ok(data) => file = Some((filename, content_type, data)),
+                    Err(err) => return error_response(StatusCode::BAD_REQUEST, err),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some((filename, content_type, data)) = file else {
+        return error_response(StatusCode::BAD_REQUEST, "Feld 'file' fehlt");
+    };
+    if data.len() > store.max_file_size() {
+        return error_response(StatusCode::PAYLOAD_TOO_LARGE, format!("Datei zu groß (maximal {} Bytes)", store.max_file_size()));
+    }
+
+    // PDF-Extraktion blockiert und darf den Executor nicht aufhalten
+    let name = filename.clone();
+    let extracted = tokio::task::spawn_blocking(move || extract_text(&data, content_type.as_deref(), name.as_deref())).await;
+    let (text, detected_type) = match extracted {
+        Ok(Ok(extracted)) => extracted,
+        Ok(Err(err)) => return error_response(err.status_code(), err),
+        Err(err) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    let title = title
+        .filter(|title| !title.trim().is_empty())
+        .or(filename)
+        .unwrap_or_else(|| "Unbenanntes Dokument".to_string());
+
+    match store.add(&title, detected_type, &text).await {
+        Ok(document) => created(document),
+        Err(err) => error_response(StatusCode::BAD_REQUEST, err),
+    }
+}
+
+async fn search_documents(
+    State(store): State<Arc<DocumentStore>>,
+    Query(query): Query<SearchQuery>
+) -> impl IntoResponse {
+    let results = store.search(&query.q, query.limit.unwrap_or(5).clamp(1, 50)).await;
+    Json(json!({
+        "query": query.q,
+        "results": results,
+        "count": results.len(),
+        "status": "success"
+    }))
+}
+
+async fn get_document(
+    Path(id): Path<String>,
+    State(store): State<Arc<DocumentStore>>
+) -> impl IntoResponse {
+    let Some(document) = store.get(&id) else {
+        return error_response(StatusCode::NOT_FOUND, format!("Dokument '{}' nicht gefunden", id));
+    };
+
+    let chunks: Vec<_> = document.chunks
+        .iter()
+        .map(|chunk| json!({ "index": chunk.index, "text": chunk.text }))
+        .collect();
+    Json(json!({
+        "document": DocumentSummary::from(&document),
+        "chunks": chunks,
+        "status": "success"
+    })).into_response()
+}
+
+/// Löst eine Quellenangabe `doc:<id>#<index>` auf
+async fn get_chunk(
+    Path((id, index)): Path<(String, usize)>,
+    State(store): State<Arc<DocumentStore>>
+) -> impl IntoResponse {
+    let Some(document) = store.get(&id) else {
+        return error_response(StatusCode::NOT_FOUND, format!("Dokument '{}' nicht gefunden", id));
+    };
+    let Some(chunk) = document.chunks.get(index) else {
+        return error_response(StatusCode::NOT_FOUND, format!("Abschnitt {} nicht gefunden", index));
+    };
+
+    Json(json!({
+        "document_id": document.id,
+        "title": document.title,
+        "index": chunk.index,
+        "text": chunk.text,
+        "status": "success"
+    })).into_response()
+}
+
+async fn delete_document(
+    Path(id): Path<String>,
+    State(store): State<Arc<DocumentStore>>
+) -> impl IntoResponse {
+    match store.delete(&id).await {
+        Ok(true) => Json(json!({ "id": id, "status": "deleted" })).into_response(),
+        Ok(false) => error_response(StatusCode::NOT_FOUND, format!("Dokument '{}' nicht gefunden", id)),
+        Err(err) => error_response(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
@@ -0,0 +1,53 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use crate::client::GlmClient;
+
+/// `input` als einzelner Text oder als Liste
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsPayload {
+    input: EmbeddingInput,
+    model: Option<String>,
+}
+
+pub fn embeddings_routes(client: Arc<GlmClient>) -> Router {
+    Router::new()
+        .route("/api/embeddings", post(create_embeddings))
+        .with_state(client)
+}
+
+async fn create_embeddings(
+    State(client): State<Arc<GlmClient>>,
+    Json(payload): Json<EmbeddingsPayload>
+) -> impl IntoResponse {
+    let inputs = match payload.input {
+        EmbeddingInput::Single(text) => vec![text],
+        EmbeddingInput::Batch(texts) => texts,
+    };
+
+    match client.embeddings(inputs, payload.model.as_deref()).await {
+        Ok(response) => Json(json!({
+            "object": "list",
+            "model": response.model,
+            "data": response.data,
+            "usage": response.usage,
+            "dimensions": response.dimensions,
+            "status": "success"
+        })).into_response(),
+        Err(err) => {
+            let status = StatusCode::from_u16(err.http_status()).unwrap_or(StatusCode::BAD_GATEWAY);
+            (status, Json(json!({
+                "error": err.to_string(),
+                "status": "error"
+            }))).into_response()
+        }
+    }
+}
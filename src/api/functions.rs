@@ -1,8 +1,8 @@
-use axum::{extract::Path, response::IntoResponse, routing::{get, post}, Json, Router};
+use axum::{extract::{Path, State}, http::StatusCode, response::IntoResponse, routing::{get, post}, Json, Router};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::json;
 use std::sync::Arc;
-use crate::functions::{FunctionRegistry, FunctionCall};
+use crate::functions::FunctionRegistry;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ExecuteFunctionRequest {
@@ -10,26 +10,17 @@ struct ExecuteFunctionRequest {
     arguments: std::collections::HashMap<String, serde_json::Value>,
 }
 
-pub fn functions_routes() -> Router {
-    let registry = Arc::new(FunctionRegistry::new());
-    
-    // Erweitere Registry mit zusätzlichen Funktionen
-    let mut enhanced_registry = FunctionRegistry::new();
-    enhanced_registry.register("calculate", Arc::new(crate::functions::Calculator));
-    enhanced_registry.register("analyze_text", Arc::new(crate::functions::TextAnalyzer));
-    enhanced_registry.register("generate_uuid", Arc::new(crate::functions::UuidGenerator));
-    
-    let registry = Arc::new(enhanced_registry);
-    
+pub fn functions_routes(registry: Arc<FunctionRegistry>) -> Router {
     Router::new()
         .route("/api/functions", get(list_functions))
         .route("/api/functions/definitions", get(get_function_definitions))
         .route("/api/functions/execute", post(execute_function))
+        .route("/api/functions/approvals", get(list_approvals))
         .route("/api/functions/:name", get(get_function_info))
         .with_state(registry)
 }
 
-async fn list_functions(registry: Arc<FunctionRegistry>) -> impl IntoResponse {
+async fn list_functions(State(registry): State<Arc<FunctionRegistry>>) -> impl IntoResponse {
     let functions = registry.list_functions();
     
     Json(json!({
@@ -39,7 +30,7 @@ async fn list_functions(registry: Arc<FunctionRegistry>) -> impl IntoResponse {
     }))
 }
 
-async fn get_function_definitions(registry: Arc<FunctionRegistry>) -> impl IntoResponse {
+async fn get_function_definitions(State(registry): State<Arc<FunctionRegistry>>) -> impl IntoResponse {
     let definitions = registry.get_definitions();
     
     Json(json!({
@@ -49,22 +40,30 @@ async fn get_function_definitions(registry: Arc<FunctionRegistry>) -> impl IntoR
     }))
 }
 
+/// Führt eine Funktion direkt aus; riskante Tools brauchen eine Bestätigung,
+/// die nur über `/ws` möglich ist, und werden hier mit 403 abgelehnt
 async fn execute_function(
-    registry: Arc<FunctionRegistry>,
+    State(registry): State<Arc<FunctionRegistry>>,
     Json(request): Json<ExecuteFunctionRequest>
 ) -> impl IntoResponse {
+    if registry.requires_approval(&request.name) {
+        return (StatusCode::FORBIDDEN, Json(json!({
+            "error": format!("'{}' muss bestätigt werden und ist nur über /ws ausführbar", request.name),
+            "status": "error"
+        }))).into_response();
+    }
     let result = registry.execute_function(&request.name, request.arguments).await;
     
     Json(json!({
         "function_name": request.name,
         "result": result,
         "status": if result.success { "success" } else { "error" }
-    }))
+    })).into_response()
 }
 
 async fn get_function_info(
     Path(name): Path<String>,
-    registry: Arc<FunctionRegistry>
+    State(registry): State<Arc<FunctionRegistry>>
 ) -> impl IntoResponse {
     if !registry.has_function(&name) {
         return Json(json!({
@@ -74,7 +73,7 @@ async fn get_function_info(
     }
 
     let definitions = registry.get_definitions();
-    let definition = definitions.iter().find(|def| def.name == name);
+    let definition = definitions.iter().find(|def| def.function.name == name);
 
     match definition {
         Some(def) => Json(json!({
@@ -87,3 +86,21 @@ async fn get_function_info(
         }))
     }
 }
+
+/// Offene Bestätigungen und letzte Entscheidungen (nur lesend); beantwortet
+/// werden Anfragen über die WebSocket-Verbindung, die den Aufruf gestartet hat
+async fn list_approvals(State(registry): State<Arc<FunctionRegistry>>) -> impl IntoResponse {
+    let Some(approvals) = registry.approvals() else {
+        return Json(json!({
+            "pending": [],
+            "decisions": [],
+            "status": "disabled"
+        }));
+    };
+
+    Json(json!({
+        "pending": approvals.pending(),
+        "decisions": approvals.decisions(100).await,
+        "status": "success"
+    }))
+}
@@ -0,0 +1,244 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use crate::client::GlmClient;
+use crate::config::{AppConfig, CacheBackendKind};
+use crate::functions::FunctionRegistry;
+use crate::mcp::McpClient;
+
+/// Version aus `Cargo.toml`
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Git-Hash des Builds (siehe `build.rs`)
+pub const GIT_HASH: &str = env!("GIT_HASH");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Degraded,
+    /// Prüfung ist deaktiviert und zählt nicht
+    Skipped,
+}
+
+/// Ergebnis einer einzelnen Prüfung
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    pub latency_ms: u64,
+}
+
+impl CheckResult {
+    fn new(started: Instant, problems: Vec<String>) -> Self {
+        let status = if problems.is_empty() { CheckStatus::Ok } else { CheckStatus::Degraded };
+        let message = (!problems.is_empty()).then(|| problems.join("; "));
+        Self { status, message, latency_ms: started.elapsed().as_millis() as u64 }
+    }
+
+    fn skipped(message: &str) -> Self {
+        Self { status: CheckStatus::Skipped, message: Some(message.to_string()), latency_ms: 0 }
+    }
+}
+
+/// Prüfungen für Liveness und Readiness.
+///
+/// Die Erreichbarkeit der GLM-API wird höchstens alle `health.probe_ttl`
+/// Sekunden geprüft, damit häufige Health-Checks keine Last erzeugen.
+pub struct HealthChecker {
+    config: AppConfig,
+    client: Arc<GlmClient>,
+    registry: Arc<FunctionRegistry>,
+    mcp_clients: Vec<Arc<McpClient>>,
+    upstream: Mutex<Option<(Instant, CheckResult)>>,
+    started: Instant,
+}
+
+impl HealthChecker {
+    pub fn new(config: AppConfig, client: Arc<GlmClient>, registry: Arc<FunctionRegistry>) -> Self {
+        Self {
+            config,
+            client,
+            registry,
+            mcp_clients: Vec::new(),
+            upstream: Mutex::new(None),
+            started: Instant::now(),
+        }
+    }
+
+    /// Verbundene MCP-Server, die bei der Tool-Prüfung angepingt werden
+    pub fn with_mcp_clients(mut self, clients: Vec<Arc<McpClient>>) -> Self {
+        self.mcp_clients = clients;
+        self
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Alle Prüfungen; `ready` ist falsch, sobald eine davon fehlschlägt
+    pub async fn check(&self) -> (bool, BTreeMap<&'static str, CheckResult>) {
+        let (upstream, storage, tools) = tokio::join!(self.check_upstream(), self.check_storage(), self.check_tools());
+        let checks = BTreeMap::from([
+            ("config", self.check_config()),
+            ("upstream", upstream),
+            ("storage", storage),
+            ("tools", tools),
+        ]);
+        let ready = checks.values().all(|check| check.status != CheckStatus::Degraded);
+        (ready, checks)
+    }
+
+    fn check_config(&self) -> CheckResult {
+        CheckResult::new(Instant::now(), config_problems(&self.config))
+    }
+
+    async fn check_upstream(&self) -> CheckResult {
+        let health = &self.config.health;
+        if !health.upstream_probe {
+            return CheckResult::skipped("deaktiviert");
+        }
+
+        // Sperre über die Prüfung halten, damit parallele Anfragen sie teilen
+        let mut cached = self.upstream.lock().await;
+        if let Some((checked_at, result)) = cached.as_ref() {
+            if checked_at.elapsed() < Duration::from_secs(health.probe_ttl) {
+                return result.clone();
+            }
+        }
+
+        let started = Instant::now();
+        let problems = match self.client.probe(Duration::from_secs(health.probe_timeout.max(1))).await {
+            Ok(()) => Vec::new(),
+            Err(err) => vec![format!("GLM-API nicht erreichbar: {}", err)],
+        };
+        let result = CheckResult::new(started, problems);
+        *cached = Some((Instant::now(), result.clone()));
+        result
+    }
+
+    async fn check_storage(&self) -> CheckResult {
+        let started = Instant::now();
+        let mut problems = Vec::new();
+        for (name, dir) in storage_dirs(&self.config) {
+            if let Err(err) = check_writable(&dir).await {
+                problems.push(format!("{} ({}) nicht beschreibbar: {}", name, dir.display(), err));
+            }
+        }
+        CheckResult::new(started, problems)
+    }
+
+    async fn check_tools(&self) -> CheckResult {
+        let started = Instant::now();
+        let mut problems = Vec::new();
+        if self.registry.list_functions().is_empty() {
+            problems.push("keine Tools registriert".to_string());
+        }
+
+        let configured = self.config.mcp.servers.iter().filter(|server| server.enabled).count();
+        if self.mcp_clients.len() < configured {
+            problems.push(format!("{} von {} MCP-Servern verbunden", self.mcp_clients.len(), configured));
+        }
+        let timeout = Duration::from_secs(self.config.health.probe_timeout.max(1));
+        for client in &self.mcp_clients {
+            match tokio::time::timeout(timeout, client.request("ping", json!({}))).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => problems.push(format!("MCP-Server '{}': {}", client.name(), err)),
+                Err(_) => problems.push(format!("MCP-Server '{}' antwortet nicht", client.name())),
+            }
+        }
+        CheckResult::new(started, problems)
+    }
+}
+
+/// Fehler in der Konfiguration, die Anfragen scheitern lassen: fehlender
+/// API-Key und alles, was [`AppConfig::validate`] bemängelt
+pub fn config_problems(config: &AppConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+    let keys = config.chatglm.keys();
+    if keys.is_empty() {
+        problems.push("chatglm.api_key ist leer".to_string());
+    } else if keys.iter().any(|key| key.expose().contains("${")) {
+        problems.push("chatglm.api_key enthält eine nicht ersetzte Variable".to_string());
+    }
+    problems.extend(config.validate(false));
+    problems
+}
+
+/// Verzeichnisse, in die die Anwendung im Betrieb schreibt
+fn storage_dirs(config: &AppConfig) -> Vec<(&'static str, PathBuf)> {
+    let mut dirs = vec![
+        ("uploads", PathBuf::from(&config.uploads.dir)),
+        ("settings", PathBuf::from(&config.settings.dir)),
+    ];
+    if config.presets.enabled {
+        dirs.push(("presets", PathBuf::from(&config.presets.dir)));
+    }
+    if config.templates.enabled {
+        dirs.push(("templates", PathBuf::from(&config.templates.dir)));
+    }
+    if config.conversations.enabled {
+        dirs.push(("conversations", PathBuf::from(&config.conversations.dir)));
+    }
+    if config.documents.enabled {
+        dirs.push(("documents", PathBuf::from(&config.documents.dir)));
+    }
+    if config.usage.enabled {
+        let file = PathBuf::from(&config.usage.file);
+        dirs.push(("usage", file.parent().map(PathBuf::from).unwrap_or_default()));
+    }
+    if config.cache.enabled && config.cache.backend == CacheBackendKind::Disk {
+        dirs.push(("cache", PathBuf::from(&config.cache.dir)));
+    }
+    if let Some(file) = &config.logging.file {
+        dirs.push(("logs", PathBuf::from(&file.dir)));
+    }
+    dirs
+}
+
+async fn check_writable(dir: &Path) -> std::io::Result<()> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    tokio::fs::create_dir_all(&dir).await?;
+    let probe = dir.join(format!(".health-{}", uuid::Uuid::new_v4()));
+    tokio::fs::write(&probe, b"ok").await?;
+    tokio::fs::remove_file(&probe).await
+}
+
+pub fn health_routes(checker: Arc<HealthChecker>) -> Router {
+    Router::new()
+        .route("/api/health", get(live))
+        .route("/api/health/live", get(live))
+        .route("/api/health/ready", get(ready))
+        .with_state(checker)
+}
+
+fn build_info(checker: &HealthChecker) -> serde_json::Value {
+    json!({
+        "service": "chatglm-web",
+        "version": VERSION,
+        "git_hash": GIT_HASH,
+        "uptime_seconds": checker.uptime().as_secs(),
+    })
+}
+
+/// Liveness: der Prozess läuft und beantwortet Anfragen
+async fn live(State(checker): State<Arc<HealthChecker>>) -> impl IntoResponse {
+    let mut body = build_info(&checker);
+    body["status"] = json!("ok");
+    Json(body)
+}
+
+/// Readiness: 503, solange eine Prüfung fehlschlägt
+async fn ready(State(checker): State<Arc<HealthChecker>>) -> impl IntoResponse {
+    let (ready, checks) = checker.check().await;
+    let mut body = build_info(&checker);
+    body["status"] = json!(if ready { "ok" } else { "degraded" });
+    body["checks"] = json!(checks);
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(body))
+}
@@ -2,10 +2,54 @@ pub mod chat;
 pub mod settings;
 pub mod models;
 pub mod websocket;
-// pub mod functions;
+pub mod functions;
+pub mod uploads;
+pub mod documents;
+pub mod presets;
+pub mod templates;
+pub mod conversations;
+pub mod embeddings;
+pub mod usage;
+pub mod health;
 
 pub use chat::*;
 pub use settings::*;
 pub use models::*;
 pub use websocket::*;
-// pub use functions::*;
+pub use functions::*;
+pub use uploads::*;
+pub use documents::*;
+pub use presets::*;
+pub use templates::*;
+pub use conversations::*;
+pub use embeddings::*;
+pub use usage::*;
+pub use health::*;
+
+use axum::http::HeaderMap;
+
+/// Benutzerkennung aus dem Header `X-User-Id` (bis eine Anmeldung existiert)
+pub fn user_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-user-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Benutzer der Anfrage (`X-User-Id`, sonst [`ANONYMOUS_USER`])
+pub fn request_user(headers: &HeaderMap) -> String {
+    user_id(headers).unwrap_or_else(|| ANONYMOUS_USER.to_string())
+}
+
+/// Anzeigename aus dem Header `X-User-Name`, sonst die Benutzerkennung
+pub fn user_name(headers: &HeaderMap, user: Option<&str>) -> Option<String> {
+    headers
+        .get("x-user-name")
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .or(user)
+        .map(str::to_string)
+}
@@ -0,0 +1,141 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use crate::client::{ChatOptions, Message};
+use crate::presets::{builtin_variables, render, PresetError, PresetInput, PresetStore};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+impl PresetError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            PresetError::NotFound(_) => StatusCode::NOT_FOUND,
+            PresetError::Conflict(_) => StatusCode::CONFLICT,
+            PresetError::Invalid(_) => StatusCode::BAD_REQUEST,
+            PresetError::Io(_) | PresetError::Json(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for PresetError {
+    fn into_response(self) -> axum::response::Response {
+        (self.status_code(), Json(json!({
+            "error": self.to_string(),
+            "status": "error"
+        }))).into_response()
+    }
+}
+
+pub fn presets_routes(store: Arc<PresetStore>) -> Router {
+    Router::new()
+        .route("/api/presets", get(list_presets).post(create_preset))
+        .route("/api/presets/:id", get(get_preset).put(update_preset).delete(delete_preset))
+        .route("/api/presets/:id/preview", post(preview_preset))
+        .with_state(store)
+}
+
+/// Eingebaute Variablen, ergänzt bzw. überschrieben durch `variables` im Body
+fn request_variables(payload: &Value, user_name: Option<&str>) -> HashMap<String, String> {
+    let user_name = payload.get("user_name").and_then(Value::as_str).or(user_name);
+    let mut variables = builtin_variables(user_name, chrono::Local::now());
+    if let Some(Value::Object(extra)) = payload.get("variables") {
+        for (name, value) in extra {
+            let value = match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            variables.insert(name.clone(), value);
+        }
+    }
+    variables
+}
+
+/// Wendet das mit `{"preset": "<id>"}` gewählte Preset auf die Anfrage an;
+/// ohne `preset` bleibt sie unverändert
+pub fn apply_preset(
+    presets: Option<&PresetStore>,
+    payload: &Value,
+    user_name: Option<&str>,
+    messages: &mut Vec<Message>,
+    options: &mut ChatOptions,
+) -> Result<(), PresetError> {
+    let Some(id) = payload.get("preset").and_then(Value::as_str) else { return Ok(()) };
+    let Some(presets) = presets else {
+        return Err(PresetError::NotFound(id.to_string()));
+    };
+    presets.apply(id, &request_variables(payload, user_name), messages, options)?;
+    Ok(())
+}
+
+async fn list_presets(State(store): State<Arc<PresetStore>>) -> impl IntoResponse {
+    let presets = store.list();
+    Json(json!({
+        "presets": presets,
+        "count": presets.len(),
+        "status": "success"
+    }))
+}
+
+async fn create_preset(
+    State(store): State<Arc<PresetStore>>,
+    Json(input): Json<PresetInput>,
+) -> Result<impl IntoResponse, PresetError> {
+    let preset = store.create(input).await?;
+    Ok((StatusCode::CREATED, Json(json!({
+        "preset": preset,
+        "status": "success"
+    }))))
+}
+
+async fn get_preset(State(store): State<Arc<PresetStore>>, Path(id): Path<String>) -> Result<impl IntoResponse, PresetError> {
+    let preset = store.get(&id).ok_or(PresetError::NotFound(id))?;
+    Ok(Json(json!({
+        "preset": preset,
+        "status": "success"
+    })))
+}
+
+async fn update_preset(
+    State(store): State<Arc<PresetStore>>,
+    Path(id): Path<String>,
+    Json(input): Json<PresetInput>,
+) -> Result<impl IntoResponse, PresetError> {
+    let preset = store.update(&id, input).await?;
+    Ok(Json(json!({
+        "preset": preset,
+        "status": "updated"
+    })))
+}
+
+async fn delete_preset(State(store): State<Arc<PresetStore>>, Path(id): Path<String>) -> Result<impl IntoResponse, PresetError> {
+    if !store.delete(&id).await? {
+        return Err(PresetError::NotFound(id));
+    }
+    Ok(Json(json!({
+        "id": id,
+        "status": "deleted"
+    })))
+}
+
+/// Gerenderter System-Prompt, z.B. mit `{"user_name": "Anna", "variables": {...}}`
+async fn preview_preset(
+    State(store): State<Arc<PresetStore>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    payload: Option<Json<Value>>,
+) -> Result<impl IntoResponse, PresetError> {
+    let preset = store.get(&id).ok_or(PresetError::NotFound(id))?;
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let user = super::user_id(&headers);
+    let variables = request_variables(&payload, super::user_name(&headers, user.as_deref()).as_deref());
+    Ok(Json(json!({
+        "id": preset.id,
+        "system_prompt": render(&preset.system_prompt, &variables),
+        "status": "success"
+    })))
+}
@@ -1,10 +1,29 @@
-use axum::{response::IntoResponse, routing::{get, post, put}, Json, Router, extract::State};
+use axum::{response::IntoResponse, routing::{get, post}, Json, Router, extract::State, http::{HeaderMap, StatusCode}};
+use crate::client::ChatOptions;
+use crate::config::SettingsConfig;
+use crate::store::write_atomic;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Version des gespeicherten Formats; ältere Dateien werden beim Lesen migriert
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Benutzer für Anfragen ohne `X-User-Id`
+pub const ANONYMOUS_USER: &str = "anonymous";
+
+/// Höchstzahl zwischengespeicherter Benutzer; darüber hinaus werden Einträge
+/// verdrängt und bei Bedarf neu gelesen
+const CACHE_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ChatSettings {
     pub model: String,
     pub temperature: f32,
@@ -27,49 +46,229 @@ impl Default for ChatSettings {
     }
 }
 
-pub type SettingsState = Arc<RwLock<ChatSettings>>;
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("Speicherfehler: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Speicherfehler: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl SettingsError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            SettingsError::Invalid(_) => StatusCode::BAD_REQUEST,
+            SettingsError::Io(_) | SettingsError::Json(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for SettingsError {
+    fn into_response(self) -> axum::response::Response {
+        (self.status_code(), Json(json!({
+            "error": self.to_string(),
+            "status": "error"
+        }))).into_response()
+    }
+}
+
+/// Gespeicherte Einstellungen eines Benutzers
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredSettings {
+    version: u32,
+    user: String,
+    updated_at: DateTime<Utc>,
+    settings: ChatSettings,
+}
+
+/// Liest gespeicherte Einstellungen jeder bekannten Version. Dateien ohne
+/// `version` enthalten nur die Einstellungen selbst.
+fn migrate(value: Value) -> Result<ChatSettings> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    match version {
+        0 => Ok(serde_json::from_value(value)?),
+        1 => Ok(serde_json::from_value::<StoredSettings>(value)?.settings),
+        _ => Err(anyhow!("Version {} ist neuer als die unterstützte Version {}", version, SETTINGS_VERSION)),
+    }
+}
+
+/// Chat-Einstellungen pro Benutzer, je Benutzer als JSON-Datei gespeichert
+pub struct SettingsStore {
+    dir: PathBuf,
+    /// Zuletzt gelesene Benutzer, auch solche ohne gespeicherte Einstellungen
+    settings: RwLock<HashMap<String, Option<ChatSettings>>>,
+    /// Gleichzeitige PUT/PATCH desselben Benutzers würden sonst auf
+    /// veralteten Einstellungen aufbauen
+    writes: tokio::sync::Mutex<()>,
+}
+
+impl SettingsStore {
+    pub fn new(config: &SettingsConfig) -> Self {
+        Self {
+            dir: PathBuf::from(&config.dir),
+            settings: RwLock::new(HashMap::new()),
+            writes: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Gespeicherte Einstellungen des Benutzers; `None`, wenn er keine
+    /// gespeichert hat oder sie unlesbar sind
+    pub async fn get(&self, user: &str) -> Option<ChatSettings> {
+        if let Some(settings) = self.settings.read().unwrap().get(user) {
+            return settings.clone();
+        }
+        let settings = self.load(user).await;
+        self.cache(user, settings.clone());
+        settings
+    }
+
+    fn cache(&self, user: &str, settings: Option<ChatSettings>) {
+        let mut cache = self.settings.write().unwrap();
+        if cache.len() >= CACHE_CAPACITY && !cache.contains_key(user) {
+            if let Some(evicted) = cache.keys().next().cloned() {
+                cache.remove(&evicted);
+            }
+        }
+        cache.insert(user.to_string(), settings);
+    }
+
+    async fn load(&self, user: &str) -> Option<ChatSettings> {
+        let path = self.path(user);
+        let data = match tokio::fs::read(&path).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!("Einstellungen {} konnten nicht gelesen werden: {}", path.display(), err);
+                return None;
+            }
+        };
+        let settings = serde_json::from_slice(&data)
+            .map_err(anyhow::Error::from)
+            .and_then(migrate)
+            .and_then(|settings| settings.validate().map(|_| settings).map_err(|err| anyhow!(err)));
+        settings
+            .map_err(|err| warn!("Einstellungen {} ungültig, werden ignoriert: {}", path.display(), err))
+            .ok()
+    }
+
+    /// Ändert die Einstellungen des Benutzers mit `change` und speichert sie,
+    /// wenn das Ergebnis gültig ist; sonst bleiben sie unverändert
+    pub async fn update(
+        &self,
+        user: &str,
+        change: impl FnOnce(ChatSettings) -> Result<ChatSettings, String>,
+    ) -> Result<ChatSettings, SettingsError> {
+        let _guard = self.writes.lock().await;
+        let current = self.get(user).await.unwrap_or_default();
+        let settings = change(current).map_err(SettingsError::Invalid)?;
+        settings.validate().map_err(SettingsError::Invalid)?;
+
+        let stored = StoredSettings {
+            version: SETTINGS_VERSION,
+            user: user.to_string(),
+            updated_at: Utc::now(),
+            settings: settings.clone(),
+        };
+        write_atomic(&self.path(user), serde_json::to_vec_pretty(&stored)?).await?;
+
+        self.cache(user, Some(settings.clone()));
+        Ok(settings)
+    }
+
+    /// Löscht die gespeicherten Einstellungen des Benutzers
+    pub async fn reset(&self, user: &str) -> Result<ChatSettings, SettingsError> {
+        let _guard = self.writes.lock().await;
+        match tokio::fs::remove_file(self.path(user)).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        self.settings.write().unwrap().remove(user);
+        Ok(ChatSettings::default())
+    }
+
+    /// Setzt die gespeicherten Einstellungen des Benutzers der Anfrage
+    /// (`options.user`) als Standard für diese Anfrage. Ohne gespeicherte
+    /// Einstellungen gilt weiter die Serverkonfiguration.
+    pub async fn apply_defaults(&self, options: &mut ChatOptions) {
+        let user = options.user.as_deref().unwrap_or(ANONYMOUS_USER);
+        if let Some(settings) = self.get(user).await {
+            settings.apply_defaults(options);
+        }
+    }
+
+    /// Dateiname aus dem Hash der Benutzerkennung, damit beliebige Kennungen
+    /// keine Pfade bilden können
+    fn path(&self, user: &str) -> PathBuf {
+        let digest = Sha256::digest(user.as_bytes());
+        let name: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+pub type SettingsState = Arc<SettingsStore>;
 
-pub fn settings_routes() -> Router {
-    let settings = Arc::new(RwLock::new(ChatSettings::default()));
-    
+pub fn settings_routes(store: Arc<SettingsStore>) -> Router {
     Router::new()
-        .route("/api/settings", get(get_settings))
-        .route("/api/settings", put(update_settings))
+        .route("/api/settings", get(get_settings).put(update_settings).patch(patch_settings))
         .route("/api/settings/reset", post(reset_settings))
-        .with_state(settings)
+        .with_state(store)
 }
 
-async fn get_settings(State(settings): State<SettingsState>) -> impl IntoResponse {
-    let settings = settings.read().await;
+fn saved(result: Result<ChatSettings, SettingsError>) -> axum::response::Response {
+    match result {
+        Ok(settings) => Json(json!({
+            "settings": settings,
+            "version": SETTINGS_VERSION,
+            "status": "updated",
+            "message": "Einstellungen erfolgreich aktualisiert"
+        })).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+/// Gespeicherte Einstellungen, sonst die Standardwerte mit `"stored": false`
+async fn get_settings(State(store): State<SettingsState>, headers: HeaderMap) -> impl IntoResponse {
+    let settings = store.get(&super::request_user(&headers)).await;
     Json(json!({
-        "settings": *settings,
+        "stored": settings.is_some(),
+        "settings": settings.unwrap_or_default(),
+        "version": SETTINGS_VERSION,
         "status": "success"
     }))
 }
 
+/// Ersetzt alle Einstellungen; fehlende Felder erhalten Standardwerte
 async fn update_settings(
-    State(settings): State<SettingsState>,
+    State(store): State<SettingsState>,
+    headers: HeaderMap,
     Json(payload): Json<ChatSettings>
 ) -> impl IntoResponse {
-    let mut settings_guard = settings.write().await;
-    *settings_guard = payload.clone();
-    
-    Json(json!({
-        "settings": payload,
-        "status": "updated",
-        "message": "Einstellungen erfolgreich aktualisiert"
-    }))
+    saved(store.update(&super::request_user(&headers), |_| Ok(payload)).await)
 }
 
-async fn reset_settings(State(settings): State<SettingsState>) -> impl IntoResponse {
-    let mut settings_guard = settings.write().await;
-    *settings_guard = ChatSettings::default();
-    
-    Json(json!({
-        "settings": *settings_guard,
-        "status": "reset",
-        "message": "Einstellungen auf Standard zurückgesetzt"
-    }))
+/// Ändert nur die angegebenen Felder, z.B. `{"temperature": 0.2}`
+async fn patch_settings(
+    State(store): State<SettingsState>,
+    headers: HeaderMap,
+    Json(patch): Json<Value>
+) -> impl IntoResponse {
+    saved(store.update(&super::request_user(&headers), |settings| settings.patched(&patch)).await)
+}
+
+async fn reset_settings(State(store): State<SettingsState>, headers: HeaderMap) -> impl IntoResponse {
+    match store.reset(&super::request_user(&headers)).await {
+        Ok(settings) => Json(json!({
+            "settings": settings,
+            "version": SETTINGS_VERSION,
+            "status": "reset",
+            "message": "Einstellungen auf Standard zurückgesetzt"
+        })).into_response(),
+        Err(err) => err.into_response(),
+    }
 }
 
 // Hilfsfunktionen für Validierung
@@ -78,20 +277,47 @@ impl ChatSettings {
         if self.temperature < 0.0 || self.temperature > 1.0 {
             return Err("Temperature muss zwischen 0.0 und 1.0 liegen".to_string());
         }
-        
+
         if self.top_p < 0.0 || self.top_p > 1.0 {
             return Err("top_p muss zwischen 0.0 und 1.0 liegen".to_string());
         }
-        
+
         if self.max_tokens == 0 || self.max_tokens > 32768 {
             return Err("max_tokens muss zwischen 1 und 32768 liegen".to_string());
         }
-        
-        let valid_models = vec!["glm-4.5", "glm-4.5-32k", "glm-4.5-turbo"];
+
+        let valid_models = ["glm-4.5", "glm-4.5-32k", "glm-4.5-turbo"];
         if !valid_models.contains(&self.model.as_str()) {
             return Err(format!("Ungültiges Modell. Verfügbare Modelle: {}", valid_models.join(", ")));
         }
-        
+
         Ok(())
     }
+
+    /// Übernimmt die Felder aus `patch`; unbekannte Felder und falsche Typen
+    /// werden abgelehnt (die Wertebereiche prüft [`ChatSettings::validate`])
+    pub fn patched(&self, patch: &Value) -> Result<Self, String> {
+        let Value::Object(fields) = patch else {
+            return Err("Erwartet ein JSON-Objekt mit einzelnen Einstellungen".to_string());
+        };
+        let mut current = serde_json::to_value(self).map_err(|err| err.to_string())?;
+        let Value::Object(current_fields) = &mut current else { unreachable!("ChatSettings ist ein Objekt") };
+        for (key, value) in fields {
+            match current_fields.get_mut(key) {
+                Some(field) => *field = value.clone(),
+                None => return Err(format!("Unbekannte Einstellung '{}'", key)),
+            }
+        }
+        serde_json::from_value(current).map_err(|err| format!("Ungültige Einstellungen: {}", err))
+    }
+
+    /// Setzt die Einstellungen als Standard einer Chat-Anfrage; Werte aus
+    /// der Anfrage selbst haben Vorrang
+    pub fn apply_defaults(&self, options: &mut ChatOptions) {
+        options.model.get_or_insert_with(|| self.model.clone());
+        options.temperature.get_or_insert(self.temperature);
+        options.top_p.get_or_insert(self.top_p);
+        options.max_tokens.get_or_insert(self.max_tokens);
+        options.thinking.get_or_insert(self.thinking_enabled);
+    }
 }
@@ -0,0 +1,194 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use crate::api::chat::{chat_options, sse_response};
+use crate::api::settings::SettingsStore;
+use crate::client::{GlmClient, Message};
+use crate::presets::builtin_variables;
+use crate::shutdown::Shutdown;
+use crate::templates::{Template, TemplateError, TemplateInput, TemplateStore};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+impl TemplateError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            TemplateError::NotFound(_) => StatusCode::NOT_FOUND,
+            TemplateError::Conflict(_) => StatusCode::CONFLICT,
+            TemplateError::Invalid(_) => StatusCode::BAD_REQUEST,
+            TemplateError::MissingVariables(_) | TemplateError::InvalidVariable(..) => StatusCode::UNPROCESSABLE_ENTITY,
+            TemplateError::Io(_) | TemplateError::Json(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for TemplateError {
+    fn into_response(self) -> axum::response::Response {
+        let mut body = json!({
+            "error": self.to_string(),
+            "status": "error"
+        });
+        match &self {
+            TemplateError::MissingVariables(names) => body["missing"] = json!(names),
+            TemplateError::InvalidVariable(name, _) => body["variable"] = json!(name),
+            _ => {}
+        }
+        (self.status_code(), Json(body)).into_response()
+    }
+}
+
+#[derive(Clone)]
+pub struct TemplatesState {
+    pub store: Arc<TemplateStore>,
+    pub client: Arc<GlmClient>,
+    /// Gespeicherte Einstellungen des Benutzers als Standard für `run`
+    pub settings: Option<Arc<SettingsStore>>,
+    /// Beendet laufende Streams beim Herunterfahren
+    pub shutdown: Option<Arc<Shutdown>>,
+}
+
+pub fn templates_routes(
+    store: Arc<TemplateStore>,
+    client: Arc<GlmClient>,
+    settings: Option<Arc<SettingsStore>>,
+    shutdown: Option<Arc<Shutdown>>,
+) -> Router {
+    Router::new()
+        .route("/api/templates", get(list_templates).post(create_template))
+        .route("/api/templates/:id", get(get_template).put(update_template).delete(delete_template))
+        .route("/api/templates/:id/render", post(render_template))
+        .route("/api/templates/:id/run", post(run_template))
+        .with_state(TemplatesState { store, client, settings, shutdown })
+}
+
+/// Rendert das Template mit `{"variables": {...}}` aus dem Body
+fn rendered_messages(
+    store: &TemplateStore,
+    id: String,
+    headers: &HeaderMap,
+    payload: &Value,
+) -> Result<(Vec<Message>, Template), TemplateError> {
+    let template = store.get(&id).ok_or(TemplateError::NotFound(id))?;
+    let values = match payload.get("variables") {
+        None | Some(Value::Null) => Map::new(),
+        Some(Value::Object(values)) => values.clone(),
+        Some(_) => return Err(TemplateError::Invalid("variables muss ein JSON-Objekt sein".to_string())),
+    };
+    let user = super::user_id(headers);
+    let user_name = super::user_name(headers, user.as_deref());
+    let messages = template.render(&values, builtin_variables(user_name.as_deref(), chrono::Local::now()))?;
+    Ok((messages, template))
+}
+
+async fn list_templates(State(state): State<TemplatesState>) -> impl IntoResponse {
+    let templates = state.store.list();
+    Json(json!({
+        "templates": templates,
+        "count": templates.len(),
+        "status": "success"
+    }))
+}
+
+async fn create_template(
+    State(state): State<TemplatesState>,
+    Json(input): Json<TemplateInput>,
+) -> Result<impl IntoResponse, TemplateError> {
+    let template = state.store.create(input).await?;
+    Ok((StatusCode::CREATED, Json(json!({
+        "template": template,
+        "status": "success"
+    }))))
+}
+
+async fn get_template(State(state): State<TemplatesState>, Path(id): Path<String>) -> Result<impl IntoResponse, TemplateError> {
+    let template = state.store.get(&id).ok_or(TemplateError::NotFound(id))?;
+    Ok(Json(json!({
+        "template": template,
+        "status": "success"
+    })))
+}
+
+async fn update_template(
+    State(state): State<TemplatesState>,
+    Path(id): Path<String>,
+    Json(input): Json<TemplateInput>,
+) -> Result<impl IntoResponse, TemplateError> {
+    let template = state.store.update(&id, input).await?;
+    Ok(Json(json!({
+        "template": template,
+        "status": "updated"
+    })))
+}
+
+async fn delete_template(State(state): State<TemplatesState>, Path(id): Path<String>) -> Result<impl IntoResponse, TemplateError> {
+    if !state.store.delete(&id).await? {
+        return Err(TemplateError::NotFound(id));
+    }
+    Ok(Json(json!({
+        "id": id,
+        "status": "deleted"
+    })))
+}
+
+/// Gerenderte Nachrichten ohne Anfrage an das Modell, z.B. zur Vorschau
+async fn render_template(
+    State(state): State<TemplatesState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    payload: Option<Json<Value>>,
+) -> Result<impl IntoResponse, TemplateError> {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let (messages, template) = rendered_messages(&state.store, id, &headers, &payload)?;
+    Ok(Json(json!({
+        "id": template.id,
+        "messages": messages,
+        "status": "success"
+    })))
+}
+
+/// Führt das Template aus: `{"variables": {...}}` plus optionale Chat-Optionen
+/// wie `temperature`. Standardmäßig wird die Antwort wie bei `/api/chat/stream`
+/// als Server-Sent Events gestreamt, mit `"stream": false` als JSON.
+async fn run_template(
+    State(state): State<TemplatesState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    payload: Option<Json<Value>>,
+) -> axum::response::Response {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let (messages, template) = match rendered_messages(&state.store, id, &headers, &payload) {
+        Ok(rendered) => rendered,
+        Err(err) => return err.into_response(),
+    };
+    let mut options = chat_options(&headers, &payload);
+    template.apply_defaults(&mut options);
+    if let Some(settings) = &state.settings {
+        settings.apply_defaults(&mut options).await;
+    }
+
+    let stream = payload.get("stream").and_then(Value::as_bool).unwrap_or(true);
+    let result = if stream {
+        state
+            .client
+            .chat_completions_stream_with(messages, &options)
+            .await
+            .map(|stream| sse_response(stream, state.shutdown.as_ref(), &options, None))
+    } else {
+        state
+            .client
+            .chat_completions_with(messages, &options)
+            .await
+            .map(|response| Json(json!({"response": response, "template": template.id, "status": "success"})).into_response())
+    };
+    result.unwrap_or_else(|err| {
+        let status = StatusCode::from_u16(err.http_status()).unwrap_or(StatusCode::BAD_GATEWAY);
+        (status, Json(json!({
+            "error": err.to_string(),
+            "status": "error"
+        }))).into_response()
+    })
+}
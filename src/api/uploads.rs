@@ -0,0 +1,257 @@
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use crate::client::{ContentPart, Message, MessageContent};
+use crate::config::UploadsConfig;
+
+/// Metadaten einer hochgeladenen Datei
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredUpload {
+    pub id: String,
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub size: usize,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("Datei zu groß: {size} Bytes (maximal {max} Bytes)")]
+    TooLarge { size: usize, max: usize },
+
+    #[error("Dateityp nicht erlaubt: {0}")]
+    UnsupportedType(String),
+
+    #[error("Ungültige Daten: {0}")]
+    Invalid(String),
+
+    #[error("Datei '{0}' nicht gefunden")]
+    NotFound(String),
+
+    #[error("Speicherfehler: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl UploadError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            UploadError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::UnsupportedType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            UploadError::Invalid(_) => StatusCode::BAD_REQUEST,
+            UploadError::NotFound(_) => StatusCode::NOT_FOUND,
+            UploadError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for UploadError {
+    fn into_response(self) -> axum::response::Response {
+        (self.status_code(), Json(json!({
+            "error": self.to_string(),
+            "status": "error"
+        }))).into_response()
+    }
+}
+
+/// Ablage für hochgeladene Bilder (Datei + JSON-Metadaten je Upload)
+pub struct UploadStore {
+    dir: PathBuf,
+    max_file_size: usize,
+    allowed_types: Vec<String>,
+}
+
+impl UploadStore {
+    pub fn new(config: &UploadsConfig) -> Self {
+        Self {
+            dir: PathBuf::from(&config.dir),
+            max_file_size: config.max_file_size,
+            allowed_types: config.allowed_types.clone(),
+        }
+    }
+
+    /// Obergrenze für Request-Bodies, die Bilder enthalten dürfen
+    /// (Base64 vergrößert die Daten um ein Drittel)
+    pub fn body_limit(&self) -> usize {
+        self.max_file_size * 2 + 1024 * 1024
+    }
+
+    /// Prüft Größe und Typ anhand des Dateiinhalts; gibt den MIME-Typ zurück
+    pub fn validate(&self, data: &[u8], declared_type: Option<&str>) -> Result<&'static str, UploadError> {
+        if data.len() > self.max_file_size {
+            return Err(UploadError::TooLarge { size: data.len(), max: self.max_file_size });
+        }
+
+        let detected = sniff_image_type(data)
+            .ok_or_else(|| UploadError::UnsupportedType(declared_type.unwrap_or("unbekannt").to_string()))?;
+
+        if let Some(declared) = declared_type.filter(|t| *t != "application/octet-stream") {
+            if declared != detected {
+                return Err(UploadError::UnsupportedType(format!("{} (Inhalt ist {})", declared, detected)));
+            }
+        }
+        if !self.allowed_types.iter().any(|allowed| allowed == detected) {
+            return Err(UploadError::UnsupportedType(detected.to_string()));
+        }
+
+        Ok(detected)
+    }
+
+    pub async fn store(&self, data: &[u8], declared_type: Option<&str>, filename: Option<String>) -> Result<StoredUpload, UploadError> {
+        let content_type = self.validate(data, declared_type)?;
+        let upload = StoredUpload {
+            id: uuid::Uuid::new_v4().to_string(),
+            filename,
+            content_type: content_type.to_string(),
+            size: data.len(),
+            created_at: Utc::now(),
+        };
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.data_path(&upload.id), data).await?;
+        let metadata = serde_json::to_vec_pretty(&upload).map_err(std::io::Error::other)?;
+        tokio::fs::write(self.metadata_path(&upload.id), metadata).await?;
+
+        Ok(upload)
+    }
+
+    pub async fn load(&self, id: &str) -> Result<(StoredUpload, Vec<u8>), UploadError> {
+        // Nur UUIDs zulassen, damit keine Pfade außerhalb des Verzeichnisses entstehen
+        if uuid::Uuid::parse_str(id).is_err() {
+            return Err(UploadError::NotFound(id.to_string()));
+        }
+
+        let metadata = tokio::fs::read(self.metadata_path(id))
+            .await
+            .map_err(|_| UploadError::NotFound(id.to_string()))?;
+        let upload: StoredUpload = serde_json::from_slice(&metadata)
+            .map_err(|err| UploadError::Invalid(err.to_string()))?;
+        let data = tokio::fs::read(self.data_path(id)).await?;
+
+        Ok((upload, data))
+    }
+
+    /// Bereitet multimodale Nachrichten für die API vor: Dateiverweise werden
+    /// zu Base64-Bildern aufgelöst, eingebettete Bilder und URLs geprüft
+    pub async fn resolve_messages(&self, messages: &mut [Message]) -> Result<(), UploadError> {
+        for message in messages {
+            let Some(MessageContent::Parts(parts)) = &mut message.content else { continue };
+
+            for part in parts.iter_mut() {
+                match part {
+                    ContentPart::Text { .. } => {}
+                    ContentPart::File { file } => {
+                        let (upload, data) = self.load(&file.file_id).await?;
+                        let encoded = base64::engine::general_purpose::STANDARD.encode(&data);
+                        *part = ContentPart::image_base64(&upload.content_type, encoded);
+                    }
+                    ContentPart::ImageUrl { image_url } => self.validate_image_url(&image_url.url)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_image_url(&self, url: &str) -> Result<(), UploadError> {
+        if url.starts_with("https://") || url.starts_with("http://") {
+            return Ok(());
+        }
+
+        let Some((header, data)) = url.strip_prefix("data:").and_then(|rest| rest.split_once(',')) else {
+            return Err(UploadError::Invalid("Bild-URL muss http(s) oder eine Base64-Data-URL sein".to_string()));
+        };
+        let Some(declared_type) = header.strip_suffix(";base64") else {
+            return Err(UploadError::Invalid("Data-URL ist nicht Base64-kodiert".to_string()));
+        };
+
+        // Größe vor dem Dekodieren abschätzen
+        let estimated = data.len() / 4 * 3;
+        if estimated > self.max_file_size {
+            return Err(UploadError::TooLarge { size: estimated, max: self.max_file_size });
+        }
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|err| UploadError::Invalid(format!("Base64: {}", err)))?;
+
+        self.validate(&decoded, Some(declared_type)).map(|_| ())
+    }
+
+    fn data_path(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    fn metadata_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+/// Erkennt Bildformate an ihren Magic Bytes
+pub fn sniff_image_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+pub fn uploads_routes(store: Arc<UploadStore>) -> Router {
+    let body_limit = store.body_limit();
+
+    Router::new()
+        .route("/api/uploads", post(upload_file))
+        .route("/api/uploads/:id", get(get_upload))
+        .layer(DefaultBodyLimit::max(body_limit))
+        .with_state(store)
+}
+
+/// Multipart-Upload mit dem Feld `file`; liefert die ID für `{"type": "file", "file": {"file_id": ...}}`
+async fn upload_file(
+    State(store): State<Arc<UploadStore>>,
+    mut multipart: Multipart
+) -> Result<impl IntoResponse, UploadError> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| UploadError::Invalid(err.to_string()))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let filename = field.file_name().map(str::to_string);
+        let declared_type = field.content_type().map(str::to_string);
+        let data = field.bytes().await.map_err(|err| UploadError::Invalid(err.to_string()))?;
+
+        let upload = store.store(&data, declared_type.as_deref(), filename).await?;
+        return Ok((StatusCode::CREATED, Json(json!({
+            "upload": upload,
+            "content_part": ContentPart::file(&upload.id),
+            "status": "success"
+        }))));
+    }
+
+    Err(UploadError::Invalid("Feld 'file' fehlt".to_string()))
+}
+
+async fn get_upload(
+    Path(id): Path<String>,
+    State(store): State<Arc<UploadStore>>
+) -> Result<impl IntoResponse, UploadError> {
+    let (upload, data) = store.load(&id).await?;
+    Ok(([(header::CONTENT_TYPE, upload.content_type)], data))
+}
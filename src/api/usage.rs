@@ -0,0 +1,104 @@
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use crate::usage::{buckets_to_csv, records_to_csv, total, UsageFilter, UsageGroupBy, UsagePeriod, UsageStore};
+
+#[derive(Debug, Default, Deserialize)]
+struct UsageQuery {
+    #[serde(default)]
+    period: UsagePeriod,
+    #[serde(default)]
+    group_by: UsageGroupBy,
+    user: Option<String>,
+    model: Option<String>,
+    conversation_id: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    /// `json` (Standard) oder `csv`
+    format: Option<String>,
+    /// Nur für `/api/usage/records`: die neuesten n Einträge
+    limit: Option<usize>,
+}
+
+impl UsageQuery {
+    fn filter(&self) -> UsageFilter {
+        UsageFilter {
+            user: self.user.clone(),
+            model: self.model.clone(),
+            conversation_id: self.conversation_id.clone(),
+            from: self.from,
+            to: self.to,
+        }
+    }
+
+    fn wants_csv(&self) -> bool {
+        self.format.as_deref() == Some("csv")
+    }
+}
+
+pub fn usage_routes(store: Arc<UsageStore>) -> Router {
+    Router::new()
+        .route("/api/usage", get(get_usage))
+        .route("/api/usage/records", get(get_records))
+        .with_state(store)
+}
+
+fn csv_response(filename: &str, body: String) -> axum::response::Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    ).into_response()
+}
+
+/// Verbrauch je Tag oder Monat, z.B. `/api/usage?period=month&group_by=model&format=csv`
+async fn get_usage(
+    State(store): State<Arc<UsageStore>>,
+    Query(query): Query<UsageQuery>
+) -> impl IntoResponse {
+    let buckets = store.summarize(&query.filter(), query.period, query.group_by);
+    if query.wants_csv() {
+        return csv_response("usage.csv", buckets_to_csv(&buckets));
+    }
+
+    Json(json!({
+        "period": query.period,
+        "group_by": query.group_by,
+        "currency": store.currency(),
+        "total": total(&buckets),
+        "buckets": buckets,
+        "status": "success"
+    })).into_response()
+}
+
+/// Einzelne Anfragen, neueste zuerst
+async fn get_records(
+    State(store): State<Arc<UsageStore>>,
+    Query(query): Query<UsageQuery>
+) -> impl IntoResponse {
+    let mut records = store.records(&query.filter());
+    records.reverse();
+    if let Some(limit) = query.limit {
+        records.truncate(limit);
+    }
+    if query.wants_csv() {
+        return csv_response("usage-records.csv", records_to_csv(&records));
+    }
+
+    Json(json!({
+        "currency": store.currency(),
+        "count": records.len(),
+        "records": records,
+        "status": "success"
+    })).into_response()
+}
@@ -1,97 +1,504 @@
-use axum::{extract::ws::{Message, WebSocket, WebSocketUpgrade}, response::IntoResponse, routing::get, Router, extract::State};
-use futures::{SinkExt, StreamExt};
+use axum::{extract::ws::{Message, WebSocket, WebSocketUpgrade}, http::HeaderMap, response::IntoResponse, routing::get, Router, extract::State};
+use futures::StreamExt;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use crate::client::{GlmClient, Message as ChatMessage};
-use serde_json::json;
+use crate::api::presets::apply_preset;
+use crate::api::settings::SettingsStore;
+use crate::client::{ChatOptions, GlmClient, Message as ChatMessage, ToolCall};
+use crate::functions::{approval, ApprovalRequest, FunctionRegistry, ToolOutput};
+use crate::logging::{current_request_id, with_request_id};
+use crate::metrics::Metrics;
+use crate::presets::PresetStore;
+use crate::shutdown::{Phase, Shutdown, StreamContext};
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, mpsc::UnboundedReceiver, watch};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
 
-pub fn websocket_route(client: Arc<GlmClient>) -> Router {
+#[derive(Clone)]
+pub struct WebSocketState {
+    pub client: Arc<GlmClient>,
+    pub registry: Arc<FunctionRegistry>,
+    pub metrics: Option<Arc<Metrics>>,
+    pub settings: Option<Arc<SettingsStore>>,
+    pub presets: Option<Arc<PresetStore>>,
+    pub shutdown: Option<Arc<Shutdown>>,
+}
+
+pub fn websocket_route(
+    client: Arc<GlmClient>,
+    registry: Arc<FunctionRegistry>,
+    metrics: Option<Arc<Metrics>>,
+    settings: Option<Arc<SettingsStore>>,
+    presets: Option<Arc<PresetStore>>,
+    shutdown: Option<Arc<Shutdown>>,
+) -> Router {
     Router::new()
         .route("/ws", get(websocket_handler))
-        .with_state(client)
+        .with_state(WebSocketState { client, registry, metrics, settings, presets, shutdown })
 }
 
 async fn websocket_handler(
     ws: WebSocketUpgrade,
-    State(client): State<Arc<GlmClient>>,
+    State(state): State<WebSocketState>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, client))
+    let user = super::user_id(&headers);
+    // Anzeigename für `{{user_name}}` wie bei `/api/chat`
+    let user_name = super::user_name(&headers, user.as_deref());
+    // Die Request-ID des Upgrades kennzeichnet die Verbindung
+    let connection_id = current_request_id().unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let span = tracing::info_span!("websocket", connection_id = %connection_id);
+    ws.on_upgrade(move |socket| handle_socket(socket, state, user, user_name, connection_id).instrument(span))
 }
 
-async fn handle_socket(mut socket: WebSocket, client: Arc<GlmClient>) {
-    while let Some(Ok(msg)) = socket.next().await {
+/// Beim Herunterfahren erhält der Client einmalig `server.shutdown`, neue
+/// Nachrichten werden abgelehnt und nach Ablauf der Frist wird die Verbindung geschlossen
+async fn handle_socket(
+    mut socket: WebSocket,
+    state: WebSocketState,
+    user: Option<String>,
+    user_name: Option<String>,
+    connection_id: String,
+) {
+    let _connection = state.metrics.as_ref().map(|metrics| metrics.websocket_connected());
+    let mut notice = ShutdownNotice::new(state.shutdown.as_deref());
+    let mut sequence = 0u64;
+    // Nur diese Verbindung darf Bestätigungen für ihre Tool-Aufrufe erteilen
+    let owner = uuid::Uuid::new_v4().to_string();
+    loop {
+        let msg = tokio::select! {
+            phase = notice.next() => {
+                if phase == Phase::Closing {
+                    let _ = socket.send(Message::Close(None)).await;
+                    return;
+                }
+                notice.send(&mut socket).await;
+                continue;
+            }
+            msg = socket.next() => match msg {
+                Some(Ok(msg)) => msg,
+                _ => return,
+            },
+        };
         if let Message::Text(text) = msg {
+            if state.shutdown.as_ref().is_some_and(|shutdown| shutdown.is_draining()) {
+                send_json(&mut socket, &json!({
+                    "type": "error",
+                    "message": "Server wird heruntergefahren"
+                })).await;
+                continue;
+            }
+
             // Parse incoming message
             let request: Result<serde_json::Value, _> = serde_json::from_str(&text);
-            
+
             match request {
                 Ok(data) => {
-                    if let Some(content) = data.get("message").and_then(|m| m.as_str()) {
-                        let messages = vec![ChatMessage::user(content)];
-                        
-                        // Handle streaming response
-                        match client.chat_completions_stream(messages).await {
-                            Ok(mut stream) => {
-                                while let Some(result) = stream.next().await {
-                                    match result {
-                                        Ok(response) => {
-                                            let json_response = json!({
-                                                "type": "stream_chunk",
-                                                "data": response
-                                            });
-                                            
-                                            if let Ok(json_str) = serde_json::to_string(&json_response) {
-                                                if socket.send(Message::Text(json_str)).await.is_err() {
-                                                    break;
-                                                }
-                                            }
-                                        },
-                                        Err(err) => {
-                                            let error_response = json!({
-                                                "type": "error",
-                                                "message": err.to_string()
-                                            });
-                                            
-                                            if let Ok(json_str) = serde_json::to_string(&error_response) {
-                                                let _ = socket.send(Message::Text(json_str)).await;
-                                            }
-                                            break;
-                                        }
-                                    }
-                                }
-                                
-                                // Send completion marker
-                                let completion = json!({
-                                    "type": "stream_complete"
-                                });
-                                
-                                if let Ok(json_str) = serde_json::to_string(&completion) {
-                                    let _ = socket.send(Message::Text(json_str)).await;
-                                }
-                            },
-                            Err(err) => {
-                                let error_response = json!({
-                                    "type": "error",
-                                    "message": format!("Stream-Fehler: {}", err)
-                                });
-                                
-                                if let Ok(json_str) = serde_json::to_string(&error_response) {
-                                    let _ = socket.send(Message::Text(json_str)).await;
-                                }
-                            }
+                    // Jede Nachricht erhält eine eigene Request-ID für Logs und GLM-Aufrufe
+                    sequence += 1;
+                    let request_id = format!("{}-{}", connection_id, sequence);
+                    let kind = data.get("type").and_then(|t| t.as_str()).unwrap_or("chat");
+                    let span = tracing::info_span!("ws_message", request_id = %request_id, kind);
+                    // Laufende Nachrichten verzögern das Herunterfahren bis zur Frist
+                    let _in_flight = state.shutdown.as_ref().map(|shutdown| shutdown.track());
+                    let handle = async {
+                        match kind {
+                            "tool.call" => handle_tool_call(&mut socket, &state.registry, &owner, &data).await,
+                            "tool.calls" => handle_tool_calls(&mut socket, &state.registry, &owner, &data).await,
+                            _ => handle_chat_message(&mut socket, &state, &mut notice, &data, user.as_deref(), user_name.as_deref()).await,
                         }
-                    }
-                },
+                    };
+                    with_request_id(request_id, handle.instrument(span)).await;
+                }
                 Err(_) => {
-                    let error_response = json!({
+                    send_json(&mut socket, &json!({
                         "type": "error",
                         "message": "Ungültiges JSON-Format"
-                    });
-                    
-                    if let Ok(json_str) = serde_json::to_string(&error_response) {
-                        let _ = socket.send(Message::Text(json_str)).await;
+                    })).await;
+                }
+            }
+        }
+    }
+}
+
+/// Meldet dem Client den Beginn des Herunterfahrens
+struct ShutdownNotice {
+    phases: Option<watch::Receiver<Phase>>,
+    drain_seconds: u64,
+    sent: bool,
+}
+
+impl ShutdownNotice {
+    fn new(shutdown: Option<&Shutdown>) -> Self {
+        Self {
+            phases: shutdown.map(Shutdown::subscribe),
+            drain_seconds: shutdown.map(|shutdown| shutdown.drain_period().as_secs()).unwrap_or_default(),
+            sent: false,
+        }
+    }
+
+    /// Wartet auf `Draining` (solange die Meldung aussteht) bzw. `Closing`
+    async fn next(&mut self) -> Phase {
+        let sent = self.sent;
+        let Some(phases) = &mut self.phases else { return std::future::pending().await };
+        let phase = phases
+            .wait_for(|phase| if sent { *phase == Phase::Closing } else { *phase != Phase::Running })
+            .await
+            .map(|phase| *phase);
+        match phase {
+            Ok(phase) => phase,
+            Err(_) => std::future::pending().await,
+        }
+    }
+
+    async fn send(&mut self, socket: &mut WebSocket) {
+        if !self.sent {
+            self.sent = true;
+            send_json(socket, &json!({
+                "type": "server.shutdown",
+                "drain_seconds": self.drain_seconds
+            })).await;
+        }
+    }
+}
+
+/// Sendet ein JSON-Objekt; gibt `false` zurück, wenn die Verbindung geschlossen ist
+async fn send_json(socket: &mut WebSocket, value: &Value) -> bool {
+    match serde_json::to_string(value) {
+        Ok(json_str) => socket.send(Message::Text(json_str)).await.is_ok(),
+        Err(_) => true,
+    }
+}
+
+/// Legacy-Chat: `{"message": "...", "thinking": bool, "thinking_budget": n}`.
+///
+/// Reasoning wird als `thinking` Event gesendet, `stream_chunk` enthält nur den
+/// Antworttext und `stream_complete` die vollständige Nachricht mit Reasoning.
+/// `conversation_id` und der Benutzer aus `X-User-Id` gehen in die Verbrauchserfassung ein;
+/// mit `preset` wird dessen System-Prompt vorangestellt (`{{user_name}}` aus
+/// `X-User-Name`, sonst der Benutzer), danach gelten die gespeicherten
+/// Einstellungen des Benutzers als Standard.
+/// Beim Herunterfahren wird `server.shutdown` auch während des Streams gesendet;
+/// nach Ablauf der Frist endet der Stream mit `finish_reason: "shutdown"`.
+async fn handle_chat_message(
+    socket: &mut WebSocket,
+    state: &WebSocketState,
+    notice: &mut ShutdownNotice,
+    data: &Value,
+    user: Option<&str>,
+    user_name: Option<&str>,
+) {
+    if let Some(content) = data.get("message").and_then(|m| m.as_str()) {
+        let mut messages = vec![ChatMessage::user(content)];
+        let mut options: ChatOptions = serde_json::from_value(data.clone()).unwrap_or_default();
+        if let Some(user) = user {
+            options.user = Some(user.to_string());
+        }
+        if let Err(err) = apply_preset(state.presets.as_deref(), data, user_name, &mut messages, &mut options) {
+            send_json(socket, &json!({
+                "type": "error",
+                "message": err.to_string()
+            })).await;
+            return;
+        }
+        if let Some(settings) = &state.settings {
+            settings.apply_defaults(&mut options).await;
+        }
+
+        // Handle streaming response
+        match state.client.chat_completions_stream_with(messages, &options).await {
+            Ok(stream) => {
+                let mut stream = match &state.shutdown {
+                    Some(shutdown) => shutdown.guard_stream(stream, StreamContext {
+                        user: options.user.clone(),
+                        conversation_id: options.conversation_id.clone(),
+                    }),
+                    None => stream,
+                };
+                let mut thinking = String::new();
+                let mut answer = String::new();
+
+                loop {
+                    let result = tokio::select! {
+                        _ = notice.next(), if !notice.sent => {
+                            notice.send(socket).await;
+                            continue;
+                        }
+                        result = stream.next() => match result {
+                            Some(result) => result,
+                            None => break,
+                        },
+                    };
+                    match result {
+                        Ok(mut response) => {
+                            // Reasoning als eigenes Ereignis, nicht im `stream_chunk`
+                            let reasoning = response.choices.first_mut().and_then(|choice| choice.delta.reasoning_content.take());
+                            if let Some(delta) = reasoning.filter(|delta| !delta.is_empty()) {
+                                thinking.push_str(&delta);
+                                if !send_json(socket, &json!({
+                                    "type": "thinking",
+                                    "content": delta
+                                })).await {
+                                    return;
+                                }
+                            }
+
+                            // Bis zum Ende lesen: nach finish_reason folgt noch das Chunk mit `usage`
+                            let Some(choice) = response.choices.first() else { continue };
+                            let finished = choice.finish_reason.is_some();
+                            if let Some(delta) = &choice.delta.content {
+                                answer.push_str(delta);
+                            } else if !finished {
+                                continue;
+                            }
+
+                            let json_response = json!({
+                                "type": "stream_chunk",
+                                "data": response
+                            });
+
+                            if !send_json(socket, &json_response).await {
+                                return;
+                            }
+                        },
+                        Err(err) => {
+                            send_json(socket, &json!({
+                                "type": "error",
+                                "message": err.to_string()
+                            })).await;
+                            break;
+                        }
                     }
                 }
+
+                let thinking = thinking.trim();
+                let message = if thinking.is_empty() {
+                    ChatMessage::assistant(answer)
+                } else {
+                    ChatMessage::assistant(answer.trim_start()).with_thinking(thinking)
+                };
+
+                // Send completion marker
+                send_json(socket, &json!({
+                    "type": "stream_complete",
+                    "message": message
+                })).await;
+            },
+            Err(err) => {
+                send_json(socket, &json!({
+                    "type": "error",
+                    "message": format!("Stream-Fehler: {}", err)
+                })).await;
             }
         }
     }
 }
+
+/// Führt ein Tool aus und streamt dessen Ausgabe als `tool.output` Events.
+///
+/// Erwartet `{"type": "tool.call", "id": "...", "name": "...", "arguments": {...}}`.
+/// Liegt das Tool oberhalb der Risikoschwelle, wird `tool.approval_required`
+/// mit einer vom Server vergebenen `id` gesendet und auf
+/// `{"type": "tool.approval", "id": "...", "approved": true}` gewartet.
+async fn handle_tool_call(socket: &mut WebSocket, registry: &Arc<FunctionRegistry>, owner: &str, data: &Value) {
+    let id = data.get("id")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let name = data.get("name").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let arguments: HashMap<String, Value> = match serde_json::from_value(data.get("arguments").cloned().unwrap_or_else(|| json!({}))) {
+        Ok(arguments) => arguments,
+        Err(_) => {
+            send_json(socket, &json!({
+                "type": "error",
+                "id": id,
+                "message": "Ungültige Tool-Argumente"
+            })).await;
+            return;
+        }
+    };
+
+    // Vor dem Start abonnieren, damit keine Bestätigungsanfrage verloren geht
+    let approval_requests = registry.approvals().map(|approvals| approvals.subscribe());
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let task = {
+        let registry = registry.clone();
+        let id = id.clone();
+        let name = name.clone();
+        let execution = async move { registry.execute_function_streaming(&id, &name, arguments, tx).await };
+        tokio::spawn(approval::with_owner(owner.to_string(), execution).in_current_span())
+    };
+
+    let call_ids = [id.clone()];
+    if let Some(result) = supervise_tool_task(socket, registry, owner, &call_ids, task, rx, approval_requests).await {
+        send_json(socket, &json!({
+            "type": "tool.result",
+            "id": id,
+            "name": name,
+            "result": result
+        })).await;
+    }
+}
+
+/// Führt mehrere Tool Calls einer Modellantwort parallel aus.
+///
+/// Erwartet `{"type": "tool.calls", "tool_calls": [ToolCall, ...]}` und antwortet mit
+/// `{"type": "tool.results", "results": [ToolCallResult, ...]}` in derselben Reihenfolge.
+async fn handle_tool_calls(socket: &mut WebSocket, registry: &Arc<FunctionRegistry>, owner: &str, data: &Value) {
+    let tool_calls: Vec<ToolCall> = match serde_json::from_value(data.get("tool_calls").cloned().unwrap_or_default()) {
+        Ok(tool_calls) => tool_calls,
+        Err(_) => {
+            send_json(socket, &json!({
+                "type": "error",
+                "message": "Ungültige Tool Calls"
+            })).await;
+            return;
+        }
+    };
+
+    let approval_requests = registry.approvals().map(|approvals| approvals.subscribe());
+    let call_ids: Vec<String> = tool_calls.iter().map(|call| call.id.clone()).collect();
+
+    // Keine Zwischenausgaben im Batch-Modus
+    let (_, rx) = tokio::sync::mpsc::unbounded_channel();
+    let task = {
+        let registry = registry.clone();
+        let execution = async move { registry.execute_tool_calls(&tool_calls).await };
+        tokio::spawn(approval::with_owner(owner.to_string(), execution).in_current_span())
+    };
+
+    if let Some(results) = supervise_tool_task(socket, registry, owner, &call_ids, task, rx, approval_requests).await {
+        send_json(socket, &json!({
+            "type": "tool.results",
+            "results": results
+        })).await;
+    }
+}
+
+/// Begleitet eine laufende Tool-Ausführung: leitet `tool.output` und
+/// `tool.approval_required` Events weiter, verarbeitet `tool.approval` Antworten
+/// für die eigenen Aufrufe von `owner` und bricht alle Aufrufe ab, wenn die
+/// Verbindung getrennt wird.
+///
+/// Gibt `None` zurück, wenn die Ausführung abgebrochen wurde.
+async fn supervise_tool_task<T>(
+    socket: &mut WebSocket,
+    registry: &FunctionRegistry,
+    owner: &str,
+    call_ids: &[String],
+    mut task: JoinHandle<T>,
+    mut outputs: UnboundedReceiver<ToolOutput>,
+    mut approval_requests: Option<broadcast::Receiver<ApprovalRequest>>,
+) -> Option<T> {
+    let output_id = call_ids.first().cloned().unwrap_or_default();
+    // Bereits gemeldete Anfragen, damit sie nach `Lagged` nicht doppelt kommen
+    let mut announced = HashSet::new();
+
+    let result = loop {
+        tokio::select! {
+            result = &mut task => break result,
+            Some(output) = outputs.recv() => {
+                let event = json!({
+                    "type": "tool.output",
+                    "id": output_id,
+                    "stream": output.stream,
+                    "data": output.data
+                });
+                if !send_json(socket, &event).await {
+                    cancel_tool_task(registry, owner, &task);
+                    return None;
+                }
+            }
+            Some(requests) = next_approval_requests(&mut approval_requests, registry, owner) => {
+                for request in requests {
+                    let own = request.owner.as_deref() == Some(owner) && call_ids.contains(&request.call_id);
+                    if own && announced.insert(request.id.clone()) {
+                        send_json(socket, &json!({
+                            "type": "tool.approval_required",
+                            "id": request.id,
+                            "call_id": request.call_id,
+                            "name": request.tool,
+                            "arguments": request.arguments,
+                            "risk_level": request.risk_level
+                        })).await;
+                    }
+                }
+            }
+            incoming = socket.next() => match incoming {
+                Some(Ok(Message::Text(text))) => handle_approval_reply(registry, owner, &text),
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => {
+                    cancel_tool_task(registry, owner, &task);
+                    return None;
+                }
+            },
+        }
+    };
+
+    // Restliche Ausgaben vor dem Ergebnis senden
+    while let Ok(output) = outputs.try_recv() {
+        send_json(socket, &json!({
+            "type": "tool.output",
+            "id": output_id,
+            "stream": output.stream,
+            "data": output.data
+        })).await;
+    }
+
+    match result {
+        Ok(result) => Some(result),
+        Err(err) => {
+            send_json(socket, &json!({
+                "type": "error",
+                "id": output_id,
+                "message": format!("Tool-Ausführung abgebrochen: {}", err)
+            })).await;
+            None
+        }
+    }
+}
+
+/// Verbindung getrennt: offene Bestätigungen ablehnen (wird protokolliert)
+/// und alle noch laufenden Aufrufe abbrechen
+fn cancel_tool_task<T>(registry: &FunctionRegistry, owner: &str, task: &JoinHandle<T>) {
+    if let Some(approvals) = registry.approvals() {
+        approvals.reject_all(owner, "Verbindung getrennt");
+    }
+    task.abort();
+}
+
+/// Nächste Bestätigungsanfrage; wurden Meldungen verpasst, weil der Kanal
+/// übergelaufen ist, stattdessen alle noch offenen Anfragen von `owner`
+async fn next_approval_requests(
+    receiver: &mut Option<broadcast::Receiver<ApprovalRequest>>,
+    registry: &FunctionRegistry,
+    owner: &str,
+) -> Option<Vec<ApprovalRequest>> {
+    let Some(receiver) = receiver else { return std::future::pending().await };
+    match receiver.recv().await {
+        Ok(request) => Some(vec![request]),
+        Err(broadcast::error::RecvError::Lagged(_)) => {
+            let pending = registry.approvals().map(|approvals| approvals.pending()).unwrap_or_default();
+            Some(pending.into_iter().filter(|request| request.owner.as_deref() == Some(owner)).collect())
+        }
+        Err(broadcast::error::RecvError::Closed) => None,
+    }
+}
+
+/// Verarbeitet `{"type": "tool.approval", "id": "...", "approved": bool, "reason": "..."}`;
+/// Anfragen anderer Verbindungen werden ignoriert
+fn handle_approval_reply(registry: &FunctionRegistry, owner: &str, text: &str) {
+    let Ok(reply) = serde_json::from_str::<Value>(text) else { return };
+    if reply.get("type").and_then(|t| t.as_str()) != Some("tool.approval") {
+        return;
+    }
+    let (Some(approvals), Some(id)) = (registry.approvals(), reply.get("id").and_then(|v| v.as_str())) else {
+        return;
+    };
+
+    let approved = reply.get("approved").and_then(|v| v.as_bool()).unwrap_or(false);
+    let reason = reply.get("reason").and_then(|v| v.as_str()).map(str::to_string);
+    approvals.resolve_as(owner, id, approved, reason);
+}
@@ -1,4 +1,4 @@
-use chatglm_web::client::{GlmClient, GlmConfig, GlmModel, Message};
+use chatglm_web::client::{GlmClient, GlmConfig, Message};
 use dotenv::dotenv;
 use tracing::{error, info};
 
@@ -53,7 +53,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             
             if let Some(choice) = response.choices.first() {
                 if let Some(content) = &choice.message.content {
-                    info!("Antwort: {}", content);
+                    info!("Antwort: {}", content.text());
                 } else {
                     info!("Antwort: [Leere Antwort]");
                 }
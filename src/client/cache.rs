@@ -0,0 +1,367 @@
+use super::types::{
+    ChatCompletionRequest, ChatCompletionResponse, Choice, Delta, Message, StreamChoice,
+    StreamingChatCompletionResponse, Usage,
+};
+use async_trait::async_trait;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+/// Umgang mit dem Antwort-Cache für eine einzelne Anfrage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CachePolicy {
+    /// Lesen und schreiben
+    #[default]
+    Use,
+    /// Nicht lesen, aber die neue Antwort speichern (`Cache-Control: no-cache`)
+    Refresh,
+    /// Cache vollständig umgehen (`Cache-Control: no-store`, `X-Cache-Bypass`)
+    Bypass,
+}
+
+impl CachePolicy {
+    pub fn reads(self) -> bool {
+        self == CachePolicy::Use
+    }
+
+    pub fn writes(self) -> bool {
+        self != CachePolicy::Bypass
+    }
+}
+
+/// Gespeicherte Antwort mit Zeitpunkt (Unix-Sekunden)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub response: ChatCompletionResponse,
+    pub stored_at: i64,
+}
+
+/// Speicher für Cache-Einträge
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, key: &str) -> Option<CacheEntry>;
+    async fn put(&self, key: &str, entry: CacheEntry);
+    async fn remove(&self, key: &str);
+}
+
+/// LRU-Cache im Arbeitsspeicher
+pub struct MemoryCache {
+    entries: Mutex<LruCache<String, CacheEntry>>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self { entries: Mutex::new(LruCache::new(capacity)) }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    async fn get(&self, key: &str) -> Option<CacheEntry> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    async fn put(&self, key: &str, entry: CacheEntry) {
+        self.entries.lock().unwrap().put(key.to_string(), entry);
+    }
+
+    async fn remove(&self, key: &str) {
+        self.entries.lock().unwrap().pop(key);
+    }
+}
+
+/// Cache auf der Festplatte, eine JSON-Datei pro Schlüssel.
+///
+/// Ein LRU-Index im Arbeitsspeicher begrenzt die Anzahl der Dateien auf
+/// `capacity`; verdrängte Einträge werden gelöscht.
+pub struct DiskCache {
+    dir: PathBuf,
+    index: Mutex<LruCache<String, ()>>,
+}
+
+impl DiskCache {
+    /// Übernimmt vorhandene Einträge, die neuesten zuletzt verwendet.
+    /// Überzählige und mit `ttl` abgelaufene Dateien werden dabei gelöscht.
+    pub fn open(dir: impl Into<PathBuf>, capacity: usize, ttl: Option<Duration>) -> Self {
+        let dir = dir.into();
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        let mut files: Vec<(SystemTime, String)> = std::fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let key = path.file_stem()?.to_str()?.to_string();
+                let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok()?;
+                (path.extension()? == "json").then_some((modified, key))
+            })
+            .collect();
+        files.sort();
+
+        let cache = Self { dir, index: Mutex::new(LruCache::new(capacity)) };
+        let expired_before = ttl.and_then(|ttl| SystemTime::now().checked_sub(ttl));
+        let mut index = cache.index.lock().unwrap();
+        for (modified, key) in files {
+            if expired_before.is_some_and(|expired_before| modified < expired_before) {
+                let _ = std::fs::remove_file(cache.entry_path(&key));
+            } else if let Some((evicted, _)) = index.push(key, ()) {
+                let _ = std::fs::remove_file(cache.entry_path(&evicted));
+            }
+        }
+        drop(index);
+        cache
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+#[async_trait]
+impl CacheBackend for DiskCache {
+    async fn get(&self, key: &str) -> Option<CacheEntry> {
+        // Nur indexierte Schlüssel lesen, das vermeidet Zugriffe bei Fehltreffern
+        self.index.lock().unwrap().get(key)?;
+        let data = tokio::fs::read(self.entry_path(key)).await.ok()?;
+        match serde_json::from_slice(&data) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                warn!("Cache-Eintrag {} ist beschädigt: {}", key, err);
+                self.remove(key).await;
+                None
+            }
+        }
+    }
+
+    async fn put(&self, key: &str, entry: CacheEntry) {
+        let result = async {
+            let data = serde_json::to_vec(&entry).map_err(std::io::Error::other)?;
+            tokio::fs::create_dir_all(&self.dir).await?;
+            tokio::fs::write(self.entry_path(key), data).await
+        }.await;
+        if let Err(err) = result {
+            warn!("Cache-Eintrag {} konnte nicht gespeichert werden: {}", key, err);
+            return;
+        }
+
+        let evicted = self.index.lock().unwrap().push(key.to_string(), ());
+        if let Some((evicted, _)) = evicted.filter(|(evicted, _)| evicted != key) {
+            let _ = tokio::fs::remove_file(self.entry_path(&evicted)).await;
+        }
+    }
+
+    async fn remove(&self, key: &str) {
+        self.index.lock().unwrap().pop(key);
+        let _ = tokio::fs::remove_file(self.entry_path(key)).await;
+    }
+}
+
+/// Cache für Antworten auf identische Anfragen.
+///
+/// Standardmäßig werden nur deterministische Anfragen (Temperatur 0)
+/// gespeichert, da sonst unterschiedliche Antworten erwartet werden.
+pub struct ResponseCache {
+    backend: Box<dyn CacheBackend>,
+    ttl: Option<Duration>,
+    deterministic_only: bool,
+}
+
+impl std::fmt::Debug for ResponseCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseCache")
+            .field("ttl", &self.ttl)
+            .field("deterministic_only", &self.deterministic_only)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResponseCache {
+    pub fn new(backend: Box<dyn CacheBackend>) -> Self {
+        Self { backend, ttl: None, deterministic_only: true }
+    }
+
+    pub fn memory(capacity: usize) -> Self {
+        Self::new(Box::new(MemoryCache::new(capacity)))
+    }
+
+    /// Festplatten-Cache mit höchstens `capacity` Einträgen, die nach `ttl` ablaufen
+    pub fn disk(dir: impl Into<PathBuf>, capacity: usize, ttl: Option<Duration>) -> Self {
+        Self::new(Box::new(DiskCache::open(dir, capacity, ttl))).with_ttl(ttl)
+    }
+
+    /// Lebensdauer der Einträge; `None` bedeutet unbegrenzt
+    pub fn with_ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn with_deterministic_only(mut self, deterministic_only: bool) -> Self {
+        self.deterministic_only = deterministic_only;
+        self
+    }
+
+    /// Schlüssel für die Anfrage oder `None`, wenn sie nicht gecacht werden soll
+    pub fn key_for(&self, request: &ChatCompletionRequest) -> Option<String> {
+        if self.deterministic_only && request.temperature.is_some_and(|temperature| temperature > 0.0) {
+            return None;
+        }
+        Some(cache_key(request))
+    }
+
+    pub async fn get(&self, key: &str) -> Option<ChatCompletionResponse> {
+        let entry = self.backend.get(key).await?;
+        if let Some(ttl) = self.ttl {
+            let age = chrono::Utc::now().timestamp() - entry.stored_at;
+            if age < 0 || age as u64 >= ttl.as_secs() {
+                self.backend.remove(key).await;
+                return None;
+            }
+        }
+        Some(entry.response)
+    }
+
+    /// Speichert die Antwort; Tool-Aufrufe werden nicht gecacht, da sie
+    /// Nebenwirkungen auslösen und nicht als Stream wiedergegeben werden können
+    pub async fn put(&self, key: &str, response: &ChatCompletionResponse) {
+        let has_tool_calls = response.choices.iter().any(|choice| {
+            choice.message.tool_calls.as_ref().is_some_and(|calls| !calls.is_empty())
+                || choice.finish_reason.as_deref() == Some("tool_calls")
+        });
+        if response.choices.is_empty() || has_tool_calls {
+            return;
+        }
+        self.backend.put(key, CacheEntry {
+            response: response.clone(),
+            stored_at: chrono::Utc::now().timestamp(),
+        }).await;
+    }
+}
+
+/// Kanonischer Hash über Modell, Nachrichten, Tools und Sampling-Parameter.
+///
+/// `stream`, `stream_options` und `user` gehören nicht dazu, damit gestreamte und normale
+/// Anfragen denselben Eintrag nutzen. Die Serialisierung über `serde_json::Value`
+/// sortiert die Objektschlüssel.
+pub fn cache_key(request: &ChatCompletionRequest) -> String {
+    let mut request = request.clone();
+    request.stream = None;
+    request.stream_options = None;
+    request.user = None;
+
+    let canonical = serde_json::to_value(&request)
+        .map(|value| value.to_string())
+        .unwrap_or_default();
+    let digest = Sha256::digest(canonical.as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Zerlegt eine gespeicherte Antwort in Stream-Chunks, damit sie auf den
+/// Streaming-Endpunkten wie eine Live-Antwort ausgegeben werden kann
+pub fn replay_chunks(response: &ChatCompletionResponse) -> Vec<StreamingChatCompletionResponse> {
+    const PIECE_CHARS: usize = 32;
+
+    let chunk = |index: u32, delta: Delta, finish_reason: Option<String>| StreamingChatCompletionResponse {
+        id: response.id.clone(),
+        object: "chat.completion.chunk".to_string(),
+        created: response.created,
+        model: response.model.clone(),
+        usage: None,
+        choices: vec![StreamChoice { index, delta, finish_reason }],
+    };
+    let delta = |content: Option<String>, reasoning_content: Option<String>| Delta {
+        content,
+        role: None,
+        reasoning_content,
+    };
+
+    let mut chunks = Vec::new();
+    for choice in &response.choices {
+        if let Some(thinking) = choice.message.thinking.as_ref().filter(|thinking| !thinking.is_empty()) {
+            chunks.push(chunk(choice.index, delta(None, Some(thinking.clone())), None));
+        }
+
+        let content = choice.message.text().unwrap_or_default();
+        let chars: Vec<char> = content.chars().collect();
+        for piece in chars.chunks(PIECE_CHARS) {
+            chunks.push(chunk(choice.index, delta(Some(piece.iter().collect()), None), None));
+        }
+
+        let finish_reason = choice.finish_reason.clone().unwrap_or_else(|| "stop".to_string());
+        chunks.push(chunk(choice.index, delta(None, None), Some(finish_reason)));
+    }
+    if let Some(last) = chunks.last_mut() {
+        last.usage = response.usage.clone();
+    }
+    chunks
+}
+
+/// Setzt eine gestreamte Antwort (erste Choice) wieder zusammen, um sie im
+/// Cache abzulegen
+#[derive(Debug, Default)]
+pub struct StreamRecorder {
+    id: String,
+    created: u64,
+    model: String,
+    content: String,
+    thinking: String,
+    finish_reason: Option<String>,
+    /// Kommt mit `include_usage` erst in einem Chunk nach `finish_reason`
+    usage: Option<Usage>,
+    failed: bool,
+}
+
+impl StreamRecorder {
+    /// Nimmt einen Chunk auf
+    pub fn push(&mut self, chunk: &StreamingChatCompletionResponse) {
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage.clone();
+        }
+        let Some(choice) = chunk.choices.iter().find(|choice| choice.index == 0) else { return };
+        if self.finish_reason.is_some() {
+            return;
+        }
+        if self.id.is_empty() {
+            self.id = chunk.id.clone();
+            self.created = chunk.created;
+            self.model = chunk.model.clone();
+        }
+        if let Some(content) = &choice.delta.content {
+            self.content.push_str(content);
+        }
+        if let Some(reasoning) = &choice.delta.reasoning_content {
+            self.thinking.push_str(reasoning);
+        }
+        self.finish_reason = choice.finish_reason.clone();
+    }
+
+    /// Der Stream ist mit einem Fehler abgebrochen; nichts wird gespeichert
+    pub fn fail(&mut self) {
+        self.failed = true;
+    }
+
+    /// Vollständige Antwort am Ende des Streams; `None`, wenn die erste
+    /// Choice nicht abgeschlossen wurde oder ein Fehler auftrat
+    pub fn finish(self) -> Option<ChatCompletionResponse> {
+        let finish_reason = self.finish_reason.filter(|_| !self.failed)?;
+        let mut message = Message::assistant(self.content);
+        if !self.thinking.is_empty() {
+            message.thinking = Some(self.thinking);
+        }
+
+        Some(ChatCompletionResponse {
+            id: self.id,
+            object: "chat.completion".to_string(),
+            created: self.created,
+            model: self.model,
+            choices: vec![Choice { index: 0, message, finish_reason: Some(finish_reason) }],
+            usage: self.usage,
+        })
+    }
+}
@@ -1,108 +1,663 @@
-use super::types::{ChatCompletionRequest, ChatCompletionResponse, GlmConfig, Message, StreamingChatCompletionResponse};
+use super::types::{
+    ChatCompletionRequest, ChatCompletionResponse, ChatOptions, EmbeddingRequest, EmbeddingResponse, EmbeddingUsage,
+    GlmConfig, Message, MAX_CANDIDATES,
+};
+use super::cache::{replay_chunks, CachePolicy, ResponseCache, StreamRecorder};
 use super::error::{GlmError, GlmResult, ApiErrorResponse};
-use reqwest::{Client, Response, Url};
+use super::keys::{KeyPool, KeyStatus};
+use super::streaming::{parse_sse_stream, StreamingResponse};
+use super::types::Usage;
+use super::usage::{estimate_prompt_tokens, estimate_tokens, StreamUsageTracker, UsageEvent, UsageSink};
+use crate::logging::{body_for_log, current_request_id, REQUEST_ID_HEADER};
+use crate::telemetry::trace_headers;
+use crate::metrics::Metrics;
+use crate::secrets::Secret;
 use futures::StreamExt;
-use std::time::Duration;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn, Instrument, Span};
 
 /// GLM API Client
 #[derive(Debug, Clone)]
 pub struct GlmClient {
     client: Client,
-    config: GlmConfig,
+    /// Von allen Klonen geteilt, damit neue Einstellungen überall gelten
+    config: Arc<RwLock<GlmConfig>>,
+    /// API-Schlüssel im Round-Robin, ebenfalls von allen Klonen geteilt
+    keys: Arc<KeyPool>,
+    cache: Option<Arc<ResponseCache>>,
+    usage_sinks: Vec<Arc<dyn UsageSink>>,
+    metrics: Option<Arc<Metrics>>,
+    log_bodies: bool,
 }
 
 impl GlmClient {
     /// Erstellt einen neuen GlmClient
     pub fn new(config: GlmConfig) -> GlmResult<Self> {
         // Validiere API-Key
-        if config.api_key.trim().is_empty() {
+        let keys = config.keys();
+        if keys.is_empty() {
             return Err(GlmError::ConfigError { 
                 message: "API-Key darf nicht leer sein".to_string() 
             });
         }
+        let keys = Arc::new(KeyPool::new(keys, config.key_cooldown));
 
         let client = Client::builder()
             .timeout(config.timeout)
             .build()
             .map_err(|err| GlmError::ConfigError { message: err.to_string() })?;
 
-        Ok(Self { client, config })
+        Ok(Self { client, config: Arc::new(RwLock::new(config)), keys, cache: None, usage_sinks: Vec::new(), metrics: None, log_bodies: false })
+    }
+
+    /// Aktuelle Einstellungen
+    pub fn config(&self) -> GlmConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Ersetzt die Einstellungen zur Laufzeit für alle Klone dieses Clients;
+    /// laufende Anfragen sind nicht betroffen
+    pub fn update_config(&self, config: GlmConfig) -> GlmResult<()> {
+        let keys = config.keys();
+        if keys.is_empty() {
+            return Err(GlmError::ConfigError {
+                message: "API-Key darf nicht leer sein".to_string()
+            });
+        }
+        self.keys.replace(keys, config.key_cooldown);
+        *self.config.write().unwrap() = config;
+        Ok(())
+    }
+
+    /// Zustand der API-Schlüssel (maskiert)
+    pub fn key_status(&self) -> Vec<KeyStatus> {
+        self.keys.status()
+    }
+
+    /// Aktiviert den Antwort-Cache für identische Anfragen
+    pub fn with_cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Meldet den Token-Verbrauch jeder Chat-Anfrage an `sink`
+    pub fn with_usage_sink(mut self, sink: Arc<dyn UsageSink>) -> Self {
+        self.usage_sinks.push(sink);
+        self
+    }
+
+    /// Erfasst Latenzen, Time-to-First-Token, Tokens und Fehler in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.usage_sinks.push(metrics.clone());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Gibt Nachrichten und Antworten im Debug-Log aus (sonst nur ihre Länge)
+    pub fn with_body_logging(mut self, enabled: bool) -> Self {
+        self.log_bodies = enabled;
+        self
     }
 
     /// Erstellt einen Chat Completion Request
     pub async fn chat_completions(&self, messages: Vec<Message>) -> GlmResult<ChatCompletionResponse> {
-        let request = ChatCompletionRequest::new(self.config.model.to_string(), messages)
-            .with_stream(self.config.stream)
-            .with_max_tokens(self.config.max_tokens)
-            .with_temperature(self.config.temperature)
-            .with_top_p(self.config.top_p)
-            .with_thinking(self.config.thinking_enabled);
+        self.chat_completions_with(messages, &ChatOptions::default()).await
+    }
 
-        let url = format!("{}/chat/completions", self.config.api_url.trim_end_matches('/'));
-        let url = Url::parse(&url).map_err(|err| GlmError::ConfigError { message: err.to_string() })?;
+    /// Chat Completion mit Optionen für diese Anfrage (z.B. Thinking);
+    /// mit `options.n` mehrere Kandidaten als `choices`
+    pub async fn chat_completions_with(&self, messages: Vec<Message>, options: &ChatOptions) -> GlmResult<ChatCompletionResponse> {
+        match options.n.unwrap_or(1) {
+            0 | 1 => self.single_completion(messages, options).await,
+            n => self.candidate_completions(messages, options, n).await,
+        }
+    }
 
-        let response = self.client
+    async fn single_completion(&self, messages: Vec<Message>, options: &ChatOptions) -> GlmResult<ChatCompletionResponse> {
+        // Die Antwort wird als Ganzes gelesen, auch wenn `stream` konfiguriert ist
+        let request = self.build_request(messages, false, options);
+        let span = self.request_span("chat", &request.model);
+        self.complete(request, options).instrument(span).await
+    }
+
+    /// `n` Antwortkandidaten: nativ über `n`, wenn die API es unterstützt;
+    /// fehlende Kandidaten werden parallel einzeln und am Cache vorbei angefragt
+    async fn candidate_completions(&self, messages: Vec<Message>, options: &ChatOptions, n: u32) -> GlmResult<ChatCompletionResponse> {
+        if n > MAX_CANDIDATES {
+            return Err(GlmError::InvalidRequest {
+                message: format!("n darf höchstens {} sein", MAX_CANDIDATES),
+            });
+        }
+
+        let mut responses = Vec::new();
+        if self.config.read().unwrap().native_candidates {
+            let mut request = self.build_request(messages.clone(), false, options);
+            request.n = Some(n);
+            let span = self.request_span("chat", &request.model);
+            responses.push(self.complete(request, options).instrument(span).await?);
+        }
+
+        let received: usize = responses.iter().map(|response| response.choices.len()).sum();
+        let single = ChatOptions { n: None, cache: CachePolicy::Bypass, ..options.clone() };
+        let missing = (n as usize).saturating_sub(received);
+        let extra = futures::future::try_join_all((0..missing).map(|_| self.single_completion(messages.clone(), &single))).await?;
+        responses.extend(extra);
+
+        let mut responses = responses.into_iter();
+        let mut merged = responses.next().ok_or_else(|| GlmError::ParsingError {
+            message: "Keine Antwortkandidaten erhalten".to_string(),
+        })?;
+        for response in responses {
+            merged.choices.extend(response.choices);
+            merged.usage = match (merged.usage, response.usage) {
+                (Some(total), Some(usage)) => Some(Usage {
+                    prompt_tokens: total.prompt_tokens + usage.prompt_tokens,
+                    completion_tokens: total.completion_tokens + usage.completion_tokens,
+                    total_tokens: total.total_tokens + usage.total_tokens,
+                }),
+                (total, usage) => total.or(usage),
+            };
+        }
+        merged.choices.truncate(n as usize);
+        for (index, choice) in merged.choices.iter_mut().enumerate() {
+            choice.index = index as u32;
+        }
+        Ok(merged)
+    }
+
+    async fn complete(&self, request: ChatCompletionRequest, options: &ChatOptions) -> GlmResult<ChatCompletionResponse> {
+        self.log_request(&request);
+        let cache_key = self.cache_key(&request, options);
+        if let Some(cached) = self.cached_response(cache_key.as_deref(), options).await {
+            self.record_usage(&request, &cached, options, true);
+            return Ok(cached);
+        }
+
+        let started = Instant::now();
+        let result = self.send_chat_request(&request).await;
+        self.observe_upstream("chat", &request.model, started, &result);
+        let response = result?;
+        self.log_response(&response, started);
+
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key) {
+            if options.cache.writes() {
+                cache.put(key, &response).await;
+            }
+        }
+        self.record_usage(&request, &response, options, false);
+        Ok(response)
+    }
+
+    async fn send_chat_request(&self, request: &ChatCompletionRequest) -> GlmResult<ChatCompletionResponse> {
+        let response = self.send_json(self.completions_url()?, request).await?;
+        Self::parse_response(response).await
+    }
+
+    /// Sendet `body` per POST mit dem nächsten Schlüssel. Wird ein Schlüssel
+    /// abgelehnt oder ist sein Kontingent erschöpft, wird er deaktiviert und
+    /// die Anfrage mit dem nächsten wiederholt; andere Fehlerstatus werden
+    /// direkt als `GlmError` zurückgegeben.
+    async fn send_json<T: serde::Serialize + ?Sized>(&self, url: Url, body: &T) -> GlmResult<Response> {
+        for _ in 0..self.keys.len() {
+            let Some(key) = self.keys.next() else { break };
+            let response = self.post(url.clone(), &key).json(body).send().await?;
+            if response.status().is_success() {
+                return Ok(response);
+            }
+            let error = Self::error_response(response).await;
+            if !self.keys.disable(&key, &error) {
+                return Err(error);
+            }
+        }
+        Err(self.keys.exhausted_error())
+    }
+
+    /// POST an die API mit Authentifizierung, Trace-Kontext und der Request-ID
+    /// der laufenden Anfrage
+    fn post(&self, url: Url, key: &Secret) -> RequestBuilder {
+        let mut builder = self.client
             .post(url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .json(&request)
-            .send()
-            .await?;
+            .header("Authorization", format!("Bearer {}", key.expose()))
+            .timeout(self.config.read().unwrap().timeout);
+        for (name, value) in trace_headers() {
+            builder = builder.header(name, value);
+        }
+        match current_request_id() {
+            Some(id) => builder.header(REQUEST_ID_HEADER, id),
+            None => builder,
+        }
+    }
 
-        Self::handle_response(response).await
+    /// Span für einen Aufruf der API; er liegt im Span der HTTP- oder
+    /// WebSocket-Anfrage und trägt deren Request-ID
+    fn request_span(&self, endpoint: &str, model: &str) -> Span {
+        let span = tracing::info_span!(
+            "glm_request",
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+            endpoint,
+            model,
+            request_id = tracing::field::Empty,
+            prompt_tokens = tracing::field::Empty,
+            completion_tokens = tracing::field::Empty,
+        );
+        if let Some(id) = current_request_id() {
+            span.record("request_id", id.as_str());
+        }
+        span
     }
 
-    /// Handhabt die API-Antwort
-    async fn handle_response(response: Response) -> GlmResult<ChatCompletionResponse> {
-        let status = response.status();
+    fn log_request(&self, request: &ChatCompletionRequest) {
+        if !tracing::enabled!(tracing::Level::DEBUG) {
+            return;
+        }
+        let body = serde_json::to_string(&request.messages).unwrap_or_default();
+        debug!(
+            messages = request.messages.len(),
+            stream = request.stream.unwrap_or(false),
+            body = %body_for_log(&body, self.log_bodies),
+            "GLM-Anfrage"
+        );
+    }
+
+    fn log_response(&self, response: &ChatCompletionResponse, started: Instant) {
+        if let Some(usage) = &response.usage {
+            record_usage_on_span(&Span::current(), usage);
+        }
+        if !tracing::enabled!(tracing::Level::DEBUG) {
+            return;
+        }
+        let choice = response.choices.first();
+        let content = choice
+            .and_then(|choice| choice.message.content.as_ref())
+            .map(|content| content.text())
+            .unwrap_or_default();
+        debug!(
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            finish_reason = choice.and_then(|choice| choice.finish_reason.as_deref()),
+            total_tokens = response.usage.as_ref().map(|usage| usage.total_tokens),
+            content = %body_for_log(&content, self.log_bodies),
+            "GLM-Antwort"
+        );
+    }
+
+    /// Misst eine abgeschlossene Anfrage an die API und zählt Fehler
+    fn observe_upstream<T>(&self, endpoint: &str, model: &str, started: Instant, result: &GlmResult<T>) {
+        if let Err(err) = result {
+            Span::current().record("otel.status_code", "ERROR");
+            warn!(endpoint, error = %err, "GLM-Anfrage fehlgeschlagen");
+        }
+        let Some(metrics) = &self.metrics else { return };
+        metrics.observe_upstream(endpoint, model, result.is_ok(), started.elapsed());
+        if let Err(err) = result {
+            metrics.count_error(err);
+        }
+    }
+
+    /// Meldet den Verbrauch einer vollständigen Antwort; fehlt `usage`,
+    /// wird aus Prompt und Antworttext geschätzt
+    fn record_usage(&self, request: &ChatCompletionRequest, response: &ChatCompletionResponse, options: &ChatOptions, cached: bool) {
+        if self.usage_sinks.is_empty() {
+            return;
+        }
+
+        let (usage, estimated) = match &response.usage {
+            Some(usage) => (usage.clone(), false),
+            None => {
+                let prompt_tokens = estimate_prompt_tokens(&request.messages);
+                let completion_tokens = response.choices
+                    .iter()
+                    .map(|choice| {
+                        let message = &choice.message;
+                        estimate_tokens(&message.content.as_ref().map(|content| content.text()).unwrap_or_default())
+                            + estimate_tokens(message.thinking.as_deref().unwrap_or_default())
+                    })
+                    .sum::<u32>();
+                (Usage { prompt_tokens, completion_tokens, total_tokens: prompt_tokens + completion_tokens }, true)
+            }
+        };
+        let model = if response.model.is_empty() { request.model.clone() } else { response.model.clone() };
+
+        let event = UsageEvent {
+            model,
+            usage,
+            estimated,
+            cached,
+            streamed: false,
+            user: options.user.clone(),
+            conversation_id: options.conversation_id.clone(),
+        };
+        for sink in &self.usage_sinks {
+            sink.record(event.clone());
+        }
+    }
+
+    /// Hängt Cache-Aufzeichnung, Verbrauchserfassung und Metriken an einen
+    /// Stream; `started` ist der Beginn der API-Anfrage (nicht bei Cache-Treffern)
+    fn instrument_stream(
+        &self,
+        stream: StreamingResponse,
+        request: &ChatCompletionRequest,
+        options: &ChatOptions,
+        cache_key: Option<String>,
+        started: Option<Instant>,
+    ) -> StreamingResponse {
+        let cached = started.is_none();
+        let recorder = match (self.cache.clone(), cache_key) {
+            (Some(cache), Some(key)) if options.cache.writes() && !cached => {
+                Some((cache, key, Arc::new(Mutex::new(StreamRecorder::default()))))
+            }
+            _ => None,
+        };
+        let mut tracker = (!self.usage_sinks.is_empty()).then(|| {
+            StreamUsageTracker::new(self.usage_sinks.clone(), request.model.clone(), &request.messages, options, cached)
+        });
+        let mut timing = match (&self.metrics, started) {
+            (Some(metrics), Some(started)) => Some(StreamTiming {
+                metrics: metrics.clone(),
+                model: request.model.clone(),
+                started,
+                first_token: false,
+                failed: false,
+            }),
+            _ => None,
+        };
+        // Chunks werden erst beim Senden der Antwort gelesen, also außerhalb
+        // des Anfrage-Spans; er wird deshalb für jeden Chunk betreten
+        let span = Span::current();
+        let log_bodies = self.log_bodies;
+        let mut content = String::new();
+        let chunk_recorder = recorder.as_ref().map(|(_, _, recorder)| recorder.clone());
+
+        let stream = stream.inspect(move |chunk| {
+            let _entered = span.enter();
+            if let Some(timing) = &mut timing {
+                timing.observe(chunk);
+            }
+            if let Some(recorder) = &chunk_recorder {
+                match chunk {
+                    Ok(chunk) => recorder.lock().unwrap().push(chunk),
+                    Err(_) => recorder.lock().unwrap().fail(),
+                }
+            }
+            let Ok(chunk) = chunk else { return };
+            if let Some(usage) = &chunk.usage {
+                record_usage_on_span(&span, usage);
+            }
+            if let Some(choice) = chunk.choices.first() {
+                if log_bodies {
+                    content.push_str(choice.delta.content.as_deref().unwrap_or_default());
+                }
+                if let Some(finish_reason) = &choice.finish_reason {
+                    debug!(finish_reason = %finish_reason, content = %body_for_log(&content, log_bodies), "GLM-Stream beendet");
+                }
+            }
+            if let Some(tracker) = &mut tracker {
+                tracker.observe(chunk);
+            }
+        });
+        // Vollständig empfangene Antworten am Ende des Streams für spätere
+        // Anfragen speichern, erst dann ist auch der Verbrauch bekannt
+        let finish = futures::stream::once(async move {
+            if let Some((cache, key, recorder)) = recorder {
+                let recorder = std::mem::take(&mut *recorder.lock().unwrap());
+                if let Some(response) = recorder.finish() {
+                    tokio::spawn(async move { cache.put(&key, &response).await });
+                }
+            }
+            None
+        });
+        StreamingResponse::new(stream.chain(finish.filter_map(futures::future::ready)))
+    }
+
+    fn cache_key(&self, request: &ChatCompletionRequest, options: &ChatOptions) -> Option<String> {
+        let cache = self.cache.as_ref()?;
+        if !options.cache.reads() && !options.cache.writes() {
+            return None;
+        }
+        cache.key_for(request)
+    }
+
+    async fn cached_response(&self, key: Option<&str>, options: &ChatOptions) -> Option<ChatCompletionResponse> {
+        let (cache, key) = (self.cache.as_ref()?, key?);
+        if !options.cache.reads() {
+            return None;
+        }
+        let response = cache.get(key).await?;
+        debug!("Antwort aus dem Cache: {}", key);
+        Some(response)
+    }
+
+    fn build_request(&self, messages: Vec<Message>, stream: bool, options: &ChatOptions) -> ChatCompletionRequest {
+        let config = self.config.read().unwrap();
+        let model = options.model.clone().unwrap_or_else(|| config.model.to_string());
+        let mut request = ChatCompletionRequest::new(model, messages)
+            .with_stream(stream)
+            .with_max_tokens(options.max_tokens.unwrap_or(config.max_tokens))
+            .with_temperature(options.temperature.unwrap_or(config.temperature))
+            .with_top_p(options.top_p.unwrap_or(config.top_p))
+            .with_thinking(options.thinking.unwrap_or(config.thinking_enabled))
+            .with_thinking_budget(options.thinking_budget.or(config.thinking_budget));
+        if !options.tools.is_empty() {
+            request.tools = Some(options.tools.clone());
+        }
+        request
+    }
+
+    fn completions_url(&self) -> GlmResult<Url> {
+        self.endpoint_url("chat/completions")
+    }
+
+    fn endpoint_url(&self, path: &str) -> GlmResult<Url> {
+        let url = format!("{}/{}", self.config.read().unwrap().api_url.trim_end_matches('/'), path);
+        Url::parse(&url).map_err(|err| GlmError::ConfigError { message: err.to_string() })
+    }
+
+    /// Wandelt Fehlerstatus in `GlmError` um und deserialisiert die Antwort
+    async fn parse_response<T: serde::de::DeserializeOwned>(response: Response) -> GlmResult<T> {
+        if !response.status().is_success() {
+            return Err(Self::error_response(response).await);
+        }
         let text = response.text().await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// `GlmError` für eine Fehlerantwort; ohne bekannten Fehlertyp im Body
+    /// entscheidet der HTTP-Status
+    async fn error_response(response: Response) -> GlmError {
+        let status = response.status().as_u16();
+        let text = match response.text().await {
+            Ok(text) => text,
+            Err(err) => return err.into(),
+        };
+        match serde_json::from_str::<ApiErrorResponse>(&text) {
+            Ok(error_response) => match GlmError::from(error_response) {
+                GlmError::Unknown { message } => GlmError::from_api_response(status, message),
+                error => error,
+            },
+            Err(_) => GlmError::from_api_response(status, text),
+        }
+    }
+
+    /// Erstellt Embeddings für alle Texte; große Eingaben werden in Batches
+    /// von `embedding_batch_size` aufgeteilt und wieder zusammengeführt
+    pub async fn embeddings(&self, inputs: Vec<String>, model: Option<&str>) -> GlmResult<EmbeddingResponse> {
+        let (default_model, batch_size) = {
+            let config = self.config.read().unwrap();
+            (config.embedding_model.clone(), config.embedding_batch_size)
+        };
+        let model = model.unwrap_or(&default_model).to_string();
+        if inputs.is_empty() {
+            return Err(GlmError::InvalidRequest { message: "Keine Eingaben für Embeddings".to_string() });
+        }
+
+        let mut merged = EmbeddingResponse {
+            model: model.clone(),
+            data: Vec::with_capacity(inputs.len()),
+            usage: None,
+            dimensions: 0,
+        };
 
-        if !status.is_success() {
-            let api_error = match serde_json::from_str::<ApiErrorResponse>(&text) {
-                Ok(error_response) => GlmError::from(error_response),
-                Err(_) => GlmError::from_api_response(status.as_u16(), text),
+        for batch in inputs.chunks(batch_size.max(1)) {
+            let request = EmbeddingRequest {
+                model: model.clone(),
+                input: batch.to_vec(),
+                dimensions: None,
             };
-            return Err(api_error);
+            let started = Instant::now();
+            let span = self.request_span("embeddings", &model);
+            let result = self.send_embedding_request(&request).instrument(span).await;
+            self.observe_upstream("embeddings", &model, started, &result);
+            let mut batch_response = result?;
+
+            if batch_response.data.len() != batch.len() {
+                return Err(GlmError::ParsingError {
+                    message: format!("{} Embeddings für {} Eingaben erhalten", batch_response.data.len(), batch.len()),
+                });
+            }
+
+            // Indizes auf die Gesamteingabe beziehen
+            let offset = merged.data.len();
+            batch_response.data.sort_by_key(|data| data.index);
+            for (position, mut data) in batch_response.data.into_iter().enumerate() {
+                data.index = offset + position;
+                merged.data.push(data);
+            }
+
+            if let Some(usage) = batch_response.usage {
+                let total = merged.usage.get_or_insert_with(EmbeddingUsage::default);
+                total.prompt_tokens += usage.prompt_tokens;
+                total.total_tokens += usage.total_tokens;
+            }
+            merged.model = batch_response.model;
         }
 
-        let completion_response: ChatCompletionResponse = serde_json::from_str(&text)?;
-        Ok(completion_response)
-    }
+        merged.dimensions = merged.data.first().map(|data| data.embedding.len()).unwrap_or(0);
+        if merged.data.iter().any(|data| data.embedding.len() != merged.dimensions) {
+            return Err(GlmError::ParsingError { message: "Embeddings mit unterschiedlicher Dimension".to_string() });
+        }
 
-    /// Handhabt die API-Antwort für Streaming
-    pub async fn chat_completions_stream<'a>(&'a self, messages: Vec<Message>) -> GlmResult<impl futures::Stream<Item = GlmResult<StreamingChatCompletionResponse>> + 'a> {
-        let request = ChatCompletionRequest::new(self.config.model.to_string(), messages)
-            .with_stream(true)
-            .with_max_tokens(self.config.max_tokens)
-            .with_temperature(self.config.temperature)
-            .with_top_p(self.config.top_p)
-            .with_thinking(self.config.thinking_enabled);
+        Ok(merged)
+    }
 
-        let url = format!("{}/chat/completions", self.config.api_url.trim_end_matches('/'));
-        let url = Url::parse(&url).map_err(|err| GlmError::ConfigError { message: err.to_string() })?;
+    async fn send_embedding_request(&self, request: &EmbeddingRequest) -> GlmResult<EmbeddingResponse> {
+        let response = self.send_json(self.endpoint_url("embeddings")?, request).await?;
+        Self::parse_response(response).await
+    }
 
+    /// Prüft die Erreichbarkeit der API mit `GET /models`. Abgelehnte
+    /// Authentifizierung gilt als Fehler, jede andere HTTP-Antwort als erreichbar.
+    pub async fn probe(&self, timeout: Duration) -> GlmResult<()> {
+        let Some(key) = self.keys.next() else {
+            return Err(self.keys.exhausted_error());
+        };
         let response = self.client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key.clone()))
-            .json(&request)
+            .get(self.endpoint_url("models")?)
+            .header("Authorization", format!("Bearer {}", key.expose()))
+            .timeout(timeout)
             .send()
-            .await?
-            .error_for_status()?;
+            .await?;
+        let status = response.status();
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            let error = GlmError::from_api_response(status.as_u16(), response.text().await.unwrap_or_default());
+            self.keys.disable(&key, &error);
+            return Err(error);
+        }
+        Ok(())
+    }
 
-        Ok(Self::stream_response(response).await)
+    /// Handhabt die API-Antwort für Streaming
+    pub async fn chat_completions_stream(&self, messages: Vec<Message>) -> GlmResult<StreamingResponse> {
+        self.chat_completions_stream_with(messages, &ChatOptions::default()).await
     }
 
-    /// Streamt die Antwort
-    async fn stream_response(response: Response) -> impl futures::Stream<Item = GlmResult<StreamingChatCompletionResponse>> {
-        let stream = response.bytes_stream().map(|result| {
-            result.map_err(GlmError::from).and_then(|bytes| {
-                let text = String::from_utf8(bytes.to_vec()).map_err(|err| GlmError::StreamingError {
-                    message: format!("UTF-8 Dekodierungsfehler: {}", err),
-                })?;
-                serde_json::from_str::<StreamingChatCompletionResponse>(&text).map_err(GlmError::from)
-            })
-        });
+    /// Streaming mit Optionen für diese Anfrage
+    pub async fn chat_completions_stream_with(&self, messages: Vec<Message>, options: &ChatOptions) -> GlmResult<StreamingResponse> {
+        Self::check_stream_candidates(options)?;
+        let request = self.build_request(messages, true, options);
+        let span = self.request_span("chat_stream", &request.model);
+        self.stream(request, options).instrument(span).await
+    }
+
+    /// Ein Stream liefert genau einen Kandidaten; `n > 1` wird abgelehnt,
+    /// statt stillschweigend nur eine Antwort zu senden
+    pub fn check_stream_candidates(options: &ChatOptions) -> GlmResult<()> {
+        if options.n.unwrap_or(1) > 1 {
+            return Err(GlmError::InvalidRequest {
+                message: "Mehrere Kandidaten (n > 1) sind nur ohne Streaming möglich".to_string(),
+            });
+        }
+        Ok(())
+    }
 
-        stream
+    async fn stream(&self, request: ChatCompletionRequest, options: &ChatOptions) -> GlmResult<StreamingResponse> {
+        self.log_request(&request);
+        let cache_key = self.cache_key(&request, options);
+        // Gespeicherte Antworten als synthetischen Stream wiedergeben
+        if let Some(cached) = self.cached_response(cache_key.as_deref(), options).await {
+            let chunks = replay_chunks(&cached).into_iter().map(Ok);
+            let stream = StreamingResponse::new(futures::stream::iter(chunks));
+            return Ok(self.instrument_stream(stream, &request, options, cache_key, None));
+        }
+
+        let started = Instant::now();
+        let result = self.open_stream(&request).await;
+        // Erfolgreiche Streams werden erst an ihrem Ende gemessen
+        if result.is_err() {
+            self.observe_upstream("chat_stream", &request.model, started, &result);
+        }
+        let response = result?;
+
+        let stream = StreamingResponse::new(parse_sse_stream(response.bytes_stream()));
+        Ok(self.instrument_stream(stream, &request, options, cache_key, Some(started)))
+    }
+
+    async fn open_stream(&self, request: &ChatCompletionRequest) -> GlmResult<Response> {
+        self.send_json(self.completions_url()?, request).await
+    }
+}
+
+/// Token-Zahlen als Attribute des `glm_request`-Spans
+fn record_usage_on_span(span: &Span, usage: &Usage) {
+    span.record("prompt_tokens", usage.prompt_tokens);
+    span.record("completion_tokens", usage.completion_tokens);
+}
+
+/// Misst Time-to-First-Token und Gesamtdauer eines Streams; die Dauer wird
+/// beim Verwerfen erfasst, also auch bei abgebrochenen Streams
+struct StreamTiming {
+    metrics: Arc<Metrics>,
+    model: String,
+    started: Instant,
+    first_token: bool,
+    failed: bool,
+}
+
+impl StreamTiming {
+    fn observe(&mut self, chunk: &GlmResult<super::types::StreamingChatCompletionResponse>) {
+        match chunk {
+            Ok(chunk) => {
+                let has_token = chunk.choices.iter().any(|choice| {
+                    choice.delta.content.as_deref().is_some_and(|text| !text.is_empty())
+                        || choice.delta.reasoning_content.as_deref().is_some_and(|text| !text.is_empty())
+                });
+                if has_token && !self.first_token {
+                    self.first_token = true;
+                    self.metrics.observe_time_to_first_token(&self.model, self.started.elapsed());
+                }
+            }
+            Err(err) => {
+                self.failed = true;
+                self.metrics.count_error(err);
+            }
+        }
     }
 }
 
+impl Drop for StreamTiming {
+    fn drop(&mut self) {
+        self.metrics.observe_upstream("chat_stream", &self.model, !self.failed, self.started.elapsed());
+    }
+}
@@ -18,6 +18,9 @@ pub enum GlmError {
     #[error("Rate Limit erreicht: {message}")]
     RateLimitError { message: String },
 
+    #[error("Kontingent erschöpft: {message}")]
+    QuotaExceeded { message: String },
+
     #[error("Modell nicht verfügbar: {model}")]
     ModelNotAvailable { model: String },
 
@@ -51,6 +54,7 @@ impl GlmError {
     pub fn from_api_response(status: u16, message: String) -> Self {
         match status {
             401 => Self::AuthenticationError,
+            402 => Self::QuotaExceeded { message },
             429 => Self::RateLimitError { message },
             400..=499 => Self::InvalidRequest { message },
             500..=599 => Self::ServerError { message },
@@ -58,6 +62,42 @@ impl GlmError {
         }
     }
 
+    /// Name der Variante, z.B. als Label für Fehlerzähler
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            Self::HttpError(_) => "http_error",
+            Self::JsonError(_) => "json_error",
+            Self::ApiError { .. } => "api_error",
+            Self::AuthenticationError => "authentication_error",
+            Self::RateLimitError { .. } => "rate_limit_error",
+            Self::QuotaExceeded { .. } => "quota_exceeded",
+            Self::ModelNotAvailable { .. } => "model_not_available",
+            Self::InvalidRequest { .. } => "invalid_request",
+            Self::ServerError { .. } => "server_error",
+            Self::StreamingError { .. } => "streaming_error",
+            Self::TimeoutError => "timeout_error",
+            Self::ConfigError { .. } => "config_error",
+            Self::ParsingError { .. } => "parsing_error",
+            Self::NetworkError { .. } => "network_error",
+            Self::Unknown { .. } => "unknown",
+        }
+    }
+
+    /// Passender HTTP-Status, wenn der Fehler an API-Clients weitergereicht wird
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Self::ApiError { status, .. } => *status,
+            Self::AuthenticationError => 401,
+            Self::RateLimitError { .. } => 429,
+            Self::QuotaExceeded { .. } => 429,
+            Self::ModelNotAvailable { .. } => 404,
+            Self::InvalidRequest { .. } => 400,
+            Self::TimeoutError => 504,
+            Self::ConfigError { .. } => 500,
+            _ => 502,
+        }
+    }
+
     /// Prüft, ob der Fehler wiederholbar ist
     pub fn is_retryable(&self) -> bool {
         match self {
@@ -111,11 +151,24 @@ pub struct ApiErrorDetails {
     pub param: Option<String>,
 }
 
+/// Fehlercodes für ein erschöpftes Guthaben oder Kontingent
+/// (OpenAI-kompatibel bzw. Zhipu `1113`)
+const QUOTA_CODES: [&str; 3] = ["insufficient_quota", "quota_exceeded", "1113"];
+
 impl From<ApiErrorResponse> for GlmError {
     fn from(error_response: ApiErrorResponse) -> Self {
-        let message = error_response.error.message;
-        
-        match error_response.error.error_type.as_deref() {
+        let details = error_response.error;
+        let message = details.message;
+
+        let is_quota = [details.code.as_deref(), details.error_type.as_deref()]
+            .into_iter()
+            .flatten()
+            .any(|code| QUOTA_CODES.contains(&code));
+        if is_quota {
+            return Self::QuotaExceeded { message };
+        }
+
+        match details.error_type.as_deref() {
             Some("invalid_request_error") => Self::InvalidRequest { message },
             Some("authentication_error") => Self::AuthenticationError,
             Some("rate_limit_error") => Self::RateLimitError { message },
@@ -0,0 +1,162 @@
+use super::error::GlmError;
+use crate::secrets::Secret;
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Grund, aus dem ein Schlüssel nicht verwendet wird
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Disabled {
+    /// Von der API abgelehnt; erst nach einer neuen Konfiguration wieder aktiv
+    Rejected,
+    /// Kontingent erschöpft; nach der Pause wieder aktiv
+    Quota { until: Instant },
+}
+
+#[derive(Debug)]
+struct KeyState {
+    key: Secret,
+    disabled: Option<Disabled>,
+}
+
+impl KeyState {
+    fn available(&self, now: Instant) -> bool {
+        match self.disabled {
+            None => true,
+            Some(Disabled::Rejected) => false,
+            Some(Disabled::Quota { until }) => now >= until,
+        }
+    }
+}
+
+/// Zustand eines Schlüssels für Health-Checks und Logs
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyStatus {
+    /// Maskierter Schlüssel
+    pub key: String,
+    pub available: bool,
+    /// `rejected` oder `quota_exceeded`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    /// Verbleibende Pause in Sekunden
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_in: Option<u64>,
+}
+
+/// API-Schlüssel, die reihum verwendet werden.
+///
+/// Schlüssel, die mit [`GlmError::AuthenticationError`] abgelehnt werden,
+/// bleiben bis zur nächsten Konfiguration deaktiviert; bei
+/// [`GlmError::QuotaExceeded`] pausiert ein Schlüssel für `cooldown`.
+#[derive(Debug)]
+pub struct KeyPool {
+    keys: RwLock<Vec<KeyState>>,
+    cooldown: RwLock<Duration>,
+    next: AtomicUsize,
+}
+
+impl KeyPool {
+    pub fn new(keys: Vec<Secret>, cooldown: Duration) -> Self {
+        Self {
+            keys: RwLock::new(keys.into_iter().map(|key| KeyState { key, disabled: None }).collect()),
+            cooldown: RwLock::new(cooldown),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Übernimmt neue Schlüssel. Pausen wegen erschöpfter Kontingente bleiben
+    /// für weiter konfigurierte Schlüssel bestehen, abgelehnte Schlüssel
+    /// werden wieder versucht.
+    pub fn replace(&self, keys: Vec<Secret>, cooldown: Duration) {
+        let mut states = self.keys.write().unwrap();
+        let next = keys
+            .into_iter()
+            .map(|key| {
+                let disabled = states
+                    .iter()
+                    .find(|state| state.key == key)
+                    .and_then(|state| state.disabled)
+                    .filter(|disabled| matches!(disabled, Disabled::Quota { .. }));
+                KeyState { key, disabled }
+            })
+            .collect();
+        *states = next;
+        *self.cooldown.write().unwrap() = cooldown;
+    }
+
+    /// Anzahl konfigurierter Schlüssel (auch deaktivierter)
+    pub fn len(&self) -> usize {
+        self.keys.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Nächster verfügbarer Schlüssel im Round-Robin
+    pub fn next(&self) -> Option<Secret> {
+        let states = self.keys.read().unwrap();
+        if states.is_empty() {
+            return None;
+        }
+        let now = Instant::now();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..states.len())
+            .map(|offset| &states[(start + offset) % states.len()])
+            .find(|state| state.available(now))
+            .map(|state| state.key.clone())
+    }
+
+    /// Deaktiviert `key`, wenn `error` auf einen ungültigen Schlüssel oder ein
+    /// erschöpftes Kontingent hinweist; gibt zurück, ob er deaktiviert wurde
+    pub fn disable(&self, key: &Secret, error: &GlmError) -> bool {
+        let disabled = match error {
+            GlmError::AuthenticationError => Disabled::Rejected,
+            GlmError::QuotaExceeded { .. } => Disabled::Quota { until: Instant::now() + *self.cooldown.read().unwrap() },
+            _ => return false,
+        };
+        let mut states = self.keys.write().unwrap();
+        let Some(state) = states.iter_mut().find(|state| state.key == *key) else {
+            return false;
+        };
+        state.disabled = Some(disabled);
+        let remaining = states.iter().filter(|state| state.available(Instant::now())).count();
+        warn!(key = %key, remaining, "API-Schlüssel deaktiviert: {}", error);
+        true
+    }
+
+    /// Fehler, wenn kein Schlüssel mehr verfügbar ist: erschöpftes
+    /// Kontingent, falls ein Schlüssel nur pausiert, sonst abgelehnte Schlüssel
+    pub fn exhausted_error(&self) -> GlmError {
+        let states = self.keys.read().unwrap();
+        if states.iter().any(|state| matches!(state.disabled, Some(Disabled::Quota { .. }))) {
+            GlmError::QuotaExceeded { message: "Kontingent aller API-Schlüssel erschöpft".to_string() }
+        } else {
+            GlmError::AuthenticationError
+        }
+    }
+
+    /// Zustand aller Schlüssel mit maskierten Werten
+    pub fn status(&self) -> Vec<KeyStatus> {
+        let now = Instant::now();
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .map(|state| {
+                let available = state.available(now);
+                let (reason, retry_in) = match state.disabled {
+                    _ if available => (None, None),
+                    Some(Disabled::Rejected) => (Some("rejected"), None),
+                    Some(Disabled::Quota { until }) => {
+                        (Some("quota_exceeded"), Some(until.saturating_duration_since(now).as_secs()))
+                    }
+                    None => (None, None),
+                };
+                KeyStatus { key: state.key.masked(), available, reason, retry_in }
+            })
+            .collect()
+    }
+}
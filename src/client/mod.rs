@@ -1,9 +1,16 @@
 pub mod types;
+pub mod cache;
+#[allow(clippy::module_inception)]
 pub mod client;
 pub mod error;
+pub mod keys;
 pub mod streaming;
+pub mod usage;
 
 pub use client::GlmClient;
 pub use types::*;
 pub use error::GlmError;
-pub use streaming::StreamingResponse;
+pub use keys::{KeyPool, KeyStatus};
+pub use cache::{CacheBackend, CachePolicy, DiskCache, MemoryCache, ResponseCache};
+pub use streaming::{StreamEvent, StreamingResponse};
+pub use usage::{estimate_tokens, UsageEvent, UsageSink};
@@ -1,10 +1,27 @@
 use super::error::{GlmError, GlmResult};
-use super::types::StreamingChatCompletionResponse;
-use futures::{Stream, StreamExt};
+use super::types::{Message, StreamingChatCompletionResponse};
+use futures::{stream, Stream, StreamExt};
+use serde::Serialize;
 use serde_json;
+use std::collections::VecDeque;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+/// Aufbereitetes Ereignis eines Antwort-Streams
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// Reasoning-Text
+    Thinking { content: String },
+    /// Antworttext
+    Content { content: String },
+    /// Ende des Streams mit der vollständigen Nachricht
+    Done {
+        message: Message,
+        finish_reason: Option<String>,
+    },
+}
+
 /// Wrapper für Streaming-Antworten
 pub struct StreamingResponse {
     inner: Pin<Box<dyn Stream<Item = GlmResult<StreamingChatCompletionResponse>> + Send>>,
@@ -20,28 +37,89 @@ impl StreamingResponse {
         }
     }
 
-    /// Sammelt alle Streaming-Chunks zu einer vollständigen Antwort
-    pub async fn collect_content(mut self) -> GlmResult<String> {
+    /// Sammelt alle Streaming-Chunks zu einer vollständigen Antwort (ohne Reasoning)
+    pub async fn collect_content(self) -> GlmResult<String> {
+        let mut events = Box::pin(self.events());
         let mut content = String::new();
-        
-        while let Some(chunk_result) = self.next().await {
-            let chunk = chunk_result?;
-            
-            if let Some(choice) = chunk.choices.first() {
-                if let Some(delta_content) = &choice.delta.content {
-                    content.push_str(delta_content);
-                }
-                
-                // Prüfe auf Ende des Streams
-                if choice.finish_reason.is_some() {
-                    break;
-                }
+
+        while let Some(event) = events.next().await {
+            if let StreamEvent::Content { content: delta } = event? {
+                content.push_str(&delta);
             }
         }
-        
+
         Ok(content)
     }
 
+    /// Sammelt den Stream zu einer Assistenten-Nachricht inklusive Reasoning;
+    /// liest bis zum Ende, damit Verbrauch und Cache-Eintrag erfasst werden
+    pub async fn collect_message(self) -> GlmResult<Message> {
+        let mut events = Box::pin(self.events());
+        let mut message = None;
+
+        while let Some(event) = events.next().await {
+            if let StreamEvent::Done { message: done, .. } = event? {
+                message = Some(done);
+            }
+        }
+
+        Ok(message.unwrap_or_else(|| Message::assistant("")))
+    }
+
+    /// Wandelt die Chunks in getrennte Reasoning-/Antwort-Ereignisse um und
+    /// schließt mit `StreamEvent::Done` ab
+    pub fn events(self) -> impl Stream<Item = GlmResult<StreamEvent>> + Send {
+        let state = EventState {
+            inner: self,
+            thinking: String::new(),
+            content: String::new(),
+            queue: VecDeque::new(),
+            answered: false,
+            finished: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(event) = state.queue.pop_front() {
+                    return Some((event, state));
+                }
+                if state.finished {
+                    return None;
+                }
+
+                match state.inner.next().await {
+                    // Nach `Done` wird der Rest nur noch gelesen, damit das
+                    // abschließende Chunk mit `usage` erfasst wird
+                    Some(_) if state.answered => {}
+                    None if state.answered => state.finished = true,
+                    Some(Ok(chunk)) => {
+                        let Some(choice) = chunk.choices.into_iter().next() else { continue };
+                        if let Some(thinking) = choice.delta.reasoning_content.filter(|thinking| !thinking.is_empty()) {
+                            state.thinking.push_str(&thinking);
+                            state.queue.push_back(Ok(StreamEvent::Thinking { content: thinking }));
+                        }
+                        if let Some(content) = choice.delta.content {
+                            state.content.push_str(&content);
+                            state.queue.push_back(Ok(StreamEvent::Content { content }));
+                        }
+                        // Prüfe auf Ende des Streams
+                        if choice.finish_reason.is_some() {
+                            state.finish(choice.finish_reason);
+                        }
+                    }
+                    Some(Err(err)) => {
+                        state.finished = true;
+                        state.queue.push_back(Err(err));
+                    }
+                    None => {
+                        state.finish(None);
+                        state.finished = true;
+                    }
+                }
+            }
+        })
+    }
+
     /// Verarbeitet jedes Chunk mit einer Callback-Funktion
     pub async fn for_each<F>(mut self, mut callback: F) -> GlmResult<()>
     where
@@ -63,6 +141,33 @@ impl StreamingResponse {
     }
 }
 
+struct EventState {
+    inner: StreamingResponse,
+    thinking: String,
+    content: String,
+    queue: VecDeque<GlmResult<StreamEvent>>,
+    /// `Done` wurde ausgegeben, es folgen höchstens noch Chunks ohne Inhalt
+    answered: bool,
+    finished: bool,
+}
+
+impl EventState {
+    fn finish(&mut self, finish_reason: Option<String>) {
+        let thinking = self.thinking.trim();
+        let mut message = Message::assistant(if thinking.is_empty() {
+            self.content.clone()
+        } else {
+            self.content.trim_start().to_string()
+        });
+        if !thinking.is_empty() {
+            message = message.with_thinking(thinking);
+        }
+
+        self.queue.push_back(Ok(StreamEvent::Done { message, finish_reason }));
+        self.answered = true;
+    }
+}
+
 impl Stream for StreamingResponse {
     type Item = GlmResult<StreamingChatCompletionResponse>;
 
@@ -71,6 +176,44 @@ impl Stream for StreamingResponse {
     }
 }
 
+/// Zerlegt einen Byte-Stream im SSE-Format in Chunks; Zeilen und
+/// UTF-8-Zeichen dürfen über Netzwerkpakete verteilt sein
+pub fn parse_sse_stream<S, B, E>(bytes: S) -> impl Stream<Item = GlmResult<StreamingChatCompletionResponse>> + Send
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]>,
+    E: Into<GlmError>,
+{
+    bytes
+        .scan(Vec::new(), |buffer: &mut Vec<u8>, result| {
+            let items = match result {
+                Ok(bytes) => {
+                    buffer.extend_from_slice(bytes.as_ref());
+                    let mut items = Vec::new();
+                    while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                        let line: Vec<u8> = buffer.drain(..=pos).collect();
+                        let line = match String::from_utf8(line) {
+                            Ok(line) => line,
+                            Err(err) => {
+                                items.push(Err(GlmError::StreamingError {
+                                    message: format!("UTF-8 Dekodierungsfehler: {}", err),
+                                }));
+                                continue;
+                            }
+                        };
+                        if let Some(item) = parse_sse_line(line.trim_end_matches(['\r', '\n'])) {
+                            items.push(item);
+                        }
+                    }
+                    items
+                }
+                Err(err) => vec![Err(err.into())],
+            };
+            futures::future::ready(Some(stream::iter(items)))
+        })
+        .flatten()
+}
+
 /// Hilfsfunktion zum Parsen von Server-Sent Events
 pub fn parse_sse_line(line: &str) -> Option<GlmResult<StreamingChatCompletionResponse>> {
     // Überspringe leere Zeilen und Kommentare
@@ -100,7 +243,6 @@ pub fn parse_sse_line(line: &str) -> Option<GlmResult<StreamingChatCompletionRes
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::client::types::{Delta, Role, StreamChoice};
 
     #[test]
     fn test_parse_sse_line() {
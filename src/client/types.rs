@@ -1,9 +1,11 @@
+use crate::secrets::Secret;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Verfügbare GLM-4.5 Modelle
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum GlmModel {
+    #[default]
     #[serde(rename = "glm-4.5")]
     Glm45,
     #[serde(rename = "glm-4.5-32k")]
@@ -12,18 +14,21 @@ pub enum GlmModel {
     Glm45Turbo,
 }
 
-impl Default for GlmModel {
-    fn default() -> Self {
-        Self::Glm45
+impl GlmModel {
+    pub const ALL: [GlmModel; 3] = [GlmModel::Glm45, GlmModel::Glm4532K, GlmModel::Glm45Turbo];
+
+    /// Modell zur ID, z.B. `"glm-4.5-turbo"`
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|model| model.to_string() == id)
     }
 }
 
-impl ToString for GlmModel {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for GlmModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            GlmModel::Glm45 => "glm-4.5".to_string(),
-            GlmModel::Glm4532K => "glm-4.5-32k".to_string(),
-            GlmModel::Glm45Turbo => "glm-4.5-turbo".to_string(),
+            GlmModel::Glm45 => write!(f, "glm-4.5"),
+            GlmModel::Glm4532K => write!(f, "glm-4.5-32k"),
+            GlmModel::Glm45Turbo => write!(f, "glm-4.5-turbo"),
         }
     }
 }
@@ -38,7 +43,7 @@ pub enum Role {
 }
 
 /// Tool Call für Function Calling
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolCall {
     pub id: String,
     #[serde(rename = "type")]
@@ -47,7 +52,7 @@ pub struct ToolCall {
 }
 
 /// Function Call Details
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FunctionCall {
     pub name: String,
     pub arguments: String,
@@ -59,27 +64,134 @@ pub struct ToolCallResult {
     pub tool_call_id: String,
     pub output: String,
     pub error: Option<String>,
+    /// Ausführungsdauer in Millisekunden
+    #[serde(default)]
+    pub duration_ms: u64,
 }
 
-/// Message Content (kann Text oder Tool Calls enthalten)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Message Content (kann Text, multimodale Teile oder Tool Calls enthalten)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum MessageContent {
     Text(String),
+    Parts(Vec<ContentPart>),
     ToolCalls(Vec<ToolCall>),
 }
 
+impl MessageContent {
+    /// Reiner Textinhalt, `None` bei multimodalen Inhalten
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            MessageContent::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Alle Textanteile, z.B. für Logging oder Token-Schätzungen
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            MessageContent::ToolCalls(_) => String::new(),
+        }
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        MessageContent::Text(text.to_string())
+    }
+}
+
+impl From<Vec<ContentPart>> for MessageContent {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        MessageContent::Parts(parts)
+    }
+}
+
+impl PartialEq<str> for MessageContent {
+    fn eq(&self, other: &str) -> bool {
+        self.as_text() == Some(other)
+    }
+}
+
+impl PartialEq<String> for MessageContent {
+    fn eq(&self, other: &String) -> bool {
+        self.as_text() == Some(other.as_str())
+    }
+}
+
+impl PartialEq<&str> for MessageContent {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_text() == Some(*other)
+    }
+}
+
+/// Teil einer multimodalen Nachricht im Vision-Format der GLM-API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    /// Bild per URL oder als `data:<mime>;base64,...`
+    ImageUrl { image_url: ImageUrl },
+    /// Verweis auf eine hochgeladene Datei; wird vor dem Senden aufgelöst
+    File { file: FileRef },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageUrl {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileRef {
+    pub file_id: String,
+}
+
+impl ContentPart {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    pub fn image_url(url: impl Into<String>) -> Self {
+        ContentPart::ImageUrl { image_url: ImageUrl { url: url.into() } }
+    }
+
+    /// Bild aus Base64-Daten als Data-URL
+    pub fn image_base64(mime_type: &str, data: impl AsRef<str>) -> Self {
+        Self::image_url(format!("data:{};base64,{}", mime_type, data.as_ref()))
+    }
+
+    pub fn file(file_id: impl Into<String>) -> Self {
+        ContentPart::File { file: FileRef { file_id: file_id.into() } }
+    }
+}
+
 /// Chat-Nachricht
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: Role,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub content: Option<String>,
+    pub content: Option<MessageContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Reasoning des Modells (upstream `reasoning_content`)
+    #[serde(default, alias = "reasoning_content", skip_serializing_if = "Option::is_none")]
     pub thinking: Option<String>,
 }
 
@@ -87,7 +199,7 @@ impl Message {
     pub fn system(content: impl Into<String>) -> Self {
         Self {
             role: Role::System,
-            content: Some(content.into()),
+            content: Some(MessageContent::Text(content.into())),
             tool_calls: None,
             tool_call_id: None,
             thinking: None,
@@ -97,7 +209,7 @@ impl Message {
     pub fn user(content: impl Into<String>) -> Self {
         Self {
             role: Role::User,
-            content: Some(content.into()),
+            content: Some(MessageContent::Text(content.into())),
             tool_calls: None,
             tool_call_id: None,
             thinking: None,
@@ -107,7 +219,7 @@ impl Message {
     pub fn assistant(content: impl Into<String>) -> Self {
         Self {
             role: Role::Assistant,
-            content: Some(content.into()),
+            content: Some(MessageContent::Text(content.into())),
             tool_calls: None,
             tool_call_id: None,
             thinking: None,
@@ -127,13 +239,29 @@ impl Message {
     pub fn tool_result(tool_call_id: String, content: impl Into<String>) -> Self {
         Self {
             role: Role::System, // Tool results are typically system messages
-            content: Some(content.into()),
+            content: Some(MessageContent::Text(content.into())),
             tool_calls: None,
             tool_call_id: Some(tool_call_id),
             thinking: None,
         }
     }
 
+    /// Benutzernachricht mit Text, Bildern oder Dateiverweisen
+    pub fn user_with_parts(parts: Vec<ContentPart>) -> Self {
+        Self {
+            role: Role::User,
+            content: Some(MessageContent::Parts(parts)),
+            tool_calls: None,
+            tool_call_id: None,
+            thinking: None,
+        }
+    }
+
+    /// Reiner Textinhalt der Nachricht
+    pub fn text(&self) -> Option<&str> {
+        self.content.as_ref().and_then(MessageContent::as_text)
+    }
+
     pub fn with_thinking(mut self, thinking: impl Into<String>) -> Self {
         self.thinking = Some(thinking.into());
         self
@@ -145,6 +273,9 @@ impl Message {
 pub struct ThinkingConfig {
     #[serde(rename = "type")]
     pub thinking_type: ThinkingType,
+    /// Maximale Anzahl Tokens für das Reasoning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_tokens: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -158,6 +289,7 @@ impl Default for ThinkingConfig {
     fn default() -> Self {
         Self {
             thinking_type: ThinkingType::Enabled,
+            budget_tokens: None,
         }
     }
 }
@@ -258,6 +390,18 @@ pub struct ChatCompletionRequest {
     pub logit_bias: Option<HashMap<String, f32>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+    /// Anzahl Antwortkandidaten, falls die API sie in einer Anfrage liefert
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+/// Optionen für gestreamte Antworten
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamOptions {
+    /// Tatsächlichen Verbrauch im letzten Chunk mitsenden
+    pub include_usage: bool,
 }
 
 impl ChatCompletionRequest {
@@ -276,11 +420,15 @@ impl ChatCompletionRequest {
             frequency_penalty: None,
             logit_bias: None,
             user: None,
+            n: None,
+            stream_options: None,
         }
     }
 
+    /// Streams fordern den Verbrauch an, damit er nicht geschätzt werden muss
     pub fn with_stream(mut self, stream: bool) -> Self {
         self.stream = Some(stream);
+        self.stream_options = stream.then_some(StreamOptions { include_usage: true });
         self
     }
 
@@ -306,10 +454,21 @@ impl ChatCompletionRequest {
             } else {
                 ThinkingType::Disabled
             },
+            budget_tokens: None,
         });
         self
     }
 
+    /// Begrenzt das Reasoning; wirkt nur bei aktiviertem Thinking
+    pub fn with_thinking_budget(mut self, budget_tokens: Option<u32>) -> Self {
+        if let Some(thinking) = &mut self.thinking {
+            if matches!(thinking.thinking_type, ThinkingType::Enabled) {
+                thinking.budget_tokens = budget_tokens;
+            }
+        }
+        self
+    }
+
     pub fn with_user(mut self, user: impl Into<String>) -> Self {
         self.user = Some(user.into());
         self
@@ -317,7 +476,7 @@ impl ChatCompletionRequest {
 }
 
 /// Usage-Statistiken
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
@@ -333,10 +492,13 @@ pub struct Choice {
 }
 
 /// Delta für Streaming-Responses
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Delta {
     pub content: Option<String>,
     pub role: Option<Role>,
+    /// Reasoning-Anteil des Chunks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
 }
 
 /// Choice für Streaming-Responses
@@ -358,6 +520,44 @@ pub struct ChatCompletionResponse {
     pub usage: Option<Usage>,
 }
 
+/// Optionen pro Anfrage, die die Client-Konfiguration überschreiben
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatOptions {
+    /// Modell und Sampling für diese Anfrage (Standard: Werte aus `GlmConfig`)
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Thinking ein-/ausschalten (Standard: `GlmConfig.thinking_enabled`)
+    #[serde(default)]
+    pub thinking: Option<bool>,
+    /// Token-Budget für das Reasoning (Standard: `GlmConfig.thinking_budget`)
+    #[serde(default)]
+    pub thinking_budget: Option<u32>,
+    /// Umgang mit dem Antwort-Cache (`"use"`, `"refresh"` oder `"bypass"`)
+    #[serde(default)]
+    pub cache: super::cache::CachePolicy,
+    /// Benutzer und Unterhaltung für die Verbrauchserfassung
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Tools, die mit der Anfrage gesendet werden (z.B. aus einem Preset)
+    #[serde(skip)]
+    pub tools: Vec<ToolDefinition>,
+    /// Anzahl Antwortkandidaten (höchstens [`MAX_CANDIDATES`]); Streams
+    /// lehnen mehr als einen ab
+    #[serde(default)]
+    pub n: Option<u32>,
+}
+
+/// Höchstzahl an Antwortkandidaten pro Anfrage
+pub const MAX_CANDIDATES: u32 = 8;
+
 /// Streaming Chat-Completion-Response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamingChatCompletionResponse {
@@ -365,13 +565,61 @@ pub struct StreamingChatCompletionResponse {
     pub object: String,
     pub created: u64,
     pub model: String,
+    /// Verbrauch, meist nur im letzten Chunk
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
     pub choices: Vec<StreamChoice>,
 }
 
+/// Embeddings-Request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+}
+
+/// Ein Vektor aus einer Embeddings-Antwort
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingData {
+    pub index: usize,
+    pub embedding: Vec<f32>,
+    #[serde(default = "default_embedding_object")]
+    pub object: String,
+}
+
+fn default_embedding_object() -> String {
+    "embedding".to_string()
+}
+
+/// Usage-Statistiken für Embeddings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingUsage {
+    pub prompt_tokens: u32,
+    pub total_tokens: u32,
+}
+
+/// Embeddings-Response (bei Batching zusammengeführt)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    pub model: String,
+    pub data: Vec<EmbeddingData>,
+    #[serde(default)]
+    pub usage: Option<EmbeddingUsage>,
+    /// Dimension der Vektoren (0 bei leerer Antwort)
+    #[serde(default)]
+    pub dimensions: usize,
+}
+
 /// Client-Konfiguration
 #[derive(Debug, Clone)]
 pub struct GlmConfig {
-    pub api_key: String,
+    pub api_key: Secret,
+    /// Weitere Schlüssel, die reihum verwendet werden
+    pub api_keys: Vec<Secret>,
+    /// Pause eines Schlüssels nach einem Kontingentfehler
+    pub key_cooldown: std::time::Duration,
     pub api_url: String,
     pub model: GlmModel,
     pub max_tokens: u32,
@@ -379,13 +627,24 @@ pub struct GlmConfig {
     pub top_p: f32,
     pub stream: bool,
     pub thinking_enabled: bool,
+    /// Standard-Token-Budget für das Reasoning
+    pub thinking_budget: Option<u32>,
+    /// Standardmodell für `/embeddings`
+    pub embedding_model: String,
+    /// Maximale Anzahl Texte pro Embeddings-Request
+    pub embedding_batch_size: usize,
+    /// Die API liefert mit `n` mehrere Kandidaten in einer Anfrage; sonst
+    /// werden Kandidaten parallel einzeln angefragt
+    pub native_candidates: bool,
     pub timeout: std::time::Duration,
 }
 
 impl Default for GlmConfig {
     fn default() -> Self {
         Self {
-            api_key: String::new(),
+            api_key: Secret::default(),
+            api_keys: Vec::new(),
+            key_cooldown: std::time::Duration::from_secs(3600),
             api_url: "https://api.z.ai/v1".to_string(),
             model: GlmModel::default(),
             max_tokens: 4096,
@@ -393,6 +652,10 @@ impl Default for GlmConfig {
             top_p: 0.9,
             stream: false,
             thinking_enabled: true,
+            thinking_budget: None,
+            embedding_model: "embedding-3".to_string(),
+            embedding_batch_size: 64,
+            native_candidates: false,
             timeout: std::time::Duration::from_secs(30),
         }
     }
@@ -402,8 +665,16 @@ impl GlmConfig {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
         use std::env;
 
-        let api_key = env::var("GLM_API_KEY")
-            .map_err(|_| "GLM_API_KEY Umgebungsvariable nicht gefunden")?;
+        // Docker Secrets: ein Schlüssel pro Zeile
+        let api_keys = match env::var("GLM_API_KEY_FILE") {
+            Ok(path) if !path.trim().is_empty() => crate::secrets::read_secret_file(path.trim())
+                .map_err(|err| format!("GLM_API_KEY_FILE kann nicht gelesen werden: {}", err))?,
+            _ => Vec::new(),
+        };
+        let api_key = env::var("GLM_API_KEY").map(Secret::from).unwrap_or_default();
+        if api_key.is_empty() && api_keys.is_empty() {
+            return Err("GLM_API_KEY Umgebungsvariable nicht gefunden".into());
+        }
 
         let api_url = env::var("GLM_API_URL")
             .unwrap_or_else(|_| "https://api.z.ai/v1".to_string());
@@ -411,12 +682,7 @@ impl GlmConfig {
         let model = env::var("GLM_MODEL")
             .unwrap_or_else(|_| "glm-4.5".to_string());
         
-        let model = match model.as_str() {
-            "glm-4.5" => GlmModel::Glm45,
-            "glm-4.5-32k" => GlmModel::Glm4532K,
-            "glm-4.5-turbo" => GlmModel::Glm45Turbo,
-            _ => GlmModel::Glm45,
-        };
+        let model = GlmModel::from_id(&model).unwrap_or_default();
 
         let max_tokens = env::var("GLM_MAX_TOKENS")
             .unwrap_or_else(|_| "4096".to_string())
@@ -443,8 +709,22 @@ impl GlmConfig {
             .parse()
             .unwrap_or(true);
 
+        let thinking_budget = env::var("GLM_THINKING_BUDGET")
+            .ok()
+            .and_then(|value| value.parse().ok());
+
+        let embedding_model = env::var("GLM_EMBEDDING_MODEL")
+            .unwrap_or_else(|_| "embedding-3".to_string());
+
+        let native_candidates = env::var("GLM_NATIVE_CANDIDATES")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+
         Ok(Self {
             api_key,
+            api_keys,
+            key_cooldown: std::time::Duration::from_secs(3600),
             api_url,
             model,
             max_tokens,
@@ -452,15 +732,30 @@ impl GlmConfig {
             top_p,
             stream,
             thinking_enabled,
+            thinking_budget,
+            embedding_model,
+            embedding_batch_size: 64,
+            native_candidates,
             timeout: std::time::Duration::from_secs(30),
         })
     }
 
-    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+    pub fn with_api_key(mut self, api_key: impl Into<Secret>) -> Self {
         self.api_key = api_key.into();
         self
     }
 
+    /// Weitere Schlüssel für die Rotation
+    pub fn with_api_keys(mut self, api_keys: Vec<Secret>) -> Self {
+        self.api_keys = api_keys;
+        self
+    }
+
+    /// Alle Schlüssel ohne leere Einträge und Duplikate, `api_key` zuerst
+    pub fn keys(&self) -> Vec<Secret> {
+        crate::secrets::distinct(std::iter::once(&self.api_key).chain(&self.api_keys))
+    }
+
     pub fn with_model(mut self, model: GlmModel) -> Self {
         self.model = model;
         self
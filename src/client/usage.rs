@@ -0,0 +1,114 @@
+use super::types::{ChatOptions, Message, StreamingChatCompletionResponse, Usage};
+use std::sync::Arc;
+
+/// Verbrauch einer einzelnen Anfrage
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageEvent {
+    pub model: String,
+    pub usage: Usage,
+    /// Token-Zahlen wurden geschätzt, weil die API keine geliefert hat
+    pub estimated: bool,
+    /// Antwort kam aus dem Cache und hat keine API-Kosten verursacht
+    pub cached: bool,
+    pub streamed: bool,
+    pub user: Option<String>,
+    pub conversation_id: Option<String>,
+}
+
+/// Empfänger für den Verbrauch aller Chat-Anfragen des Clients
+pub trait UsageSink: Send + Sync + std::fmt::Debug {
+    fn record(&self, event: UsageEvent);
+}
+
+/// Grobe Schätzung der Token-Zahl: etwa vier ASCII-Zeichen pro Token,
+/// andere Zeichen (z.B. CJK) zählen stärker
+pub fn estimate_tokens(text: &str) -> u32 {
+    let ascii = text.chars().filter(char::is_ascii).count() as u32;
+    let other = text.chars().count() as u32 - ascii;
+    ascii.div_ceil(4) + other.div_ceil(2)
+}
+
+/// Geschätzte Prompt-Tokens inklusive eines kleinen Aufschlags pro Nachricht
+pub fn estimate_prompt_tokens(messages: &[Message]) -> u32 {
+    messages
+        .iter()
+        .map(|message| 4 + message.content.as_ref().map(|content| estimate_tokens(&content.text())).unwrap_or(0))
+        .sum()
+}
+
+/// Erfasst den Verbrauch eines Streams. Liefert die API im letzten Chunk
+/// keine `usage`, wird aus dem empfangenen Text geschätzt. Erfasst wird beim
+/// Verwerfen, damit auch abgebrochene Streams gezählt werden.
+pub(crate) struct StreamUsageTracker {
+    sinks: Vec<Arc<dyn UsageSink>>,
+    model: String,
+    prompt_tokens: u32,
+    completion_text: String,
+    usage: Option<Usage>,
+    received: bool,
+    cached: bool,
+    user: Option<String>,
+    conversation_id: Option<String>,
+}
+
+impl StreamUsageTracker {
+    pub(crate) fn new(sinks: Vec<Arc<dyn UsageSink>>, model: String, messages: &[Message], options: &ChatOptions, cached: bool) -> Self {
+        Self {
+            sinks,
+            model,
+            prompt_tokens: estimate_prompt_tokens(messages),
+            completion_text: String::new(),
+            usage: None,
+            received: false,
+            cached,
+            user: options.user.clone(),
+            conversation_id: options.conversation_id.clone(),
+        }
+    }
+
+    pub(crate) fn observe(&mut self, chunk: &StreamingChatCompletionResponse) {
+        self.received = true;
+        if !chunk.model.is_empty() {
+            self.model = chunk.model.clone();
+        }
+        if let Some(usage) = &chunk.usage {
+            self.usage = Some(usage.clone());
+        }
+        for choice in &chunk.choices {
+            self.completion_text.push_str(choice.delta.content.as_deref().unwrap_or_default());
+            self.completion_text.push_str(choice.delta.reasoning_content.as_deref().unwrap_or_default());
+        }
+    }
+}
+
+impl Drop for StreamUsageTracker {
+    fn drop(&mut self) {
+        if !self.received {
+            return;
+        }
+
+        let (usage, estimated) = match self.usage.take() {
+            Some(usage) => (usage, false),
+            None => {
+                let completion_tokens = estimate_tokens(&self.completion_text);
+                (Usage {
+                    prompt_tokens: self.prompt_tokens,
+                    completion_tokens,
+                    total_tokens: self.prompt_tokens + completion_tokens,
+                }, true)
+            }
+        };
+        let event = UsageEvent {
+            model: std::mem::take(&mut self.model),
+            usage,
+            estimated,
+            cached: self.cached,
+            streamed: true,
+            user: self.user.take(),
+            conversation_id: self.conversation_id.take(),
+        };
+        for sink in &self.sinks {
+            sink.record(event.clone());
+        }
+    }
+}
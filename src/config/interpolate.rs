@@ -0,0 +1,46 @@
+/// Ersetzt `${VAR}` und `${VAR:-standard}` im Text einer Konfigurationsdatei.
+///
+/// Der Standardwert gilt, wenn die Variable fehlt oder leer ist; `$${` ergibt
+/// ein literales `${`. Nicht gesetzte Variablen ohne Standardwert werden durch
+/// einen leeren Text ersetzt und zusätzlich zurückgegeben.
+pub fn interpolate(text: &str, lookup: impl Fn(&str) -> Option<String>) -> (String, Vec<String>) {
+    let mut output = String::with_capacity(text.len());
+    let mut missing = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        output.push_str(&rest[..start]);
+
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            // Nicht geschlossener Platzhalter bleibt unverändert
+            output.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let (name, default) = match after[..end].split_once(":-") {
+            Some((name, default)) => (name.trim(), Some(default)),
+            None => (after[..end].trim(), None),
+        };
+
+        let value = match default {
+            Some(default) => Some(lookup(name).filter(|value| !value.is_empty()).unwrap_or_else(|| default.to_string())),
+            None => lookup(name),
+        };
+        match value {
+            Some(value) => output.push_str(&value),
+            None if !missing.iter().any(|known| known == name) => missing.push(name.to_string()),
+            None => {}
+        }
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    (output, missing)
+}
@@ -1,8 +1,21 @@
-use config::{Config, ConfigError, Environment, File};
-use serde::Deserialize;
+mod interpolate;
+mod reload;
+mod validation;
+
+pub use interpolate::interpolate;
+pub use reload::{restart_required, ConfigHandle};
+
+use config::{Config, ConfigError, Environment, File, FileFormat};
+use crate::functions::RiskLevel;
+use crate::secrets::Secret;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize, Clone)]
+/// Gesamte Konfiguration; fehlende Abschnitte erhalten Standardwerte
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub chatglm: ChatGLMConfig,
@@ -11,76 +24,900 @@ pub struct AppConfig {
     pub static_files: StaticFilesConfig,
     pub session: SessionConfig,
     pub websocket: WebSocketConfig,
+    pub tools: ToolsConfig,
+    pub mcp: McpConfig,
+    pub uploads: UploadsConfig,
+    pub settings: SettingsConfig,
+    pub presets: PresetsConfig,
+    pub templates: TemplatesConfig,
+    pub conversations: ConversationsConfig,
+    pub documents: DocumentsConfig,
+    pub cache: CacheConfig,
+    pub usage: UsageConfig,
+    pub metrics: MetricsConfig,
+    pub telemetry: TelemetryConfig,
+    pub health: HealthConfig,
+    pub reload: ReloadConfig,
+}
+
+/// Neuladen der Konfiguration zur Laufzeit (zusätzlich immer per SIGHUP)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ReloadConfig {
+    /// Konfigurationsdateien auf Änderungen überwachen
+    pub watch: bool,
+    /// Prüfintervall in Sekunden
+    pub poll_interval: u64,
+}
+
+impl Default for ReloadConfig {
+    fn default() -> Self {
+        Self {
+            watch: true,
+            poll_interval: 2,
+        }
+    }
+}
+
+/// Prometheus-Endpunkt `/metrics`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Prüfungen für `/api/health/ready`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Erreichbarkeit der GLM-API prüfen
+    pub upstream_probe: bool,
+    /// Sekunden, für die das Ergebnis der API-Prüfung wiederverwendet wird
+    pub probe_ttl: u64,
+    /// Timeout für die API- und MCP-Prüfungen in Sekunden
+    pub probe_timeout: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            upstream_probe: true,
+            probe_ttl: 30,
+            probe_timeout: 5,
+        }
+    }
+}
+
+/// Export von Traces per OTLP/HTTP, z.B. an einen OpenTelemetry Collector
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// Basis-URL des Collectors; `/v1/traces` wird angehängt
+    pub endpoint: String,
+    pub service_name: String,
+    /// Anteil der neuen Traces, die aufgezeichnet werden (0.0 bis 1.0).
+    /// Eingehende `traceparent` mit gesetztem Sampling-Flag werden immer übernommen.
+    pub sample_ratio: f64,
+    /// Timeout für den Export in Sekunden
+    pub timeout: u64,
+    /// Zusätzliche Header für den Collector, z.B. zur Authentifizierung
+    pub headers: HashMap<String, String>,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "http://localhost:4318".to_string(),
+            service_name: "chatglm-web".to_string(),
+            sample_ratio: 1.0,
+            timeout: 10,
+            headers: HashMap::new(),
+        }
+    }
+}
+
+/// Preise eines Modells pro einer Million Tokens
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModelPrice {
+    /// Modellname; `default` gilt für alle nicht aufgeführten Modelle
+    pub model: String,
+    pub input: f64,
+    pub output: f64,
+}
+
+/// Erfassung von Token-Verbrauch und Kosten
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct UsageConfig {
+    pub enabled: bool,
+    /// JSONL-Datei mit einem Eintrag pro Anfrage
+    pub file: String,
+    pub currency: String,
+    pub prices: Vec<ModelPrice>,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            file: "data/usage/usage.jsonl".to_string(),
+            currency: "USD".to_string(),
+            prices: Vec::new(),
+        }
+    }
+}
+
+/// Speicherort des Antwort-Caches
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackendKind {
+    Memory,
+    Disk,
+}
+
+/// Cache für Antworten auf identische Anfragen
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub backend: CacheBackendKind,
+    /// Maximale Anzahl Einträge, im Arbeitsspeicher oder als Dateien
+    pub capacity: usize,
+    /// Verzeichnis für das Festplatten-Backend
+    pub dir: String,
+    /// Lebensdauer in Sekunden, 0 = unbegrenzt
+    pub ttl: u64,
+    /// Nur Anfragen mit Temperatur 0 cachen
+    pub deterministic_only: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: CacheBackendKind::Memory,
+            capacity: 1000,
+            dir: "data/cache".to_string(),
+            ttl: 3600,
+            deterministic_only: true,
+        }
+    }
+}
+
+/// Dokumente für die Suche und als Kontext in Chat-Anfragen
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DocumentsConfig {
+    pub enabled: bool,
+    pub dir: String,
+    /// Maximale Größe einer hochgeladenen Datei in Bytes
+    pub max_file_size: usize,
+    /// Abschnittsgröße und Überlappung in Zeichen
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    /// Passende Abschnitte automatisch als Kontext einfügen (pro Anfrage überschreibbar)
+    pub auto_context: bool,
+    /// Anzahl der Abschnitte im automatischen Kontext
+    pub context_chunks: usize,
+    /// Abschnitte zusätzlich über die Embeddings-API des Anbieters indexieren
+    pub embeddings: bool,
+}
+
+impl Default for DocumentsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "data/documents".to_string(),
+            max_file_size: 20 * 1024 * 1024,
+            chunk_size: 1200,
+            chunk_overlap: 200,
+            auto_context: false,
+            context_chunks: 4,
+            embeddings: false,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// Hochgeladene Bilder für multimodale Nachrichten
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct UploadsConfig {
+    pub dir: String,
+    /// Maximale Dateigröße in Bytes (gilt auch für Base64-Bilder in Nachrichten)
+    pub max_file_size: usize,
+    /// Erlaubte MIME-Typen
+    pub allowed_types: Vec<String>,
+}
+
+impl Default for UploadsConfig {
+    fn default() -> Self {
+        Self {
+            dir: "data/uploads".to_string(),
+            max_file_size: 10 * 1024 * 1024,
+            allowed_types: ["image/png", "image/jpeg", "image/gif", "image/webp"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+/// Gespeicherte Chat-Einstellungen pro Benutzer
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SettingsConfig {
+    pub dir: String,
+}
+
+impl Default for SettingsConfig {
+    fn default() -> Self {
+        Self {
+            dir: "data/settings".to_string(),
+        }
+    }
+}
+
+/// Bibliothek der System-Prompt-Presets
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PresetsConfig {
+    pub enabled: bool,
+    pub dir: String,
+}
+
+impl Default for PresetsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "data/presets".to_string(),
+        }
+    }
+}
+
+/// Prompt-Templates mit typisierten Variablen
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TemplatesConfig {
+    pub enabled: bool,
+    pub dir: String,
+}
+
+impl Default for TemplatesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "data/templates".to_string(),
+        }
+    }
+}
+
+/// Gespeicherte Unterhaltungen mit verzweigten Nachrichten
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ConversationsConfig {
+    pub enabled: bool,
+    pub dir: String,
+}
+
+impl Default for ConversationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "data/conversations".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct McpConfig {
+    #[serde(default)]
+    pub servers: Vec<McpServerConfig>,
+    #[serde(default)]
+    pub serve: McpServeConfig,
+}
+
+/// Bereitstellung dieses Servers selbst als MCP-Server
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct McpServeConfig {
+    /// Streamable-HTTP-Endpunkt `/mcp` im Webserver aktivieren
+    pub http_enabled: bool,
+    /// Auch Tools oberhalb der Bestätigungsschwelle anbieten
+    /// (der MCP-Client ist dann für die Bestätigung verantwortlich);
+    /// nur über stdio, nicht zusammen mit `http_enabled`
+    pub allow_risky_tools: bool,
+}
+
+/// Ein per stdio angebundener MCP-Server
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct McpServerConfig {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Tool-Namen mit `<server>__` präfixen, um Kollisionen zu vermeiden
+    #[serde(default = "default_true")]
+    pub prefix_tools: bool,
+    /// Risikostufe für alle Tools dieses Servers
+    #[serde(default = "default_mcp_risk_level")]
+    pub risk_level: RiskLevel,
+    /// Timeout pro Tool-Aufruf in Sekunden
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_mcp_risk_level() -> RiskLevel {
+    RiskLevel::High
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub timeout: u64,
+    /// Sekunden, die laufende Anfragen und Streams beim Herunterfahren
+    /// (SIGTERM/SIGINT) noch erhalten; danach werden Streams abgebrochen
+    pub drain_timeout: u64,
+    /// Abgebrochene Antworten als JSON Lines
+    pub partial_messages_file: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 3000,
+            timeout: 30,
+            drain_timeout: 30,
+            partial_messages_file: "data/partial_messages.jsonl".to_string(),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct ChatGLMConfig {
     pub api_url: String,
-    pub api_key: String,
+    pub api_key: Secret,
+    /// Weitere Schlüssel, die reihum verwendet werden
+    pub api_keys: Vec<Secret>,
+    /// Datei mit je einem Schlüssel pro Zeile (z.B. ein Docker Secret);
+    /// die Schlüssel werden beim Laden an `api_keys` angehängt
+    pub api_key_file: String,
+    /// Sekunden, die ein Schlüssel nach einem Kontingentfehler pausiert
+    pub key_cooldown: u64,
     pub model: String,
     pub max_tokens: u32,
     pub temperature: f32,
     pub top_p: f32,
     pub stream: bool,
+    /// Reasoning standardmäßig anfordern (pro Anfrage überschreibbar)
+    pub thinking_enabled: bool,
+    /// Standard-Token-Budget für das Reasoning
+    pub thinking_budget: Option<u32>,
+    pub embedding_model: String,
+    /// Maximale Anzahl Texte pro Embeddings-Request
+    pub embedding_batch_size: usize,
+    /// Mehrere Antwortkandidaten über `n` in einer Anfrage anfordern
+    /// (sonst parallele Einzelanfragen)
+    pub native_candidates: bool,
+}
+
+impl Default for ChatGLMConfig {
+    fn default() -> Self {
+        Self {
+            api_url: "https://api.z.ai/v1".to_string(),
+            api_key: Secret::default(),
+            api_keys: Vec::new(),
+            api_key_file: String::new(),
+            key_cooldown: 3600,
+            model: "glm-4.5".to_string(),
+            max_tokens: 4096,
+            temperature: 0.7,
+            top_p: 0.9,
+            stream: false,
+            thinking_enabled: true,
+            thinking_budget: None,
+            embedding_model: "embedding-3".to_string(),
+            embedding_batch_size: 64,
+            native_candidates: false,
+        }
+    }
+}
+
+impl ChatGLMConfig {
+    /// Alle konfigurierten Schlüssel ohne leere Einträge und Duplikate
+    pub fn keys(&self) -> Vec<Secret> {
+        crate::secrets::distinct(std::iter::once(&self.api_key).chain(&self.api_keys))
+    }
+
+    /// Hängt die Schlüssel aus `api_key_file` an `api_keys` an
+    fn read_key_file(&mut self) -> Result<Option<PathBuf>, ConfigError> {
+        let path = self.api_key_file.trim();
+        if path.is_empty() {
+            return Ok(None);
+        }
+        let keys = crate::secrets::read_secret_file(path).map_err(|err| {
+            ConfigError::Message(format!("chatglm.api_key_file {} kann nicht gelesen werden: {}", path, err))
+        })?;
+        self.api_keys.extend(keys);
+        Ok(Some(PathBuf::from(path)))
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct CorsConfig {
     pub allowed_origins: Vec<String>,
     pub allowed_methods: Vec<String>,
     pub allowed_headers: Vec<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl Default for CorsConfig {
+    fn default() -> Self {
+        let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
+        Self {
+            allowed_origins: strings(&["http://localhost:3000", "http://127.0.0.1:3000"]),
+            allowed_methods: strings(&["GET", "POST", "PUT", "DELETE", "OPTIONS"]),
+            allowed_headers: strings(&["Content-Type", "Authorization"]),
+        }
+    }
+}
+
+/// Logging: Level, Ausgabeformat und optionale Logdateien.
+///
+/// Ist `RUST_LOG` gesetzt, ersetzt es `level` und `filters`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct LoggingConfig {
     pub level: String,
-    pub format: String,
+    /// `json`, `pretty` oder `compact`
+    pub format: LogFormat,
+    /// Zusätzliche Filter je Modul, z.B. `"chatglm_web::client=debug"`
+    pub filters: Vec<String>,
+    /// Zusätzliche Ausgabe in täglich oder stündlich rotierende Dateien (JSON)
+    pub file: Option<LogFileConfig>,
+    /// Nachrichteninhalte und Request-Bodies im Debug-Log ausgeben.
+    /// Nur zur Fehlersuche, sonst werden lediglich Größen geloggt.
+    pub log_bodies: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Pretty,
+            filters: Vec::new(),
+            file: None,
+            log_bodies: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Json,
+    Pretty,
+    Compact,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LogFileConfig {
+    pub dir: String,
+    /// Dateiname vor dem Datumssuffix
+    pub prefix: String,
+    pub rotation: LogRotation,
+}
+
+impl Default for LogFileConfig {
+    fn default() -> Self {
+        Self {
+            dir: "data/logs".to_string(),
+            prefix: "chatglm-web.log".to_string(),
+            rotation: LogRotation::Daily,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct StaticFilesConfig {
     pub path: String,
     pub max_age: u64,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl Default for StaticFilesConfig {
+    fn default() -> Self {
+        Self {
+            path: "./static".to_string(),
+            max_age: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct SessionConfig {
-    pub secret: String,
+    pub secret: Secret,
     pub timeout: u64,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            secret: Secret::default(),
+            timeout: 1800,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct WebSocketConfig {
     pub max_connections: u32,
     pub heartbeat_interval: u64,
 }
 
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 100,
+            heartbeat_interval: 30,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ToolsConfig {
+    #[serde(default)]
+    pub run_command: RunCommandConfig,
+    #[serde(default)]
+    pub approval: ApprovalConfig,
+    #[serde(default)]
+    pub execution: ToolExecutionConfig,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+}
+
+/// Externe Tools, die über Manifeste in einem Verzeichnis definiert werden
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PluginsConfig {
+    pub enabled: bool,
+    pub dir: String,
+    /// Prüfintervall für Änderungen im Verzeichnis in Sekunden
+    pub poll_interval: u64,
+    /// Umgebungsvariablen, die an Plugin-Programme weitergereicht werden; alle anderen werden entfernt
+    pub env_passthrough: Vec<String>,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: "plugins".to_string(),
+            poll_interval: 2,
+            env_passthrough: ["PATH", "HOME", "LANG"].iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+/// Zeit- und Parallelitätslimits für Tool-Aufrufe
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ToolExecutionConfig {
+    /// Standard-Timeout pro Aufruf in Sekunden
+    pub default_timeout: u64,
+    /// Timeouts in Sekunden für einzelne Tools, z.B. `run_command = 300`
+    pub timeouts: HashMap<String, u64>,
+    /// Maximale Anzahl gleichzeitig laufender Tool-Aufrufe (serverweit)
+    pub max_concurrency: usize,
+}
+
+impl Default for ToolExecutionConfig {
+    fn default() -> Self {
+        Self {
+            default_timeout: 60,
+            timeouts: HashMap::new(),
+            max_concurrency: 8,
+        }
+    }
+}
+
+/// Bestätigungspflicht für riskante Tool-Aufrufe
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ApprovalConfig {
+    /// Aufrufe mit einer Risikostufe oberhalb dieser Schwelle müssen bestätigt werden
+    pub threshold: RiskLevel,
+    /// Wartezeit auf eine Entscheidung in Sekunden, danach gilt der Aufruf als abgelehnt
+    pub timeout: u64,
+    /// JSONL-Datei, in der alle Entscheidungen protokolliert werden
+    pub log_path: String,
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            threshold: RiskLevel::Medium,
+            timeout: 120,
+            log_path: "data/tool_approvals.jsonl".to_string(),
+        }
+    }
+}
+
+/// Einstellungen für das `run_command` Tool (Shell-Befehle im Workspace)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RunCommandConfig {
+    pub enabled: bool,
+    pub workspace_dir: String,
+    pub allowed_commands: Vec<String>,
+    /// Timeout in Sekunden
+    pub timeout: u64,
+    /// Maximale Ausgabe pro Stream (stdout/stderr) in Bytes
+    pub max_output_bytes: usize,
+    /// Umgebungsvariablen, die an den Prozess weitergereicht werden; alle anderen werden entfernt
+    pub env_passthrough: Vec<String>,
+    /// Speicherlimit (Adressraum) in MB, 0 = unbegrenzt
+    pub max_memory_mb: u64,
+    /// CPU-Zeitlimit in Sekunden, 0 = unbegrenzt
+    pub max_cpu_seconds: u64,
+    /// Risikostufe des Tools; Standard `high` erfordert mit der Standardschwelle eine Bestätigung
+    pub risk_level: RiskLevel,
+}
+
+impl Default for RunCommandConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            workspace_dir: ".".to_string(),
+            // Keine Freigaben: Build-Werkzeuge wie `npx`, `make` oder `git`
+            // führen über ihre Argumente beliebigen Code aus
+            allowed_commands: Vec::new(),
+            timeout: 120,
+            max_output_bytes: 64 * 1024,
+            env_passthrough: ["PATH", "HOME", "LANG", "CARGO_HOME", "RUSTUP_HOME"]
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            max_memory_mb: 0,
+            max_cpu_seconds: 300,
+            risk_level: RiskLevel::High,
+        }
+    }
+}
+
+/// Suchreihenfolge für `config`, `config-<RUN_MODE>` und `config-local`
+const CONFIG_EXTENSIONS: [(&str, FileFormat); 4] = [
+    ("toml", FileFormat::Toml),
+    ("yaml", FileFormat::Yaml),
+    ("yml", FileFormat::Yaml),
+    ("json", FileFormat::Json),
+];
+
+/// Schlüssel, deren Werte in [`AppConfig::redacted`] maskiert werden;
+/// Felder vom Typ [`Secret`] sind bereits maskiert serialisiert
+const SECRET_KEYS: [&str; 2] = ["password", "token"];
+/// Tabellen, deren Werte vollständig maskiert werden
+const SECRET_TABLES: [&str; 2] = ["headers", "env"];
+
+/// `RUN_MODE`, Standard `development`
+pub fn run_mode() -> String {
+    env::var("RUN_MODE").unwrap_or_else(|_| "development".into())
+}
+
+pub fn is_production() -> bool {
+    run_mode().eq_ignore_ascii_case("production")
+}
+
+/// Geladene Konfiguration mit ihren Quellen
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: AppConfig,
+    /// Gelesene Dateien in der Reihenfolge, in der sie angewendet wurden
+    pub sources: Vec<PathBuf>,
+    /// `${VAR}`-Platzhalter ohne Standardwert, deren Variable nicht gesetzt ist
+    pub missing_vars: Vec<String>,
+}
+
+impl LoadedConfig {
+    /// Fehler der Validierung und fehlende Umgebungsvariablen
+    pub fn problems(&self, production: bool) -> Vec<String> {
+        let mut problems: Vec<String> = self
+            .missing_vars
+            .iter()
+            .map(|name| format!("Umgebungsvariable {} ist nicht gesetzt", name))
+            .collect();
+        problems.extend(self.config.validate(production));
+        problems
+    }
+}
+
 impl AppConfig {
+    /// Lädt und validiert die Konfiguration; alle Fehler werden gemeinsam gemeldet
     pub fn new() -> Result<Self, ConfigError> {
-        let run_mode = env::var("RUN_MODE").unwrap_or_else(|_| "development".into());
-
-        let s = Config::builder()
-            // Start off by merging in the "default" configuration file
-            .add_source(File::with_name("config"))
-            // Add in the current environment file
-            // Default to 'development' env
-            // Note that this file is _optional_
-            .add_source(File::with_name(&format!("config-{}", run_mode)).required(false))
-            // Add in a local configuration file
-            // This file shouldn't be checked in to git
-            .add_source(File::with_name("config-local").required(false))
-            // Add in settings from the environment (with a prefix of APP)
-            // Eg.. `APP_DEBUG=1 ./target/app` would set the `debug` key
-            .add_source(Environment::with_prefix("app"))
-            .build()?;
-
-        s.try_deserialize()
+        Self::load_validated().map(|loaded| loaded.config)
+    }
+
+    /// Wie [`AppConfig::new`], zusätzlich mit den gelesenen Dateien
+    pub fn load_validated() -> Result<LoadedConfig, ConfigError> {
+        let loaded = Self::load()?;
+        let problems = loaded.problems(is_production());
+        if !problems.is_empty() {
+            return Err(ConfigError::Message(format!(
+                "{} Fehler in der Konfiguration:\n  - {}",
+                problems.len(),
+                problems.join("\n  - ")
+            )));
+        }
+        Ok(loaded)
+    }
+
+    /// Lädt die Konfiguration ohne Validierung.
+    ///
+    /// Quellen: `CONFIG_FILE` bzw. `config.{toml,yaml,yml,json}`, danach
+    /// `config-<RUN_MODE>` und `config-local` (alle optional), zuletzt
+    /// Umgebungsvariablen wie `APP_SERVER__PORT=8080`. In den Dateien werden
+    /// `${VAR}` und `${VAR:-standard}` ersetzt.
+    pub fn load() -> Result<LoadedConfig, ConfigError> {
+        let mut files = Vec::new();
+        match env::var("CONFIG_FILE") {
+            Ok(path) if !path.trim().is_empty() => {
+                let path = PathBuf::from(path);
+                if !path.is_file() {
+                    return Err(ConfigError::Message(format!("CONFIG_FILE {} existiert nicht", path.display())));
+                }
+                files.push(path);
+            }
+            _ => files.extend(find_config_file("config")),
+        }
+        files.extend(find_config_file(&format!("config-{}", run_mode())));
+        files.extend(find_config_file("config-local"));
+        Self::load_files(&files)
+    }
+
+    /// Lädt eine einzelne Datei (mit Ersetzung und Umgebungsvariablen) ohne Validierung
+    pub fn load_file(path: impl AsRef<Path>) -> Result<LoadedConfig, ConfigError> {
+        Self::load_files(&[path.as_ref().to_path_buf()])
+    }
+
+    fn load_files(files: &[PathBuf]) -> Result<LoadedConfig, ConfigError> {
+        let mut builder = Config::builder();
+        let mut missing_vars: Vec<String> = Vec::new();
+        for path in files {
+            let text = std::fs::read_to_string(path)
+                .map_err(|err| ConfigError::Message(format!("{} kann nicht gelesen werden: {}", path.display(), err)))?;
+            let (text, missing) = interpolate(&text, |name| env::var(name).ok());
+            for name in missing {
+                if !missing_vars.contains(&name) {
+                    missing_vars.push(name);
+                }
+            }
+            builder = builder.add_source(File::from_str(&text, file_format(path)));
+        }
+
+        let mut config: AppConfig = builder
+            .add_source(
+                Environment::with_prefix("app")
+                    .prefix_separator("_")
+                    .separator("__")
+                    .try_parsing(true),
+            )
+            .build()?
+            .try_deserialize()?;
+
+        // Die Schlüsseldatei gehört zu den Quellen, damit Änderungen neu geladen werden
+        let mut sources = files.to_vec();
+        sources.extend(config.chatglm.read_key_file()?);
+        Ok(LoadedConfig { config, sources, missing_vars })
+    }
+
+    /// Einstellungen für den GLM-Client
+    pub fn glm_config(&self) -> crate::client::GlmConfig {
+        let chatglm = &self.chatglm;
+        crate::client::GlmConfig {
+            api_key: chatglm.api_key.clone(),
+            api_keys: chatglm.api_keys.clone(),
+            key_cooldown: std::time::Duration::from_secs(chatglm.key_cooldown),
+            api_url: chatglm.api_url.clone(),
+            // Unbekannte Modelle weist die Validierung zurück
+            model: crate::client::GlmModel::from_id(&chatglm.model).unwrap_or_default(),
+            max_tokens: chatglm.max_tokens,
+            temperature: chatglm.temperature,
+            top_p: chatglm.top_p,
+            stream: chatglm.stream,
+            thinking_enabled: chatglm.thinking_enabled,
+            thinking_budget: chatglm.thinking_budget,
+            embedding_model: chatglm.embedding_model.clone(),
+            embedding_batch_size: chatglm.embedding_batch_size,
+            native_candidates: chatglm.native_candidates,
+            timeout: std::time::Duration::from_secs(self.server.timeout),
+        }
+    }
+
+    /// Effektive Konfiguration als JSON mit maskierten Secrets
+    pub fn redacted(&self) -> serde_json::Value {
+        // Über den Text, damit f32-Werte nicht als f64 (0.699999…) erscheinen
+        let mut value = serde_json::to_string(self)
+            .and_then(|text| serde_json::from_str(&text))
+            .unwrap_or_default();
+        redact(&mut value);
+        value
+    }
+}
+
+fn find_config_file(name: &str) -> Option<PathBuf> {
+    CONFIG_EXTENSIONS
+        .iter()
+        .map(|(extension, _)| PathBuf::from(format!("{}.{}", name, extension)))
+        .find(|path| path.is_file())
+}
+
+fn file_format(path: &Path) -> FileFormat {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+    CONFIG_EXTENSIONS
+        .iter()
+        .find(|(known, _)| extension.eq_ignore_ascii_case(known))
+        .map(|(_, format)| *format)
+        .unwrap_or(FileFormat::Toml)
+}
+
+fn redact(value: &mut serde_json::Value) {
+    let serde_json::Value::Object(map) = value else {
+        if let serde_json::Value::Array(items) = value {
+            items.iter_mut().for_each(redact);
+        }
+        return;
+    };
+    for (key, value) in map.iter_mut() {
+        let key = key.to_ascii_lowercase();
+        if SECRET_TABLES.contains(&key.as_str()) {
+            if let serde_json::Value::Object(table) = value {
+                for entry in table.values_mut() {
+                    mask(entry);
+                }
+            }
+        } else if SECRET_KEYS.iter().any(|secret| key == *secret || key.ends_with(&format!("_{}", secret))) {
+            mask(value);
+        } else {
+            redact(value);
+        }
+    }
+}
+
+fn mask(value: &mut serde_json::Value) {
+    // Leere Werte bleiben sichtbar, damit fehlende Secrets auffallen
+    if let serde_json::Value::String(text) = value {
+        if !text.is_empty() {
+            *text = crate::logging::mask_secret(text);
+        }
     }
 }
@@ -0,0 +1,182 @@
+use super::{is_production, AppConfig, LoadedConfig};
+use config::ConfigError;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+type Loader = Box<dyn Fn() -> Result<LoadedConfig, ConfigError> + Send + Sync>;
+type Listener = Box<dyn Fn(&AppConfig) -> anyhow::Result<()> + Send + Sync>;
+
+/// Einstellungen, die erst nach einem Neustart wirksam werden
+const RESTART_REQUIRED: [&str; 23] = [
+    "/server/host",
+    "/server/port",
+    "/server/drain_timeout",
+    "/server/partial_messages_file",
+    "/logging",
+    "/telemetry",
+    "/mcp",
+    "/uploads",
+    "/settings",
+    "/presets",
+    "/templates",
+    "/conversations",
+    "/documents",
+    "/cache",
+    "/metrics",
+    "/health",
+    "/static_files",
+    "/session",
+    "/websocket",
+    "/reload",
+    "/tools/run_command",
+    "/tools/plugins",
+    "/usage/file",
+];
+
+/// Austauschbare Konfiguration zur Laufzeit.
+///
+/// Eine neue Konfiguration wird erst validiert; ist sie ungültig, bleibt die
+/// bisherige aktiv. Gültige Konfigurationen werden über die mit
+/// [`ConfigHandle::on_reload`] registrierten Listener an Client, Verbrauch und
+/// Tools weitergegeben.
+pub struct ConfigHandle {
+    current: RwLock<Arc<AppConfig>>,
+    loader: Loader,
+    listeners: Mutex<Vec<Listener>>,
+    sources: Mutex<Vec<PathBuf>>,
+    /// Letzter bekannter Stand der Dateien (Änderungszeit, Größe)
+    fingerprint: Mutex<Vec<Option<(SystemTime, u64)>>>,
+    production: bool,
+}
+
+impl ConfigHandle {
+    /// `loader` liest die Konfiguration bei jedem Neuladen, z.B. [`AppConfig::load`]
+    pub fn new(
+        loaded: LoadedConfig,
+        loader: impl Fn() -> Result<LoadedConfig, ConfigError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            fingerprint: Mutex::new(fingerprint(&loaded.sources)),
+            sources: Mutex::new(loaded.sources),
+            current: RwLock::new(Arc::new(loaded.config)),
+            loader: Box::new(loader),
+            listeners: Mutex::new(Vec::new()),
+            production: is_production(),
+        }
+    }
+
+    /// Aktuell gültige Konfiguration
+    pub fn current(&self) -> Arc<AppConfig> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Wird bei jedem Neuladen mit der neuen Konfiguration aufgerufen. Schlägt
+    /// ein Listener fehl, bleibt die bisherige Konfiguration aktiv und die
+    /// zuvor aufgerufenen Listener erhalten sie erneut.
+    pub fn on_reload(&self, listener: impl Fn(&AppConfig) -> anyhow::Result<()> + Send + Sync + 'static) {
+        self.listeners.lock().unwrap().push(Box::new(listener));
+    }
+
+    /// Lädt, validiert und übernimmt die Konfiguration; bei Fehlern bleibt die
+    /// bisherige aktiv und alle Fehler werden zurückgegeben
+    pub fn reload(&self) -> Result<Arc<AppConfig>, Vec<String>> {
+        // Hält gleichzeitige Neuladevorgänge (Watcher und SIGHUP) auseinander
+        let listeners = self.listeners.lock().unwrap();
+
+        let loaded = (self.loader)().map_err(|err| vec![err.to_string()])?;
+        let mut problems = loaded.problems(self.production);
+        if loaded.config.chatglm.keys().is_empty() {
+            problems.push("chatglm.api_key darf nicht leer sein".to_string());
+        }
+        if !problems.is_empty() {
+            warn!("Neue Konfiguration abgelehnt, die bisherige bleibt aktiv: {}", problems.join("; "));
+            return Err(problems);
+        }
+
+        let config = Arc::new(loaded.config);
+        let previous = self.current();
+        let pending = restart_required(&previous, &config);
+        if !pending.is_empty() {
+            warn!("Erst nach einem Neustart wirksam: {}", pending.join(", "));
+        }
+
+        for (index, listener) in listeners.iter().enumerate() {
+            if let Err(err) = listener(&config) {
+                warn!("Neue Konfiguration nicht übernommen, die bisherige bleibt aktiv: {}", err);
+                for listener in &listeners[..index] {
+                    if let Err(err) = listener(&previous) {
+                        warn!("Bisherige Konfiguration konnte nicht wiederhergestellt werden: {}", err);
+                    }
+                }
+                return Err(vec![err.to_string()]);
+            }
+        }
+        *self.current.write().unwrap() = config.clone();
+        *self.sources.lock().unwrap() = loaded.sources;
+        info!("Konfiguration neu geladen");
+        Ok(config)
+    }
+
+    /// Lädt neu, wenn sich eine der gelesenen Dateien geändert hat
+    pub fn reload_if_changed(&self) -> bool {
+        let sources = self.sources.lock().unwrap().clone();
+        let current = fingerprint(&sources);
+        {
+            let mut known = self.fingerprint.lock().unwrap();
+            if *known == current {
+                return false;
+            }
+            // Auch bei ungültigem Inhalt erst nach der nächsten Änderung erneut versuchen
+            *known = current;
+        }
+        self.reload().is_ok()
+    }
+
+    /// Prüft die Dateien im angegebenen Intervall auf Änderungen
+    pub fn spawn_watcher(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.reload_if_changed();
+            }
+        })
+    }
+
+    /// Lädt bei jedem SIGHUP neu; dabei werden auch neu angelegte Dateien
+    /// wie `config-local.toml` berücksichtigt
+    #[cfg(unix)]
+    pub fn spawn_sighup_handler(self: Arc<Self>) -> std::io::Result<tokio::task::JoinHandle<()>> {
+        let mut signal = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+        Ok(tokio::spawn(async move {
+            while signal.recv().await.is_some() {
+                info!("SIGHUP empfangen, lade Konfiguration neu");
+                let _ = self.reload();
+            }
+        }))
+    }
+}
+
+/// Geänderte Einstellungen, die ein Neuladen nicht übernehmen kann
+pub fn restart_required(previous: &AppConfig, next: &AppConfig) -> Vec<String> {
+    let (Ok(previous), Ok(next)) = (serde_json::to_value(previous), serde_json::to_value(next)) else {
+        return Vec::new();
+    };
+    RESTART_REQUIRED
+        .iter()
+        .filter(|pointer| previous.pointer(pointer) != next.pointer(pointer))
+        .map(|pointer| pointer.trim_start_matches('/').replace('/', "."))
+        .collect()
+}
+
+fn fingerprint(paths: &[PathBuf]) -> Vec<Option<(SystemTime, u64)>> {
+    paths
+        .iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(path).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
@@ -0,0 +1,160 @@
+use super::AppConfig;
+use crate::client::GlmModel;
+use crate::secrets::Secret;
+use std::collections::HashSet;
+
+impl AppConfig {
+    /// Prüft Wertebereiche, URLs und Modell-IDs und liefert alle Fehler
+    /// gemeinsam. Mit `production` müssen zusätzlich alle Secrets gesetzt sein.
+    pub fn validate(&self, production: bool) -> Vec<String> {
+        let mut problems = Problems::default();
+
+        let server = &self.server;
+        problems.check(!server.host.trim().is_empty(), "server.host darf nicht leer sein");
+        problems.check(server.port != 0, "server.port muss größer als 0 sein");
+        problems.check(server.timeout > 0, "server.timeout muss größer als 0 sein");
+
+        let chatglm = &self.chatglm;
+        problems.http_url("chatglm.api_url", &chatglm.api_url);
+        if GlmModel::from_id(&chatglm.model).is_none() {
+            let known: Vec<String> = GlmModel::ALL.iter().map(ToString::to_string).collect();
+            problems.push(format!("chatglm.model '{}' ist unbekannt (erlaubt: {})", chatglm.model, known.join(", ")));
+        }
+        problems.check(chatglm.max_tokens > 0, "chatglm.max_tokens muss größer als 0 sein");
+        problems.range("chatglm.temperature", chatglm.temperature as f64, 0.0, 1.0);
+        problems.range("chatglm.top_p", chatglm.top_p as f64, 0.0, 1.0);
+        problems.check(chatglm.thinking_budget != Some(0), "chatglm.thinking_budget muss größer als 0 sein");
+        problems.check(!chatglm.embedding_model.trim().is_empty(), "chatglm.embedding_model darf nicht leer sein");
+        problems.check(chatglm.embedding_batch_size > 0, "chatglm.embedding_batch_size muss größer als 0 sein");
+        problems.check(chatglm.key_cooldown > 0, "chatglm.key_cooldown muss größer als 0 sein");
+
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" {
+                problems.http_url("cors.allowed_origins", origin);
+            }
+        }
+
+        let logging = &self.logging;
+        if logging.level.parse::<tracing_subscriber::filter::LevelFilter>().is_err() {
+            problems.push(format!("logging.level '{}' ist kein gültiges Level", logging.level));
+        }
+        for filter in &logging.filters {
+            if filter.parse::<tracing_subscriber::filter::Directive>().is_err() {
+                problems.push(format!("logging.filters: '{}' ist kein gültiger Filter", filter));
+            }
+        }
+
+        problems.check(self.session.timeout > 0, "session.timeout muss größer als 0 sein");
+        problems.check(self.websocket.max_connections > 0, "websocket.max_connections muss größer als 0 sein");
+
+        problems.check(self.uploads.max_file_size > 0, "uploads.max_file_size muss größer als 0 sein");
+        for mime_type in &self.uploads.allowed_types {
+            if !mime_type.contains('/') {
+                problems.push(format!("uploads.allowed_types: '{}' ist kein MIME-Typ", mime_type));
+            }
+        }
+
+        problems.check(!self.settings.dir.trim().is_empty(), "settings.dir darf nicht leer sein");
+        if self.presets.enabled {
+            problems.check(!self.presets.dir.trim().is_empty(), "presets.dir darf nicht leer sein");
+        }
+        if self.templates.enabled {
+            problems.check(!self.templates.dir.trim().is_empty(), "templates.dir darf nicht leer sein");
+        }
+        if self.conversations.enabled {
+            problems.check(!self.conversations.dir.trim().is_empty(), "conversations.dir darf nicht leer sein");
+        }
+
+        let documents = &self.documents;
+        if documents.enabled {
+            problems.check(documents.max_file_size > 0, "documents.max_file_size muss größer als 0 sein");
+            problems.check(documents.chunk_size > 0, "documents.chunk_size muss größer als 0 sein");
+            problems.check(
+                documents.chunk_overlap < documents.chunk_size,
+                "documents.chunk_overlap muss kleiner als documents.chunk_size sein",
+            );
+        }
+
+        if self.cache.enabled {
+            problems.check(self.cache.capacity > 0, "cache.capacity muss größer als 0 sein");
+        }
+
+        for price in &self.usage.prices {
+            if price.input < 0.0 || price.output < 0.0 {
+                problems.push(format!("usage.prices: negativer Preis für '{}'", price.model));
+            }
+        }
+
+        let telemetry = &self.telemetry;
+        if telemetry.enabled {
+            problems.http_url("telemetry.endpoint", &telemetry.endpoint);
+            problems.range("telemetry.sample_ratio", telemetry.sample_ratio, 0.0, 1.0);
+        }
+
+        let execution = &self.tools.execution;
+        problems.check(execution.default_timeout > 0, "tools.execution.default_timeout muss größer als 0 sein");
+        problems.check(execution.max_concurrency > 0, "tools.execution.max_concurrency muss größer als 0 sein");
+
+        problems.check(self.reload.poll_interval > 0, "reload.poll_interval muss größer als 0 sein");
+
+        let mut names = HashSet::new();
+        for server in &self.mcp.servers {
+            if server.name.trim().is_empty() {
+                problems.push("mcp.servers: name darf nicht leer sein".to_string());
+            } else if !names.insert(server.name.as_str()) {
+                problems.push(format!("mcp.servers: '{}' ist mehrfach definiert", server.name));
+            }
+            if server.command.trim().is_empty() {
+                problems.push(format!("mcp.servers: command für '{}' fehlt", server.name));
+            }
+        }
+        // `/mcp` ist nicht authentifiziert; riskante Tools nur über stdio
+        problems.check(
+            !(self.mcp.serve.http_enabled && self.mcp.serve.allow_risky_tools),
+            "mcp.serve.allow_risky_tools ist nur ohne mcp.serve.http_enabled erlaubt (nur für --mcp-stdio)",
+        );
+
+        if production {
+            problems.secret("chatglm.api_key", &chatglm.keys());
+            problems.secret("session.secret", std::slice::from_ref(&self.session.secret));
+        }
+
+        problems.0
+    }
+}
+
+#[derive(Default)]
+struct Problems(Vec<String>);
+
+impl Problems {
+    fn push(&mut self, problem: String) {
+        self.0.push(problem);
+    }
+
+    fn check(&mut self, ok: bool, problem: &str) {
+        if !ok {
+            self.0.push(problem.to_string());
+        }
+    }
+
+    fn range(&mut self, key: &str, value: f64, min: f64, max: f64) {
+        if !(min..=max).contains(&value) {
+            self.0.push(format!("{} muss zwischen {} und {} liegen (ist {})", key, min, max, value));
+        }
+    }
+
+    fn http_url(&mut self, key: &str, value: &str) {
+        match reqwest::Url::parse(value) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => self.0.push(format!("{} ist keine gültige HTTP-URL: '{}'", key, value)),
+        }
+    }
+
+    fn secret(&mut self, key: &str, values: &[Secret]) {
+        if values.iter().all(Secret::is_empty) {
+            self.0.push(format!("{} muss im Produktivbetrieb gesetzt sein", key));
+        } else if values.iter().any(|value| value.expose().contains("${")) {
+            self.0.push(format!("{} enthält eine nicht ersetzte Variable", key));
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use crate::client::{ContentPart, Message, MessageContent, Role};
+use crate::config::ConversationsConfig;
+use crate::store::write_atomic;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+/// Titel neuer Unterhaltungen ohne eigenen Titel
+pub const DEFAULT_TITLE: &str = "Neue Unterhaltung";
+
+/// Nachricht im Baum einer Unterhaltung
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationNode {
+    pub id: String,
+    /// Vorherige Nachricht; `None` am Anfang der Unterhaltung
+    pub parent_id: Option<String>,
+    pub message: Message,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Nachricht des aktiven Zweigs mit ihrer Position unter den Geschwistern
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchMessage {
+    pub id: String,
+    pub parent_id: Option<String>,
+    #[serde(flatten)]
+    pub message: Message,
+    pub created_at: DateTime<Utc>,
+    /// Position unter den Alternativen (ab 0, in Erstellungsreihenfolge)
+    pub sibling_index: usize,
+    pub sibling_count: usize,
+}
+
+/// Unterhaltung als Baum: Bearbeiten und Neugenerieren legen Geschwister an,
+/// statt spätere Nachrichten zu verwerfen. Der aktive Zweig führt von der
+/// Wurzel bis `active_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    pub user: String,
+    /// Alle Nachrichten in Erstellungsreihenfolge
+    pub nodes: Vec<ConversationNode>,
+    /// Letzte Nachricht des aktiven Zweigs
+    pub active_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Conversation {
+    pub fn node(&self, id: &str) -> Option<&ConversationNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    fn require(&self, id: &str) -> Result<&ConversationNode, ConversationError> {
+        self.node(id).ok_or_else(|| ConversationError::MessageNotFound(id.to_string()))
+    }
+
+    /// Nachrichten direkt nach `parent` (`None`: Anfänge der Unterhaltung)
+    pub fn children(&self, parent: Option<&str>) -> Vec<&ConversationNode> {
+        self.nodes.iter().filter(|node| node.parent_id.as_deref() == parent).collect()
+    }
+
+    /// Alternativen zu `id` einschließlich der Nachricht selbst
+    pub fn siblings(&self, id: &str) -> Result<Vec<&ConversationNode>, ConversationError> {
+        let node = self.require(id)?;
+        Ok(self.children(node.parent_id.as_deref()))
+    }
+
+    /// Weg von der Wurzel bis einschließlich `id`
+    pub fn path(&self, id: Option<&str>) -> Result<Vec<&ConversationNode>, ConversationError> {
+        let mut path = Vec::new();
+        let mut current = id;
+        while let Some(id) = current {
+            let node = self.require(id)?;
+            path.push(node);
+            current = node.parent_id.as_deref();
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    /// Kontext für das Modell: die Nachrichten bis einschließlich `id`
+    pub fn context(&self, id: Option<&str>) -> Result<Vec<Message>, ConversationError> {
+        Ok(self.path(id)?.into_iter().map(|node| node.message.clone()).collect())
+    }
+
+    pub fn active_branch(&self) -> Vec<BranchMessage> {
+        let path = self.path(self.active_id.as_deref()).unwrap_or_default();
+        path.into_iter()
+            .map(|node| {
+                let siblings = self.children(node.parent_id.as_deref());
+                BranchMessage {
+                    id: node.id.clone(),
+                    parent_id: node.parent_id.clone(),
+                    message: node.message.clone(),
+                    created_at: node.created_at,
+                    sibling_index: siblings.iter().position(|sibling| sibling.id == node.id).unwrap_or_default(),
+                    sibling_count: siblings.len(),
+                }
+            })
+            .collect()
+    }
+
+    /// Letzte Antwort des Modells im aktiven Zweig
+    pub fn last_answer(&self) -> Option<&ConversationNode> {
+        self.path(self.active_id.as_deref())
+            .unwrap_or_default()
+            .into_iter()
+            .rev()
+            .find(|node| matches!(node.message.role, Role::Assistant))
+    }
+
+    /// Hängt `message` an `parent` an und macht sie zum Ende des aktiven Zweigs
+    pub fn append(&mut self, parent: Option<&str>, message: Message) -> Result<String, ConversationError> {
+        if let Some(parent) = parent {
+            self.require(parent)?;
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        self.nodes.push(ConversationNode {
+            id: id.clone(),
+            parent_id: parent.map(str::to_string),
+            message,
+            created_at: Utc::now(),
+        });
+        self.active_id = Some(id.clone());
+        Ok(id)
+    }
+
+    /// Legt eine bearbeitete Fassung von `id` als neue Alternative an; die
+    /// ursprüngliche Nachricht und alles danach bleiben erhalten
+    pub fn edit(&mut self, id: &str, content: &str) -> Result<String, ConversationError> {
+        let node = self.require(id)?;
+        let parent = node.parent_id.clone();
+        let message = edited(&node.message, content);
+        self.append(parent.as_deref(), message)
+    }
+
+    /// Wechselt auf den Zweig durch `id`; ab dort gilt jeweils die neueste
+    /// Folgenachricht
+    pub fn activate(&mut self, id: &str) -> Result<(), ConversationError> {
+        let mut current = self.require(id)?.id.clone();
+        while let Some(child) = self.children(Some(&current)).last() {
+            current = child.id.clone();
+        }
+        self.active_id = Some(current);
+        Ok(())
+    }
+}
+
+/// Nachricht mit neuem Text; Bilder und Dateiverweise bleiben erhalten
+fn edited(message: &Message, content: &str) -> Message {
+    let content = match &message.content {
+        Some(MessageContent::Parts(parts)) => {
+            let mut parts: Vec<ContentPart> = parts
+                .iter()
+                .filter(|part| !matches!(part, ContentPart::Text { .. }))
+                .cloned()
+                .collect();
+            parts.insert(0, ContentPart::text(content));
+            MessageContent::Parts(parts)
+        }
+        _ => MessageContent::Text(content.to_string()),
+    };
+    Message {
+        content: Some(content),
+        tool_calls: None,
+        tool_call_id: None,
+        thinking: None,
+        ..message.clone()
+    }
+}
+
+/// Übersicht für die Liste der Unterhaltungen
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub message_count: usize,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConversationError {
+    #[error("Unterhaltung '{0}' nicht gefunden")]
+    NotFound(String),
+
+    #[error("Nachricht '{0}' nicht gefunden")]
+    MessageNotFound(String),
+
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("Speicherfehler: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Speicherfehler: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Gespeicherte Unterhaltungen, je Unterhaltung eine JSON-Datei
+pub struct ConversationStore {
+    dir: PathBuf,
+    conversations: RwLock<HashMap<String, Conversation>>,
+    /// Serialisiert Änderungen, damit parallel gespeicherte Antworten
+    /// einander nicht überschreiben
+    writes: tokio::sync::Mutex<()>,
+}
+
+impl ConversationStore {
+    /// Öffnet die Ablage und lädt vorhandene Unterhaltungen
+    pub fn open(config: &ConversationsConfig) -> Self {
+        let store = Self {
+            dir: PathBuf::from(&config.dir),
+            conversations: RwLock::new(HashMap::new()),
+            writes: tokio::sync::Mutex::new(()),
+        };
+        store.load_existing();
+        store
+    }
+
+    fn load_existing(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return };
+
+        let mut conversations = self.conversations.write().unwrap();
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            match std::fs::read(&path)
+                .map_err(ConversationError::from)
+                .and_then(|data| Ok(serde_json::from_slice::<Conversation>(&data)?))
+            {
+                Ok(conversation) => {
+                    conversations.insert(conversation.id.clone(), conversation);
+                }
+                Err(err) => warn!("Unterhaltung {} konnte nicht geladen werden: {}", path.display(), err),
+            }
+        }
+    }
+
+    /// Unterhaltungen des Benutzers, zuletzt geänderte zuerst
+    pub fn list(&self, user: &str) -> Vec<ConversationSummary> {
+        let mut summaries: Vec<ConversationSummary> = self
+            .conversations
+            .read()
+            .unwrap()
+            .values()
+            .filter(|conversation| conversation.user == user)
+            .map(|conversation| ConversationSummary {
+                id: conversation.id.clone(),
+                title: conversation.title.clone(),
+                message_count: conversation.nodes.len(),
+                created_at: conversation.created_at,
+                updated_at: conversation.updated_at,
+            })
+            .collect();
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.updated_at));
+        summaries
+    }
+
+    /// Unterhaltung des Benutzers; fremde Unterhaltungen gelten als nicht vorhanden
+    pub fn get(&self, user: &str, id: &str) -> Result<Conversation, ConversationError> {
+        self.conversations
+            .read()
+            .unwrap()
+            .get(id)
+            .filter(|conversation| conversation.user == user)
+            .cloned()
+            .ok_or_else(|| ConversationError::NotFound(id.to_string()))
+    }
+
+    pub async fn create(&self, user: &str, title: Option<String>) -> Result<Conversation, ConversationError> {
+        let _guard = self.writes.lock().await;
+        let now = Utc::now();
+        let conversation = Conversation {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.filter(|title| !title.trim().is_empty()).unwrap_or_else(|| DEFAULT_TITLE.to_string()),
+            user: user.to_string(),
+            nodes: Vec::new(),
+            active_id: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.save(&conversation).await?;
+        self.conversations.write().unwrap().insert(conversation.id.clone(), conversation.clone());
+        Ok(conversation)
+    }
+
+    /// Ändert die Unterhaltung mit `change` und speichert sie, wenn
+    /// `change` erfolgreich ist; sonst bleibt sie unverändert
+    pub async fn update<T>(
+        &self,
+        user: &str,
+        id: &str,
+        change: impl FnOnce(&mut Conversation) -> Result<T, ConversationError>,
+    ) -> Result<(Conversation, T), ConversationError> {
+        let _guard = self.writes.lock().await;
+        let mut conversation = self.get(user, id)?;
+        let result = change(&mut conversation)?;
+        conversation.updated_at = Utc::now();
+        self.save(&conversation).await?;
+        self.conversations.write().unwrap().insert(conversation.id.clone(), conversation.clone());
+        Ok((conversation, result))
+    }
+
+    pub async fn delete(&self, user: &str, id: &str) -> Result<(), ConversationError> {
+        let _guard = self.writes.lock().await;
+        self.get(user, id)?;
+        self.conversations.write().unwrap().remove(id);
+        match tokio::fs::remove_file(self.conversation_path(id)).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Beginnt einen Chat-Durchgang mit `messages` nach `parent` (sonst dem
+    /// Ende des aktiven Zweigs). Liefert den Kontext für das Modell und den
+    /// Durchgang; gespeichert wird erst mit der Antwort, sodass fehlgeschlagene
+    /// Anfragen die Unterhaltung nicht verändern.
+    pub fn begin_turn(
+        self: &Arc<Self>,
+        user: &str,
+        id: &str,
+        parent: Option<&str>,
+        messages: &[Message],
+    ) -> Result<(Vec<Message>, ConversationTurn), ConversationError> {
+        let conversation = self.get(user, id)?;
+        let parent = match parent {
+            Some(parent) => Some(conversation.require(parent)?.id.clone()),
+            None => conversation.active_id.clone(),
+        };
+        self.turn(&conversation, parent, messages.to_vec())
+    }
+
+    /// Beginnt einen Durchgang mit einer bearbeiteten Fassung von
+    /// `message_id` als Alternative zur ursprünglichen Nachricht
+    pub fn begin_edit_turn(
+        self: &Arc<Self>,
+        user: &str,
+        id: &str,
+        message_id: &str,
+        content: &str,
+    ) -> Result<(Vec<Message>, ConversationTurn), ConversationError> {
+        let conversation = self.get(user, id)?;
+        let node = conversation.require(message_id)?;
+        let message = edited(&node.message, content);
+        self.turn(&conversation, node.parent_id.clone(), vec![message])
+    }
+
+    fn turn(
+        self: &Arc<Self>,
+        conversation: &Conversation,
+        parent: Option<String>,
+        messages: Vec<Message>,
+    ) -> Result<(Vec<Message>, ConversationTurn), ConversationError> {
+        let mut context = conversation.context(parent.as_deref())?;
+        context.extend(messages.iter().cloned());
+        let turn = ConversationTurn {
+            store: self.clone(),
+            user: conversation.user.clone(),
+            conversation_id: conversation.id.clone(),
+            parent_id: parent,
+            messages,
+        };
+        Ok((context, turn))
+    }
+
+    async fn save(&self, conversation: &Conversation) -> Result<(), ConversationError> {
+        write_atomic(&self.conversation_path(&conversation.id), serde_json::to_vec_pretty(conversation)?).await?;
+        Ok(())
+    }
+
+    fn conversation_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+/// Laufender Chat-Durchgang; speichert die neuen Nachrichten zusammen mit
+/// der Antwort des Modells
+#[derive(Clone)]
+pub struct ConversationTurn {
+    store: Arc<ConversationStore>,
+    user: String,
+    conversation_id: String,
+    parent_id: Option<String>,
+    /// Neue Nachrichten des Benutzers, noch nicht gespeichert
+    messages: Vec<Message>,
+}
+
+impl ConversationTurn {
+    pub fn conversation_id(&self) -> &str {
+        &self.conversation_id
+    }
+
+    /// Speichert die Antwort und gibt ihre Kennung zurück
+    pub async fn complete(&self, message: Message) -> Result<String, ConversationError> {
+        let ids = self.complete_all(vec![message]).await?;
+        Ok(ids.into_iter().next().unwrap_or_default())
+    }
+
+    /// Speichert die neuen Nachrichten und mehrere Antwortkandidaten als
+    /// Alternativen; der erste wird Teil des aktiven Zweigs
+    pub async fn complete_all(&self, messages: Vec<Message>) -> Result<Vec<String>, ConversationError> {
+        let (_, ids) = self
+            .store
+            .update(&self.user, &self.conversation_id, |conversation| {
+                let mut last = self.parent_id.clone();
+                for message in &self.messages {
+                    last = Some(conversation.append(last.as_deref(), message.clone())?);
+                }
+                let ids = messages
+                    .into_iter()
+                    .map(|message| conversation.append(last.as_deref(), Message { role: Role::Assistant, ..message }))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some(first) = ids.first() {
+                    conversation.active_id = Some(first.clone());
+                }
+                Ok(ids)
+            })
+            .await?;
+        Ok(ids)
+    }
+}
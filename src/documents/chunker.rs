@@ -0,0 +1,107 @@
+/// Zerlegt Text in Abschnitte von höchstens `chunk_size` Zeichen.
+///
+/// Absätze (und Markdown-Überschriften) bleiben nach Möglichkeit zusammen;
+/// aufeinanderfolgende Abschnitte überlappen um bis zu `overlap` Zeichen,
+/// damit Kontext an den Grenzen nicht verloren geht.
+pub fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> Vec<String> {
+    let chunk_size = chunk_size.max(1);
+    let overlap = overlap.min(chunk_size / 2);
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for paragraph in paragraphs(text) {
+        for piece in split_long(&paragraph, chunk_size) {
+            if !current.is_empty() && char_len(&current) + 2 + char_len(&piece) > chunk_size {
+                let tail = overlap_tail(&current, overlap);
+                chunks.push(std::mem::take(&mut current));
+                // Überlappung nur übernehmen, wenn der nächste Abschnitt noch hineinpasst
+                if !tail.is_empty() && char_len(&tail) + 2 + char_len(&piece) <= chunk_size {
+                    current = tail;
+                }
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(&piece);
+        }
+    }
+
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Absätze getrennt durch Leerzeilen; Überschriften beginnen einen neuen Absatz
+fn paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim_end();
+        if trimmed.trim().is_empty() || (trimmed.starts_with('#') && !current.is_empty()) {
+            if !current.is_empty() {
+                paragraphs.push(current.join("\n"));
+                current.clear();
+            }
+            if trimmed.trim().is_empty() {
+                continue;
+            }
+        }
+        current.push(trimmed);
+    }
+    if !current.is_empty() {
+        paragraphs.push(current.join("\n"));
+    }
+    paragraphs
+}
+
+/// Teilt zu lange Absätze an Wortgrenzen (notfalls mitten im Wort)
+fn split_long(paragraph: &str, chunk_size: usize) -> Vec<String> {
+    if char_len(paragraph) <= chunk_size {
+        return vec![paragraph.to_string()];
+    }
+
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for word in paragraph.split_whitespace() {
+        let mut word = word.to_string();
+        while char_len(&word) > chunk_size {
+            let split: String = word.chars().take(chunk_size).collect();
+            word = word.chars().skip(chunk_size).collect();
+            if !current.is_empty() {
+                pieces.push(std::mem::take(&mut current));
+            }
+            pieces.push(split);
+        }
+        if !current.is_empty() && char_len(&current) + 1 + char_len(&word) > chunk_size {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// Ende eines Abschnitts mit höchstens `overlap` Zeichen, beginnend an einer Wortgrenze
+fn overlap_tail(chunk: &str, overlap: usize) -> String {
+    if overlap == 0 {
+        return String::new();
+    }
+    let skip = char_len(chunk).saturating_sub(overlap);
+    let tail: String = chunk.chars().skip(skip).collect();
+    match tail.find(char::is_whitespace) {
+        Some(pos) if skip > 0 => tail[pos..].trim().to_string(),
+        _ => tail.trim().to_string(),
+    }
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
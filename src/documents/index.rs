@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// Schlüssel eines Abschnitts: (Dokument-ID, Abschnittsnummer)
+pub type ChunkKey = (String, usize);
+
+struct IndexedChunk {
+    length: usize,
+    term_frequencies: HashMap<String, u32>,
+}
+
+/// Lokaler BM25-Volltextindex über Dokumentabschnitte
+#[derive(Default)]
+pub struct Bm25Index {
+    chunks: HashMap<ChunkKey, IndexedChunk>,
+    document_frequencies: HashMap<String, u32>,
+    total_length: usize,
+}
+
+impl Bm25Index {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn insert(&mut self, key: ChunkKey, text: &str) {
+        self.remove(&key);
+
+        let tokens = tokenize(text);
+        let mut term_frequencies: HashMap<String, u32> = HashMap::new();
+        for token in &tokens {
+            *term_frequencies.entry(token.clone()).or_default() += 1;
+        }
+        for term in term_frequencies.keys() {
+            *self.document_frequencies.entry(term.clone()).or_default() += 1;
+        }
+
+        self.total_length += tokens.len();
+        self.chunks.insert(key, IndexedChunk { length: tokens.len(), term_frequencies });
+    }
+
+    pub fn remove(&mut self, key: &ChunkKey) {
+        let Some(chunk) = self.chunks.remove(key) else { return };
+
+        self.total_length -= chunk.length;
+        for term in chunk.term_frequencies.keys() {
+            if let Some(count) = self.document_frequencies.get_mut(term) {
+                *count -= 1;
+                if *count == 0 {
+                    self.document_frequencies.remove(term);
+                }
+            }
+        }
+    }
+
+    /// Entfernt alle Abschnitte eines Dokuments
+    pub fn remove_document(&mut self, document_id: &str) {
+        let keys: Vec<ChunkKey> = self.chunks
+            .keys()
+            .filter(|(id, _)| id == document_id)
+            .cloned()
+            .collect();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+
+    /// Die besten `limit` Abschnitte, absteigend nach Score
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(ChunkKey, f32)> {
+        if self.chunks.is_empty() {
+            return Vec::new();
+        }
+
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        let count = self.chunks.len() as f32;
+        let average_length = (self.total_length as f32 / count).max(1.0);
+
+        let mut results: Vec<(ChunkKey, f32)> = self.chunks
+            .iter()
+            .filter_map(|(key, chunk)| {
+                let score: f32 = terms
+                    .iter()
+                    .filter_map(|term| {
+                        let tf = *chunk.term_frequencies.get(term)? as f32;
+                        let df = *self.document_frequencies.get(term)? as f32;
+                        let idf = (1.0 + (count - df + 0.5) / (df + 0.5)).ln();
+                        let norm = K1 * (1.0 - B + B * chunk.length as f32 / average_length);
+                        Some(idf * tf * (K1 + 1.0) / (tf + norm))
+                    })
+                    .sum();
+                (score > 0.0).then(|| (key.clone(), score))
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results.truncate(limit);
+        results
+    }
+}
+
+/// Kleinbuchstaben, getrennt an allem, was kein Buchstabe oder keine Ziffer ist
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .filter(|token| token.chars().count() > 1 || !token.is_ascii())
+        .collect()
+}
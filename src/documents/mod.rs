@@ -0,0 +1,9 @@
+pub mod chunker;
+pub mod index;
+pub mod store;
+pub mod tool;
+
+pub use chunker::chunk_text;
+pub use index::{tokenize, Bm25Index, ChunkKey};
+pub use store::{extract_text, Document, DocumentChunk, DocumentStore, DocumentSummary, Embedder, ExtractError, SearchHit};
+pub use tool::SearchDocuments;
@@ -0,0 +1,341 @@
+use super::chunker::chunk_text;
+use super::index::{Bm25Index, ChunkKey};
+use crate::client::{GlmClient, Message};
+use crate::config::DocumentsConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+/// Liefert Vektoren für Texte (z.B. über die Embeddings-API des Anbieters)
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>>;
+}
+
+#[async_trait]
+impl Embedder for GlmClient {
+    async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let response = self.embeddings(inputs, None).await?;
+        Ok(response.data.into_iter().map(|data| data.embedding).collect())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentChunk {
+    pub index: usize,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    pub id: String,
+    pub title: String,
+    pub content_type: String,
+    pub size: usize,
+    pub created_at: DateTime<Utc>,
+    pub chunks: Vec<DocumentChunk>,
+}
+
+/// Dokument ohne Inhalt für Listen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSummary {
+    pub id: String,
+    pub title: String,
+    pub content_type: String,
+    pub size: usize,
+    pub created_at: DateTime<Utc>,
+    pub chunk_count: usize,
+}
+
+impl From<&Document> for DocumentSummary {
+    fn from(document: &Document) -> Self {
+        Self {
+            id: document.id.clone(),
+            title: document.title.clone(),
+            content_type: document.content_type.clone(),
+            size: document.size,
+            created_at: document.created_at,
+            chunk_count: document.chunks.len(),
+        }
+    }
+}
+
+/// Treffer einer Dokumentsuche
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub document_id: String,
+    pub title: String,
+    pub chunk_index: usize,
+    /// Quellenangabe zum Zitieren, z.B. `doc:<id>#3`
+    pub reference: String,
+    pub text: String,
+    pub score: f32,
+}
+
+/// Dokumentablage mit BM25-Index (optional ergänzt um Embeddings).
+///
+/// Jedes Dokument wird als JSON-Datei inklusive Abschnitten gespeichert und
+/// beim Start neu indexiert.
+pub struct DocumentStore {
+    dir: PathBuf,
+    config: DocumentsConfig,
+    documents: RwLock<HashMap<String, Document>>,
+    index: RwLock<Bm25Index>,
+    embedder: Option<Arc<dyn Embedder>>,
+}
+
+impl DocumentStore {
+    /// Öffnet die Ablage und lädt vorhandene Dokumente
+    pub fn open(config: &DocumentsConfig) -> Self {
+        let store = Self {
+            dir: PathBuf::from(&config.dir),
+            config: config.clone(),
+            documents: RwLock::new(HashMap::new()),
+            index: RwLock::new(Bm25Index::new()),
+            embedder: None,
+        };
+        store.load_existing();
+        store
+    }
+
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    pub fn auto_context(&self) -> bool {
+        self.config.auto_context
+    }
+
+    pub fn max_file_size(&self) -> usize {
+        self.config.max_file_size
+    }
+
+    fn load_existing(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return };
+
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            match std::fs::read(&path).map_err(anyhow::Error::from).and_then(|data| Ok(serde_json::from_slice::<Document>(&data)?)) {
+                Ok(document) => self.insert(document),
+                Err(err) => warn!("Dokument {} konnte nicht geladen werden: {}", path.display(), err),
+            }
+        }
+    }
+
+    fn insert(&self, document: Document) {
+        {
+            let mut index = self.index.write().unwrap();
+            index.remove_document(&document.id);
+            for chunk in &document.chunks {
+                index.insert((document.id.clone(), chunk.index), &chunk.text);
+            }
+        }
+        self.documents.write().unwrap().insert(document.id.clone(), document);
+    }
+
+    /// Zerlegt, indexiert und speichert ein Dokument
+    pub async fn add(&self, title: &str, content_type: &str, text: &str) -> Result<DocumentSummary> {
+        if text.trim().is_empty() {
+            return Err(anyhow!("Dokument enthält keinen Text"));
+        }
+
+        let mut chunks: Vec<DocumentChunk> = chunk_text(text, self.config.chunk_size, self.config.chunk_overlap)
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| DocumentChunk { index, text, embedding: None })
+            .collect();
+
+        if let Some(embedder) = &self.embedder {
+            let inputs = chunks.iter().map(|chunk| chunk.text.clone()).collect();
+            match embedder.embed(inputs).await {
+                Ok(vectors) if vectors.len() == chunks.len() => {
+                    for (chunk, vector) in chunks.iter_mut().zip(vectors) {
+                        chunk.embedding = Some(vector);
+                    }
+                }
+                Ok(_) => warn!("Embeddings für '{}' unvollständig, nur BM25-Suche", title),
+                Err(err) => warn!("Embeddings für '{}' fehlgeschlagen, nur BM25-Suche: {}", title, err),
+            }
+        }
+
+        let document = Document {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: title.to_string(),
+            content_type: content_type.to_string(),
+            size: text.len(),
+            created_at: Utc::now(),
+            chunks,
+        };
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.document_path(&document.id), serde_json::to_vec(&document)?).await?;
+
+        let summary = DocumentSummary::from(&document);
+        self.insert(document);
+        Ok(summary)
+    }
+
+    pub fn list(&self) -> Vec<DocumentSummary> {
+        let mut documents: Vec<DocumentSummary> = self.documents
+            .read()
+            .unwrap()
+            .values()
+            .map(DocumentSummary::from)
+            .collect();
+        documents.sort_by_key(|document| std::cmp::Reverse(document.created_at));
+        documents
+    }
+
+    pub fn get(&self, id: &str) -> Option<Document> {
+        self.documents.read().unwrap().get(id).cloned()
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<bool> {
+        if self.documents.write().unwrap().remove(id).is_none() {
+            return Ok(false);
+        }
+        self.index.write().unwrap().remove_document(id);
+
+        match tokio::fs::remove_file(self.document_path(id)).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(true),
+        }
+    }
+
+    /// Sucht passende Abschnitte; mit Embeddings wird BM25 mit der
+    /// Kosinus-Ähnlichkeit kombiniert
+    pub async fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let query_embedding = match &self.embedder {
+            Some(embedder) => match embedder.embed(vec![query.to_string()]).await {
+                Ok(mut vectors) => vectors.pop(),
+                Err(err) => {
+                    warn!("Embedding der Suchanfrage fehlgeschlagen: {}", err);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let lexical = self.index.read().unwrap().search(query, limit.max(1) * 4);
+        let documents = self.documents.read().unwrap();
+
+        // Beide Verfahren auf [0, 1] normieren und gewichten
+        let lexical_weight = if query_embedding.is_some() { 0.5 } else { 1.0 };
+        let max_lexical = lexical.first().map(|(_, score)| *score).unwrap_or(1.0);
+        let mut scores: HashMap<ChunkKey, f32> = lexical
+            .into_iter()
+            .map(|(key, score)| (key, lexical_weight * score / max_lexical))
+            .collect();
+
+        if let Some(query_embedding) = &query_embedding {
+            for document in documents.values() {
+                for chunk in &document.chunks {
+                    let Some(embedding) = &chunk.embedding else { continue };
+                    let similarity = cosine_similarity(query_embedding, embedding);
+                    if similarity > 0.0 {
+                        *scores.entry((document.id.clone(), chunk.index)).or_default() += (1.0 - lexical_weight) * similarity;
+                    }
+                }
+            }
+        }
+
+        let mut ranked: Vec<(ChunkKey, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        ranked
+            .into_iter()
+            .filter_map(|((document_id, chunk_index), score)| {
+                let document = documents.get(&document_id)?;
+                let chunk = document.chunks.get(chunk_index)?;
+                Some(SearchHit {
+                    reference: format!("doc:{}#{}", document_id, chunk_index),
+                    title: document.title.clone(),
+                    text: chunk.text.clone(),
+                    document_id,
+                    chunk_index,
+                    score,
+                })
+            })
+            .take(limit)
+            .collect()
+    }
+
+    /// Systemnachricht mit passenden Auszügen und Zitierhinweis
+    pub async fn context_message(&self, query: &str) -> Option<Message> {
+        let hits = self.search(query, self.config.context_chunks).await;
+        if hits.is_empty() {
+            return None;
+        }
+
+        let mut context = String::from(
+            "Die folgenden Auszüge stammen aus hochgeladenen Dokumenten. Nutze sie, wenn sie zur Frage passen, \
+             und belege jede Aussage daraus mit der Quellenangabe in eckigen Klammern, z.B. [doc:<id>#<n>].\n",
+        );
+        for hit in &hits {
+            context.push_str(&format!("\n[{}] {}\n{}\n", hit.reference, hit.title, hit.text));
+        }
+        Some(Message::system(context))
+    }
+
+    fn document_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Fehler beim Lesen einer hochgeladenen Datei
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    #[error("Nicht unterstützter Dateityp: nur Text, Markdown und PDF")]
+    Unsupported,
+
+    #[error("PDF konnte nicht gelesen werden: {0}")]
+    InvalidPdf(String),
+}
+
+/// Extrahiert Text aus Text-, Markdown- und PDF-Dateien; gibt (Text, MIME-Typ) zurück.
+/// PDFs zu lesen ist rechenintensiv, im Server daher über `spawn_blocking` aufrufen.
+pub fn extract_text(data: &[u8], content_type: Option<&str>, filename: Option<&str>) -> Result<(String, &'static str), ExtractError> {
+    let extension = filename
+        .and_then(|name| Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    let content_type = content_type.map(|t| t.split(';').next().unwrap_or(t).trim().to_lowercase());
+
+    if data.starts_with(b"%PDF") || content_type.as_deref() == Some("application/pdf") || extension.as_deref() == Some("pdf") {
+        // pdf-extract bricht bei manchen beschädigten Dateien mit einem Panic ab
+        let text = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(data))
+            .map_err(|_| ExtractError::InvalidPdf("Datei ist beschädigt".to_string()))?
+            .map_err(|err| ExtractError::InvalidPdf(err.to_string()))?;
+        return Ok((text, "application/pdf"));
+    }
+
+    let text = String::from_utf8(data.to_vec()).map_err(|_| ExtractError::Unsupported)?;
+    let is_markdown = content_type.as_deref() == Some("text/markdown")
+        || matches!(extension.as_deref(), Some("md") | Some("markdown"));
+
+    Ok((text, if is_markdown { "text/markdown" } else { "text/plain" }))
+}
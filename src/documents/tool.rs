@@ -0,0 +1,61 @@
+use super::store::DocumentStore;
+use crate::client::types::ToolDefinition;
+use crate::functions::FunctionHandler;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Tool `search_documents`: durchsucht die hochgeladenen Dokumente
+pub struct SearchDocuments {
+    store: Arc<DocumentStore>,
+}
+
+impl SearchDocuments {
+    pub fn new(store: Arc<DocumentStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl FunctionHandler for SearchDocuments {
+    async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value> {
+        let query = arguments.get("query")
+            .and_then(|v| v.as_str())
+            .filter(|query| !query.trim().is_empty())
+            .ok_or_else(|| anyhow!("Fehlender Parameter 'query'"))?;
+        let limit = arguments.get("limit")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(5)
+            .clamp(1, 20) as usize;
+
+        let hits = self.store.search(query, limit).await;
+        Ok(json!({
+            "query": query,
+            "results": hits,
+            "count": hits.len()
+        }))
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new_function(
+            "search_documents".to_string(),
+            "Durchsucht die hochgeladenen Dokumente und liefert passende Abschnitte mit Quellenangabe (reference) zum Zitieren".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Suchbegriffe oder Frage"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximale Anzahl Treffer (1-20, Standard 5)"
+                    }
+                },
+                "required": ["query"]
+            }),
+        )
+    }
+}
@@ -0,0 +1,236 @@
+use super::function_call::RiskLevel;
+use crate::config::ApprovalConfig;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot};
+use tracing::warn;
+
+/// Grund für abgelehnte Anfragen ohne Verbindung, die sie bestätigen könnte
+const UNATTENDED_REASON: &str = "Bestätigung nur über eine WebSocket-Verbindung möglich";
+
+tokio::task_local! {
+    static OWNER: String;
+}
+
+/// Führt `future` im Namen von `owner` aus (z.B. einer WebSocket-Verbindung).
+/// Bestätigungsanfragen daraus kann nur dieser Besitzer beantworten.
+pub async fn with_owner<F: Future>(owner: String, future: F) -> F::Output {
+    OWNER.scope(owner, future).await
+}
+
+/// Besitzer der laufenden Ausführung, falls mit [`with_owner`] gesetzt
+pub fn current_owner() -> Option<String> {
+    OWNER.try_with(Clone::clone).ok()
+}
+
+/// Ein Tool-Aufruf, der auf die Bestätigung des Benutzers wartet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    /// Vom Server vergeben, unabhängig von der ID des Tool Calls
+    pub id: String,
+    /// ID des Tool Calls, zu dem die Anfrage gehört
+    pub call_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub tool: String,
+    pub arguments: HashMap<String, serde_json::Value>,
+    pub risk_level: RiskLevel,
+    pub requested_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalOutcome {
+    Approved,
+    Rejected,
+    TimedOut,
+}
+
+/// Eintrag im persistierten Entscheidungsprotokoll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalDecision {
+    #[serde(flatten)]
+    pub request: ApprovalRequest,
+    pub outcome: ApprovalOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub decided_at: DateTime<Utc>,
+}
+
+struct PendingApproval {
+    request: ApprovalRequest,
+    responder: oneshot::Sender<ApprovalDecision>,
+}
+
+/// Verwaltet offene Bestätigungen für riskante Tool-Aufrufe.
+///
+/// Neue Anfragen werden über `subscribe` verteilt, Entscheidungen kommen über
+/// `resolve_as` von der Verbindung, die den Aufruf gestartet hat, und werden
+/// als JSONL protokolliert.
+pub struct ApprovalManager {
+    threshold: RwLock<RiskLevel>,
+    timeout: RwLock<Duration>,
+    log_path: PathBuf,
+    pending: Mutex<HashMap<String, PendingApproval>>,
+    events: broadcast::Sender<ApprovalRequest>,
+}
+
+impl ApprovalManager {
+    pub fn new(config: &ApprovalConfig) -> Self {
+        let (events, _) = broadcast::channel(64);
+        Self {
+            threshold: RwLock::new(config.threshold),
+            timeout: RwLock::new(Duration::from_secs(config.timeout)),
+            log_path: PathBuf::from(&config.log_path),
+            pending: Mutex::new(HashMap::new()),
+            events,
+        }
+    }
+
+    pub fn requires_approval(&self, risk_level: RiskLevel) -> bool {
+        risk_level > *self.threshold.read().unwrap()
+    }
+
+    /// Übernimmt Schwelle und Timeout einer neuen Konfiguration; offene
+    /// Anfragen behalten ihren Timeout, das Protokoll bleibt am alten Ort
+    pub fn update(&self, config: &ApprovalConfig) {
+        *self.threshold.write().unwrap() = config.threshold;
+        *self.timeout.write().unwrap() = Duration::from_secs(config.timeout);
+    }
+
+    /// Empfängt alle neuen Bestätigungsanfragen
+    pub fn subscribe(&self) -> broadcast::Receiver<ApprovalRequest> {
+        self.events.subscribe()
+    }
+
+    pub fn pending(&self) -> Vec<ApprovalRequest> {
+        self.pending
+            .lock()
+            .unwrap()
+            .values()
+            .map(|pending| pending.request.clone())
+            .collect()
+    }
+
+    /// Beantwortet eine offene Anfrage; `false`, wenn die ID unbekannt ist.
+    /// Die Entscheidung wird sofort protokolliert, auch wenn der wartende Aufruf
+    /// danach abgebrochen wird.
+    pub fn resolve(&self, id: &str, approved: bool, reason: Option<String>) -> bool {
+        let Some(pending) = self.pending.lock().unwrap().remove(id) else {
+            return false;
+        };
+
+        let outcome = if approved { ApprovalOutcome::Approved } else { ApprovalOutcome::Rejected };
+        let decision = self.record(pending.request, outcome, reason);
+        let _ = pending.responder.send(decision);
+        true
+    }
+
+    /// Wie [`resolve`](Self::resolve), aber nur für Anfragen von `owner`;
+    /// fremde Anfragen bleiben unverändert offen
+    pub fn resolve_as(&self, owner: &str, id: &str, approved: bool, reason: Option<String>) -> bool {
+        let owned = self
+            .pending
+            .lock()
+            .unwrap()
+            .get(id)
+            .is_some_and(|pending| pending.request.owner.as_deref() == Some(owner));
+        owned && self.resolve(id, approved, reason)
+    }
+
+    /// Lehnt alle offenen Anfragen von `owner` ab, z.B. wenn dessen
+    /// Verbindung getrennt wurde
+    pub fn reject_all(&self, owner: &str, reason: &str) {
+        let ids: Vec<String> = self
+            .pending
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|pending| pending.request.owner.as_deref() == Some(owner))
+            .map(|pending| pending.request.id.clone())
+            .collect();
+        for id in ids {
+            self.resolve(&id, false, Some(reason.to_string()));
+        }
+    }
+
+    /// Pausiert den Aufruf bis zur Entscheidung oder bis zum Timeout. Ohne
+    /// Besitzer kann niemand antworten; solche Anfragen werden sofort abgelehnt.
+    pub async fn request_approval(&self, request: ApprovalRequest) -> ApprovalDecision {
+        if request.owner.is_none() {
+            return self.record(request, ApprovalOutcome::Rejected, Some(UNATTENDED_REASON.to_string()));
+        }
+        let (responder, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(
+            request.id.clone(),
+            PendingApproval { request: request.clone(), responder },
+        );
+        let _ = self.events.send(request.clone());
+        // Wird der Aufruf abgebrochen, darf die Anfrage nicht offen bleiben
+        let _guard = PendingGuard { manager: self, id: &request.id };
+
+        let timeout = *self.timeout.read().unwrap();
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(decision)) => decision,
+            Ok(Err(_)) => self.record(request.clone(), ApprovalOutcome::Rejected, Some("Anfrage verworfen".to_string())),
+            Err(_) => self.record(request.clone(), ApprovalOutcome::TimedOut, None),
+        }
+    }
+
+    fn record(&self, request: ApprovalRequest, outcome: ApprovalOutcome, reason: Option<String>) -> ApprovalDecision {
+        let decision = ApprovalDecision {
+            request,
+            outcome,
+            reason,
+            decided_at: Utc::now(),
+        };
+        if let Err(err) = self.append_log(&decision) {
+            warn!("Bestätigungsprotokoll konnte nicht geschrieben werden: {}", err);
+        }
+        decision
+    }
+
+    /// Liest die letzten `limit` Entscheidungen aus dem Protokoll
+    pub async fn decisions(&self, limit: usize) -> Vec<ApprovalDecision> {
+        let content = tokio::fs::read_to_string(&self.log_path).await.unwrap_or_default();
+        let decisions: Vec<ApprovalDecision> = content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let skip = decisions.len().saturating_sub(limit);
+        decisions.into_iter().skip(skip).collect()
+    }
+
+    fn append_log(&self, decision: &ApprovalDecision) -> std::io::Result<()> {
+        if let Some(parent) = self.log_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut line = serde_json::to_string(decision)?;
+        line.push('\n');
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)?;
+        file.write_all(line.as_bytes())
+    }
+}
+
+struct PendingGuard<'a> {
+    manager: &'a ApprovalManager,
+    id: &'a str,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.manager.pending.lock() {
+            pending.remove(self.id);
+        }
+    }
+}
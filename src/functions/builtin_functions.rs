@@ -1,11 +1,13 @@
-use super::function_call::{FunctionDefinition, FunctionHandler, ParameterDefinition, FunctionParameters};
+use super::function_call::FunctionHandler;
 use crate::client::types::ToolDefinition;
 use std::collections::HashMap;
+use anyhow::Result;
 use async_trait::async_trait;
 
 // Mathematische Berechnungen
 pub struct Calculator;
 
+#[async_trait]
 impl FunctionHandler for Calculator {
     async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value> {
         let expression = arguments.get("expression")
@@ -67,32 +69,28 @@ impl FunctionHandler for Calculator {
         }))
     }
 
-    fn definition(&self) -> FunctionDefinition {
-        let mut properties = HashMap::new();
-        properties.insert(
-            "expression".to_string(),
-            ParameterDefinition {
-                param_type: "string".to_string(),
-                description: "Mathematischer Ausdruck zum Berechnen (z.B. '2 + 3')".to_string(),
-                enum_values: None,
-            },
-        );
-
-        FunctionDefinition {
-            name: "calculate".to_string(),
-            description: "Führt einfache mathematische Berechnungen durch".to_string(),
-            parameters: FunctionParameters {
-                param_type: "object".to_string(),
-                properties,
-                required: vec!["expression".to_string()],
-            },
-        }
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new_function(
+            "calculate".to_string(),
+            "Führt einfache mathematische Berechnungen durch".to_string(),
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "expression": {
+                        "type": "string",
+                        "description": "Mathematischer Ausdruck zum Berechnen (z.B. '2 + 3')"
+                    }
+                },
+                "required": ["expression"]
+            })
+        )
     }
 }
 
 // Text-Utilities
 pub struct TextAnalyzer;
 
+#[async_trait]
 impl FunctionHandler for TextAnalyzer {
     async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value> {
         let text = arguments.get("text")
@@ -116,32 +114,28 @@ impl FunctionHandler for TextAnalyzer {
         }))
     }
 
-    fn definition(&self) -> FunctionDefinition {
-        let mut properties = HashMap::new();
-        properties.insert(
-            "text".to_string(),
-            ParameterDefinition {
-                param_type: "string".to_string(),
-                description: "Der zu analysierende Text".to_string(),
-                enum_values: None,
-            },
-        );
-
-        FunctionDefinition {
-            name: "analyze_text".to_string(),
-            description: "Analysiert einen Text und gibt Statistiken zurück".to_string(),
-            parameters: FunctionParameters {
-                param_type: "object".to_string(),
-                properties,
-                required: vec!["text".to_string()],
-            },
-        }
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new_function(
+            "analyze_text".to_string(),
+            "Analysiert einen Text und gibt Statistiken zurück".to_string(),
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "description": "Der zu analysierende Text"
+                    }
+                },
+                "required": ["text"]
+            })
+        )
     }
 }
 
 // UUID-Generator
 pub struct UuidGenerator;
 
+#[async_trait]
 impl FunctionHandler for UuidGenerator {
     async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value> {
         let count = arguments.get("count")
@@ -159,25 +153,20 @@ impl FunctionHandler for UuidGenerator {
         }))
     }
 
-    fn definition(&self) -> FunctionDefinition {
-        let mut properties = HashMap::new();
-        properties.insert(
-            "count".to_string(),
-            ParameterDefinition {
-                param_type: "integer".to_string(),
-                description: "Anzahl der zu generierenden UUIDs (max. 10)".to_string(),
-                enum_values: None,
-            },
-        );
-
-        FunctionDefinition {
-            name: "generate_uuid".to_string(),
-            description: "Generiert eine oder mehrere UUIDs".to_string(),
-            parameters: FunctionParameters {
-                param_type: "object".to_string(),
-                properties,
-                required: vec![],
-            },
-        }
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new_function(
+            "generate_uuid".to_string(),
+            "Generiert eine oder mehrere UUIDs".to_string(),
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "count": {
+                        "type": "integer",
+                        "description": "Anzahl der zu generierenden UUIDs (max. 10)"
+                    }
+                },
+                "required": []
+            })
+        )
     }
 }
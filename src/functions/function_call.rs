@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use crate::client::types::ToolDefinition;
+use anyhow::Result;
 use async_trait::async_trait;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,16 +42,69 @@ pub struct FunctionResult {
     pub error: Option<String>,
 }
 
+/// Risikostufe eines Tools; Aufrufe oberhalb der konfigurierten Schwelle benötigen eine Bestätigung
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    /// Nur lesend, keine Seiteneffekte
+    #[default]
+    Low,
+    /// Begrenzte Seiteneffekte, z.B. Netzwerkzugriffe
+    Medium,
+    /// Schreibt Dateien oder führt Befehle aus
+    High,
+    /// Irreversible oder systemweite Änderungen
+    Critical,
+}
+
+/// Ausgabekanal eines laufenden Tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Teilausgabe, die ein Tool während der Ausführung liefert
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolOutput {
+    pub stream: OutputStream,
+    pub data: String,
+}
+
+pub type ToolOutputSender = tokio::sync::mpsc::UnboundedSender<ToolOutput>;
+
 // Trait für Function Call Handler
-#[async_trait::async_trait]
+#[async_trait]
 pub trait FunctionHandler: Send + Sync {
-    async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value, String>;
+    async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value>;
     fn definition(&self) -> ToolDefinition;
+
+    /// Wie `execute`, sendet aber Zwischenausgaben über `output`.
+    /// Handler ohne Streaming-Unterstützung liefern nur das Endergebnis.
+    async fn execute_streaming(
+        &self,
+        arguments: HashMap<String, serde_json::Value>,
+        _output: ToolOutputSender,
+    ) -> Result<serde_json::Value> {
+        self.execute(arguments).await
+    }
+
+    /// Risikostufe, anhand derer über eine Bestätigungspflicht entschieden wird
+    fn risk_level(&self) -> RiskLevel {
+        RiskLevel::Low
+    }
+
+    /// Eigenes Timeout des Tools; `None` verwendet das Standard-Timeout der Registry
+    fn timeout(&self) -> Option<std::time::Duration> {
+        None
+    }
 }
 
 // Beispiel-Handler für Zeit-Abfrage
 pub struct GetCurrentTime;
 
+#[async_trait]
 impl FunctionHandler for GetCurrentTime {
     async fn execute(&self, _arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value> {
         use chrono::{DateTime, Utc};
@@ -78,6 +132,7 @@ impl FunctionHandler for GetCurrentTime {
 // Beispiel-Handler für Wetter-Abfrage
 pub struct GetWeather;
 
+#[async_trait]
 impl FunctionHandler for GetWeather {
     async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value> {
         let location = arguments.get("location")
@@ -1,7 +1,13 @@
+pub mod approval;
 pub mod function_call;
 pub mod registry;
 pub mod builtin_functions;
+pub mod plugins;
+pub mod run_command;
 
+pub use approval::*;
 pub use function_call::*;
 pub use registry::*;
 pub use builtin_functions::*;
+pub use plugins::{PluginLoader, PluginManifest, PluginTarget, PluginTool};
+pub use run_command::RunCommand;
@@ -0,0 +1,319 @@
+use super::function_call::{FunctionHandler, RiskLevel};
+use super::registry::FunctionRegistry;
+use crate::client::types::ToolDefinition;
+use crate::config::PluginsConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// Dateiendungen, die als Tool-Manifest geladen werden
+const MANIFEST_EXTENSIONS: [&str; 4] = ["toml", "json", "yaml", "yml"];
+
+/// Beschreibung eines externen Tools aus dem Plugin-Verzeichnis
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub description: String,
+    /// JSON-Schema der Parameter
+    #[serde(default = "default_parameters")]
+    pub parameters: serde_json::Value,
+    /// Timeout in Sekunden
+    pub timeout: Option<u64>,
+    /// Ohne Angabe `high` für Programme und `low` für HTTP-Endpunkte
+    #[serde(default)]
+    pub risk_level: Option<RiskLevel>,
+    #[serde(flatten)]
+    pub target: PluginTarget,
+}
+
+/// Wie das Tool aufgerufen wird
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum PluginTarget {
+    /// Lokales Programm: erhält `{"name", "arguments"}` als JSON auf stdin und
+    /// schreibt das Ergebnis als JSON auf stdout. Es läuft im Plugin-Verzeichnis
+    /// und sieht nur die Umgebungsvariablen aus `env_passthrough`.
+    Executable {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// HTTP-Endpunkt: erhält `{"name", "arguments"}` per POST und antwortet mit JSON
+    Http {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+fn default_parameters() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+impl PluginManifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let manifest: Self = config::Config::builder()
+            .add_source(config::File::from(path))
+            .build()?
+            .try_deserialize()?;
+
+        if manifest.name.trim().is_empty() {
+            return Err(anyhow!("Tool-Name darf nicht leer sein"));
+        }
+        Ok(manifest)
+    }
+
+    /// Angegebene Risikostufe; lokale Programme ohne Angabe gelten wie
+    /// MCP-Server als `high` und brauchen eine Bestätigung
+    pub fn risk_level(&self) -> RiskLevel {
+        self.risk_level.unwrap_or(match self.target {
+            PluginTarget::Executable { .. } => RiskLevel::High,
+            PluginTarget::Http { .. } => RiskLevel::Low,
+        })
+    }
+}
+
+/// Tool, das über ein Manifest definiert ist
+pub struct PluginTool {
+    manifest: PluginManifest,
+    http: reqwest::Client,
+    /// Arbeitsverzeichnis für Programme
+    dir: PathBuf,
+    env_passthrough: Vec<String>,
+}
+
+impl PluginTool {
+    pub fn new(manifest: PluginManifest, config: &PluginsConfig) -> Self {
+        Self {
+            manifest,
+            http: reqwest::Client::new(),
+            dir: PathBuf::from(&config.dir),
+            env_passthrough: config.env_passthrough.clone(),
+        }
+    }
+
+    fn request_body(&self, arguments: HashMap<String, serde_json::Value>) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.manifest.name,
+            "arguments": arguments
+        })
+    }
+
+    async fn call_executable(&self, command: &str, args: &[String], body: serde_json::Value) -> Result<serde_json::Value> {
+        let mut command = Command::new(command);
+        command
+            .args(args)
+            .current_dir(&self.dir)
+            .env_clear()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        for name in &self.env_passthrough {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+        let mut child = command
+            .spawn()
+            .map_err(|err| anyhow!("Plugin '{}' konnte nicht gestartet werden: {}", self.manifest.name, err))?;
+
+        // Eingabe schreiben und Ausgabe lesen gleichzeitig, sonst blockieren
+        // sich Plugin und Server bei vollen Pipes gegenseitig
+        let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("stdin nicht verfügbar"))?;
+        let input = body.to_string();
+        let write = async move {
+            let written = stdin.write_all(input.as_bytes()).await;
+            drop(stdin);
+            written
+        };
+        let (written, output) = tokio::join!(write, child.wait_with_output());
+        let output = output?;
+        // Ein Plugin darf enden, ohne die Eingabe zu lesen
+        if let Err(err) = written {
+            if err.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(err.into());
+            }
+        }
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Plugin '{}' beendet mit {}: {}",
+                self.manifest.name,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        serde_json::from_slice(&output.stdout)
+            .map_err(|err| anyhow!("Plugin '{}' lieferte kein gültiges JSON: {}", self.manifest.name, err))
+    }
+
+    async fn call_http(&self, url: &str, headers: &HashMap<String, String>, body: serde_json::Value) -> Result<serde_json::Value> {
+        let mut request = self.http.post(url).json(&body);
+        for (name, value) in crate::telemetry::trace_headers().iter().chain(headers) {
+            request = request.header(name, value);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow!(
+                "Plugin '{}' antwortete mit HTTP {}: {}",
+                self.manifest.name,
+                status.as_u16(),
+                response.text().await.unwrap_or_default()
+            ));
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+#[async_trait]
+impl FunctionHandler for PluginTool {
+    async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value> {
+        let body = self.request_body(arguments);
+        match &self.manifest.target {
+            PluginTarget::Executable { command, args } => self.call_executable(command, args, body).await,
+            PluginTarget::Http { url, headers } => self.call_http(url, headers, body).await,
+        }
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new_function(
+            self.manifest.name.clone(),
+            self.manifest.description.clone(),
+            self.manifest.parameters.clone(),
+        )
+    }
+
+    fn risk_level(&self) -> RiskLevel {
+        self.manifest.risk_level()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.manifest.timeout.map(Duration::from_secs)
+    }
+}
+
+/// Lädt Tool-Manifeste aus einem Verzeichnis in die Registry und hält sie aktuell.
+pub struct PluginLoader {
+    config: PluginsConfig,
+    dir: PathBuf,
+    registry: Arc<FunctionRegistry>,
+    /// Von diesem Loader registrierte Tools
+    loaded: Mutex<HashSet<String>>,
+    /// Letzter bekannter Stand des Verzeichnisses (Datei → Änderungszeit, Größe)
+    fingerprint: Mutex<BTreeMap<PathBuf, (SystemTime, u64)>>,
+}
+
+impl PluginLoader {
+    pub fn new(config: &PluginsConfig, registry: Arc<FunctionRegistry>) -> Self {
+        Self {
+            config: config.clone(),
+            dir: PathBuf::from(&config.dir),
+            registry,
+            loaded: Mutex::new(HashSet::new()),
+            fingerprint: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Lädt alle Manifeste neu und gleicht die Registry ab.
+    /// Gibt die Namen der aktuell geladenen Plugin-Tools zurück.
+    pub fn reload(&self) -> Vec<String> {
+        let mut current = HashSet::new();
+
+        for path in self.manifest_paths() {
+            let manifest = match PluginManifest::load(&path) {
+                Ok(manifest) => manifest,
+                Err(err) => {
+                    warn!("Ungültiges Tool-Manifest {}: {}", path.display(), err);
+                    continue;
+                }
+            };
+
+            let name = manifest.name.clone();
+            let owned_by_loader = self.loaded.lock().unwrap().contains(&name);
+            if current.contains(&name) || (self.registry.has_function(&name) && !owned_by_loader) {
+                warn!("Tool '{}' aus {} existiert bereits und wird ignoriert", name, path.display());
+                continue;
+            }
+
+            self.registry.register(&name, Arc::new(PluginTool::new(manifest, &self.config)));
+            current.insert(name);
+        }
+
+        let mut loaded = self.loaded.lock().unwrap();
+        for removed in loaded.difference(&current) {
+            self.registry.unregister(removed);
+            info!("Plugin-Tool '{}' entfernt", removed);
+        }
+        *loaded = current;
+
+        let mut names: Vec<String> = loaded.iter().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Lädt neu, wenn sich Dateien im Verzeichnis geändert haben
+    pub fn reload_if_changed(&self) -> bool {
+        let fingerprint = self.fingerprint();
+        {
+            let mut known = self.fingerprint.lock().unwrap();
+            if *known == fingerprint {
+                return false;
+            }
+            *known = fingerprint;
+        }
+
+        let names = self.reload();
+        info!("Plugin-Tools neu geladen: {}", names.join(", "));
+        true
+    }
+
+    /// Prüft das Verzeichnis im angegebenen Intervall auf Änderungen
+    pub fn spawn_watcher(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.reload_if_changed();
+            }
+        })
+    }
+
+    fn manifest_paths(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| MANIFEST_EXTENSIONS.contains(&ext))
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    fn fingerprint(&self) -> BTreeMap<PathBuf, (SystemTime, u64)> {
+        self.manifest_paths()
+            .into_iter()
+            .filter_map(|path| {
+                let metadata = std::fs::metadata(&path).ok()?;
+                Some((path, (metadata.modified().ok()?, metadata.len())))
+            })
+            .collect()
+    }
+}
@@ -1,71 +1,319 @@
-use super::function_call::{FunctionDefinition, FunctionHandler, FunctionResult, GetCurrentTime, GetWeather};
+use super::approval::{current_owner, ApprovalManager, ApprovalOutcome, ApprovalRequest};
+use super::builtin_functions::{Calculator, TextAnalyzer, UuidGenerator};
+use super::function_call::{FunctionHandler, FunctionResult, GetCurrentTime, GetWeather, ToolOutputSender};
+use crate::client::types::{ToolCall, ToolCallResult, ToolDefinition};
+use crate::config::ToolExecutionConfig;
+use crate::metrics::Metrics;
 use std::collections::HashMap;
-use std::sync::Arc;
-use anyhow::Result;
-use async_trait::async_trait;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tracing::Instrument;
 
 pub struct FunctionRegistry {
-    handlers: HashMap<String, Arc<dyn FunctionHandler>>,
+    handlers: RwLock<HashMap<String, Arc<dyn FunctionHandler>>>,
+    approvals: Option<Arc<ApprovalManager>>,
+    execution: RwLock<ToolExecutionConfig>,
+    concurrency: RwLock<Arc<Semaphore>>,
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl FunctionRegistry {
     pub fn new() -> Self {
-        let mut registry = Self {
-            handlers: HashMap::new(),
+        let execution = ToolExecutionConfig::default();
+        let registry = Self {
+            handlers: RwLock::new(HashMap::new()),
+            approvals: None,
+            concurrency: RwLock::new(Arc::new(Semaphore::new(execution.max_concurrency))),
+            execution: RwLock::new(execution),
+            metrics: None,
         };
         
         // Registriere Built-in Funktionen
         registry.register("get_current_time", Arc::new(GetCurrentTime));
         registry.register("get_weather", Arc::new(GetWeather));
+        registry.register("calculate", Arc::new(Calculator));
+        registry.register("analyze_text", Arc::new(TextAnalyzer));
+        registry.register("generate_uuid", Arc::new(UuidGenerator));
         
         registry
     }
 
-    pub fn register(&mut self, name: &str, handler: Arc<dyn FunctionHandler>) {
-        self.handlers.insert(name.to_string(), handler);
+    pub fn register(&self, name: &str, handler: Arc<dyn FunctionHandler>) {
+        self.handlers.write().unwrap().insert(name.to_string(), handler);
     }
 
-    pub fn get_definitions(&self) -> Vec<FunctionDefinition> {
+    /// Entfernt eine Funktion; `false`, wenn sie nicht registriert war
+    pub fn unregister(&self, name: &str) -> bool {
+        self.handlers.write().unwrap().remove(name).is_some()
+    }
+
+    pub fn get_definitions(&self) -> Vec<ToolDefinition> {
         self.handlers
+            .read()
+            .unwrap()
             .values()
             .map(|handler| handler.definition())
             .collect()
     }
 
+    /// Aktiviert die Bestätigungspflicht für Tools oberhalb der Risikoschwelle
+    pub fn with_approvals(mut self, approvals: Arc<ApprovalManager>) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    pub fn approvals(&self) -> Option<&Arc<ApprovalManager>> {
+        self.approvals.as_ref()
+    }
+
+    /// Setzt Timeouts und die maximale Anzahl gleichzeitiger Aufrufe
+    pub fn with_execution_limits(self, execution: ToolExecutionConfig) -> Self {
+        self.set_execution_limits(execution);
+        self
+    }
+
+    /// Ersetzt die Limits zur Laufzeit. Laufende Aufrufe behalten ihren Platz
+    /// im bisherigen Kontingent, neue Aufrufe zählen gegen das neue.
+    pub fn set_execution_limits(&self, execution: ToolExecutionConfig) {
+        let max_concurrency = execution.max_concurrency.max(1);
+        let mut current = self.execution.write().unwrap();
+        if current.max_concurrency.max(1) != max_concurrency {
+            *self.concurrency.write().unwrap() = Arc::new(Semaphore::new(max_concurrency));
+        }
+        *current = execution;
+    }
+
+    /// Erfasst die Laufzeit jeder Ausführung in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Timeout für ein Tool: Konfiguration vor Handler-Vorgabe vor Standardwert
+    pub fn timeout_for(&self, name: &str, handler: &dyn FunctionHandler) -> Duration {
+        let execution = self.execution.read().unwrap();
+        execution
+            .timeouts
+            .get(name)
+            .map(|secs| Duration::from_secs(*secs))
+            .or_else(|| handler.timeout())
+            .unwrap_or_else(|| Duration::from_secs(execution.default_timeout))
+    }
+
+    /// Ob `name` vor der Ausführung bestätigt werden muss
+    pub fn requires_approval(&self, name: &str) -> bool {
+        match (self.get(name), &self.approvals) {
+            (Some(handler), Some(approvals)) => approvals.requires_approval(handler.risk_level()),
+            _ => false,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn FunctionHandler>> {
+        self.handlers.read().unwrap().get(name).cloned()
+    }
+
     pub async fn execute_function(
         &self,
         name: &str,
         arguments: HashMap<String, serde_json::Value>,
     ) -> FunctionResult {
-        match self.handlers.get(name) {
+        let call_id = uuid::Uuid::new_v4().to_string();
+        match self.get(name) {
             Some(handler) => {
-                match handler.execute(arguments).await {
-                    Ok(result) => FunctionResult {
-                        success: true,
-                        result,
-                        error: None,
-                    },
-                    Err(err) => FunctionResult {
-                        success: false,
-                        result: serde_json::Value::Null,
-                        error: Some(err.to_string()),
-                    },
+                if let Some(rejected) = self.await_approval(&call_id, name, handler.as_ref(), &arguments).await {
+                    return rejected;
                 }
+                Self::to_result(self.run_limited(name, handler.as_ref(), handler.execute(arguments)).await)
+            }
+            None => Self::not_found(name),
+        }
+    }
+
+    /// Wie [`execute_function`](Self::execute_function), aber ohne
+    /// Bestätigungspflicht: Der Aufrufer hat die Bestätigung selbst eingeholt
+    /// (z.B. ein MCP-Client mit `allow_risky_tools`)
+    pub async fn execute_function_preapproved(
+        &self,
+        name: &str,
+        arguments: HashMap<String, serde_json::Value>,
+    ) -> FunctionResult {
+        match self.get(name) {
+            Some(handler) => Self::to_result(self.run_limited(name, handler.as_ref(), handler.execute(arguments)).await),
+            None => Self::not_found(name),
+        }
+    }
+
+    /// Führt eine Funktion aus und leitet Zwischenausgaben an `output` weiter.
+    /// `call_id` identifiziert den Aufruf in Bestätigungsanfragen.
+    pub async fn execute_function_streaming(
+        &self,
+        call_id: &str,
+        name: &str,
+        arguments: HashMap<String, serde_json::Value>,
+        output: ToolOutputSender,
+    ) -> FunctionResult {
+        match self.get(name) {
+            Some(handler) => {
+                if let Some(rejected) = self.await_approval(call_id, name, handler.as_ref(), &arguments).await {
+                    return rejected;
+                }
+                let execution = handler.execute_streaming(arguments, output);
+                Self::to_result(self.run_limited(name, handler.as_ref(), execution).await)
+            }
+            None => Self::not_found(name),
+        }
+    }
+
+    /// Führt alle Tool Calls einer Modellantwort gleichzeitig aus.
+    ///
+    /// Die Ergebnisse stehen in derselben Reihenfolge wie `tool_calls`. Wird das
+    /// zurückgegebene Future verworfen (z.B. bei Verbindungsabbruch), werden alle
+    /// noch laufenden Aufrufe abgebrochen.
+    pub async fn execute_tool_calls(&self, tool_calls: &[ToolCall]) -> Vec<ToolCallResult> {
+        futures::future::join_all(tool_calls.iter().map(|call| self.execute_tool_call(call))).await
+    }
+
+    /// Führt einen einzelnen Tool Call des Modells aus
+    pub async fn execute_tool_call(&self, call: &ToolCall) -> ToolCallResult {
+        let started = Instant::now();
+        let name = call.function.name.as_str();
+
+        let result = match Self::parse_arguments(&call.function.arguments) {
+            Ok(arguments) => match self.get(name) {
+                Some(handler) => match self.await_approval(&call.id, name, handler.as_ref(), &arguments).await {
+                    Some(rejected) => rejected,
+                    None => Self::to_result(self.run_limited(name, handler.as_ref(), handler.execute(arguments)).await),
+                },
+                None => Self::not_found(name),
+            },
+            Err(err) => FunctionResult {
+                success: false,
+                result: serde_json::Value::Null,
+                error: Some(format!("Ungültige Argumente für '{}': {}", name, err)),
+            },
+        };
+
+        let output = match &result.error {
+            Some(error) => serde_json::json!({ "error": error }).to_string(),
+            None => result.result.to_string(),
+        };
+
+        ToolCallResult {
+            tool_call_id: call.id.clone(),
+            output,
+            error: result.error,
+            duration_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    fn parse_arguments(arguments: &str) -> serde_json::Result<HashMap<String, serde_json::Value>> {
+        if arguments.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+        serde_json::from_str(arguments)
+    }
+
+    /// Begrenzt Laufzeit und Parallelität einer Ausführung; sie läuft in einem
+    /// Span `tool` mit Name und Ergebnis des Tools
+    async fn run_limited<F>(&self, name: &str, handler: &dyn FunctionHandler, execution: F) -> anyhow::Result<serde_json::Value>
+    where
+        F: Future<Output = anyhow::Result<serde_json::Value>>,
+    {
+        let concurrency = self.concurrency.read().unwrap().clone();
+        let _permit = concurrency.acquire_owned().await?;
+        let timeout = self.timeout_for(name, handler);
+        let span = tracing::info_span!("tool", tool = name, outcome = tracing::field::Empty);
+        let started = Instant::now();
+        let (result, outcome) = match tokio::time::timeout(timeout, execution).instrument(span.clone()).await {
+            Ok(result) => {
+                let outcome = if result.is_ok() { "success" } else { "error" };
+                (result, outcome)
             }
-            None => FunctionResult {
+            Err(_) => (Err(anyhow::anyhow!(
+                "Zeitüberschreitung: '{}' hat länger als {} s gedauert",
+                name,
+                timeout.as_secs()
+            )), "timeout"),
+        };
+        span.record("outcome", outcome);
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_tool(name, outcome, started.elapsed());
+        }
+        result
+    }
+
+    /// Wartet bei riskanten Tools auf die Entscheidung des Benutzers.
+    /// Gibt ein Fehlerergebnis zurück, wenn der Aufruf nicht ausgeführt werden darf.
+    async fn await_approval(
+        &self,
+        call_id: &str,
+        name: &str,
+        handler: &dyn FunctionHandler,
+        arguments: &HashMap<String, serde_json::Value>,
+    ) -> Option<FunctionResult> {
+        let approvals = self.approvals.as_ref()?;
+        let risk_level = handler.risk_level();
+        if !approvals.requires_approval(risk_level) {
+            return None;
+        }
+
+        let decision = approvals.request_approval(ApprovalRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            call_id: call_id.to_string(),
+            owner: current_owner(),
+            tool: name.to_string(),
+            arguments: arguments.clone(),
+            risk_level,
+            requested_at: chrono::Utc::now(),
+        }).await;
+
+        let error = match decision.outcome {
+            ApprovalOutcome::Approved => return None,
+            ApprovalOutcome::Rejected => match decision.reason {
+                Some(reason) => format!("Ausführung von '{}' abgelehnt: {}", name, reason),
+                None => format!("Ausführung von '{}' abgelehnt", name),
+            },
+            ApprovalOutcome::TimedOut => format!("Keine Bestätigung für '{}' erhalten (Timeout)", name),
+        };
+
+        Some(FunctionResult {
+            success: false,
+            result: serde_json::Value::Null,
+            error: Some(error),
+        })
+    }
+
+    fn to_result(result: anyhow::Result<serde_json::Value>) -> FunctionResult {
+        match result {
+            Ok(result) => FunctionResult {
+                success: true,
+                result,
+                error: None,
+            },
+            Err(err) => FunctionResult {
                 success: false,
                 result: serde_json::Value::Null,
-                error: Some(format!("Funktion '{}' nicht gefunden", name)),
+                error: Some(err.to_string()),
             },
         }
     }
 
+    fn not_found(name: &str) -> FunctionResult {
+        FunctionResult {
+            success: false,
+            result: serde_json::Value::Null,
+            error: Some(format!("Funktion '{}' nicht gefunden", name)),
+        }
+    }
+
     pub fn has_function(&self, name: &str) -> bool {
-        self.handlers.contains_key(name)
+        self.handlers.read().unwrap().contains_key(name)
     }
 
     pub fn list_functions(&self) -> Vec<String> {
-        self.handlers.keys().cloned().collect()
+        self.handlers.read().unwrap().keys().cloned().collect()
     }
 }
 
@@ -0,0 +1,338 @@
+use super::function_call::{FunctionHandler, OutputStream, RiskLevel, ToolOutput, ToolOutputSender};
+use crate::client::types::ToolDefinition;
+use crate::config::RunCommandConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+
+/// Führt freigegebene Befehle im Workspace-Verzeichnis aus.
+///
+/// Befehle werden ohne Shell in einer eigenen Prozessgruppe gestartet, nur
+/// Programme aus `allowed_commands` sind erlaubt, die Umgebung wird auf
+/// `env_passthrough` reduziert und die Ausgabe pro Stream auf
+/// `max_output_bytes` gekürzt.
+pub struct RunCommand {
+    config: RunCommandConfig,
+    workspace: PathBuf,
+}
+
+/// Gesammelte Ausgabe eines Streams
+#[derive(Default)]
+struct CapturedOutput {
+    data: Vec<u8>,
+    truncated: bool,
+}
+
+/// Beendet die Prozessgruppe des Befehls samt aller Kindprozesse, wenn die
+/// Ausführung abbricht (Timeout oder verworfener Aufruf)
+struct ProcessGroup {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    id: Option<u32>,
+    finished: bool,
+}
+
+impl ProcessGroup {
+    fn kill(&mut self) {
+        #[cfg(unix)]
+        if let Some(id) = self.id.take() {
+            // SAFETY: killpg hat keine Speichervoraussetzungen
+            unsafe {
+                libc::killpg(id as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.kill();
+        }
+    }
+}
+
+impl RunCommand {
+    pub fn new(config: RunCommandConfig) -> Result<Self> {
+        let workspace = Path::new(&config.workspace_dir)
+            .canonicalize()
+            .map_err(|err| anyhow!("Workspace '{}' nicht verfügbar: {}", config.workspace_dir, err))?;
+
+        Ok(Self { config, workspace })
+    }
+
+    pub fn workspace(&self) -> &Path {
+        &self.workspace
+    }
+
+    /// Prüft Programmname und Arbeitsverzeichnis gegen die Konfiguration
+    fn prepare(&self, arguments: &HashMap<String, serde_json::Value>) -> Result<(String, Vec<String>, PathBuf)> {
+        let program = arguments.get("command")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Fehlender Parameter 'command'"))?
+            .trim()
+            .to_string();
+
+        if program.contains('/') || program.contains('\\') {
+            return Err(anyhow!("Befehl '{}' muss ohne Pfad angegeben werden", program));
+        }
+
+        if !self.config.allowed_commands.iter().any(|allowed| allowed == &program) {
+            return Err(anyhow!(
+                "Befehl '{}' ist nicht erlaubt. Erlaubte Befehle: {}",
+                program,
+                self.config.allowed_commands.join(", ")
+            ));
+        }
+
+        let args = match arguments.get("args") {
+            None | Some(serde_json::Value::Null) => Vec::new(),
+            Some(serde_json::Value::Array(values)) => values
+                .iter()
+                .map(|v| v.as_str().map(str::to_string).ok_or_else(|| anyhow!("'args' darf nur Strings enthalten")))
+                .collect::<Result<Vec<_>>>()?,
+            Some(_) => return Err(anyhow!("'args' muss ein Array sein")),
+        };
+
+        let cwd = match arguments.get("cwd").and_then(|v| v.as_str()) {
+            Some(relative) => {
+                let resolved = self.workspace
+                    .join(relative)
+                    .canonicalize()
+                    .map_err(|err| anyhow!("Verzeichnis '{}' nicht gefunden: {}", relative, err))?;
+                if !resolved.starts_with(&self.workspace) {
+                    return Err(anyhow!("Verzeichnis '{}' liegt außerhalb des Workspace", relative));
+                }
+                resolved
+            }
+            None => self.workspace.clone(),
+        };
+
+        Ok((program, args, cwd))
+    }
+
+    fn build_command(&self, program: &str, args: &[String], cwd: &Path) -> Command {
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .current_dir(cwd)
+            .env_clear()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        for name in &self.config.env_passthrough {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            // Eigene Prozessgruppe, damit beim Abbruch auch Kindprozesse enden
+            command.process_group(0);
+            let max_memory = self.config.max_memory_mb.saturating_mul(1024 * 1024);
+            let max_cpu = self.config.max_cpu_seconds;
+            // SAFETY: zwischen fork und exec werden nur async-signal-sichere libc-Aufrufe verwendet
+            unsafe {
+                command.pre_exec(move || {
+                    if max_memory > 0 && libc::setrlimit(libc::RLIMIT_AS, &rlimit(max_memory)) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    if max_cpu > 0 && libc::setrlimit(libc::RLIMIT_CPU, &rlimit(max_cpu)) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        command
+    }
+
+    async fn run(
+        &self,
+        arguments: HashMap<String, serde_json::Value>,
+        output: Option<ToolOutputSender>,
+    ) -> Result<serde_json::Value> {
+        let (program, args, cwd) = self.prepare(&arguments)?;
+        let mut child = self.build_command(&program, &args, &cwd)
+            .spawn()
+            .map_err(|err| anyhow!("Befehl '{}' konnte nicht gestartet werden: {}", program, err))?;
+        let mut group = ProcessGroup { id: child.id(), finished: false };
+
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("stdout nicht verfügbar"))?;
+        let stderr = child.stderr.take().ok_or_else(|| anyhow!("stderr nicht verfügbar"))?;
+        let limit = self.config.max_output_bytes;
+        let started = Instant::now();
+
+        let mut captured_stdout = CapturedOutput::default();
+        let mut captured_stderr = CapturedOutput::default();
+
+        let execution = async {
+            let (_, _, status) = tokio::join!(
+                read_stream(stdout, OutputStream::Stdout, limit, output.as_ref(), &mut captured_stdout),
+                read_stream(stderr, OutputStream::Stderr, limit, output.as_ref(), &mut captured_stderr),
+                child.wait(),
+            );
+            status
+        };
+
+        let (exit_code, timed_out) =
+            match tokio::time::timeout(Duration::from_secs(self.config.timeout), execution).await {
+                Ok(status) => (status?.code(), false),
+                Err(_) => {
+                    group.kill();
+                    let _ = child.kill().await;
+                    (None, true)
+                }
+            };
+        group.finished = true;
+
+        Ok(serde_json::json!({
+            "command": program,
+            "args": args,
+            "cwd": cwd.display().to_string(),
+            "exit_code": exit_code,
+            "success": exit_code == Some(0),
+            "timed_out": timed_out,
+            "duration_ms": started.elapsed().as_millis() as u64,
+            "stdout": String::from_utf8_lossy(&captured_stdout.data),
+            "stderr": String::from_utf8_lossy(&captured_stderr.data),
+            "stdout_truncated": captured_stdout.truncated,
+            "stderr_truncated": captured_stderr.truncated,
+        }))
+    }
+}
+
+/// Liest einen Stream bis EOF, leitet Chunks weiter und behält höchstens `limit` Bytes.
+/// Weitergeleitet werden nur vollständige UTF-8-Zeichen; ein am Chunk-Ende
+/// angeschnittenes Zeichen wartet auf den nächsten Chunk.
+async fn read_stream<R: AsyncRead + Unpin>(
+    mut reader: R,
+    stream: OutputStream,
+    limit: usize,
+    output: Option<&ToolOutputSender>,
+    captured: &mut CapturedOutput,
+) {
+    let mut buffer = [0u8; 4096];
+    let mut pending = Vec::new();
+    loop {
+        let read = match reader.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        let chunk = &buffer[..read];
+
+        let remaining = limit.saturating_sub(captured.data.len());
+        if chunk.len() > remaining {
+            captured.truncated = true;
+        }
+        let kept = &chunk[..chunk.len().min(remaining)];
+        captured.data.extend_from_slice(kept);
+
+        if let Some(sender) = output {
+            pending.extend_from_slice(kept);
+            let complete = complete_utf8_len(&pending);
+            if complete > 0 {
+                let data = String::from_utf8_lossy(&pending[..complete]).into_owned();
+                pending.drain(..complete);
+                let _ = sender.send(ToolOutput { stream, data });
+            }
+        }
+    }
+
+    if let Some(sender) = output {
+        if !pending.is_empty() {
+            let _ = sender.send(ToolOutput {
+                stream,
+                data: String::from_utf8_lossy(&pending).into_owned(),
+            });
+        }
+    }
+}
+
+/// Länge von `bytes` ohne eine am Ende unvollständige UTF-8-Sequenz
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - back];
+        // Folgebytes (10xxxxxx) überspringen, bis das Startbyte gefunden ist
+        if byte & 0b1100_0000 == 0b1000_0000 {
+            continue;
+        }
+        let needed = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        return if needed > back { bytes.len() - back } else { bytes.len() };
+    }
+    bytes.len()
+}
+
+#[cfg(unix)]
+fn rlimit(value: u64) -> libc::rlimit {
+    libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    }
+}
+
+#[async_trait]
+impl FunctionHandler for RunCommand {
+    async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value> {
+        self.run(arguments, None).await
+    }
+
+    async fn execute_streaming(
+        &self,
+        arguments: HashMap<String, serde_json::Value>,
+        output: ToolOutputSender,
+    ) -> Result<serde_json::Value> {
+        self.run(arguments, Some(output)).await
+    }
+
+    fn risk_level(&self) -> RiskLevel {
+        self.config.risk_level
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        // Etwas Spielraum, damit der eigene Timeout greift und Teilausgaben zurückkommen
+        Some(Duration::from_secs(self.config.timeout + 5))
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new_function(
+            "run_command".to_string(),
+            format!(
+                "Führt einen freigegebenen Befehl (z.B. Tests oder Linter) im Projekt-Workspace aus. Erlaubte Befehle: {}",
+                self.config.allowed_commands.join(", ")
+            ),
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "description": "Name des Programms ohne Pfad, z.B. 'cargo'"
+                    },
+                    "args": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Argumente für das Programm, z.B. [\"test\", \"--workspace\"]"
+                    },
+                    "cwd": {
+                        "type": "string",
+                        "description": "Arbeitsverzeichnis relativ zum Workspace"
+                    }
+                },
+                "required": ["command"]
+            })
+        )
+    }
+}
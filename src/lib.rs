@@ -1,7 +1,19 @@
 pub mod client;
 pub mod config;
 pub mod api;
-// pub mod functions; // Temporär deaktiviert wegen Kompilierungsfehlern
+pub mod functions;
+pub mod mcp;
+pub mod documents;
+pub mod presets;
+pub mod templates;
+pub mod store;
+pub mod conversations;
+pub mod usage;
+pub mod metrics;
+pub mod logging;
+pub mod telemetry;
+pub mod shutdown;
+pub mod secrets;
 
 #[cfg(test)]
 mod tests;
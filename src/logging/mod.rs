@@ -0,0 +1,154 @@
+pub mod request_id;
+
+pub use request_id::{current_request_id, with_request_id, RequestIdLayer, RequestIdService, REQUEST_ID_HEADER};
+
+use crate::config::{LogFormat, LogRotation, LoggingConfig};
+use opentelemetry_sdk::trace::Tracer;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+
+/// Ersatztext für entfernte Geheimnisse
+pub const REDACTED: &str = "***";
+
+/// Schlüssel, deren Werte in Logausgaben maskiert werden
+const SECRET_KEYS: &[&str] = &["api_key", "api-key", "apikey", "password", "secret"];
+
+/// Initialisiert den globalen Subscriber aus der Konfiguration.
+///
+/// `stderr` leitet die Konsolenausgabe um, z.B. wenn stdout dem MCP-Protokoll
+/// gehört; mit `tracer` werden Spans zusätzlich per OpenTelemetry exportiert.
+/// Der zurückgegebene Guard muss bis zum Programmende leben, sonst gehen
+/// gepufferte Einträge der Logdatei verloren.
+pub fn init(config: &LoggingConfig, stderr: bool, tracer: Option<Tracer>) -> anyhow::Result<Option<WorkerGuard>> {
+    let writer = if stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let mut layers = vec![console_layer(config.format, writer)];
+
+    let guard = match &config.file {
+        Some(file) => {
+            std::fs::create_dir_all(&file.dir)?;
+            let rotation = match file.rotation {
+                LogRotation::Hourly => Rotation::HOURLY,
+                LogRotation::Daily => Rotation::DAILY,
+                LogRotation::Never => Rotation::NEVER,
+            };
+            let appender = RollingFileAppender::new(rotation, &file.dir, &file.prefix);
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            layers.push(fmt::layer().json().with_ansi(false).with_writer(writer).boxed());
+            Some(guard)
+        }
+        None => None,
+    };
+    if let Some(tracer) = tracer {
+        layers.push(tracing_opentelemetry::layer().with_tracer(tracer).boxed());
+    }
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(env_filter(config)?)
+        .try_init()?;
+    Ok(guard)
+}
+
+fn console_layer(format: LogFormat, writer: BoxMakeWriter) -> Box<dyn Layer<Registry> + Send + Sync> {
+    match format {
+        LogFormat::Json => fmt::layer().json().with_writer(writer).boxed(),
+        LogFormat::Pretty => fmt::layer().pretty().with_writer(writer).boxed(),
+        LogFormat::Compact => fmt::layer().compact().with_writer(writer).boxed(),
+    }
+}
+
+/// Filter aus `RUST_LOG` oder, falls nicht gesetzt, aus `level` und `filters`
+pub fn env_filter(config: &LoggingConfig) -> anyhow::Result<EnvFilter> {
+    let directives = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| filter_directives(config));
+    EnvFilter::try_new(&directives)
+        .map_err(|err| anyhow::anyhow!("Ungültiger Log-Filter '{}': {}", directives, err))
+}
+
+/// Filter-Direktiven der Konfiguration, z.B. `info,chatglm_web::client=debug`
+pub fn filter_directives(config: &LoggingConfig) -> String {
+    std::iter::once(config.level.trim())
+        .chain(config.filters.iter().map(|filter| filter.trim()))
+        .filter(|directive| !directive.is_empty())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Kürzt ein Geheimnis auf seine ersten Zeichen, z.B. für Debug-Ausgaben
+pub fn mask_secret(secret: &str) -> String {
+    if secret.chars().count() <= 8 {
+        return REDACTED.to_string();
+    }
+    let prefix: String = secret.chars().take(4).collect();
+    format!("{}{}", prefix, REDACTED)
+}
+
+/// Maskiert Bearer-Tokens und Werte von Schlüsseln wie `api_key` oder
+/// `password` in JSON, Query-Strings und Headern
+pub fn redact_secrets(text: &str) -> String {
+    // ASCII-Kleinschreibung erhält die Byte-Positionen
+    let lower = text.to_ascii_lowercase();
+    let mut redacted = String::with_capacity(text.len());
+    let mut pos = 0;
+
+    while let Some(value_start) = next_secret(&lower, pos) {
+        let value_len = text[value_start..]
+            .find(|c: char| matches!(c, '"' | '\'' | '&' | ',' | ';' | '}') || c.is_whitespace())
+            .unwrap_or(text.len() - value_start);
+        redacted.push_str(&text[pos..value_start]);
+        if value_len > 0 {
+            redacted.push_str(REDACTED);
+        }
+        pos = value_start + value_len;
+    }
+    redacted.push_str(&text[pos..]);
+    redacted
+}
+
+/// Inhalt für das Debug-Log: nur mit `log_bodies` im Klartext (ohne
+/// Geheimnisse), sonst lediglich die Länge
+pub fn body_for_log(text: &str, log_bodies: bool) -> String {
+    if log_bodies {
+        redact_secrets(text)
+    } else {
+        format!("<{} Zeichen>", text.chars().count())
+    }
+}
+
+/// Beginn des nächsten geheimen Werts ab Position `from`
+fn next_secret(lower: &str, from: usize) -> Option<usize> {
+    let bearer = find_all(lower, "bearer ", from)
+        .map(|end| end + lower[end..].len() - lower[end..].trim_start().len())
+        .next();
+    let keyed = SECRET_KEYS
+        .iter()
+        .filter_map(|key| find_all(lower, key, from).find_map(|end| keyed_value_start(lower, end)))
+        .min();
+    bearer.into_iter().chain(keyed).min()
+}
+
+/// Endpositionen aller Vorkommen von `needle` ab `from`
+fn find_all<'a>(haystack: &'a str, needle: &'a str, from: usize) -> impl Iterator<Item = usize> + 'a {
+    haystack[from..]
+        .match_indices(needle)
+        .map(move |(index, _)| from + index + needle.len())
+}
+
+/// Wertbeginn nach einem Schlüssel: `"key": "wert"`, `key=wert` oder `key: wert`
+fn keyed_value_start(lower: &str, key_end: usize) -> Option<usize> {
+    let rest = &lower[key_end..];
+    let rest = rest.trim_start_matches(['"', '\'']).trim_start();
+    let rest = rest.strip_prefix([':', '='])?;
+    let rest = rest.trim_start().trim_start_matches(['"', '\'']);
+    Some(lower.len() - rest.len())
+}
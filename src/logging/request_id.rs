@@ -0,0 +1,106 @@
+use axum::http::{HeaderValue, Request, Response};
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::{Layer, Service};
+use tracing::Instrument;
+
+/// Header, über den Request-IDs übernommen und zurückgegeben werden
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Request-ID der aktuell bearbeiteten Anfrage, z.B. für Aufrufe an die GLM-API
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Führt `future` mit der Request-ID `id` aus
+pub async fn with_request_id<F: Future>(id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(id, future).await
+}
+
+/// Tower-Layer, der jeder Anfrage eine Request-ID zuordnet.
+///
+/// Eine gültige ID aus `X-Request-Id` wird übernommen, sonst wird eine neue
+/// erzeugt. Der Handler läuft in einem Span `http_request` mit dieser ID, sie
+/// steht über [`current_request_id`] bereit und wird in der Antwort zurückgegeben.
+/// Ein eingehender `traceparent` wird als Eltern des Spans übernommen.
+#[derive(Clone, Default)]
+pub struct RequestIdLayer;
+
+impl RequestIdLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RequestIdService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let id = request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| is_valid_request_id(value))
+            .map(str::to_string)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let header = HeaderValue::from_str(&id).expect("Request-ID ist gültiges ASCII");
+        request.headers_mut().insert(REQUEST_ID_HEADER, header.clone());
+
+        let span = tracing::info_span!(
+            "http_request",
+            otel.kind = "server",
+            request_id = %id,
+            method = %request.method(),
+            path = %request.uri().path(),
+        );
+        crate::telemetry::set_parent_from_headers(&span, request.headers());
+        let started = Instant::now();
+        let response = span.in_scope(|| self.inner.call(request));
+
+        Box::pin(with_request_id(id, async move {
+            let mut response = response.await?;
+            tracing::debug!(
+                status = response.status().as_u16(),
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "Anfrage beantwortet"
+            );
+            response.headers_mut().insert(REQUEST_ID_HEADER, header);
+            Ok(response)
+        }.instrument(span)))
+    }
+}
+
+/// Übernommene IDs: höchstens 128 sichtbare ASCII-Zeichen
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty() && value.len() <= 128 && value.bytes().all(|byte| byte.is_ascii_graphic())
+}
@@ -1,72 +1,229 @@
 use axum::{response::Html, routing::get, Router};
+use chatglm_web::{api, client, config, conversations, documents, functions, logging, mcp, metrics, presets, shutdown, telemetry, templates, usage};
 use dotenv::dotenv;
-use std::{env, sync::Arc};
+use std::sync::Arc;
 use tokio::net::TcpListener;
-use tower_http::cors::{CorsLayer, Any};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tracing::{info, warn};
 
-mod config;
-mod client;
-mod api;
+/// Nachfrist nach dem Abbruch offener Streams, bevor Verbindungen getrennt werden
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Betriebsart, gewählt über die Kommandozeile
+enum RunMode {
+    /// HTTP-Server mit REST, WebSocket und optionalem MCP-Endpunkt
+    Server,
+    /// MCP-Server über stdin/stdout (`--mcp-stdio`), z.B. für Editoren
+    McpStdio,
+    /// Konfiguration prüfen und ohne Secrets ausgeben (`--check-config`)
+    CheckConfig,
+}
+
+impl RunMode {
+    fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "--check-config") {
+            Self::CheckConfig
+        } else if args.iter().any(|arg| arg == "--mcp-stdio") {
+            Self::McpStdio
+        } else {
+            Self::Server
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Lade Umgebungsvariablen
-    if let Err(_) = dotenv() {
+    let mode = RunMode::from_args();
+
+    // Lade Umgebungsvariablen und Konfiguration
+    let env_loaded = dotenv().is_ok();
+    if matches!(mode, RunMode::CheckConfig) {
+        return check_config();
+    }
+    let loaded = config::AppConfig::load_validated();
+
+    // Initialisiere Logging aus der Konfiguration (bei Fehlern mit Standardwerten);
+    // im stdio-Modus gehört stdout dem MCP-Protokoll
+    let logging_config = loaded.as_ref().map(|loaded| loaded.config.logging.clone()).unwrap_or_default();
+    let telemetry = match &loaded {
+        Ok(loaded) if loaded.config.telemetry.enabled => Some(telemetry::Telemetry::init(&loaded.config.telemetry)?),
+        _ => None,
+    };
+    let _log_guard = logging::init(
+        &logging_config,
+        matches!(mode, RunMode::McpStdio),
+        telemetry.as_ref().map(telemetry::Telemetry::tracer),
+    )?;
+    if !env_loaded {
         warn!("Keine .env Datei gefunden, verwende Systemumgebungsvariablen.");
     }
 
-    // Initialisiere Logging
-    tracing_subscriber::fmt::init();
+    let loaded = loaded.map_err(|e| anyhow::anyhow!("Konfigurationsfehler: {}", e))?;
+    let config = loaded.config.clone();
+    let config_handle = Arc::new(config::ConfigHandle::new(loaded, config::AppConfig::load));
+
+    let usage = config.usage.enabled.then(|| Arc::new(usage::UsageStore::open(&config.usage)));
+    let metrics = config.metrics.enabled.then(|| Arc::new(metrics::Metrics::new()));
+    let glm_client = Arc::new(build_glm_client(&config, usage.clone(), metrics.clone())?);
+    let documents = config.documents.enabled.then(|| {
+        let store = documents::DocumentStore::open(&config.documents);
+        Arc::new(if config.documents.embeddings {
+            store.with_embedder(glm_client.clone())
+        } else {
+            store
+        })
+    });
+    let (registry, mcp_clients) = build_registry(&config, documents.clone(), metrics.clone()).await?;
+    watch_config(&config_handle, glm_client.clone(), usage.clone(), registry.clone())?;
+
+    match mode {
+        RunMode::Server => {
+            let health = api::HealthChecker::new(config.clone(), glm_client.clone(), registry.clone())
+                .with_mcp_clients(mcp_clients);
+            run_server(config_handle, glm_client, registry, documents, usage, metrics, Arc::new(health)).await
+        }
+        RunMode::McpStdio => {
+            info!("ChatGLM MCP-Server (stdio) startet...");
+            let server = Arc::new(mcp::McpServer::new(registry, glm_client, config.mcp.serve.allow_risky_tools));
+            mcp::serve_stdio(server, tokio::io::stdin(), tokio::io::stdout()).await?;
+            Ok(())
+        }
+        RunMode::CheckConfig => unreachable!("wird vor dem Start behandelt"),
+    }
+}
+
+/// Gibt die effektive Konfiguration mit maskierten Secrets auf stdout aus und
+/// meldet alle Fehler auf stderr; Exit-Code 1, wenn die Konfiguration ungültig ist
+fn check_config() -> anyhow::Result<()> {
+    let loaded = config::AppConfig::load().map_err(|e| anyhow::anyhow!("Konfigurationsfehler: {}", e))?;
+    if loaded.sources.is_empty() {
+        eprintln!("Keine Konfigurationsdatei gefunden, verwende Standardwerte");
+    }
+    for source in &loaded.sources {
+        eprintln!("Gelesen: {}", source.display());
+    }
+    println!("{}", serde_json::to_string_pretty(&loaded.config.redacted())?);
 
-    // Lade Konfiguration
-    let config = config::AppConfig::new()
-        .map_err(|e| anyhow::anyhow!("Konfigurationsfehler: {}", e))?;
+    let problems = loaded.problems(config::is_production());
+    if problems.is_empty() {
+        eprintln!("Konfiguration ist gültig (RUN_MODE={})", config::run_mode());
+        return Ok(());
+    }
+    eprintln!("{} Fehler in der Konfiguration (RUN_MODE={}):", problems.len(), config::run_mode());
+    for problem in &problems {
+        eprintln!("  - {}", problem);
+    }
+    std::process::exit(1);
+}
 
-    info!("ChatGLM Web-Anwendung startet...");
+async fn run_server(
+    config_handle: Arc<config::ConfigHandle>,
+    glm_client: Arc<client::GlmClient>,
+    registry: Arc<functions::FunctionRegistry>,
+    documents: Option<Arc<documents::DocumentStore>>,
+    usage: Option<Arc<usage::UsageStore>>,
+    metrics: Option<Arc<metrics::Metrics>>,
+    health: Arc<api::HealthChecker>,
+) -> anyhow::Result<()> {
+    let config = config_handle.current();
+    info!("ChatGLM Web-Anwendung startet (Version {}, {})...", api::VERSION, api::GIT_HASH);
     info!("Server läuft auf {}:{}", config.server.host, config.server.port);
 
-    // Erstelle GLM-Client
-    let glm_config = client::GlmConfig {
-        api_key: config.chatglm.api_key.clone(),
-        api_url: config.chatglm.api_url.clone(),
-        model: match config.chatglm.model.as_str() {
-            "glm-4.5" => client::GlmModel::Glm45,
-            "glm-4.5-32k" => client::GlmModel::Glm4532K,
-            "glm-4.5-turbo" => client::GlmModel::Glm45Turbo,
-            _ => client::GlmModel::Glm45,
-        },
-        max_tokens: config.chatglm.max_tokens,
-        temperature: config.chatglm.temperature,
-        top_p: config.chatglm.top_p,
-        stream: config.chatglm.stream,
-        thinking_enabled: true,
-        timeout: std::time::Duration::from_secs(config.server.timeout),
-    };
-    
-    let glm_client = client::GlmClient::new(glm_config)
-        .map_err(|e| anyhow::anyhow!("GLM-Client-Fehler: {}", e))?;
+    let uploads = Arc::new(api::UploadStore::new(&config.uploads));
+    let settings = Arc::new(api::SettingsStore::new(&config.settings));
+    let conversations = config.conversations.enabled
+        .then(|| Arc::new(conversations::ConversationStore::open(&config.conversations)));
+    let presets = config.presets.enabled.then(|| {
+        Arc::new(presets::PresetStore::open(&config.presets).with_registry(registry.clone()))
+    });
+    let shutdown = Arc::new(
+        shutdown::Shutdown::new(std::time::Duration::from_secs(config.server.drain_timeout))
+            .with_partial_store(&config.server.partial_messages_file),
+    );
 
-    // CORS-Layer konfigurieren
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_headers(Any)
-        .allow_methods(Any);
+    // CORS-Layer; erlaubte Origins folgen der aktuellen Konfiguration ("*" erlaubt alle)
+    let cors = {
+        let config_handle = config_handle.clone();
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::predicate(move |origin, _| {
+                let Ok(origin) = origin.to_str() else { return false };
+                config_handle
+                    .current()
+                    .cors
+                    .allowed_origins
+                    .iter()
+                    .any(|allowed| allowed == "*" || allowed.trim_end_matches('/') == origin)
+            }))
+            .allow_headers(Any)
+            .allow_methods(Any)
+    };
 
     // Erstelle Axum Router mit allen API-Endpunkten
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/", get(hello_handler))
-        .route("/api/health", get(health_handler))
+        // Liveness und Readiness
+        .merge(api::health_routes(health))
         // Chat-API
-        .merge(create_chat_routes(glm_client.clone()))
+        .merge(create_chat_routes(glm_client.as_ref().clone(), uploads.clone(), documents.clone(), settings.clone(), presets.clone(), conversations.clone(), shutdown.clone()))
+        // Uploads für multimodale Nachrichten
+        .merge(api::uploads_routes(uploads.clone()))
         // Settings-API
-        .merge(api::settings_routes())
+        .merge(api::settings_routes(settings.clone()))
         // Models-API
         .merge(api::models_routes())
-        // Functions-API (temporär deaktiviert)
-        // .merge(api::functions_routes())
+        // Embeddings-API
+        .merge(api::embeddings_routes(glm_client.clone()))
+        // Functions-API
+        .merge(api::functions_routes(registry.clone()))
         // WebSocket
-        .merge(api::websocket_route(Arc::new(glm_client)))
+        .merge(api::websocket_route(glm_client.clone(), registry.clone(), metrics.clone(), Some(settings.clone()), presets.clone(), Some(shutdown.clone())));
+
+    // System-Prompt-Presets
+    if let Some(presets) = presets {
+        app = app.merge(api::presets_routes(presets));
+    }
+
+    // Unterhaltungen mit verzweigten Nachrichten
+    if let Some(conversations) = conversations {
+        app = app.merge(api::conversations_routes(conversations, glm_client.clone(), uploads, Some(settings.clone()), Some(shutdown.clone())));
+    }
+
+    // Prompt-Templates
+    if config.templates.enabled {
+        let templates = Arc::new(templates::TemplateStore::open(&config.templates));
+        app = app.merge(api::templates_routes(templates, glm_client.clone(), Some(settings), Some(shutdown.clone())));
+    }
+
+    // Dokumente
+    if let Some(documents) = documents {
+        app = app.merge(api::documents_routes(documents));
+    }
+
+    // Verbrauch und Kosten
+    if let Some(usage) = usage {
+        app = app.merge(api::usage_routes(usage));
+    }
+
+    // MCP (Streamable HTTP); ohne Authentifizierung nie mit riskanten Tools
+    if config.mcp.serve.http_enabled {
+        let server = Arc::new(mcp::McpServer::new(registry, glm_client, false));
+        app = app.merge(mcp::mcp_routes(server));
+        info!("MCP-Endpunkt aktiviert unter /mcp");
+    }
+
+    // Metriken; der Layer wird nach allen Routen angewendet, damit er jede Route erfasst
+    if let Some(metrics) = metrics {
+        app = app
+            .merge(metrics::metrics_routes(metrics.clone()))
+            .layer(metrics::MetricsLayer::new(metrics));
+        info!("Prometheus-Metriken unter /metrics");
+    }
+
+    // Während des Herunterfahrens 503 für neue Anfragen; Request-IDs und Spans für alle Anfragen
+    let app = app
+        .layer(shutdown::ShutdownLayer::new(shutdown.clone()))
+        .layer(logging::RequestIdLayer::new())
         .layer(cors);
 
     // Starte Server
@@ -74,12 +231,132 @@ async fn main() -> anyhow::Result<()> {
         .await?;
 
     info!("Server gestartet auf http://{}:{}", config.server.host, config.server.port);
-    
-    axum::serve(listener, app).await?;
+
+    // Nach dem Signal laufende Anfragen bis zur Frist abwarten; Verbindungen,
+    // die danach noch offen sind, werden nach einer kurzen Nachfrist getrennt
+    let (closed_tx, closed_rx) = tokio::sync::oneshot::channel();
+    let drain = {
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown::signal().await;
+            shutdown.drain().await;
+            let _ = closed_tx.send(());
+        }
+    };
+    let server = axum::serve(listener, app).with_graceful_shutdown(drain);
+    tokio::select! {
+        result = server => result?,
+        _ = async {
+            let _ = closed_rx.await;
+            tokio::time::sleep(SHUTDOWN_GRACE).await;
+        } => warn!("Verbindungen nach Ablauf der Frist getrennt"),
+    }
+    info!("Server beendet");
 
     Ok(())
 }
 
+fn build_glm_client(
+    config: &config::AppConfig,
+    usage: Option<Arc<usage::UsageStore>>,
+    metrics: Option<Arc<metrics::Metrics>>,
+) -> anyhow::Result<client::GlmClient> {
+    let glm_config = config.glm_config();
+
+    let mut client = client::GlmClient::new(glm_config)
+        .map_err(|e| anyhow::anyhow!("GLM-Client-Fehler: {}", e))?
+        .with_body_logging(config.logging.log_bodies);
+    if let Some(usage) = usage {
+        client = client.with_usage_sink(usage);
+    }
+    if let Some(metrics) = metrics {
+        client = client.with_metrics(metrics);
+    }
+
+    if !config.cache.enabled {
+        return Ok(client);
+    }
+    let ttl = (config.cache.ttl > 0).then(|| std::time::Duration::from_secs(config.cache.ttl));
+    let cache = match config.cache.backend {
+        config::CacheBackendKind::Memory => client::ResponseCache::memory(config.cache.capacity),
+        config::CacheBackendKind::Disk => client::ResponseCache::disk(&config.cache.dir, config.cache.capacity, ttl),
+    };
+    info!("Antwort-Cache aktiv ({:?})", config.cache.backend);
+    Ok(client.with_cache(Arc::new(
+        cache.with_ttl(ttl).with_deterministic_only(config.cache.deterministic_only),
+    )))
+}
+
+/// Übernimmt neu geladene Konfigurationen in Client, Preise und Tool-Limits und
+/// überwacht die Konfigurationsdateien (sowie SIGHUP)
+fn watch_config(
+    handle: &Arc<config::ConfigHandle>,
+    glm_client: Arc<client::GlmClient>,
+    usage: Option<Arc<usage::UsageStore>>,
+    registry: Arc<functions::FunctionRegistry>,
+) -> anyhow::Result<()> {
+    handle.on_reload(move |config| {
+        // Zuerst der Schritt, der fehlschlagen kann, damit sonst nichts geändert wird
+        glm_client
+            .update_config(config.glm_config())
+            .map_err(|err| anyhow::anyhow!("GLM-Einstellungen ungültig: {}", err))?;
+        if let Some(usage) = &usage {
+            usage.set_pricing(&config.usage);
+        }
+        registry.set_execution_limits(config.tools.execution.clone());
+        if let Some(approvals) = registry.approvals() {
+            approvals.update(&config.tools.approval);
+        }
+        Ok(())
+    });
+
+    let reload = handle.current().reload.clone();
+    if reload.watch {
+        handle.clone().spawn_watcher(std::time::Duration::from_secs(reload.poll_interval.max(1)));
+    }
+    #[cfg(unix)]
+    handle.clone().spawn_sighup_handler()?;
+    Ok(())
+}
+
+/// Function-Registry mit Built-ins, optionalen Tools, MCP-Servern und Plugins;
+/// die verbundenen MCP-Server werden für die Health-Checks mitgeliefert
+async fn build_registry(
+    config: &config::AppConfig,
+    documents: Option<Arc<documents::DocumentStore>>,
+    metrics: Option<Arc<metrics::Metrics>>,
+) -> anyhow::Result<(Arc<functions::FunctionRegistry>, Vec<Arc<mcp::McpClient>>)> {
+    let registry = functions::FunctionRegistry::new();
+    if let Some(documents) = documents {
+        registry.register("search_documents", Arc::new(documents::SearchDocuments::new(documents)));
+    }
+    if config.tools.run_command.enabled {
+        let run_command = functions::RunCommand::new(config.tools.run_command.clone())?;
+        info!("run_command aktiviert im Workspace {}", run_command.workspace().display());
+        registry.register("run_command", Arc::new(run_command));
+    }
+    let approvals = Arc::new(functions::ApprovalManager::new(&config.tools.approval));
+    let mut registry = registry
+        .with_approvals(approvals)
+        .with_execution_limits(config.tools.execution.clone());
+    if let Some(metrics) = metrics {
+        registry = registry.with_metrics(metrics);
+    }
+    let registry = Arc::new(registry);
+
+    // Tools der konfigurierten MCP-Server einbinden
+    let mcp_clients = mcp::connect_servers(&config.mcp, &registry).await;
+
+    // Plugin-Tools aus dem Manifest-Verzeichnis laden und auf Änderungen überwachen
+    if config.tools.plugins.enabled {
+        let loader = Arc::new(functions::PluginLoader::new(&config.tools.plugins, registry.clone()));
+        loader.reload_if_changed();
+        loader.spawn_watcher(std::time::Duration::from_secs(config.tools.plugins.poll_interval.max(1)));
+    }
+
+    Ok((registry, mcp_clients))
+}
+
 async fn hello_handler() -> Html<&'static str> {
     Html(r#"
     <!DOCTYPE html>
@@ -97,14 +374,14 @@ async fn hello_handler() -> Html<&'static str> {
     "#)
 }
 
-async fn health_handler() -> axum::Json<serde_json::Value> {
-    axum::Json(serde_json::json!({
-        "status": "ok",
-        "service": "chatglm-web",
-        "version": "0.1.0"
-    }))
-}
-
-fn create_chat_routes(client: client::GlmClient) -> Router {
-    api::chat_routes(client)
+fn create_chat_routes(
+    client: client::GlmClient,
+    uploads: Arc<api::UploadStore>,
+    documents: Option<Arc<documents::DocumentStore>>,
+    settings: Arc<api::SettingsStore>,
+    presets: Option<Arc<presets::PresetStore>>,
+    conversations: Option<Arc<conversations::ConversationStore>>,
+    shutdown: Arc<shutdown::Shutdown>,
+) -> Router {
+    api::chat_routes(client, uploads, documents, Some(settings), presets, conversations, Some(shutdown))
 }
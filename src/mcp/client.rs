@@ -0,0 +1,267 @@
+use crate::config::McpServerConfig;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Vom Client angebotene MCP-Protokollversion
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Timeout für Verwaltungsanfragen (initialize, tools/list)
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+type Writer = Arc<tokio::sync::Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value>>>>>;
+
+/// Tool-Beschreibung aus `tools/list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "default_input_schema")]
+    pub input_schema: Value,
+}
+
+fn default_input_schema() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+/// Ergebnis von `tools/call`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpToolResult {
+    #[serde(default)]
+    pub content: Vec<Value>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+impl McpToolResult {
+    /// Verbindet alle Textinhalte des Ergebnisses
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|item| item.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// JSON-RPC Client für einen MCP-Server (zeilenbasiertes JSON über stdio)
+pub struct McpClient {
+    name: String,
+    writer: Writer,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+    server_info: Value,
+    reader_task: JoinHandle<()>,
+    _child: Option<Child>,
+}
+
+impl McpClient {
+    /// Startet den konfigurierten Server-Prozess und führt den Handshake durch
+    pub async fn spawn(config: &McpServerConfig) -> Result<Self> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| anyhow!("MCP-Server '{}' konnte nicht gestartet werden: {}", config.name, err))?;
+
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("stdin nicht verfügbar"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("stdout nicht verfügbar"))?;
+
+        let mut client = Self::connect(&config.name, stdout, stdin).await?;
+        client._child = Some(child);
+        Ok(client)
+    }
+
+    /// Verbindet sich über beliebige Streams mit einem MCP-Server
+    pub async fn connect<R, W>(name: &str, reader: R, writer: W) -> Result<Self>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(Box::new(writer)));
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let reader_task = tokio::spawn(read_loop(name.to_string(), reader, writer.clone(), pending.clone()));
+
+        let mut client = Self {
+            name: name.to_string(),
+            writer,
+            pending,
+            next_id: AtomicU64::new(1),
+            server_info: Value::Null,
+            reader_task,
+            _child: None,
+        };
+
+        let init = client.request_with_timeout("initialize", json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {
+                "name": "chatglm-web",
+                "version": env!("CARGO_PKG_VERSION")
+            }
+        }), REQUEST_TIMEOUT).await?;
+        client.server_info = init.get("serverInfo").cloned().unwrap_or(Value::Null);
+        client.notify("notifications/initialized", json!({})).await?;
+
+        Ok(client)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// `serverInfo` aus der Initialisierungsantwort
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    /// Listet alle Tools des Servers (folgt der Paginierung)
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let page = self.request_with_timeout("tools/list", params, REQUEST_TIMEOUT).await?;
+            let batch: Vec<McpToolInfo> = serde_json::from_value(page.get("tools").cloned().unwrap_or_default())?;
+            tools.extend(batch);
+
+            cursor = page.get("nextCursor").and_then(|c| c.as_str()).map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Ruft ein Tool auf; das Timeout setzt der Aufrufer (Registry)
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<McpToolResult> {
+        let result = self.request("tools/call", json!({
+            "name": name,
+            "arguments": arguments
+        })).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    async fn request_with_timeout(&self, method: &str, params: Value, timeout: Duration) -> Result<Value> {
+        tokio::time::timeout(timeout, self.request(method, params))
+            .await
+            .map_err(|_| anyhow!("MCP-Server '{}' antwortet nicht auf '{}'", self.name, method))?
+    }
+
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+        if let Err(err) = write_message(&self.writer, &message).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(err);
+        }
+
+        let result = receiver
+            .await
+            .map_err(|_| anyhow!("Verbindung zu MCP-Server '{}' geschlossen", self.name));
+        self.pending.lock().unwrap().remove(&id);
+        result?
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        write_message(&self.writer, &json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params
+        })).await
+    }
+}
+
+impl Drop for McpClient {
+    fn drop(&mut self) {
+        self.reader_task.abort();
+    }
+}
+
+async fn write_message(writer: &Writer, message: &Value) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+
+    let mut writer = writer.lock().await;
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Liest Nachrichten des Servers und ordnet Antworten den offenen Anfragen zu
+async fn read_loop<R: AsyncRead + Unpin>(name: String, reader: R, writer: Writer, pending: PendingRequests) {
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            debug!("MCP-Server '{}': Zeile ohne JSON ignoriert: {}", name, line);
+            continue;
+        };
+
+        let method = message.get("method").and_then(|m| m.as_str());
+        match (message.get("id").and_then(|id| id.as_u64()), method) {
+            // Antwort auf eine eigene Anfrage
+            (Some(id), None) => {
+                let Some(sender) = pending.lock().unwrap().remove(&id) else { continue };
+                let result = match message.get("error") {
+                    Some(error) => Err(anyhow!(
+                        "MCP-Fehler von '{}': {}",
+                        name,
+                        error.get("message").and_then(|m| m.as_str()).unwrap_or("unbekannt")
+                    )),
+                    None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                let _ = sender.send(result);
+            }
+            // Anfrage des Servers: nur `ping` wird unterstützt
+            (Some(_), Some(method)) => {
+                let response = if method == "ping" {
+                    json!({ "jsonrpc": "2.0", "id": message["id"], "result": {} })
+                } else {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": message["id"],
+                        "error": { "code": -32601, "message": format!("Methode '{}' nicht unterstützt", method) }
+                    })
+                };
+                if let Err(err) = write_message(&writer, &response).await {
+                    warn!("MCP-Server '{}': Antwort konnte nicht gesendet werden: {}", name, err);
+                }
+            }
+            _ => debug!("MCP-Server '{}': Benachrichtigung {:?}", name, method),
+        }
+    }
+
+    // Verbindung beendet: alle wartenden Anfragen schlagen fehl
+    pending.lock().unwrap().clear();
+}
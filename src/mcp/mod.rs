@@ -0,0 +1,82 @@
+pub mod client;
+pub mod server;
+pub mod tool;
+
+pub use client::{McpClient, McpToolInfo, McpToolResult};
+pub use server::{mcp_routes, serve_stdio, McpServer};
+pub use tool::McpTool;
+
+use crate::config::{McpConfig, McpServerConfig};
+use crate::functions::FunctionRegistry;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Registriert alle Tools eines verbundenen MCP-Servers in der Registry.
+/// Gibt die registrierten Namen zurück.
+pub async fn register_server_tools(
+    client: Arc<McpClient>,
+    config: &McpServerConfig,
+    registry: &FunctionRegistry,
+) -> anyhow::Result<Vec<String>> {
+    let mut registered = Vec::new();
+
+    for info in client.list_tools().await? {
+        let name = tool_name(config, &info.name);
+        if registry.has_function(&name) {
+            warn!("MCP-Tool '{}' existiert bereits und wird ignoriert", name);
+            continue;
+        }
+
+        let tool = McpTool::new(
+            client.clone(),
+            info,
+            name.clone(),
+            config.risk_level,
+            config.timeout.map(Duration::from_secs),
+        );
+        registry.register(&name, Arc::new(tool));
+        registered.push(name);
+    }
+
+    Ok(registered)
+}
+
+/// Startet alle konfigurierten MCP-Server und registriert ihre Tools.
+/// Fehlerhafte Server werden protokolliert und übersprungen.
+pub async fn connect_servers(config: &McpConfig, registry: &FunctionRegistry) -> Vec<Arc<McpClient>> {
+    let mut clients = Vec::new();
+
+    for server in config.servers.iter().filter(|server| server.enabled) {
+        let client = match McpClient::spawn(server).await {
+            Ok(client) => Arc::new(client),
+            Err(err) => {
+                warn!("MCP-Server '{}' nicht verfügbar: {}", server.name, err);
+                continue;
+            }
+        };
+
+        match register_server_tools(client.clone(), server, registry).await {
+            Ok(names) => {
+                info!("MCP-Server '{}' verbunden, Tools: {}", server.name, names.join(", "));
+                clients.push(client);
+            }
+            Err(err) => warn!("Tools von MCP-Server '{}' konnten nicht geladen werden: {}", server.name, err),
+        }
+    }
+
+    clients
+}
+
+/// Name in der Registry; Funktionsnamen dürfen nur `[a-zA-Z0-9_-]` enthalten
+fn tool_name(config: &McpServerConfig, tool: &str) -> String {
+    let name = if config.prefix_tools {
+        format!("{}__{}", config.name, tool)
+    } else {
+        tool.to_string()
+    };
+
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,245 @@
+use super::client::PROTOCOL_VERSION;
+use crate::client::{GlmClient, Message};
+use crate::functions::FunctionRegistry;
+use axum::{
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::debug;
+
+/// Name des zusätzlichen Chat-Tools
+const CHAT_TOOL: &str = "chat";
+
+/// Stellt die Registry-Tools und ein `chat` Tool (GLM) per MCP bereit
+pub struct McpServer {
+    registry: Arc<FunctionRegistry>,
+    client: Arc<GlmClient>,
+    allow_risky_tools: bool,
+}
+
+impl McpServer {
+    pub fn new(registry: Arc<FunctionRegistry>, client: Arc<GlmClient>, allow_risky_tools: bool) -> Self {
+        Self {
+            registry,
+            client,
+            allow_risky_tools,
+        }
+    }
+
+    /// Verarbeitet eine JSON-RPC Nachricht; Benachrichtigungen liefern `None`
+    pub async fn handle_message(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let method = message.get("method").and_then(|m| m.as_str()).unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": params.get("protocolVersion").cloned().unwrap_or_else(|| json!(PROTOCOL_VERSION)),
+                "capabilities": { "tools": { "listChanged": false } },
+                "serverInfo": {
+                    "name": "chatglm-web",
+                    "version": env!("CARGO_PKG_VERSION")
+                }
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": self.list_tools() })),
+            "tools/call" => self.call_tool(&params).await,
+            _ => Err((-32601, format!("Methode '{}' nicht gefunden", method))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message }
+            }),
+        })
+    }
+
+    fn is_published(&self, name: &str) -> bool {
+        if self.allow_risky_tools {
+            return true;
+        }
+        // Ohne Bestätigungsweg werden nur Tools unterhalb der Risikoschwelle angeboten
+        self.registry.has_function(name) && !self.registry.requires_approval(name)
+    }
+
+    fn list_tools(&self) -> Vec<Value> {
+        let mut tools: Vec<Value> = self.registry
+            .get_definitions()
+            .into_iter()
+            .filter(|definition| self.is_published(&definition.function.name))
+            .map(|definition| json!({
+                "name": definition.function.name,
+                "description": definition.function.description,
+                "inputSchema": definition.function.parameters
+            }))
+            .collect();
+
+        tools.push(json!({
+            "name": CHAT_TOOL,
+            "description": "Sendet eine Nachricht an das konfigurierte GLM-Modell und gibt die Antwort zurück",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "message": {
+                        "type": "string",
+                        "description": "Nachricht an das Modell"
+                    },
+                    "system": {
+                        "type": "string",
+                        "description": "Optionaler System-Prompt"
+                    }
+                },
+                "required": ["message"]
+            }
+        }));
+        tools
+    }
+
+    async fn call_tool(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+        let arguments: HashMap<String, Value> = serde_json::from_value(params.get("arguments").cloned().unwrap_or_else(|| json!({})))
+            .map_err(|err| (-32602, format!("Ungültige Argumente: {}", err)))?;
+
+        if name == CHAT_TOOL {
+            return Ok(self.chat(arguments).await);
+        }
+        if !self.is_published(name) {
+            return Err((-32602, format!("Tool '{}' nicht gefunden", name)));
+        }
+
+        // Mit `allow_risky_tools` bestätigt der MCP-Client selbst; über
+        // WebSocket kann hier niemand bestätigen
+        let result = if self.allow_risky_tools {
+            self.registry.execute_function_preapproved(name, arguments).await
+        } else {
+            self.registry.execute_function(name, arguments).await
+        };
+        Ok(match result.error {
+            Some(error) => tool_result(error, true),
+            None => tool_result(result.result.to_string(), false),
+        })
+    }
+
+    async fn chat(&self, arguments: HashMap<String, Value>) -> Value {
+        let Some(message) = arguments.get("message").and_then(|m| m.as_str()) else {
+            return tool_result("Fehlender Parameter 'message'".to_string(), true);
+        };
+
+        let mut messages = Vec::new();
+        if let Some(system) = arguments.get("system").and_then(|s| s.as_str()) {
+            messages.push(Message::system(system));
+        }
+        messages.push(Message::user(message));
+
+        match self.client.chat_completions(messages).await {
+            Ok(response) => {
+                let answer = response.choices
+                    .first()
+                    .and_then(|choice| choice.message.content.as_ref().map(|content| content.text()))
+                    .unwrap_or_default();
+                tool_result(answer, false)
+            }
+            Err(err) => tool_result(err.to_string(), true),
+        }
+    }
+}
+
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error
+    })
+}
+
+/// Bedient MCP über zeilenbasiertes JSON (stdin/stdout)
+pub async fn serve_stdio<R, W>(server: Arc<McpServer>, reader: R, mut writer: W) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => server.handle_message(message).await,
+            Err(err) => Some(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": format!("Parse-Fehler: {}", err) }
+            })),
+        };
+
+        if let Some(response) = response {
+            let mut line = response.to_string();
+            line.push('\n');
+            writer.write_all(line.as_bytes()).await?;
+            writer.flush().await?;
+        }
+    }
+
+    debug!("MCP stdio: Eingabe beendet");
+    Ok(())
+}
+
+/// Streamable-HTTP-Transport unter `/mcp` (JSON-Antworten, kein Server-Push)
+pub fn mcp_routes(server: Arc<McpServer>) -> Router {
+    Router::new()
+        .route("/mcp", post(mcp_post_handler).get(mcp_get_handler))
+        .with_state(server)
+}
+
+async fn mcp_post_handler(
+    State(server): State<Arc<McpServer>>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> Response {
+    let is_initialize = payload.get("method").and_then(|m| m.as_str()) == Some("initialize");
+
+    let response = match payload {
+        Value::Array(messages) => {
+            let mut responses = Vec::new();
+            for message in messages {
+                if let Some(response) = server.handle_message(message).await {
+                    responses.push(response);
+                }
+            }
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        message => server.handle_message(message).await,
+    };
+
+    let Some(response) = response else {
+        // Nur Benachrichtigungen/Antworten empfangen
+        return StatusCode::ACCEPTED.into_response();
+    };
+
+    let mut http_response = Json(response).into_response();
+    let session_id = if is_initialize {
+        Some(uuid::Uuid::new_v4().to_string())
+    } else {
+        headers.get("mcp-session-id").and_then(|v| v.to_str().ok()).map(str::to_string)
+    };
+    if let Some(value) = session_id.and_then(|id| HeaderValue::from_str(&id).ok()) {
+        http_response.headers_mut().insert("mcp-session-id", value);
+    }
+    http_response
+}
+
+async fn mcp_get_handler() -> impl IntoResponse {
+    // Server-initiierte Nachrichten werden nicht unterstützt
+    StatusCode::METHOD_NOT_ALLOWED
+}
@@ -0,0 +1,77 @@
+use super::client::{McpClient, McpToolInfo};
+use crate::client::types::ToolDefinition;
+use crate::functions::{FunctionHandler, RiskLevel};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Stellt ein Tool eines MCP-Servers als `FunctionHandler` bereit
+pub struct McpTool {
+    client: Arc<McpClient>,
+    info: McpToolInfo,
+    /// Name in der Registry (ggf. mit Server-Präfix)
+    registered_name: String,
+    risk_level: RiskLevel,
+    timeout: Option<Duration>,
+}
+
+impl McpTool {
+    pub fn new(
+        client: Arc<McpClient>,
+        info: McpToolInfo,
+        registered_name: String,
+        risk_level: RiskLevel,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            client,
+            info,
+            registered_name,
+            risk_level,
+            timeout,
+        }
+    }
+
+    pub fn registered_name(&self) -> &str {
+        &self.registered_name
+    }
+}
+
+#[async_trait]
+impl FunctionHandler for McpTool {
+    async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> Result<serde_json::Value> {
+        let result = self.client
+            .call_tool(&self.info.name, serde_json::to_value(arguments)?)
+            .await?;
+
+        let text = result.text();
+        if result.is_error {
+            return Err(anyhow!("MCP-Tool '{}' meldet einen Fehler: {}", self.info.name, text));
+        }
+
+        Ok(serde_json::json!({
+            "text": text,
+            "content": result.content
+        }))
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new_function(
+            self.registered_name.clone(),
+            self.info.description.clone().unwrap_or_else(|| {
+                format!("Tool '{}' des MCP-Servers '{}'", self.info.name, self.client.name())
+            }),
+            self.info.input_schema.clone(),
+        )
+    }
+
+    fn risk_level(&self) -> RiskLevel {
+        self.risk_level
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
@@ -0,0 +1,161 @@
+pub mod middleware;
+
+pub use middleware::{MetricsLayer, MetricsService};
+
+use crate::client::{GlmError, UsageEvent, UsageSink};
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Prometheus-Metriken der Anwendung in einer eigenen Registry
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    upstream_duration: HistogramVec,
+    time_to_first_token: HistogramVec,
+    tokens: IntCounterVec,
+    glm_errors: IntCounterVec,
+    websocket_connections: IntGauge,
+    tool_duration: HistogramVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("chatglm".to_string()), None)
+            .expect("gültiger Prometheus-Namespace");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP-Anfragen nach Route, Methode und Status"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Dauer bis zum Antwortkopf je Route"),
+            &["method", "route"],
+        ).unwrap();
+        let upstream_duration = HistogramVec::new(
+            HistogramOpts::new("upstream_request_duration_seconds", "Dauer der Anfragen an die GLM-API")
+                .buckets(vec![0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0]),
+            &["endpoint", "model", "outcome"],
+        ).unwrap();
+        let time_to_first_token = HistogramVec::new(
+            HistogramOpts::new("upstream_time_to_first_token_seconds", "Zeit bis zum ersten Stream-Chunk mit Inhalt")
+                .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0]),
+            &["model"],
+        ).unwrap();
+        let tokens = IntCounterVec::new(
+            Opts::new("tokens_total", "Verbrauchte Tokens je Modell (ohne Cache-Treffer)"),
+            &["model", "direction"],
+        ).unwrap();
+        let glm_errors = IntCounterVec::new(
+            Opts::new("glm_errors_total", "Fehler des GLM-Clients je Variante"),
+            &["variant"],
+        ).unwrap();
+        let websocket_connections = IntGauge::new("websocket_connections_active", "Offene WebSocket-Verbindungen").unwrap();
+        let tool_duration = HistogramVec::new(
+            HistogramOpts::new("tool_execution_duration_seconds", "Laufzeit der Tool-Ausführungen")
+                .buckets(vec![0.005, 0.025, 0.1, 0.25, 1.0, 2.5, 10.0, 30.0, 60.0, 300.0]),
+            &["tool", "outcome"],
+        ).unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_duration.clone())).unwrap();
+        registry.register(Box::new(upstream_duration.clone())).unwrap();
+        registry.register(Box::new(time_to_first_token.clone())).unwrap();
+        registry.register(Box::new(tokens.clone())).unwrap();
+        registry.register(Box::new(glm_errors.clone())).unwrap();
+        registry.register(Box::new(websocket_connections.clone())).unwrap();
+        registry.register(Box::new(tool_duration.clone())).unwrap();
+
+        Self {
+            registry,
+            http_requests,
+            http_duration,
+            upstream_duration,
+            time_to_first_token,
+            tokens,
+            glm_errors,
+            websocket_connections,
+            tool_duration,
+        }
+    }
+
+    pub fn observe_http(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.http_requests.with_label_values(&[method, route, &status.to_string()]).inc();
+        self.http_duration.with_label_values(&[method, route]).observe(elapsed.as_secs_f64());
+    }
+
+    /// `endpoint` ist z.B. `chat`, `chat_stream` oder `embeddings`
+    pub fn observe_upstream(&self, endpoint: &str, model: &str, success: bool, elapsed: Duration) {
+        let outcome = if success { "success" } else { "error" };
+        self.upstream_duration.with_label_values(&[endpoint, model, outcome]).observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_time_to_first_token(&self, model: &str, elapsed: Duration) {
+        self.time_to_first_token.with_label_values(&[model]).observe(elapsed.as_secs_f64());
+    }
+
+    pub fn count_error(&self, error: &GlmError) {
+        self.glm_errors.with_label_values(&[error.variant_name()]).inc();
+    }
+
+    pub fn observe_tool(&self, tool: &str, outcome: &str, elapsed: Duration) {
+        self.tool_duration.with_label_values(&[tool, outcome]).observe(elapsed.as_secs_f64());
+    }
+
+    /// Zählt eine WebSocket-Verbindung, bis der Guard verworfen wird
+    pub fn websocket_connected(self: &Arc<Self>) -> WebSocketGuard {
+        self.websocket_connections.inc();
+        WebSocketGuard { metrics: self.clone() }
+    }
+
+    /// Alle Metriken im Prometheus-Textformat
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::warn!("Metriken konnten nicht kodiert werden: {}", err);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsageSink for Metrics {
+    fn record(&self, event: UsageEvent) {
+        if event.cached {
+            return;
+        }
+        self.tokens.with_label_values(&[&event.model, "prompt"]).inc_by(event.usage.prompt_tokens as u64);
+        self.tokens.with_label_values(&[&event.model, "completion"]).inc_by(event.usage.completion_tokens as u64);
+    }
+}
+
+/// Hält den Zähler offener WebSocket-Verbindungen aktuell
+pub struct WebSocketGuard {
+    metrics: Arc<Metrics>,
+}
+
+impl Drop for WebSocketGuard {
+    fn drop(&mut self) {
+        self.metrics.websocket_connections.dec();
+    }
+}
+
+pub fn metrics_routes(metrics: Arc<Metrics>) -> Router {
+    Router::new()
+        .route("/metrics", get(render_metrics))
+        .with_state(metrics)
+}
+
+async fn render_metrics(State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], metrics.render())
+}
@@ -0,0 +1,262 @@
+use crate::client::{ChatOptions, GlmModel, Message};
+use crate::config::PresetsConfig;
+use crate::functions::FunctionRegistry;
+use crate::store::{validate_id, JsonStore, StoreError, StoredItem};
+use chrono::{DateTime, Datelike, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Gespeicherter System-Prompt mit Modell- und Sampling-Vorgaben (Persona)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// System-Prompt mit Variablen wie `{{date}}` oder `{{user_name}}`
+    pub system_prompt: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub thinking: Option<bool>,
+    /// Tools der Registry mitsenden
+    #[serde(default)]
+    pub tools_enabled: bool,
+    /// Auswahl an Tools; leer bedeutet alle
+    #[serde(default)]
+    pub tools: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Felder eines Presets beim Anlegen und Ändern
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresetInput {
+    /// Optionale Kennung (Buchstaben, Ziffern, `-` und `_`), sonst eine UUID
+    pub id: Option<String>,
+    pub name: String,
+    pub description: String,
+    pub system_prompt: String,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub thinking: Option<bool>,
+    pub tools_enabled: bool,
+    pub tools: Vec<String>,
+}
+
+impl PresetInput {
+    pub fn validate(&self) -> Result<(), PresetError> {
+        if let Some(id) = &self.id {
+            validate_id(id).map_err(PresetError::Invalid)?;
+        }
+        if self.name.trim().is_empty() {
+            return Err(PresetError::Invalid("name darf nicht leer sein".to_string()));
+        }
+        if self.system_prompt.trim().is_empty() {
+            return Err(PresetError::Invalid("system_prompt darf nicht leer sein".to_string()));
+        }
+        if let Some(model) = &self.model {
+            if GlmModel::from_id(model).is_none() {
+                return Err(PresetError::Invalid(format!("Unbekanntes Modell '{}'", model)));
+            }
+        }
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=1.0).contains(&temperature) {
+                return Err(PresetError::Invalid("temperature muss zwischen 0.0 und 1.0 liegen".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PresetError {
+    #[error("Preset '{0}' nicht gefunden")]
+    NotFound(String),
+
+    #[error("Preset '{0}' existiert bereits")]
+    Conflict(String),
+
+    #[error("Ungültiges Preset: {0}")]
+    Invalid(String),
+
+    #[error("Speicherfehler: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Speicherfehler: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl StoreError for PresetError {
+    fn not_found(id: String) -> Self {
+        PresetError::NotFound(id)
+    }
+
+    fn conflict(id: String) -> Self {
+        PresetError::Conflict(id)
+    }
+}
+
+impl StoredItem for Preset {
+    const KIND: &'static str = "Preset";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+/// Ersetzt `{{name}}` durch den Wert aus `variables`; unbekannte Variablen
+/// bleiben unverändert stehen
+pub fn render(template: &str, variables: &HashMap<String, String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            output.push_str(&rest[start..]);
+            return output;
+        };
+        match variables.get(after[..end].trim()) {
+            Some(value) => output.push_str(value),
+            None => output.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Namen der in `template` verwendeten Variablen, ohne Duplikate
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        let name = after[..end].trim().to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+        rest = &after[end + 2..];
+    }
+    names
+}
+
+const WEEKDAYS: [&str; 7] = ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag", "Sonntag"];
+
+/// Eingebaute Variablen: `date`, `time` und `weekday` (Serverzeit) sowie `user_name`
+pub fn builtin_variables(user_name: Option<&str>, now: DateTime<Local>) -> HashMap<String, String> {
+    HashMap::from([
+        ("date".to_string(), now.format("%Y-%m-%d").to_string()),
+        ("time".to_string(), now.format("%H:%M").to_string()),
+        ("weekday".to_string(), WEEKDAYS[now.weekday().num_days_from_monday() as usize].to_string()),
+        ("user_name".to_string(), user_name.unwrap_or_default().to_string()),
+    ])
+}
+
+/// Ablage für Presets, je Preset eine JSON-Datei
+pub struct PresetStore {
+    presets: JsonStore<Preset>,
+    /// Liefert die Tool-Definitionen für Presets mit `tools_enabled`
+    registry: Option<Arc<FunctionRegistry>>,
+}
+
+impl PresetStore {
+    /// Öffnet die Ablage und lädt vorhandene Presets
+    pub fn open(config: &PresetsConfig) -> Self {
+        Self { presets: JsonStore::open(&config.dir), registry: None }
+    }
+
+    pub fn with_registry(mut self, registry: Arc<FunctionRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    pub fn list(&self) -> Vec<Preset> {
+        self.presets.list()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Preset> {
+        self.presets.get(id)
+    }
+
+    pub async fn create(&self, input: PresetInput) -> Result<Preset, PresetError> {
+        input.validate()?;
+        self.presets.create(input.id.clone(), |id, created_at, updated_at| Self::build(id, input, created_at, updated_at)).await
+    }
+
+    /// Ersetzt alle Felder eines vorhandenen Presets; die Kennung bleibt
+    pub async fn update(&self, id: &str, input: PresetInput) -> Result<Preset, PresetError> {
+        input.validate()?;
+        self.presets.update(id, |id, created_at, updated_at| Self::build(id, input, created_at, updated_at)).await
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<bool, PresetError> {
+        Ok(self.presets.delete(id).await?)
+    }
+
+    fn build(id: String, input: PresetInput, created_at: DateTime<Utc>, updated_at: DateTime<Utc>) -> Preset {
+        Preset {
+            id,
+            name: input.name.trim().to_string(),
+            description: input.description,
+            system_prompt: input.system_prompt,
+            model: input.model,
+            temperature: input.temperature,
+            thinking: input.thinking,
+            tools_enabled: input.tools_enabled,
+            tools: input.tools,
+            created_at,
+            updated_at,
+        }
+    }
+
+    /// Wendet ein Preset auf eine Chat-Anfrage an: Der gerenderte System-Prompt
+    /// wird vorangestellt, Modell, Temperatur, Thinking und Tools gelten, soweit
+    /// die Anfrage sie nicht selbst setzt
+    pub fn apply(
+        &self,
+        id: &str,
+        variables: &HashMap<String, String>,
+        messages: &mut Vec<Message>,
+        options: &mut ChatOptions,
+    ) -> Result<Preset, PresetError> {
+        let preset = self.get(id).ok_or_else(|| PresetError::NotFound(id.to_string()))?;
+
+        messages.insert(0, Message::system(render(&preset.system_prompt, variables)));
+        if options.model.is_none() {
+            options.model = preset.model.clone();
+        }
+        if options.temperature.is_none() {
+            options.temperature = preset.temperature;
+        }
+        if options.thinking.is_none() {
+            options.thinking = preset.thinking;
+        }
+        if preset.tools_enabled && options.tools.is_empty() {
+            if let Some(registry) = &self.registry {
+                options.tools = registry
+                    .get_definitions()
+                    .into_iter()
+                    .filter(|tool| preset.tools.is_empty() || preset.tools.contains(&tool.function.name))
+                    .collect();
+                options.tools.sort_by(|a, b| a.function.name.cmp(&b.function.name));
+            }
+        }
+        Ok(preset)
+    }
+}
+
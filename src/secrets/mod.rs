@@ -0,0 +1,98 @@
+use crate::logging::mask_secret;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::path::Path;
+
+/// Geheimer Wert wie ein API-Schlüssel.
+///
+/// `Debug`, `Display` und `Serialize` geben nur die maskierte Form aus (siehe
+/// [`mask_secret`]); den Klartext liefert ausschließlich [`Secret::expose`].
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Klartext, z.B. für den `Authorization`-Header
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Leer oder nur Leerzeichen
+    pub fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+
+    /// Maskierte Form; leere Werte bleiben leer, damit fehlende Secrets auffallen
+    pub fn masked(&self) -> String {
+        if self.0.is_empty() {
+            String::new()
+        } else {
+            mask_secret(&self.0)
+        }
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({:?})", self.masked())
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.masked())
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.masked())
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+/// Liest Schlüssel aus einer Datei, z.B. einem Docker Secret unter
+/// `/run/secrets`: ein Schlüssel pro Zeile, leere Zeilen und `#`-Kommentare
+/// werden übersprungen
+pub fn read_secret_file(path: impl AsRef<Path>) -> std::io::Result<Vec<Secret>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Secret::from)
+        .collect())
+}
+
+/// Nicht leere Schlüssel ohne umgebende Leerzeichen und ohne Duplikate, in
+/// der ursprünglichen Reihenfolge
+pub fn distinct<'a>(keys: impl IntoIterator<Item = &'a Secret>) -> Vec<Secret> {
+    let mut distinct: Vec<Secret> = Vec::new();
+    for key in keys {
+        let key = Secret::from(key.expose().trim());
+        if !key.is_empty() && !distinct.contains(&key) {
+            distinct.push(key);
+        }
+    }
+    distinct
+}
@@ -0,0 +1,76 @@
+use super::Shutdown;
+use axum::http::{header, HeaderValue, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures::future::BoxFuture;
+use serde_json::json;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Tower-Layer, der Anfragen während des Herunterfahrens mit 503 ablehnt
+/// und laufende Anfragen bis zur Antwort als in Bearbeitung zählt
+#[derive(Clone)]
+pub struct ShutdownLayer {
+    shutdown: Arc<Shutdown>,
+}
+
+impl ShutdownLayer {
+    pub fn new(shutdown: Arc<Shutdown>) -> Self {
+        Self { shutdown }
+    }
+}
+
+impl<S> Layer<S> for ShutdownLayer {
+    type Service = ShutdownService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ShutdownService { inner, shutdown: self.shutdown.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct ShutdownService<S> {
+    inner: S,
+    shutdown: Arc<Shutdown>,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for ShutdownService<S>
+where
+    S: Service<Request<ReqBody>> + Send + 'static,
+    S::Response: IntoResponse,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        if self.shutdown.is_draining() {
+            let retry_after = self.shutdown.drain_period().as_secs().max(1).to_string();
+            let mut response = (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({"error": "Server wird heruntergefahren"})),
+            ).into_response();
+            let headers = response.headers_mut();
+            headers.insert(header::CONNECTION, HeaderValue::from_static("close"));
+            if let Ok(value) = HeaderValue::from_str(&retry_after) {
+                headers.insert(header::RETRY_AFTER, value);
+            }
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let in_flight = self.shutdown.track();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            drop(in_flight);
+            Ok(response.into_response())
+        })
+    }
+}
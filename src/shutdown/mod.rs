@@ -0,0 +1,307 @@
+pub mod middleware;
+
+pub use middleware::{ShutdownLayer, ShutdownService};
+
+use crate::client::error::GlmResult;
+use crate::client::{Delta, Message, StreamChoice, StreamingChatCompletionResponse, StreamingResponse};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+use tracing::{info, warn};
+
+/// `finish_reason` von Streams, die beim Herunterfahren abgebrochen wurden
+pub const SHUTDOWN_FINISH_REASON: &str = "shutdown";
+
+/// Zustand des Servers beim Herunterfahren
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    Running,
+    /// Neue Anfragen werden mit 503 abgelehnt, laufende dürfen fertig werden
+    Draining,
+    /// Frist abgelaufen: offene Streams werden beendet
+    Closing,
+}
+
+/// Beim Herunterfahren abgebrochene Antwort
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialMessage {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    pub model: String,
+    pub message: Message,
+}
+
+/// Zuordnung eines Streams für die Speicherung als Teilantwort
+#[derive(Debug, Clone, Default)]
+pub struct StreamContext {
+    pub user: Option<String>,
+    pub conversation_id: Option<String>,
+}
+
+/// Koordiniert das Herunterfahren: zählt laufende Anfragen und Streams,
+/// wartet bis zu `drain_period` auf sie und bricht verbleibende Streams ab.
+#[derive(Debug)]
+pub struct Shutdown {
+    phase: watch::Sender<Phase>,
+    in_flight: AtomicUsize,
+    idle: Notify,
+    drain_period: Duration,
+    partials: Option<PathBuf>,
+    write_lock: Mutex<()>,
+}
+
+impl Shutdown {
+    pub fn new(drain_period: Duration) -> Self {
+        Self {
+            phase: watch::Sender::new(Phase::Running),
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+            drain_period,
+            partials: None,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Abgebrochene Antworten als JSON Lines in `path` speichern
+    pub fn with_partial_store(mut self, path: impl Into<PathBuf>) -> Self {
+        self.partials = Some(path.into());
+        self
+    }
+
+    pub fn phase(&self) -> Phase {
+        *self.phase.borrow()
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.phase() != Phase::Running
+    }
+
+    pub fn drain_period(&self) -> Duration {
+        self.drain_period
+    }
+
+    /// Empfänger für Phasenwechsel, z.B. für WebSocket-Verbindungen
+    pub fn subscribe(&self) -> watch::Receiver<Phase> {
+        self.phase.subscribe()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Zählt eine laufende Anfrage, bis der Guard verworfen wird
+    pub fn track(self: &Arc<Self>) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight { shutdown: self.clone() }
+    }
+
+    /// Ablauf nach dem Signal: keine neuen Anfragen mehr, bis zu `drain_period`
+    /// auf laufende warten, danach verbleibende Streams beenden
+    pub async fn drain(&self) {
+        self.phase.send_replace(Phase::Draining);
+        info!("Herunterfahren: {} laufende Anfrage(n), Frist {} s", self.in_flight(), self.drain_period.as_secs());
+
+        let idle = async {
+            loop {
+                let notified = self.idle.notified();
+                if self.in_flight() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        if tokio::time::timeout(self.drain_period, idle).await.is_err() {
+            warn!("Frist abgelaufen, {} Anfrage(n) werden abgebrochen", self.in_flight());
+        }
+        self.phase.send_replace(Phase::Closing);
+    }
+
+    /// Beendet `stream` beim Übergang in [`Phase::Closing`] mit einem letzten
+    /// Chunk (`finish_reason: "shutdown"`) und speichert die bis dahin
+    /// empfangene Antwort. Der Stream zählt als laufende Anfrage.
+    pub fn guard_stream(self: &Arc<Self>, stream: StreamingResponse, context: StreamContext) -> StreamingResponse {
+        let state = GuardedStream {
+            inner: stream,
+            phase: self.subscribe(),
+            _in_flight: self.track(),
+            shutdown: self.clone(),
+            request_id: crate::logging::current_request_id(),
+            context,
+            model: String::new(),
+            content: String::new(),
+            thinking: String::new(),
+            finished: false,
+        };
+
+        StreamingResponse::new(stream::unfold(state, |mut state| async move {
+            if state.finished {
+                return None;
+            }
+            let next = tokio::select! {
+                biased;
+                _ = state.phase.wait_for(|phase| *phase == Phase::Closing) => None,
+                chunk = state.inner.next() => Some(chunk),
+            };
+            match next {
+                Some(chunk) => {
+                    let chunk = chunk?;
+                    state.observe(&chunk);
+                    Some((chunk, state))
+                }
+                None => {
+                    state.finished = true;
+                    state.persist();
+                    let chunk = shutdown_chunk(&state.model);
+                    Some((Ok(chunk), state))
+                }
+            }
+        }))
+    }
+
+    /// Hängt eine Teilantwort an die Datei an
+    pub fn persist_partial(&self, partial: &PartialMessage) {
+        let Some(path) = &self.partials else { return };
+        let _lock = self.write_lock.lock().unwrap();
+        let result = serde_json::to_string(partial)
+            .map_err(std::io::Error::other)
+            .and_then(|line| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", line)
+            });
+        match result {
+            Ok(()) => info!("Teilantwort {} gespeichert", partial.id),
+            Err(err) => warn!("Teilantwort konnte nicht gespeichert werden: {}", err),
+        }
+    }
+
+    /// Alle gespeicherten Teilantworten
+    pub fn partial_messages(&self) -> Vec<PartialMessage> {
+        let Some(path) = &self.partials else { return Vec::new() };
+        let Ok(content) = std::fs::read_to_string(path) else { return Vec::new() };
+        content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+}
+
+/// Laufende Anfrage; meldet beim Verwerfen, wenn keine mehr offen ist
+pub struct InFlight {
+    shutdown: Arc<Shutdown>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.shutdown.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shutdown.idle.notify_waiters();
+        }
+    }
+}
+
+struct GuardedStream {
+    inner: StreamingResponse,
+    phase: watch::Receiver<Phase>,
+    _in_flight: InFlight,
+    shutdown: Arc<Shutdown>,
+    request_id: Option<String>,
+    context: StreamContext,
+    model: String,
+    content: String,
+    thinking: String,
+    finished: bool,
+}
+
+impl GuardedStream {
+    fn observe(&mut self, chunk: &GlmResult<StreamingChatCompletionResponse>) {
+        let Ok(chunk) = chunk else {
+            self.finished = true;
+            return;
+        };
+        if !chunk.model.is_empty() {
+            self.model = chunk.model.clone();
+        }
+        for choice in &chunk.choices {
+            self.content.push_str(choice.delta.content.as_deref().unwrap_or_default());
+            self.thinking.push_str(choice.delta.reasoning_content.as_deref().unwrap_or_default());
+            if choice.finish_reason.is_some() {
+                self.finished = true;
+            }
+        }
+    }
+
+    fn persist(&self) {
+        let mut message = Message::assistant(self.content.as_str());
+        if !self.thinking.trim().is_empty() {
+            message = message.with_thinking(self.thinking.trim());
+        }
+        self.shutdown.persist_partial(&PartialMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            request_id: self.request_id.clone(),
+            user: self.context.user.clone(),
+            conversation_id: self.context.conversation_id.clone(),
+            model: self.model.clone(),
+            message,
+        });
+    }
+}
+
+fn shutdown_chunk(model: &str) -> StreamingChatCompletionResponse {
+    StreamingChatCompletionResponse {
+        id: String::new(),
+        object: "chat.completion.chunk".to_string(),
+        created: Utc::now().timestamp() as u64,
+        model: model.to_string(),
+        usage: None,
+        choices: vec![StreamChoice {
+            index: 0,
+            delta: Delta::default(),
+            finish_reason: Some(SHUTDOWN_FINISH_REASON.to_string()),
+        }],
+    }
+}
+
+/// Wartet auf SIGTERM oder SIGINT (Strg+C)
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!("SIGINT kann nicht empfangen werden: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                warn!("SIGTERM kann nicht empfangen werden: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("SIGINT empfangen"),
+        _ = terminate => info!("SIGTERM empfangen"),
+    }
+}
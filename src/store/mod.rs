@@ -0,0 +1,151 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tracing::warn;
+
+/// Prüft eine vom Benutzer gewählte Kennung (Buchstaben, Ziffern, `-` und `_`,
+/// höchstens 64 Zeichen), die auch als Dateiname dient
+pub fn validate_id(id: &str) -> Result<(), String> {
+    let valid = !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Ungültige Kennung '{}' (erlaubt: A-Z, a-z, 0-9, - und _)", id))
+    }
+}
+
+/// Schreibt `data` in eine temporäre Datei neben `path` und benennt sie dann
+/// um, damit Leser nie eine halb geschriebene Datei sehen. Der Name der
+/// temporären Datei ist pro Aufruf eindeutig.
+pub async fn write_atomic(path: &Path, data: Vec<u8>) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let temp = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+    if let Err(err) = tokio::fs::write(&temp, data).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(err);
+    }
+    tokio::fs::rename(&temp, path).await
+}
+
+/// Eintrag einer [`JsonStore`]
+pub trait StoredItem: Clone + Serialize + DeserializeOwned {
+    /// Bezeichnung in Logmeldungen, z.B. "Preset"
+    const KIND: &'static str;
+
+    fn id(&self) -> &str;
+    fn name(&self) -> &str;
+    fn created_at(&self) -> DateTime<Utc>;
+}
+
+/// Fehler einer Ablage, die [`JsonStore`] für fehlende und doppelte
+/// Kennungen erzeugt
+pub trait StoreError: From<std::io::Error> + From<serde_json::Error> {
+    fn not_found(id: String) -> Self;
+    fn conflict(id: String) -> Self;
+}
+
+/// Ablage mit einer JSON-Datei pro Eintrag, alle Einträge im Arbeitsspeicher
+pub struct JsonStore<T> {
+    dir: PathBuf,
+    items: RwLock<HashMap<String, T>>,
+    /// Serialisiert Änderungen, damit gleichzeitige Anlagen derselben
+    /// Kennung einander nicht überschreiben
+    writes: tokio::sync::Mutex<()>,
+}
+
+impl<T: StoredItem> JsonStore<T> {
+    /// Öffnet die Ablage und lädt vorhandene Einträge
+    pub fn open(dir: impl Into<PathBuf>) -> Self {
+        let store = Self {
+            dir: dir.into(),
+            items: RwLock::new(HashMap::new()),
+            writes: tokio::sync::Mutex::new(()),
+        };
+        store.load_existing();
+        store
+    }
+
+    fn load_existing(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return };
+
+        let mut items = self.items.write().unwrap();
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let item = std::fs::read(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|data| serde_json::from_slice::<T>(&data).map_err(|err| err.to_string()));
+            match item {
+                Ok(item) => {
+                    items.insert(item.id().to_string(), item);
+                }
+                Err(err) => warn!("{} {} konnte nicht geladen werden: {}", T::KIND, path.display(), err),
+            }
+        }
+    }
+
+    /// Alle Einträge, sortiert nach Name und Kennung
+    pub fn list(&self) -> Vec<T> {
+        let mut items: Vec<T> = self.items.read().unwrap().values().cloned().collect();
+        items.sort_by(|a, b| a.name().to_lowercase().cmp(&b.name().to_lowercase()).then_with(|| a.id().cmp(b.id())));
+        items
+    }
+
+    pub fn get(&self, id: &str) -> Option<T> {
+        self.items.read().unwrap().get(id).cloned()
+    }
+
+    /// Legt einen Eintrag unter `id` an, ohne Kennung unter einer neuen UUID;
+    /// `build` erhält Kennung, Erstell- und Änderungszeitpunkt
+    pub async fn create<E: StoreError>(
+        &self,
+        id: Option<String>,
+        build: impl FnOnce(String, DateTime<Utc>, DateTime<Utc>) -> T,
+    ) -> Result<T, E> {
+        let id = id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let _guard = self.writes.lock().await;
+        if self.items.read().unwrap().contains_key(&id) {
+            return Err(E::conflict(id));
+        }
+        let now = Utc::now();
+        self.save(build(id, now, now)).await
+    }
+
+    /// Ersetzt einen vorhandenen Eintrag; Kennung und Erstellzeitpunkt bleiben
+    pub async fn update<E: StoreError>(
+        &self,
+        id: &str,
+        build: impl FnOnce(String, DateTime<Utc>, DateTime<Utc>) -> T,
+    ) -> Result<T, E> {
+        let _guard = self.writes.lock().await;
+        let created_at = self.get(id).ok_or_else(|| E::not_found(id.to_string()))?.created_at();
+        self.save(build(id.to_string(), created_at, Utc::now())).await
+    }
+
+    pub async fn delete(&self, id: &str) -> std::io::Result<bool> {
+        let _guard = self.writes.lock().await;
+        if self.items.write().unwrap().remove(id).is_none() {
+            return Ok(false);
+        }
+        match tokio::fs::remove_file(self.item_path(id)).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(true),
+        }
+    }
+
+    async fn save<E: StoreError>(&self, item: T) -> Result<T, E> {
+        write_atomic(&self.item_path(item.id()), serde_json::to_vec_pretty(&item)?).await?;
+        self.items.write().unwrap().insert(item.id().to_string(), item.clone());
+        Ok(item)
+    }
+
+    fn item_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
@@ -0,0 +1,98 @@
+use crate::config::TelemetryConfig;
+use axum::http::HeaderMap;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{Protocol, WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Aktiver Trace-Export; beim Verwerfen werden ausstehende Spans gesendet
+#[derive(Debug)]
+pub struct Telemetry {
+    provider: TracerProvider,
+}
+
+impl Telemetry {
+    /// Exporter, Sampler und W3C-Propagation nach `config` einrichten.
+    /// Die Spans werden gebündelt im Hintergrund exportiert (Tokio-Runtime).
+    pub fn init(config: &TelemetryConfig) -> anyhow::Result<Self> {
+        let endpoint = format!("{}/v1/traces", config.endpoint.trim_end_matches('/'));
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .with_endpoint(endpoint)
+            .with_timeout(Duration::from_secs(config.timeout.max(1)))
+            .with_headers(config.headers.clone())
+            .build()
+            .map_err(|err| anyhow::anyhow!("OTLP-Exporter konnte nicht erstellt werden: {}", err))?;
+
+        let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.sample_ratio.clamp(0.0, 1.0))));
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_sampler(sampler)
+            .with_resource(Resource::new([
+                KeyValue::new("service.name", config.service_name.clone()),
+                KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+            ]))
+            .build();
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        Ok(Self { provider })
+    }
+
+    /// Tracer für den `tracing-opentelemetry`-Layer
+    pub fn tracer(&self) -> Tracer {
+        self.provider.tracer("chatglm-web")
+    }
+
+    /// Sendet alle gepufferten Spans sofort
+    pub fn flush(&self) {
+        for result in self.provider.force_flush() {
+            if let Err(err) = result {
+                tracing::warn!("Traces konnten nicht exportiert werden: {}", err);
+            }
+        }
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(err) = self.provider.shutdown() {
+            tracing::warn!("Trace-Export konnte nicht beendet werden: {}", err);
+        }
+    }
+}
+
+/// Setzt den Kontext aus einem eingehenden `traceparent` als Eltern von `span`.
+/// Ohne gültigen Header (oder ohne Export) beginnt ein neuer Trace.
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    let context = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    span.set_parent(context);
+}
+
+/// `traceparent`/`tracestate` für ausgehende Anfragen aus dem aktuellen Span
+pub fn trace_headers() -> HashMap<String, String> {
+    let context = Span::current().context();
+    let mut headers = HashMap::new();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut headers));
+    headers
+}
+
+/// Liest Propagations-Header aus einer axum-`HeaderMap`
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
@@ -0,0 +1,315 @@
+use crate::client::{ChatOptions, GlmModel, Message, Role};
+use crate::config::TemplatesConfig;
+use crate::presets::{placeholders, render};
+use crate::store::{validate_id, JsonStore, StoreError, StoredItem};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Vom Server bereitgestellte Variablen, die nicht deklariert werden müssen
+pub const BUILTIN_VARIABLES: [&str; 4] = ["date", "time", "weekday", "user_name"];
+
+/// Typ einer Template-Variable
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    #[default]
+    String,
+    Number,
+    Integer,
+    Boolean,
+    /// Einer der Werte aus `options`
+    Enum,
+}
+
+/// Deklarierte Variable eines Templates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: VariableType,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_required")]
+    pub required: bool,
+    /// Wert, wenn die Variable nicht angegeben wird
+    #[serde(default)]
+    pub default: Option<Value>,
+    /// Erlaubte Werte für `enum`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+impl TemplateVariable {
+    /// Prüft `value` gegen den Typ und gibt den Text zum Einsetzen zurück
+    fn text(&self, value: &Value) -> Result<String, String> {
+        match (self.kind, value) {
+            (VariableType::String, Value::String(text)) => Ok(text.clone()),
+            (VariableType::Number, Value::Number(number)) => Ok(number.to_string()),
+            (VariableType::Integer, Value::Number(number)) if number.is_i64() || number.is_u64() => Ok(number.to_string()),
+            (VariableType::Boolean, Value::Bool(flag)) => Ok(flag.to_string()),
+            (VariableType::Enum, Value::String(text)) if self.options.contains(text) => Ok(text.clone()),
+            (VariableType::Enum, _) => Err(format!("erlaubt sind: {}", self.options.join(", "))),
+            (kind, _) => Err(format!("erwartet {}", serde_json::to_value(kind).unwrap_or_default().as_str().unwrap_or_default())),
+        }
+    }
+}
+
+/// Nachricht eines Templates; `content` enthält Variablen wie `{{code}}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+/// Wiederverwendbarer, strukturierter Prompt (z.B. Code-Review oder Übersetzung)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    pub messages: Vec<TemplateMessage>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Template {
+    /// Rendert die Nachrichten mit den Werten aus `values`. `variables`
+    /// enthält vorbelegte Werte wie die eingebauten Variablen; deklarierte
+    /// Variablen haben Vorrang.
+    pub fn render(&self, values: &Map<String, Value>, mut variables: HashMap<String, String>) -> Result<Vec<Message>, TemplateError> {
+        if let Some(name) = values.keys().find(|name| !self.variables.iter().any(|variable| &variable.name == *name)) {
+            return Err(TemplateError::InvalidVariable(name.clone(), "ist im Template nicht deklariert".to_string()));
+        }
+
+        let mut missing = Vec::new();
+        for variable in &self.variables {
+            let value = match values.get(&variable.name).filter(|value| !value.is_null()).or(variable.default.as_ref()) {
+                Some(value) => variable
+                    .text(value)
+                    .map_err(|message| TemplateError::InvalidVariable(variable.name.clone(), message))?,
+                None if variable.required => {
+                    missing.push(variable.name.clone());
+                    continue;
+                }
+                None => String::new(),
+            };
+            variables.insert(variable.name.clone(), value);
+        }
+        if !missing.is_empty() {
+            return Err(TemplateError::MissingVariables(missing));
+        }
+
+        Ok(self
+            .messages
+            .iter()
+            .map(|message| {
+                let content = render(&message.content, &variables);
+                match message.role {
+                    Role::System => Message::system(content),
+                    Role::User => Message::user(content),
+                    Role::Assistant => Message::assistant(content),
+                }
+            })
+            .collect())
+    }
+
+    /// Setzt Modell, Temperatur und `max_tokens` des Templates, soweit die
+    /// Anfrage sie nicht selbst setzt
+    pub fn apply_defaults(&self, options: &mut ChatOptions) {
+        if options.model.is_none() {
+            options.model = self.model.clone();
+        }
+        if options.temperature.is_none() {
+            options.temperature = self.temperature;
+        }
+        if options.max_tokens.is_none() {
+            options.max_tokens = self.max_tokens;
+        }
+    }
+}
+
+/// Felder eines Templates beim Anlegen und Ändern
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateInput {
+    /// Optionale Kennung (Buchstaben, Ziffern, `-` und `_`), sonst eine UUID
+    pub id: Option<String>,
+    pub name: String,
+    pub description: String,
+    pub variables: Vec<TemplateVariable>,
+    pub messages: Vec<TemplateMessage>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+impl TemplateInput {
+    pub fn validate(&self) -> Result<(), TemplateError> {
+        if let Some(id) = &self.id {
+            validate_id(id).map_err(TemplateError::Invalid)?;
+        }
+        if self.name.trim().is_empty() {
+            return Err(TemplateError::Invalid("name darf nicht leer sein".to_string()));
+        }
+        if self.messages.is_empty() {
+            return Err(TemplateError::Invalid("messages darf nicht leer sein".to_string()));
+        }
+
+        for (position, variable) in self.variables.iter().enumerate() {
+            if validate_id(&variable.name).is_err() {
+                return Err(TemplateError::Invalid(format!("Ungültiger Variablenname '{}'", variable.name)));
+            }
+            if self.variables[..position].iter().any(|other| other.name == variable.name) {
+                return Err(TemplateError::Invalid(format!("Variable '{}' ist doppelt deklariert", variable.name)));
+            }
+            if variable.kind == VariableType::Enum && variable.options.is_empty() {
+                return Err(TemplateError::Invalid(format!("Variable '{}': enum benötigt options", variable.name)));
+            }
+            if let Some(default) = &variable.default {
+                variable
+                    .text(default)
+                    .map_err(|message| TemplateError::Invalid(format!("Standardwert von '{}': {}", variable.name, message)))?;
+            }
+        }
+
+        // Nicht deklarierte Variablen sind meist Tippfehler
+        for message in &self.messages {
+            for name in placeholders(&message.content) {
+                let declared = self.variables.iter().any(|variable| variable.name == name);
+                if !declared && !BUILTIN_VARIABLES.contains(&name.as_str()) {
+                    return Err(TemplateError::Invalid(format!("Variable '{}' wird verwendet, ist aber nicht deklariert", name)));
+                }
+            }
+        }
+
+        if let Some(model) = &self.model {
+            if GlmModel::from_id(model).is_none() {
+                return Err(TemplateError::Invalid(format!("Unbekanntes Modell '{}'", model)));
+            }
+        }
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=1.0).contains(&temperature) {
+                return Err(TemplateError::Invalid("temperature muss zwischen 0.0 und 1.0 liegen".to_string()));
+            }
+        }
+        if self.max_tokens == Some(0) {
+            return Err(TemplateError::Invalid("max_tokens muss größer als 0 sein".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("Template '{0}' nicht gefunden")]
+    NotFound(String),
+
+    #[error("Template '{0}' existiert bereits")]
+    Conflict(String),
+
+    #[error("Ungültiges Template: {0}")]
+    Invalid(String),
+
+    #[error("Fehlende Variablen: {}", .0.join(", "))]
+    MissingVariables(Vec<String>),
+
+    #[error("Ungültige Variable '{0}': {1}")]
+    InvalidVariable(String, String),
+
+    #[error("Speicherfehler: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Speicherfehler: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl StoreError for TemplateError {
+    fn not_found(id: String) -> Self {
+        TemplateError::NotFound(id)
+    }
+
+    fn conflict(id: String) -> Self {
+        TemplateError::Conflict(id)
+    }
+}
+
+impl StoredItem for Template {
+    const KIND: &'static str = "Template";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+/// Ablage für Templates, je Template eine JSON-Datei
+pub struct TemplateStore {
+    templates: JsonStore<Template>,
+}
+
+impl TemplateStore {
+    /// Öffnet die Ablage und lädt vorhandene Templates
+    pub fn open(config: &TemplatesConfig) -> Self {
+        Self { templates: JsonStore::open(&config.dir) }
+    }
+
+    pub fn list(&self) -> Vec<Template> {
+        self.templates.list()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Template> {
+        self.templates.get(id)
+    }
+
+    pub async fn create(&self, input: TemplateInput) -> Result<Template, TemplateError> {
+        input.validate()?;
+        self.templates.create(input.id.clone(), |id, created_at, updated_at| Self::build(id, input, created_at, updated_at)).await
+    }
+
+    /// Ersetzt alle Felder eines vorhandenen Templates; die Kennung bleibt
+    pub async fn update(&self, id: &str, input: TemplateInput) -> Result<Template, TemplateError> {
+        input.validate()?;
+        self.templates.update(id, |id, created_at, updated_at| Self::build(id, input, created_at, updated_at)).await
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<bool, TemplateError> {
+        Ok(self.templates.delete(id).await?)
+    }
+
+    fn build(id: String, input: TemplateInput, created_at: DateTime<Utc>, updated_at: DateTime<Utc>) -> Template {
+        Template {
+            id,
+            name: input.name.trim().to_string(),
+            description: input.description,
+            variables: input.variables,
+            messages: input.messages,
+            model: input.model,
+            temperature: input.temperature,
+            max_tokens: input.max_tokens,
+            created_at,
+            updated_at,
+        }
+    }
+}
@@ -1,7 +1,5 @@
 #[cfg(test)]
 mod api_tests {
-    use crate::api::*;
-    use serde_json::json;
 
     #[tokio::test]
     async fn test_health_check() {
@@ -0,0 +1,261 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::{chat_routes, UploadStore};
+    use crate::client::cache::{cache_key, replay_chunks, CacheEntry};
+    use crate::client::*;
+    use crate::config::UploadsConfig;
+    use axum::body::Body;
+    use axum::http::Request;
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tower::ServiceExt;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn completion(content: &str) -> Value {
+        json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "glm-4.5",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content, "reasoning_content": "Überlegung" },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5 }
+        })
+    }
+
+    fn cached_client(uri: &str, temperature: f32) -> GlmClient {
+        GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: uri.to_string(),
+            temperature,
+            stream: false,
+            ..GlmConfig::default()
+        })
+        .unwrap()
+        .with_cache(Arc::new(ResponseCache::memory(16)))
+    }
+
+    fn request(temperature: f32) -> ChatCompletionRequest {
+        ChatCompletionRequest::new("glm-4.5".to_string(), vec![Message::user("Hallo")])
+            .with_temperature(temperature)
+    }
+
+    #[test]
+    fn test_cache_key_ignores_stream_and_user() {
+        let base = cache_key(&request(0.0));
+        assert_eq!(base, cache_key(&request(0.0).with_stream(true).with_user("anna")));
+        assert_ne!(base, cache_key(&request(0.5)));
+        assert_ne!(base, cache_key(&request(0.0).with_max_tokens(10)));
+
+        let other = ChatCompletionRequest::new("glm-4.5".to_string(), vec![Message::user("Hallo!")]);
+        assert_ne!(base, cache_key(&other.with_temperature(0.0)));
+    }
+
+    #[test]
+    fn test_only_deterministic_requests_are_cached() {
+        let cache = ResponseCache::memory(4);
+        assert!(cache.key_for(&request(0.0)).is_some());
+        assert!(cache.key_for(&request(0.7)).is_none());
+
+        let cache = cache.with_deterministic_only(false);
+        assert!(cache.key_for(&request(0.7)).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_memory_cache_evicts_least_recently_used() {
+        let response: ChatCompletionResponse = serde_json::from_value(completion("A")).unwrap();
+        let cache = ResponseCache::memory(2);
+        cache.put("a", &response).await;
+        cache.put("b", &response).await;
+        assert!(cache.get("a").await.is_some());
+        cache.put("c", &response).await;
+
+        assert!(cache.get("a").await.is_some());
+        assert!(cache.get("b").await.is_none());
+        assert!(cache.get("c").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_expires_entries() {
+        let dir = std::env::temp_dir().join(format!("chatglm-cache-{}", uuid::Uuid::new_v4()));
+        let response: ChatCompletionResponse = serde_json::from_value(completion("Alt")).unwrap();
+
+        let backend = DiskCache::open(&dir, 16, None);
+        backend.put("alt", CacheEntry { response: response.clone(), stored_at: chrono::Utc::now().timestamp() - 120 }).await;
+        let cache = ResponseCache::new(Box::new(backend)).with_ttl(Some(Duration::from_secs(60)));
+        cache.put("neu", &response).await;
+
+        assert!(cache.get("alt").await.is_none());
+        assert!(!dir.join("alt.json").exists());
+        assert_eq!(cache.get("neu").await.unwrap().choices[0].message.text(), Some("Alt"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_evicts_and_cleans_up_on_open() {
+        let dir = std::env::temp_dir().join(format!("chatglm-cache-{}", uuid::Uuid::new_v4()));
+        let response: ChatCompletionResponse = serde_json::from_value(completion("A")).unwrap();
+
+        let cache = ResponseCache::disk(&dir, 2, None);
+        cache.put("a", &response).await;
+        cache.put("b", &response).await;
+        assert!(cache.get("a").await.is_some());
+        cache.put("c", &response).await;
+
+        assert!(!dir.join("b.json").exists());
+        assert!(cache.get("b").await.is_none());
+        assert!(cache.get("a").await.is_some());
+
+        // Beim Öffnen mit kleinerer Kapazität bleiben nur die neuesten Dateien
+        std::fs::write(dir.join("alt.json"), b"{}").unwrap();
+        let old = std::time::SystemTime::now() - Duration::from_secs(600);
+        std::fs::File::options().write(true).open(dir.join("alt.json")).unwrap().set_modified(old).unwrap();
+        let reopened = ResponseCache::disk(&dir, 1, Some(Duration::from_secs(60)));
+        assert!(!dir.join("alt.json").exists());
+        assert!(!dir.join("a.json").exists());
+        assert!(reopened.get("c").await.is_some());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_responses_are_not_cached() {
+        let mut response: ChatCompletionResponse = serde_json::from_value(completion("")).unwrap();
+        response.choices[0].finish_reason = Some("tool_calls".to_string());
+        let cache = ResponseCache::memory(4);
+        cache.put("tools", &response).await;
+        assert!(cache.get("tools").await.is_none());
+    }
+
+    #[test]
+    fn test_replay_chunks_reproduce_message() {
+        let response: ChatCompletionResponse = serde_json::from_value(completion(&"x".repeat(70))).unwrap();
+        let chunks = replay_chunks(&response);
+
+        assert_eq!(chunks.first().unwrap().choices[0].delta.reasoning_content.as_deref(), Some("Überlegung"));
+        let content: String = chunks.iter().filter_map(|chunk| chunk.choices[0].delta.content.clone()).collect();
+        assert_eq!(content, "x".repeat(70));
+        assert_eq!(chunks.last().unwrap().choices[0].finish_reason.as_deref(), Some("stop"));
+    }
+
+    #[tokio::test]
+    async fn test_identical_requests_hit_cache_and_replay_as_stream() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion("Gecacht")))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = cached_client(&mock_server.uri(), 0.0);
+        let first = client.chat_completions(vec![Message::user("Hallo")]).await.unwrap();
+        let second = client.chat_completions(vec![Message::user("Hallo")]).await.unwrap();
+        assert_eq!(first.choices[0].message.text(), second.choices[0].message.text());
+
+        let message = client
+            .chat_completions_stream(vec![Message::user("Hallo")])
+            .await
+            .unwrap()
+            .collect_message()
+            .await
+            .unwrap();
+        assert_eq!(message.text(), Some("Gecacht"));
+        assert_eq!(message.thinking.as_deref(), Some("Überlegung"));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_response_is_recorded() {
+        let mock_server = MockServer::start().await;
+        let body = [
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"content":"Hallo "},"finish_reason":null}]}"#,
+            "",
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"content":"Welt"},"finish_reason":"stop"}]}"#,
+            "",
+            // Mit `include_usage` folgt der Verbrauch in einem eigenen Chunk
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[],"usage":{"prompt_tokens":3,"completion_tokens":2,"total_tokens":5}}"#,
+            "",
+            "data: [DONE]",
+            "",
+        ].join("\n");
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = cached_client(&mock_server.uri(), 0.0);
+        let streamed = client.chat_completions_stream(vec![Message::user("Hi")]).await.unwrap();
+        assert_eq!(streamed.collect_content().await.unwrap(), "Hallo Welt");
+        // Der Eintrag wird in einem eigenen Task gespeichert
+        tokio::task::yield_now().await;
+
+        let cached = client.chat_completions(vec![Message::user("Hi")]).await.unwrap();
+        assert_eq!(cached.choices[0].message.text(), Some("Hallo Welt"));
+        assert_eq!(cached.usage.unwrap().total_tokens, 5);
+
+        // Auch die Wiedergabe als Stream liefert den Verbrauch
+        let mut replayed = client.chat_completions_stream(vec![Message::user("Hi")]).await.unwrap();
+        let mut usage = None;
+        while let Some(chunk) = replayed.next().await {
+            usage = chunk.unwrap().usage.or(usage);
+        }
+        assert_eq!(usage.unwrap().total_tokens, 5);
+    }
+
+    #[tokio::test]
+    async fn test_nondeterministic_and_bypassed_requests_reach_upstream() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion("Neu")))
+            .expect(4)
+            .mount(&mock_server)
+            .await;
+
+        let client = cached_client(&mock_server.uri(), 0.8);
+        client.chat_completions(vec![Message::user("Hallo")]).await.unwrap();
+        client.chat_completions(vec![Message::user("Hallo")]).await.unwrap();
+
+        let client = cached_client(&mock_server.uri(), 0.0);
+        let bypass = ChatOptions { cache: CachePolicy::Bypass, ..ChatOptions::default() };
+        client.chat_completions_with(vec![Message::user("Hallo")], &bypass).await.unwrap();
+        // Bypass speichert nichts; die folgende Anfrage geht daher ebenfalls an die API
+        client.chat_completions(vec![Message::user("Hallo")]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cache_control_header_forces_refresh() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion("Antwort")))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let uploads = Arc::new(UploadStore::new(&UploadsConfig::default()));
+        let app = chat_routes(cached_client(&mock_server.uri(), 0.0), uploads, None, None, None, None, None);
+        let body = json!({ "messages": [{ "role": "user", "content": "Hallo" }] }).to_string();
+
+        for cache_control in [None, Some("no-cache"), None] {
+            let mut request = Request::post("/api/chat").header("content-type", "application/json");
+            if let Some(value) = cache_control {
+                request = request.header("cache-control", value);
+            }
+            let response = app.clone().oneshot(request.body(Body::from(body.clone())).unwrap()).await.unwrap();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let value: Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(value["response"]["choices"][0]["message"]["content"], "Antwort");
+        }
+    }
+}
@@ -0,0 +1,185 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::{chat_routes, conversations_routes, UploadStore};
+    use crate::client::*;
+    use crate::config::{ConversationsConfig, UploadsConfig};
+    use crate::conversations::ConversationStore;
+    use crate::tests::send;
+    use axum::http::StatusCode;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn completion(answers: &[&str]) -> Value {
+        let choices: Vec<Value> = answers
+            .iter()
+            .enumerate()
+            .map(|(index, answer)| json!({
+                "index": index,
+                "message": { "role": "assistant", "content": answer },
+                "finish_reason": "stop"
+            }))
+            .collect();
+        json!({
+            "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+            "choices": choices,
+            "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+        })
+    }
+
+    fn client(server: &MockServer, native: bool) -> GlmClient {
+        GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: server.uri(),
+            native_candidates: native,
+            ..GlmConfig::default()
+        }).unwrap()
+    }
+
+    fn candidates(n: u32) -> ChatOptions {
+        ChatOptions { n: Some(n), ..ChatOptions::default() }
+    }
+
+    async fn request_bodies(server: &MockServer) -> Vec<Value> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_candidates_use_parallel_requests_without_native_support() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion(&["Antwort"])))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let response = client(&server, false).chat_completions_with(vec![Message::user("Hallo")], &candidates(3)).await.unwrap();
+        let indices: Vec<u32> = response.choices.iter().map(|choice| choice.index).collect();
+        assert_eq!(indices, [0, 1, 2]);
+        assert_eq!(response.usage.unwrap().total_tokens, 45);
+        assert!(request_bodies(&server).await.iter().all(|body| body.get("n").is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_native_candidates_are_topped_up_when_upstream_returns_fewer() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "n": 3 })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion(&["Eins", "Zwei"])))
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion(&["Drei"])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = client(&server, true).chat_completions_with(vec![Message::user("Hallo")], &candidates(3)).await.unwrap();
+        let answers: Vec<&str> = response.choices.iter().filter_map(|choice| choice.message.text()).collect();
+        assert_eq!(answers, ["Eins", "Zwei", "Drei"]);
+        assert_eq!(response.choices[2].index, 2);
+
+        let err = client(&server, true)
+            .chat_completions_with(vec![Message::user("Hallo")], &candidates(MAX_CANDIDATES + 1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GlmError::InvalidRequest { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_candidates_are_stored_as_alternatives() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion(&["Antwort"])))
+            .mount(&server)
+            .await;
+
+        let dir = std::env::temp_dir().join(format!("chatglm-candidates-{}", uuid::Uuid::new_v4()));
+        let store = Arc::new(ConversationStore::open(&ConversationsConfig { enabled: true, dir: dir.to_string_lossy().to_string() }));
+        let client = client(&server, false);
+        let uploads = Arc::new(UploadStore::new(&UploadsConfig::default()));
+        let app = chat_routes(client.clone(), uploads.clone(), None, None, None, Some(store.clone()), None)
+            .merge(conversations_routes(store.clone(), Arc::new(client), uploads, None, None));
+
+        let (_, body) = send(&app, "POST", "/api/conversations", &[], Some(json!({}))).await;
+        let id = body["conversation"]["id"].as_str().unwrap().to_string();
+
+        let (status, _) = send(&app, "POST", &format!("/api/conversations/{}/regenerate", id), &[], Some(json!({}))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let chat = json!({ "conversation_id": id, "n": 2, "messages": [{ "role": "user", "content": "Hallo" }] });
+        let (status, body) = send(&app, "POST", "/api/chat", &[], Some(chat)).await;
+        assert_eq!(status, StatusCode::OK);
+        let ids: Vec<String> = serde_json::from_value(body["message_ids"].clone()).unwrap();
+        assert_eq!(ids.len(), 2);
+        // Der erste Kandidat ist aktiv, der zweite als Alternative wählbar
+        let conversation = store.get("anonymous", &id).unwrap();
+        assert_eq!(conversation.active_id.as_deref(), Some(ids[0].as_str()));
+        assert_eq!(conversation.siblings(&ids[0]).unwrap().len(), 2);
+
+        let (status, body) = send(&app, "POST", &format!("/api/conversations/{}/regenerate", id), &[], Some(json!({ "n": 2 }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["message_ids"].as_array().unwrap().len(), 2);
+        let messages = body["conversation"]["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1]["sibling_count"], 4);
+        assert_eq!(messages[1]["sibling_index"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_rejects_multiple_candidates() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion(&["Antwort"])))
+            .mount(&server)
+            .await;
+
+        // Einzelne Kandidaten werden auch mit `stream = true` als Ganzes angefragt
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: server.uri(),
+            stream: true,
+            ..GlmConfig::default()
+        }).unwrap();
+        let response = client.chat_completions_with(vec![Message::user("Hallo")], &candidates(2)).await.unwrap();
+        assert_eq!(response.choices.len(), 2);
+        assert!(request_bodies(&server).await.iter().all(|body| body["stream"] == false));
+        assert!(client.chat_completions_stream_with(vec![Message::user("Hallo")], &candidates(2)).await.is_err());
+
+        let dir = std::env::temp_dir().join(format!("chatglm-candidates-{}", uuid::Uuid::new_v4()));
+        let store = Arc::new(ConversationStore::open(&ConversationsConfig { enabled: true, dir: dir.to_string_lossy().to_string() }));
+        let uploads = Arc::new(UploadStore::new(&UploadsConfig::default()));
+        let app = chat_routes(client.clone(), uploads.clone(), None, None, None, Some(store.clone()), None)
+            .merge(conversations_routes(store.clone(), Arc::new(client), uploads, None, None));
+        let (_, body) = send(&app, "POST", "/api/conversations", &[], Some(json!({}))).await;
+        let id = body["conversation"]["id"].as_str().unwrap().to_string();
+
+        let chat = json!({ "conversation_id": id, "n": 3, "messages": [{ "role": "user", "content": "Hallo" }] });
+        let (status, body) = send(&app, "POST", "/api/chat/stream", &[], Some(chat)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().contains("n > 1"));
+        // Abgelehnte Anfragen hinterlassen keine Nachricht in der Unterhaltung
+        assert!(store.get("anonymous", &id).unwrap().nodes.is_empty());
+
+        let chat = json!({ "conversation_id": id, "messages": [{ "role": "user", "content": "Hallo" }] });
+        send(&app, "POST", "/api/chat", &[], Some(chat)).await;
+        let regenerate = json!({ "stream": true, "n": 3 });
+        let (status, _) = send(&app, "POST", &format!("/api/conversations/{}/regenerate", id), &[], Some(regenerate)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(request_bodies(&server).await.len(), 3);
+    }
+}
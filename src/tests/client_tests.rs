@@ -1,9 +1,7 @@
 #[cfg(test)]
 mod tests {
     use crate::client::*;
-    use crate::client::types::*;
     use crate::client::error::*;
-    use tokio_test;
     use wiremock::{MockServer, Mock, ResponseTemplate};
     use wiremock::matchers::{method, path, header};
     use serde_json::json;
@@ -13,7 +11,9 @@ mod tests {
     #[tokio::test]
     async fn test_glm_config_creation() {
         let config = GlmConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            api_keys: Vec::new(),
+            key_cooldown: std::time::Duration::from_secs(3600),
             api_url: "https://api.test.com".to_string(),
             model: GlmModel::Glm45,
             max_tokens: 2048,
@@ -21,10 +21,14 @@ mod tests {
             top_p: 0.9,
             stream: false,
             thinking_enabled: true,
+            thinking_budget: None,
+            embedding_model: "embedding-3".to_string(),
+            embedding_batch_size: 64,
+            native_candidates: false,
             timeout: std::time::Duration::from_secs(30),
         };
 
-        assert_eq!(config.api_key, "test-key");
+        assert_eq!(config.api_key.expose(), "test-key");
         assert_eq!(config.api_url, "https://api.test.com");
         assert_eq!(config.max_tokens, 2048);
     }
@@ -115,7 +119,9 @@ mod tests {
     #[tokio::test]
     async fn test_glm_client_creation_success() {
         let config = GlmConfig {
-            api_key: "valid-key".to_string(),
+            api_key: "valid-key".into(),
+            api_keys: Vec::new(),
+            key_cooldown: std::time::Duration::from_secs(3600),
             api_url: "https://api.test.com".to_string(),
             model: GlmModel::Glm45,
             max_tokens: 2048,
@@ -123,6 +129,10 @@ mod tests {
             top_p: 0.9,
             stream: false,
             thinking_enabled: true,
+            thinking_budget: None,
+            embedding_model: "embedding-3".to_string(),
+            embedding_batch_size: 64,
+            native_candidates: false,
             timeout: std::time::Duration::from_secs(30),
         };
 
@@ -133,7 +143,9 @@ mod tests {
     #[tokio::test]
     async fn test_glm_client_creation_empty_api_key() {
         let config = GlmConfig {
-            api_key: "".to_string(),
+            api_key: "".into(),
+            api_keys: Vec::new(),
+            key_cooldown: std::time::Duration::from_secs(3600),
             api_url: "https://api.test.com".to_string(),
             model: GlmModel::Glm45,
             max_tokens: 2048,
@@ -141,6 +153,10 @@ mod tests {
             top_p: 0.9,
             stream: false,
             thinking_enabled: true,
+            thinking_budget: None,
+            embedding_model: "embedding-3".to_string(),
+            embedding_batch_size: 64,
+            native_candidates: false,
             timeout: std::time::Duration::from_secs(30),
         };
 
@@ -181,7 +197,9 @@ mod tests {
             .await;
 
         let config = GlmConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            api_keys: Vec::new(),
+            key_cooldown: std::time::Duration::from_secs(3600),
             api_url: mock_server.uri(),
             model: GlmModel::Glm45,
             max_tokens: 2048,
@@ -189,6 +207,10 @@ mod tests {
             top_p: 0.9,
             stream: false,
             thinking_enabled: true,
+            thinking_budget: None,
+            embedding_model: "embedding-3".to_string(),
+            embedding_batch_size: 64,
+            native_candidates: false,
             timeout: std::time::Duration::from_secs(30),
         };
 
@@ -222,7 +244,9 @@ mod tests {
             .await;
 
         let config = GlmConfig {
-            api_key: "invalid-key".to_string(),
+            api_key: "invalid-key".into(),
+            api_keys: Vec::new(),
+            key_cooldown: std::time::Duration::from_secs(3600),
             api_url: mock_server.uri(),
             model: GlmModel::Glm45,
             max_tokens: 2048,
@@ -230,6 +254,10 @@ mod tests {
             top_p: 0.9,
             stream: false,
             thinking_enabled: true,
+            thinking_budget: None,
+            embedding_model: "embedding-3".to_string(),
+            embedding_batch_size: 64,
+            native_candidates: false,
             timeout: std::time::Duration::from_secs(30),
         };
 
@@ -257,7 +285,9 @@ mod tests {
             .await;
 
         let config = GlmConfig {
-            api_key: "test-key".to_string(),
+            api_key: "test-key".into(),
+            api_keys: Vec::new(),
+            key_cooldown: std::time::Duration::from_secs(3600),
             api_url: mock_server.uri(),
             model: GlmModel::Glm45,
             max_tokens: 2048,
@@ -265,6 +295,10 @@ mod tests {
             top_p: 0.9,
             stream: false,
             thinking_enabled: true,
+            thinking_budget: None,
+            embedding_model: "embedding-3".to_string(),
+            embedding_batch_size: 64,
+            native_candidates: false,
             timeout: std::time::Duration::from_secs(30),
         };
 
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use crate::config::{interpolate, AppConfig};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn write_config(extension: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("chatglm-config-{}.{}", uuid::Uuid::new_v4(), extension));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_interpolate_variables_and_defaults() {
+        let vars = lookup(&[("KEY", "abc"), ("EMPTY", "")]);
+        let (text, missing) = interpolate(
+            r#"key = "${KEY}", url = "${URL:-http://localhost}", empty = "${EMPTY:-x}", raw = "$${KEY}""#,
+            &vars,
+        );
+        assert_eq!(text, r#"key = "abc", url = "http://localhost", empty = "x", raw = "${KEY}""#);
+        assert!(missing.is_empty());
+
+        let (text, missing) = interpolate("a = \"${MISSING}\"\nb = \"${MISSING}\"\nc = \"${OPEN\"", &vars);
+        assert_eq!(text, "a = \"\"\nb = \"\"\nc = \"${OPEN\"");
+        assert_eq!(missing, vec!["MISSING".to_string()]);
+    }
+
+    #[test]
+    fn test_missing_sections_use_defaults() {
+        let path = write_config("toml", "[chatglm]\napi_key = \"${CHATGLM_CONFIG_TEST_KEY:-from-default}\"\n");
+        let loaded = AppConfig::load_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let config = loaded.config;
+        assert_eq!(config.chatglm.api_key.expose(), "from-default");
+        assert_eq!(config.chatglm.model, "glm-4.5");
+        assert_eq!(config.server.port, 3000);
+        assert_eq!(config.websocket.max_connections, 100);
+        assert!(config.validate(false).is_empty(), "{:?}", config.validate(false));
+        assert!(loaded.missing_vars.is_empty());
+    }
+
+    #[test]
+    fn test_shipped_config_files_are_valid() {
+        for file in ["config.toml", "config.yaml"] {
+            let loaded = AppConfig::load_file(file).unwrap();
+            assert!(loaded.problems(false).is_empty(), "{}: {:?}", file, loaded.problems(false));
+        }
+    }
+
+    #[test]
+    fn test_yaml_with_unset_variable_is_reported() {
+        let path = write_config("yaml", "chatglm:\n  api_key: \"${CHATGLM_CONFIG_TEST_UNSET}\"\n");
+        let loaded = AppConfig::load_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.config.chatglm.api_key.expose(), "");
+        let problems = loaded.problems(false);
+        assert_eq!(problems, vec!["Umgebungsvariable CHATGLM_CONFIG_TEST_UNSET ist nicht gesetzt".to_string()]);
+    }
+
+    #[test]
+    fn test_validation_reports_all_problems() {
+        let mut config = AppConfig::default();
+        config.chatglm.api_url = "api.z.ai".to_string();
+        config.chatglm.model = "glm-3".to_string();
+        config.chatglm.temperature = 1.5;
+        config.server.port = 0;
+        config.logging.level = "laut".to_string();
+        config.documents.chunk_overlap = config.documents.chunk_size;
+        config.mcp.serve.http_enabled = true;
+        config.mcp.serve.allow_risky_tools = true;
+
+        let problems = config.validate(false);
+        assert_eq!(problems.len(), 7, "{:?}", problems);
+        assert!(problems.iter().any(|p| p.contains("chatglm.model 'glm-3'") && p.contains("glm-4.5-turbo")));
+        assert!(problems.iter().any(|p| p.starts_with("chatglm.temperature")));
+        assert!(problems.iter().any(|p| p.starts_with("mcp.serve.allow_risky_tools")));
+    }
+
+    #[test]
+    fn test_production_requires_secrets() {
+        let mut config = AppConfig::default();
+        assert!(config.validate(false).is_empty());
+
+        let problems = config.validate(true);
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].starts_with("chatglm.api_key"));
+        assert!(problems[1].starts_with("session.secret"));
+
+        config.chatglm.api_key = "sk-test-1234567890".into();
+        config.session.secret = "geheim-geheim-geheim".into();
+        assert!(config.validate(true).is_empty());
+    }
+
+    #[test]
+    fn test_redacted_masks_secrets() {
+        let mut config = AppConfig::default();
+        config.chatglm.api_key = "sk-test-1234567890".into();
+        config.session.secret = "geheim-geheim-geheim".into();
+        config.telemetry.headers.insert("authorization".to_string(), "Bearer collector-token".to_string());
+
+        let redacted = config.redacted();
+        let text = redacted.to_string();
+        assert!(!text.contains("1234567890"));
+        assert!(!text.contains("geheim-geheim"));
+        assert!(!text.contains("collector-token"));
+        assert_eq!(redacted["chatglm"]["api_key"], "sk-t***");
+        assert_eq!(redacted["chatglm"]["temperature"], 0.7);
+        assert_eq!(redacted["chatglm"]["max_tokens"], 4096);
+    }
+}
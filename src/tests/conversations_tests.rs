@@ -0,0 +1,248 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::{chat_routes, conversations_routes, UploadStore};
+    use crate::client::*;
+    use crate::config::{ConversationsConfig, UploadsConfig};
+    use crate::conversations::{Conversation, ConversationError, ConversationStore};
+    use crate::tests::send;
+    use axum::http::StatusCode;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn conversations_config() -> ConversationsConfig {
+        let dir = std::env::temp_dir().join(format!("chatglm-conversations-{}", uuid::Uuid::new_v4()));
+        ConversationsConfig { enabled: true, dir: dir.to_string_lossy().to_string() }
+    }
+
+    fn conversation() -> Conversation {
+        Conversation {
+            id: "c1".to_string(),
+            title: "Test".to_string(),
+            user: "anna".to_string(),
+            nodes: Vec::new(),
+            active_id: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn texts(messages: &[Message]) -> Vec<String> {
+        messages.iter().map(|message| message.text().unwrap_or_default().to_string()).collect()
+    }
+
+    const USER: &[(&str, &str)] = &[("x-user-id", "anna")];
+
+    /// Inhalte der Nachrichten, die bei der `index`-ten Anfrage an die API gingen
+    async fn sent_messages(server: &MockServer, index: usize) -> Vec<String> {
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = serde_json::from_slice(&requests[index].body).unwrap();
+        body["messages"].as_array().unwrap().iter().map(|message| message["content"].as_str().unwrap().to_string()).collect()
+    }
+
+    fn branch(body: &Value) -> Vec<String> {
+        body["conversation"]["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["content"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_editing_creates_branches_instead_of_discarding() {
+        let mut conversation = conversation();
+        let question = conversation.append(None, Message::user("Frage")).unwrap();
+        let answer = conversation.append(Some(&question), Message::assistant("Antwort")).unwrap();
+        conversation.append(Some(&answer), Message::user("Nachfrage")).unwrap();
+
+        let edited = conversation.edit(&question, "Bessere Frage").unwrap();
+        assert_eq!(conversation.active_id.as_deref(), Some(edited.as_str()));
+        assert_eq!(texts(&conversation.context(conversation.active_id.as_deref()).unwrap()), ["Bessere Frage"]);
+        assert_eq!(conversation.nodes.len(), 4);
+
+        let branch = conversation.active_branch();
+        assert_eq!((branch[0].sibling_index, branch[0].sibling_count), (1, 2));
+        assert_eq!(conversation.siblings(&question).unwrap().len(), 2);
+
+        // Zurück zum ursprünglichen Zweig, bis zu dessen letzter Nachricht
+        conversation.activate(&question).unwrap();
+        assert_eq!(texts(&conversation.context(conversation.active_id.as_deref()).unwrap()), ["Frage", "Antwort", "Nachfrage"]);
+
+        assert!(matches!(conversation.activate("fehlt"), Err(ConversationError::MessageNotFound(_))));
+        assert!(matches!(conversation.append(Some("fehlt"), Message::user("x")), Err(ConversationError::MessageNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_chat_continues_active_branch_and_edits_regenerate() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Antwort" }, "finish_reason": "stop" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let config = conversations_config();
+        let store = Arc::new(ConversationStore::open(&config));
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: server.uri(),
+            ..GlmConfig::default()
+        }).unwrap();
+        let uploads = Arc::new(UploadStore::new(&UploadsConfig::default()));
+        let app = chat_routes(client.clone(), uploads.clone(), None, None, None, Some(store.clone()), None)
+            .merge(conversations_routes(store, Arc::new(client), uploads, None, None));
+
+        let (status, body) = send(&app, "POST", "/api/conversations", USER, Some(json!({ "title": "Rust" }))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = body["conversation"]["id"].as_str().unwrap().to_string();
+
+        for question in ["Frage 1", "Frage 2"] {
+            let chat = json!({ "conversation_id": id, "messages": [{ "role": "user", "content": question }] });
+            let (status, body) = send(&app, "POST", "/api/chat", USER, Some(chat)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(body["message_id"].is_string());
+        }
+        // Der Kontext stammt aus der gespeicherten Unterhaltung
+        assert_eq!(sent_messages(&server, 1).await, ["Frage 1", "Antwort", "Frage 2"]);
+
+        let (_, body) = send(&app, "GET", &format!("/api/conversations/{}", id), USER, None).await;
+        let first = body["conversation"]["messages"][0]["id"].as_str().unwrap().to_string();
+        let last = body["conversation"]["messages"][3]["id"].as_str().unwrap().to_string();
+
+        // Bearbeiten der ersten Frage erzeugt einen neuen Zweig mit neuer Antwort
+        let uri = format!("/api/conversations/{}/messages/{}", id, first);
+        let (status, body) = send(&app, "PUT", &uri, USER, Some(json!({ "content": "Frage 1b" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(branch(&body), ["Frage 1b", "Antwort"]);
+        assert_eq!(body["conversation"]["messages"][0]["sibling_count"], 2);
+        assert_eq!(sent_messages(&server, 2).await, ["Frage 1b"]);
+
+        let (_, body) = send(&app, "GET", &format!("{}/siblings", uri), USER, None).await;
+        assert_eq!(body["count"], 2);
+        assert_eq!(body["siblings"][0]["active"], false);
+        assert_eq!(body["siblings"][1]["active"], true);
+
+        // Zurück zum ursprünglichen Zweig und dessen letzte Antwort neu generieren
+        let (status, body) = send(&app, "POST", &format!("{}/activate", uri), USER, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(branch(&body), ["Frage 1", "Antwort", "Frage 2", "Antwort"]);
+
+        let regenerate = format!("/api/conversations/{}/messages/{}/regenerate", id, last);
+        let (status, body) = send(&app, "POST", &regenerate, USER, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["conversation"]["messages"][3]["sibling_count"], 2);
+        assert_eq!(sent_messages(&server, 3).await, ["Frage 1", "Antwort", "Frage 2"]);
+
+        // Gespeichert und nur für den eigenen Benutzer sichtbar
+        let reopened = ConversationStore::open(&config);
+        assert_eq!(reopened.get("anna", &id).unwrap().nodes.len(), 7);
+        assert!(matches!(reopened.get("ben", &id), Err(ConversationError::NotFound(_))));
+
+        let (status, _) = send(&app, "POST", &format!("/api/conversations/{}/messages/fehlt/regenerate", id), USER, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_regenerate_resolves_uploaded_files() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Ein Bild" }, "finish_reason": "stop" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let store = Arc::new(ConversationStore::open(&conversations_config()));
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: server.uri(),
+            ..GlmConfig::default()
+        }).unwrap();
+        let dir = std::env::temp_dir().join(format!("chatglm-uploads-{}", uuid::Uuid::new_v4()));
+        let uploads = Arc::new(UploadStore::new(&UploadsConfig { dir: dir.to_string_lossy().to_string(), ..UploadsConfig::default() }));
+        let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+        let upload = uploads.store(&png, None, None).await.unwrap();
+        let app = chat_routes(client.clone(), uploads.clone(), None, None, None, Some(store.clone()), None)
+            .merge(conversations_routes(store, Arc::new(client), uploads, None, None));
+
+        let (_, body) = send(&app, "POST", "/api/conversations", USER, None).await;
+        let id = body["conversation"]["id"].as_str().unwrap().to_string();
+        let content = json!([{ "type": "text", "text": "Was ist das?" }, { "type": "file", "file": { "file_id": upload.id } }]);
+        let chat = json!({ "conversation_id": id, "messages": [{ "role": "user", "content": content }] });
+        let (status, _) = send(&app, "POST", "/api/chat", USER, Some(chat)).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(&app, "POST", &format!("/api/conversations/{}/regenerate", id), USER, None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Auch beim Neugenerieren gehen Bilder statt Dateiverweisen an die API
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        let parts = body["messages"][0]["content"].as_array().unwrap();
+        assert_eq!(parts[1]["type"], "image_url");
+        assert!(parts[1]["image_url"]["url"].as_str().unwrap().starts_with("data:image/png;base64,"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_turns_leave_conversation_unchanged() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("kaputt"))
+            .mount(&server)
+            .await;
+
+        let store = Arc::new(ConversationStore::open(&conversations_config()));
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: server.uri(),
+            ..GlmConfig::default()
+        }).unwrap();
+        let uploads = Arc::new(UploadStore::new(&UploadsConfig::default()));
+        let app = chat_routes(client.clone(), uploads.clone(), None, None, None, Some(store.clone()), None)
+            .merge(conversations_routes(store.clone(), Arc::new(client), uploads, None, None));
+
+        let (_, body) = send(&app, "POST", "/api/conversations", USER, None).await;
+        let id = body["conversation"]["id"].as_str().unwrap().to_string();
+        let (_, question) = store
+            .update("anna", &id, |conversation| {
+                let question = conversation.append(None, Message::user("Frage"))?;
+                conversation.append(Some(&question), Message::assistant("Antwort"))?;
+                Ok(question)
+            })
+            .await
+            .unwrap();
+        let before = store.get("anna", &id).unwrap();
+
+        let chat = json!({ "conversation_id": id, "messages": [{ "role": "user", "content": "Nachfrage" }] });
+        let (_, body) = send(&app, "POST", "/api/chat", USER, Some(chat)).await;
+        assert!(body["error"].is_string());
+        let (_, body) = send(&app, "POST", "/api/chat/stream", USER, Some(json!({
+            "conversation_id": id,
+            "messages": [{ "role": "user", "content": "Nachfrage" }]
+        }))).await;
+        assert!(body["error"].is_string());
+        let content = json!([{ "type": "text", "text": "Und das?" }, { "type": "file", "file": { "file_id": "fehlt" } }]);
+        let chat = json!({ "conversation_id": id, "messages": [{ "role": "user", "content": content }] });
+        let (status, _) = send(&app, "POST", "/api/chat", USER, Some(chat)).await;
+        assert!(status.is_client_error());
+
+        let uri = format!("/api/conversations/{}/messages/{}", id, question);
+        let (status, _) = send(&app, "PUT", &uri, USER, Some(json!({ "content": "Bessere Frage" }))).await;
+        assert!(!status.is_success());
+        let (status, _) = send(&app, "POST", &format!("/api/conversations/{}/regenerate", id), USER, None).await;
+        assert!(!status.is_success());
+
+        // Keine verwaisten Benutzernachrichten, der aktive Zweig ist unverändert
+        let after = store.get("anna", &id).unwrap();
+        assert_eq!(after.nodes.len(), before.nodes.len());
+        assert_eq!(after.active_id, before.active_id);
+    }
+}
@@ -0,0 +1,235 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::{chat_routes, documents_routes, UploadStore};
+    use crate::client::{GlmClient, GlmConfig};
+    use crate::config::{DocumentsConfig, UploadsConfig};
+    use crate::documents::*;
+    use crate::functions::FunctionRegistry;
+    use async_trait::async_trait;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config() -> DocumentsConfig {
+        DocumentsConfig {
+            dir: std::env::temp_dir()
+                .join(format!("chatglm-documents-{}", uuid::Uuid::new_v4()))
+                .to_string_lossy()
+                .to_string(),
+            chunk_size: 200,
+            chunk_overlap: 40,
+            ..DocumentsConfig::default()
+        }
+    }
+
+    /// Einfache Embeddings: Anzahl von "rust" und "garten" im Text
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, inputs: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+            Ok(inputs
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    vec![text.matches("rust").count() as f32, text.matches("garten").count() as f32]
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_chunk_text_respects_size_and_headings() {
+        let text = "# Einleitung\nKurzer Absatz.\n# Details\n".to_string() + &"wort ".repeat(100);
+        let chunks = chunk_text(&text, 120, 20);
+
+        assert!(chunks.len() > 2);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 120));
+        assert!(chunks[0].starts_with("# Einleitung"));
+        assert!(chunks.iter().any(|chunk| chunk.starts_with("# Details")));
+        // Überlappung: der zweite Wort-Abschnitt beginnt mit dem Ende des ersten
+        assert!(chunks[2].starts_with("wort"));
+    }
+
+    #[test]
+    fn test_bm25_ranks_relevant_chunks() {
+        let mut index = Bm25Index::new();
+        index.insert(("a".to_string(), 0), "Rust ist eine Programmiersprache mit Ownership");
+        index.insert(("b".to_string(), 0), "Tomaten wachsen im Garten besonders gut");
+        index.insert(("c".to_string(), 0), "Ownership und Borrowing sind zentrale Konzepte in Rust, Rust, Rust");
+
+        let results = index.search("rust ownership", 10);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, ("c".to_string(), 0));
+
+        index.remove_document("c");
+        assert_eq!(index.search("rust", 10)[0].0, ("a".to_string(), 0));
+        assert!(index.search("unbekannt", 10).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_persists_searches_and_deletes() {
+        let config = config();
+        let store = DocumentStore::open(&config);
+        let summary = store.add("Handbuch", "text/markdown", "# Installation\nFühre cargo build aus.\n\n# Garten\nGießen nicht vergessen.").await.unwrap();
+        store.add("Rezepte", "text/plain", "Kuchen backen mit Mehl und Zucker.").await.unwrap();
+
+        let hits = store.search("cargo build", 3).await;
+        assert_eq!(hits[0].document_id, summary.id);
+        assert_eq!(hits[0].reference, format!("doc:{}#{}", summary.id, hits[0].chunk_index));
+
+        // Neu geöffnet werden die Dokumente aus den Dateien indexiert
+        let reopened = DocumentStore::open(&config);
+        assert_eq!(reopened.list().len(), 2);
+        assert_eq!(reopened.search("cargo", 1).await[0].title, "Handbuch");
+
+        assert!(reopened.delete(&summary.id).await.unwrap());
+        assert!(!reopened.delete(&summary.id).await.unwrap());
+        assert!(reopened.search("cargo", 1).await.is_empty());
+        assert_eq!(DocumentStore::open(&config).list().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_complement_bm25() {
+        let store = DocumentStore::open(&config()).with_embedder(Arc::new(KeywordEmbedder));
+        store.add("Sprache", "text/plain", "Rust Rust Rust").await.unwrap();
+        store.add("Hobby", "text/plain", "Garten und Blumen").await.unwrap();
+
+        // "garten" kommt im Suchtext nicht als eigenes Wort vor, die Embeddings finden es trotzdem
+        let hits = store.search("gartenarbeit", 2).await;
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].title, "Hobby");
+        assert!(store.get(&hits[0].document_id).unwrap().chunks[0].embedding.is_some());
+    }
+
+    #[test]
+    fn test_extract_text_detects_formats() {
+        let (text, content_type) = extract_text(b"# Titel", None, Some("notiz.md")).unwrap();
+        assert_eq!((text.as_str(), content_type), ("# Titel", "text/markdown"));
+
+        let (_, content_type) = extract_text(b"Hallo", Some("text/plain; charset=utf-8"), None).unwrap();
+        assert_eq!(content_type, "text/plain");
+
+        assert!(matches!(extract_text(&[0xFF, 0xFE, 0x00], None, Some("bild.bin")), Err(ExtractError::Unsupported)));
+        assert!(matches!(extract_text(b"%PDF-1.7 kaputt", None, None), Err(ExtractError::InvalidPdf(_))));
+    }
+
+    #[tokio::test]
+    async fn test_search_documents_tool() {
+        let store = Arc::new(DocumentStore::open(&config()));
+        store.add("FAQ", "text/plain", "Das Passwort wird unter Einstellungen geändert.").await.unwrap();
+
+        let registry = FunctionRegistry::new();
+        registry.register("search_documents", Arc::new(SearchDocuments::new(store)));
+
+        let mut arguments = HashMap::new();
+        arguments.insert("query".to_string(), json!("Passwort ändern"));
+        let result = registry.execute_function("search_documents", arguments).await;
+        assert!(result.success);
+        assert_eq!(result.result["count"], 1);
+        assert_eq!(result.result["results"][0]["title"], "FAQ");
+
+        let result = registry.execute_function("search_documents", HashMap::new()).await;
+        assert!(!result.success);
+    }
+
+    #[tokio::test]
+    async fn test_documents_endpoints() {
+        let app = documents_routes(Arc::new(DocumentStore::open(&config())));
+
+        let response = app.clone().oneshot(
+            Request::post("/api/documents")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "title": "Notiz", "content": "Meeting am Dienstag" }).to_string()))
+                .unwrap()
+        ).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let boundary = "grenze";
+        let body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"plan.md\"\r\nContent-Type: text/markdown\r\n\r\n# Plan\nReleases jeden Freitag\r\n--{b}--\r\n",
+            b = boundary
+        );
+        let response = app.clone().oneshot(
+            Request::post("/api/documents/upload")
+                .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+                .body(Body::from(body))
+                .unwrap()
+        ).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let broken = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"kaputt.pdf\"\r\nContent-Type: application/pdf\r\n\r\n%PDF-1.7 kaputt\r\n--{b}--\r\n",
+            b = boundary
+        );
+        let response = app.clone().oneshot(
+            Request::post("/api/documents/upload")
+                .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+                .body(Body::from(broken))
+                .unwrap()
+        ).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = app.oneshot(
+            Request::get("/api/documents/search?q=freitag").body(Body::empty()).unwrap()
+        ).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value["count"], 1);
+        assert_eq!(value["results"][0]["title"], "plan.md");
+    }
+
+    #[tokio::test]
+    async fn test_chat_injects_document_context() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "glm-4.5",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "Laut [doc] am Freitag." },
+                    "finish_reason": "stop"
+                }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let store = Arc::new(DocumentStore::open(&config()));
+        let document = store.add("Plan", "text/plain", "Releases finden jeden Freitag statt.").await.unwrap();
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: mock_server.uri(),
+            ..GlmConfig::default()
+        }).unwrap();
+        let app = chat_routes(client, Arc::new(UploadStore::new(&UploadsConfig::default())), Some(store), None, None, None, None);
+
+        let payload = json!({
+            "messages": [{ "role": "user", "content": "Wann sind Releases?" }],
+            "use_documents": true
+        });
+        let response = app.oneshot(
+            Request::post("/api/chat")
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap()
+        ).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["role"], "system");
+        assert!(messages[0]["content"].as_str().unwrap().contains(&format!("[doc:{}#0]", document.id)));
+        assert_eq!(messages[1]["content"], "Wann sind Releases?");
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::embeddings_routes;
+    use crate::client::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, Respond, ResponseTemplate};
+
+    /// Antwortet mit einem 3-dimensionalen Vektor je Eingabe: [Länge des Texts, Position, 1]
+    struct EchoEmbeddings;
+
+    impl Respond for EchoEmbeddings {
+        fn respond(&self, request: &wiremock::Request) -> ResponseTemplate {
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let inputs = body["input"].as_array().unwrap();
+            let data: Vec<Value> = inputs
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, text)| json!({
+                    "index": index,
+                    "object": "embedding",
+                    "embedding": [text.as_str().unwrap().len() as f32, index as f32, 1.0]
+                }))
+                .collect();
+            ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "model": body["model"],
+                "data": data,
+                "usage": { "prompt_tokens": inputs.len(), "total_tokens": inputs.len() }
+            }))
+        }
+    }
+
+    async fn client_with_batch_size(batch_size: usize) -> (MockServer, GlmClient) {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(EchoEmbeddings)
+            .mount(&mock_server)
+            .await;
+
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: mock_server.uri(),
+            embedding_batch_size: batch_size,
+            ..GlmConfig::default()
+        }).unwrap();
+        (mock_server, client)
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_are_batched_and_merged() {
+        let (mock_server, client) = client_with_batch_size(2).await;
+        let inputs: Vec<String> = ["a", "bb", "ccc", "dddd", "eeeee"].iter().map(|s| s.to_string()).collect();
+
+        let response = client.embeddings(inputs, None).await.unwrap();
+
+        assert_eq!(mock_server.received_requests().await.unwrap().len(), 3);
+        assert_eq!(response.model, "embedding-3");
+        assert_eq!(response.dimensions, 3);
+        assert_eq!(response.data.len(), 5);
+        for (index, data) in response.data.iter().enumerate() {
+            assert_eq!(data.index, index);
+            assert_eq!(data.embedding[0], (index + 1) as f32);
+        }
+        assert_eq!(response.usage.unwrap().total_tokens, 5);
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_error_mapping() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": { "message": "Invalid API key", "type": "authentication_error" }
+            })))
+            .mount(&mock_server)
+            .await;
+        let client = GlmClient::new(GlmConfig {
+            api_key: "falsch".into(),
+            api_url: mock_server.uri(),
+            ..GlmConfig::default()
+        }).unwrap();
+
+        let result = client.embeddings(vec!["Hallo".to_string()], Some("embedding-2")).await;
+        assert!(matches!(result, Err(GlmError::AuthenticationError)));
+        assert!(matches!(client.embeddings(Vec::new(), None).await, Err(GlmError::InvalidRequest { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_endpoint_passthrough() {
+        let (_mock_server, client) = client_with_batch_size(16).await;
+        let app = embeddings_routes(Arc::new(client));
+
+        let response = app.oneshot(
+            Request::post("/api/embeddings")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "input": "Hallo", "model": "embedding-2" }).to_string()))
+                .unwrap()
+        ).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value["model"], "embedding-2");
+        assert_eq!(value["dimensions"], 3);
+        assert_eq!(value["data"][0]["embedding"], json!([5.0, 0.0, 1.0]));
+    }
+}
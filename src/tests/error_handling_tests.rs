@@ -1,6 +1,5 @@
 #[cfg(test)]
 mod error_handling_tests {
-    use crate::client::error::GlmError;
 
     #[test]
     fn test_network_error() {
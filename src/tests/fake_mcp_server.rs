@@ -0,0 +1,82 @@
+//! Minimaler MCP-Server für Tests: bietet `echo` und `fail` an und
+//! paginiert `tools/list` in zwei Seiten.
+
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+pub async fn serve<R, W>(reader: R, mut writer: W)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = BufReader::new(reader).lines();
+    let mut initialized = false;
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(_) => continue,
+        };
+        let method = request["method"].as_str().unwrap_or_default();
+
+        // Benachrichtigungen haben keine ID
+        if request.get("id").is_none() {
+            if method == "notifications/initialized" {
+                initialized = true;
+            }
+            continue;
+        }
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": request["params"]["protocolVersion"],
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "fake-mcp", "version": "0.0.1" }
+            })),
+            "tools/list" if !initialized => Err("nicht initialisiert"),
+            "tools/list" => Ok(match request["params"]["cursor"].as_str() {
+                None => json!({
+                    "tools": [{
+                        "name": "echo",
+                        "description": "Gibt den Text zurück",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": { "text": { "type": "string" } },
+                            "required": ["text"]
+                        }
+                    }],
+                    "nextCursor": "seite-2"
+                }),
+                Some(_) => json!({
+                    "tools": [{ "name": "fail", "inputSchema": { "type": "object" } }]
+                }),
+            }),
+            "tools/call" => match request["params"]["name"].as_str() {
+                Some("echo") => Ok(json!({
+                    "content": [{ "type": "text", "text": request["params"]["arguments"]["text"] }]
+                })),
+                Some("fail") => Ok(json!({
+                    "content": [{ "type": "text", "text": "absichtlich fehlgeschlagen" }],
+                    "isError": true
+                })),
+                _ => Err("unbekanntes Tool"),
+            },
+            _ => Err("unbekannte Methode"),
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+            Err(message) => json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": -32601, "message": message }
+            }),
+        };
+
+        let mut line = response.to_string();
+        line.push('\n');
+        if writer.write_all(line.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
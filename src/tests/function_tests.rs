@@ -0,0 +1,556 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::types::{FunctionCall, ToolCall, ToolDefinition};
+    use crate::config::{ApprovalConfig, PluginsConfig, RunCommandConfig, ToolExecutionConfig};
+    use crate::functions::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    fn args(value: serde_json::Value) -> HashMap<String, serde_json::Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn run_command_config() -> RunCommandConfig {
+        RunCommandConfig {
+            enabled: true,
+            workspace_dir: env!("CARGO_MANIFEST_DIR").to_string(),
+            allowed_commands: vec!["echo".to_string(), "sh".to_string(), "sleep".to_string()],
+            timeout: 5,
+            max_output_bytes: 1024,
+            ..RunCommandConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_builtin_functions() {
+        let registry = FunctionRegistry::new();
+
+        for name in ["get_current_time", "get_weather", "calculate", "analyze_text", "generate_uuid"] {
+            assert!(registry.has_function(name), "{} fehlt", name);
+        }
+
+        let result = registry.execute_function("calculate", args(json!({"expression": "2 + 3"}))).await;
+        assert!(result.success);
+        assert_eq!(result.result["result"], 5.0);
+
+        let missing = registry.execute_function("unknown", HashMap::new()).await;
+        assert!(!missing.success);
+        assert!(missing.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_run_command_success() {
+        let handler = RunCommand::new(run_command_config()).unwrap();
+        let result = handler.execute(args(json!({"command": "echo", "args": ["hallo", "welt"]}))).await.unwrap();
+
+        assert_eq!(result["exit_code"], 0);
+        assert_eq!(result["success"], true);
+        assert_eq!(result["stdout"], "hallo welt\n");
+        assert_eq!(result["timed_out"], false);
+    }
+
+    #[tokio::test]
+    async fn test_run_command_rejects_unlisted_and_paths() {
+        let handler = RunCommand::new(run_command_config()).unwrap();
+
+        let unlisted = handler.execute(args(json!({"command": "rm", "args": ["-rf", "/"]}))).await;
+        assert!(unlisted.is_err());
+
+        let with_path = handler.execute(args(json!({"command": "/bin/echo"}))).await;
+        assert!(with_path.is_err());
+
+        let outside = handler.execute(args(json!({"command": "echo", "cwd": ".."}))).await;
+        assert!(outside.is_err());
+
+        // Ohne Konfiguration ist kein Befehl freigegeben
+        assert!(RunCommandConfig::default().allowed_commands.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_command_timeout() {
+        let config = RunCommandConfig { timeout: 1, ..run_command_config() };
+        let handler = RunCommand::new(config).unwrap();
+
+        let result = handler.execute(args(json!({"command": "sleep", "args": ["10"]}))).await.unwrap();
+        assert_eq!(result["timed_out"], true);
+        assert_eq!(result["success"], false);
+
+        // Auch Kindprozesse des Befehls werden beendet
+        let marker = std::env::temp_dir().join(format!("chatglm-run-{}", uuid::Uuid::new_v4()));
+        let script = format!("(sleep 2; touch {}) & sleep 10", marker.display());
+        let result = handler.execute(args(json!({"command": "sh", "args": ["-c", script]}))).await.unwrap();
+        assert_eq!(result["timed_out"], true);
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_run_command_truncates_output() {
+        let config = RunCommandConfig { max_output_bytes: 10, ..run_command_config() };
+        let handler = RunCommand::new(config).unwrap();
+
+        let result = handler.execute(args(json!({"command": "echo", "args": ["0123456789abcdef"]}))).await.unwrap();
+        assert_eq!(result["stdout"], "0123456789");
+        assert_eq!(result["stdout_truncated"], true);
+    }
+
+    #[tokio::test]
+    async fn test_run_command_scrubs_environment() {
+        std::env::set_var("CHATGLM_TEST_SECRET", "geheim");
+        let handler = RunCommand::new(run_command_config()).unwrap();
+
+        let result = handler
+            .execute(args(json!({"command": "sh", "args": ["-c", "echo ${CHATGLM_TEST_SECRET:-leer}"]})))
+            .await
+            .unwrap();
+        assert_eq!(result["stdout"], "leer\n");
+    }
+
+    #[tokio::test]
+    async fn test_run_command_streams_output() {
+        let handler = RunCommand::new(run_command_config()).unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let result = handler
+            .execute_streaming(args(json!({"command": "sh", "args": ["-c", "echo out; echo err >&2"]})), tx)
+            .await
+            .unwrap();
+        assert_eq!(result["exit_code"], 0);
+
+        let mut outputs = Vec::new();
+        while let Some(output) = rx.recv().await {
+            outputs.push(output);
+        }
+        assert!(outputs.iter().any(|o| o.stream == OutputStream::Stdout && o.data.contains("out")));
+        assert!(outputs.iter().any(|o| o.stream == OutputStream::Stderr && o.data.contains("err")));
+    }
+
+    #[tokio::test]
+    async fn test_run_command_streams_multibyte_characters_intact() {
+        let config = RunCommandConfig { max_output_bytes: 64 * 1024, ..run_command_config() };
+        let handler = RunCommand::new(config).unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        // 3 Bytes pro Zeichen, über mehrere Lesepuffer verteilt
+        let script = "i=0; while [ $i -lt 3000 ]; do printf '€'; i=$((i+1)); done";
+        handler.execute_streaming(args(json!({"command": "sh", "args": ["-c", script]})), tx).await.unwrap();
+
+        let mut streamed = String::new();
+        while let Some(output) = rx.recv().await {
+            streamed.push_str(&output.data);
+        }
+        assert_eq!(streamed, "€".repeat(3000));
+    }
+
+    fn approval_config(timeout: u64) -> ApprovalConfig {
+        ApprovalConfig {
+            threshold: RiskLevel::Medium,
+            timeout,
+            log_path: std::env::temp_dir()
+                .join(format!("chatglm-approvals-{}.jsonl", uuid::Uuid::new_v4()))
+                .display()
+                .to_string(),
+        }
+    }
+
+    fn registry_with_approvals(timeout: u64) -> (FunctionRegistry, Arc<ApprovalManager>) {
+        let approvals = Arc::new(ApprovalManager::new(&approval_config(timeout)));
+        let registry = FunctionRegistry::new().with_approvals(approvals.clone());
+        registry.register("run_command", Arc::new(RunCommand::new(run_command_config()).unwrap()));
+        (registry, approvals)
+    }
+
+    #[tokio::test]
+    async fn test_low_risk_tools_skip_approval() {
+        let (registry, approvals) = registry_with_approvals(5);
+
+        let result = registry.execute_function("generate_uuid", HashMap::new()).await;
+        assert!(result.success);
+        assert!(approvals.pending().is_empty());
+        assert!(approvals.decisions(10).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_risky_tool_waits_for_approval() {
+        let (registry, approvals) = registry_with_approvals(5);
+        let mut requests = approvals.subscribe();
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let execution = approval::with_owner("anna".to_string(), registry.execute_function_streaming(
+            "call-1",
+            "run_command",
+            args(json!({"command": "echo", "args": ["ok"]})),
+            tx,
+        ));
+        let approve = async {
+            let request = requests.recv().await.unwrap();
+            assert_eq!(request.call_id, "call-1");
+            assert_ne!(request.id, "call-1");
+            assert_eq!(request.risk_level, RiskLevel::High);
+            assert!(approvals.resolve(&request.id, true, None));
+        };
+        let (result, _) = tokio::join!(execution, approve);
+
+        assert!(result.success);
+        assert_eq!(result.result["stdout"], "ok\n");
+
+        let decisions = approvals.decisions(10).await;
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].outcome, ApprovalOutcome::Approved);
+        assert_eq!(decisions[0].request.tool, "run_command");
+    }
+
+    #[tokio::test]
+    async fn test_risky_tool_rejected() {
+        let (registry, approvals) = registry_with_approvals(5);
+        let mut requests = approvals.subscribe();
+
+        let execution = approval::with_owner("anna".to_string(), registry.execute_function("run_command", args(json!({"command": "echo"}))));
+        let reject = async {
+            let request = requests.recv().await.unwrap();
+            approvals.resolve(&request.id, false, Some("nicht jetzt".to_string()));
+        };
+        let (result, _) = tokio::join!(execution, reject);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("nicht jetzt"));
+        assert_eq!(approvals.decisions(10).await[0].outcome, ApprovalOutcome::Rejected);
+    }
+
+    #[tokio::test]
+    async fn test_approvals_belong_to_their_owner() {
+        let (registry, approvals) = registry_with_approvals(5);
+        let mut requests = approvals.subscribe();
+        let call = |owner: &str| {
+            let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+            let execution = registry.execute_function_streaming("call-1", "run_command", args(json!({"command": "echo"})), tx);
+            approval::with_owner(owner.to_string(), execution)
+        };
+        let decide = async {
+            let first = requests.recv().await.unwrap();
+            let second = requests.recv().await.unwrap();
+            // Gleiche Call-ID, aber getrennte Anfragen
+            assert_eq!(first.call_id, second.call_id);
+            assert_ne!(first.id, second.id);
+            assert_eq!(approvals.pending().len(), 2);
+
+            let anna = if first.owner.as_deref() == Some("anna") { first } else { second };
+            assert!(!approvals.resolve_as("ben", &anna.id, true, None));
+            assert!(approvals.resolve_as("anna", &anna.id, true, None));
+            approvals.reject_all("ben", "Verbindung getrennt");
+            assert!(approvals.pending().is_empty());
+        };
+        let (anna, ben, _) = tokio::join!(call("anna"), call("ben"), decide);
+
+        assert!(anna.success);
+        assert!(ben.error.unwrap().contains("Verbindung getrennt"));
+    }
+
+    #[tokio::test]
+    async fn test_approval_timeout() {
+        let (registry, approvals) = registry_with_approvals(0);
+
+        let execution = registry.execute_function("run_command", args(json!({"command": "echo"})));
+        let result = approval::with_owner("anna".to_string(), execution).await;
+
+        assert!(!result.success);
+        assert!(approvals.pending().is_empty());
+        assert_eq!(approvals.decisions(10).await[0].outcome, ApprovalOutcome::TimedOut);
+        assert!(!approvals.resolve("unbekannt", true, None));
+    }
+
+    #[tokio::test]
+    async fn test_risky_tool_without_owner_fails_fast() {
+        use crate::tests::send;
+        use axum::http::StatusCode;
+
+        let (registry, approvals) = registry_with_approvals(120);
+        let registry = Arc::new(registry);
+        let started = Instant::now();
+
+        // Ohne Verbindung, die bestätigen könnte, wird nicht bis zum Timeout gewartet
+        let result = registry.execute_function("run_command", args(json!({"command": "echo"}))).await;
+        assert!(result.error.unwrap().contains("WebSocket"));
+        assert!(approvals.pending().is_empty());
+        assert_eq!(approvals.decisions(10).await[0].outcome, ApprovalOutcome::Rejected);
+
+        let app = crate::api::functions_routes(registry);
+        let request = json!({"name": "run_command", "arguments": {"command": "echo"}});
+        let (status, body) = send(&app, "POST", "/api/functions/execute", &[], Some(request)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body["error"].as_str().unwrap().contains("/ws"));
+        let request = json!({"name": "generate_uuid", "arguments": {}});
+        let (status, body) = send(&app, "POST", "/api/functions/execute", &[], Some(request)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "success");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    /// Wartet `ms` Millisekunden und zählt abgeschlossene Aufrufe
+    struct SleepTool {
+        completed: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl FunctionHandler for SleepTool {
+        async fn execute(&self, arguments: HashMap<String, serde_json::Value>) -> anyhow::Result<serde_json::Value> {
+            let ms = arguments.get("ms").and_then(|v| v.as_u64()).unwrap_or(0);
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            self.completed.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({ "slept_ms": ms }))
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new_function("sleep".to_string(), "Wartet".to_string(), json!({"type": "object"}))
+        }
+    }
+
+    fn sleep_registry(execution: ToolExecutionConfig) -> (FunctionRegistry, Arc<AtomicUsize>) {
+        let completed = Arc::new(AtomicUsize::new(0));
+        let registry = FunctionRegistry::new().with_execution_limits(execution);
+        registry.register("sleep", Arc::new(SleepTool { completed: completed.clone() }));
+        (registry, completed)
+    }
+
+    fn tool_call(id: &str, name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_run_concurrently() {
+        let (registry, _) = sleep_registry(ToolExecutionConfig::default());
+        let calls = vec![
+            tool_call("a", "sleep", r#"{"ms": 200}"#),
+            tool_call("b", "sleep", r#"{"ms": 200}"#),
+            tool_call("c", "calculate", r#"{"expression": "6 * 7"}"#),
+        ];
+
+        let started = Instant::now();
+        let results = registry.execute_tool_calls(&calls).await;
+
+        assert!(started.elapsed() < Duration::from_millis(390));
+        assert_eq!(results.iter().map(|r| r.tool_call_id.as_str()).collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert!(results.iter().all(|r| r.error.is_none()));
+        assert!(results[0].duration_ms >= 200);
+        assert!(results[2].output.contains("42"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_respect_concurrency_cap() {
+        let (registry, _) = sleep_registry(ToolExecutionConfig { max_concurrency: 1, ..ToolExecutionConfig::default() });
+        let calls = vec![
+            tool_call("a", "sleep", r#"{"ms": 100}"#),
+            tool_call("b", "sleep", r#"{"ms": 100}"#),
+        ];
+
+        let started = Instant::now();
+        registry.execute_tool_calls(&calls).await;
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_timeout_and_errors() {
+        let mut timeouts = HashMap::new();
+        timeouts.insert("sleep".to_string(), 1);
+        let (registry, _) = sleep_registry(ToolExecutionConfig { timeouts, ..ToolExecutionConfig::default() });
+        let calls = vec![
+            tool_call("slow", "sleep", r#"{"ms": 5000}"#),
+            tool_call("invalid", "calculate", "{kein json"),
+            tool_call("missing", "unknown", "{}"),
+        ];
+
+        let results = registry.execute_tool_calls(&calls).await;
+
+        assert!(results[0].error.as_ref().unwrap().contains("Zeitüberschreitung"));
+        assert!(results[0].duration_ms >= 1000 && results[0].duration_ms < 5000);
+        assert!(results[1].error.as_ref().unwrap().contains("Ungültige Argumente"));
+        assert!(results[2].error.is_some());
+        assert!(results.iter().all(|r| r.output.contains("error")));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_tool_calls_are_cancelled() {
+        let (registry, completed) = sleep_registry(ToolExecutionConfig::default());
+        let calls = vec![
+            tool_call("fast", "sleep", r#"{"ms": 10}"#),
+            tool_call("slow", "sleep", r#"{"ms": 500}"#),
+        ];
+
+        let cancelled = tokio::time::timeout(Duration::from_millis(100), registry.execute_tool_calls(&calls)).await;
+        assert!(cancelled.is_err());
+
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+    }
+
+    fn plugin_dir() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("chatglm-plugins-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn plugin_loader(dir: &std::path::Path) -> (PluginLoader, Arc<FunctionRegistry>) {
+        plugin_loader_for(dir, FunctionRegistry::new())
+    }
+
+    fn plugin_loader_for(dir: &std::path::Path, registry: FunctionRegistry) -> (PluginLoader, Arc<FunctionRegistry>) {
+        let registry = Arc::new(registry);
+        let config = PluginsConfig {
+            enabled: true,
+            dir: dir.display().to_string(),
+            poll_interval: 1,
+            ..PluginsConfig::default()
+        };
+        (PluginLoader::new(&config, registry.clone()), registry)
+    }
+
+    #[tokio::test]
+    async fn test_executable_plugin() {
+        let dir = plugin_dir();
+        std::fs::write(dir.join("echo.toml"), r#"
+name = "echo_args"
+description = "Gibt die Eingabe zurück"
+kind = "executable"
+command = "cat"
+risk_level = "medium"
+timeout = 5
+"#).unwrap();
+
+        let (loader, registry) = plugin_loader(&dir);
+        assert_eq!(loader.reload(), ["echo_args"]);
+
+        let handler = registry.get("echo_args").unwrap();
+        assert_eq!(handler.risk_level(), RiskLevel::Medium);
+        assert_eq!(handler.timeout(), Some(Duration::from_secs(5)));
+
+        let result = registry.execute_function("echo_args", args(json!({"text": "hallo"}))).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.result["name"], "echo_args");
+        assert_eq!(result.result["arguments"]["text"], "hallo");
+    }
+
+    #[tokio::test]
+    async fn test_executable_plugin_is_isolated() {
+        std::env::set_var("CHATGLM_PLUGIN_SECRET", "geheim");
+        let dir = plugin_dir();
+        std::fs::write(dir.join("env.toml"), r#"
+name = "env_info"
+description = "Zeigt Umgebung und Verzeichnis"
+kind = "executable"
+command = "sh"
+args = ["-c", "cat >/dev/null; printf '{\"secret\": \"%s\", \"cwd\": \"%s\"}' \"${CHATGLM_PLUGIN_SECRET:-leer}\" \"$(pwd -P)\""]
+"#).unwrap();
+        // Schreibt viel auf stderr, bevor es stdin liest
+        std::fs::write(dir.join("chatty.toml"), r#"
+name = "chatty"
+description = "Viel Ausgabe"
+kind = "executable"
+command = "sh"
+args = ["-c", "head -c 200000 /dev/zero >&2; cat"]
+timeout = 5
+"#).unwrap();
+
+        let (loader, registry) = plugin_loader(&dir);
+        loader.reload();
+
+        let result = registry.execute_function("env_info", HashMap::new()).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.result["secret"], "leer");
+        assert_eq!(result.result["cwd"], dir.canonicalize().unwrap().display().to_string());
+
+        let text = "x".repeat(200_000);
+        let result = registry.execute_function("chatty", args(json!({"text": text}))).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.result["arguments"]["text"].as_str().unwrap().len(), 200_000);
+    }
+
+    #[tokio::test]
+    async fn test_executable_plugin_without_risk_level_requires_approval() {
+        let dir = plugin_dir();
+        std::fs::write(dir.join("touch.toml"), r#"
+name = "touch_marker"
+description = "Legt eine Datei an"
+kind = "executable"
+command = "sh"
+args = ["-c", "touch marker; echo '{}'"]
+"#).unwrap();
+
+        let (registry, approvals) = registry_with_approvals(0);
+        let (loader, registry) = plugin_loader_for(&dir, registry);
+        loader.reload();
+        assert_eq!(registry.get("touch_marker").unwrap().risk_level(), RiskLevel::High);
+
+        let result = approval::with_owner("anna".to_string(), registry.execute_function("touch_marker", HashMap::new())).await;
+        assert!(result.error.unwrap().contains("Timeout"));
+        assert_eq!(approvals.decisions(10).await[0].outcome, ApprovalOutcome::TimedOut);
+        assert!(!dir.join("marker").exists());
+    }
+
+    #[tokio::test]
+    async fn test_http_plugin() {
+        use wiremock::matchers::{body_partial_json, header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/tool"))
+            .and(header("x-token", "abc"))
+            .and(body_partial_json(json!({"name": "lookup", "arguments": {"id": 7}})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"found": true})))
+            .mount(&server)
+            .await;
+
+        let dir = plugin_dir();
+        std::fs::write(dir.join("lookup.json"), json!({
+            "name": "lookup",
+            "description": "Sucht einen Eintrag",
+            "kind": "http",
+            "url": format!("{}/tool", server.uri()),
+            "headers": {"x-token": "abc"},
+            "parameters": {"type": "object", "properties": {"id": {"type": "integer"}}}
+        }).to_string()).unwrap();
+
+        let (loader, registry) = plugin_loader(&dir);
+        loader.reload();
+
+        let definition = registry.get("lookup").unwrap().definition();
+        assert_eq!(definition.function.parameters["properties"]["id"]["type"], "integer");
+
+        let result = registry.execute_function("lookup", args(json!({"id": 7}))).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.result["found"], true);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_hot_reload() {
+        let dir = plugin_dir();
+        let (loader, registry) = plugin_loader(&dir);
+        assert!(!loader.reload_if_changed());
+
+        std::fs::write(dir.join("a.toml"), "name = \"plugin_a\"\ndescription = \"A\"\nkind = \"executable\"\ncommand = \"cat\"\n").unwrap();
+        std::fs::write(dir.join("broken.toml"), "name = ").unwrap();
+        std::fs::write(dir.join("builtin.toml"), "name = \"calculate\"\ndescription = \"X\"\nkind = \"executable\"\ncommand = \"cat\"\n").unwrap();
+
+        assert!(loader.reload_if_changed());
+        assert!(registry.has_function("plugin_a"));
+        assert!(registry.has_function("calculate"));
+        assert!(!loader.reload_if_changed());
+
+        std::fs::remove_file(dir.join("a.toml")).unwrap();
+        assert!(loader.reload_if_changed());
+        assert!(!registry.has_function("plugin_a"));
+        // Built-in Funktionen werden nie durch Plugins ersetzt oder entfernt
+        assert!(registry.get("calculate").unwrap().definition().function.description.contains("mathematische"));
+    }
+}
@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::{config_problems, health_routes, HealthChecker, GIT_HASH, VERSION};
+    use crate::client::*;
+    use crate::config::AppConfig;
+    use crate::functions::FunctionRegistry;
+    use crate::tests::send;
+    use axum::http::StatusCode;
+    use axum::Router;
+    use std::sync::Arc;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// `config.toml` mit Test-Key, Upstream `api_url` und Daten in einem Temp-Verzeichnis
+    fn config(api_url: &str) -> AppConfig {
+        let mut config: AppConfig = config::Config::builder()
+            .add_source(config::File::new("config.toml", config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("chatglm-health-{}", uuid::Uuid::new_v4()));
+        config.chatglm.api_key = "test-key".into();
+        config.chatglm.api_url = api_url.to_string();
+        config.uploads.dir = dir.join("uploads").display().to_string();
+        config.documents.dir = dir.join("documents").display().to_string();
+        config.usage.file = dir.join("usage").join("usage.jsonl").display().to_string();
+        config
+    }
+
+    fn app(config: AppConfig) -> Router {
+        let client = GlmClient::new(GlmConfig {
+            api_key: config.chatglm.api_key.clone(),
+            api_url: config.chatglm.api_url.clone(),
+            ..GlmConfig::default()
+        })
+        .unwrap();
+        let checker = HealthChecker::new(config, Arc::new(client), Arc::new(FunctionRegistry::new()));
+        health_routes(Arc::new(checker))
+    }
+
+    #[tokio::test]
+    async fn test_live_reports_build_info() {
+        let app = app(config("http://127.0.0.1:9"));
+        for uri in ["/api/health", "/api/health/live"] {
+            let (status, body) = send(&app, "GET", uri, &[], None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["status"], "ok");
+            assert_eq!(body["version"], VERSION);
+            assert_eq!(body["git_hash"], GIT_HASH);
+        }
+        assert!(!GIT_HASH.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ready_probes_upstream_once_per_ttl() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let app = app(config(&mock_server.uri()));
+        for _ in 0..2 {
+            let (status, body) = send(&app, "GET", "/api/health/ready", &[], None).await;
+            assert_eq!(status, StatusCode::OK, "{}", body);
+            assert_eq!(body["status"], "ok");
+            for check in ["config", "upstream", "storage", "tools"] {
+                assert_eq!(body["checks"][check]["status"], "ok", "{}", check);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ready_is_unavailable_when_degraded() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&mock_server)
+            .await;
+
+        let mut config = config(&mock_server.uri());
+        // Eine Datei an Stelle des Upload-Verzeichnisses
+        let blocker = std::env::temp_dir().join(format!("chatglm-health-file-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&blocker, b"x").unwrap();
+        config.uploads.dir = blocker.display().to_string();
+
+        let (status, body) = send(&app(config), "GET", "/api/health/ready", &[], None).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["checks"]["upstream"]["status"], "degraded");
+        assert_eq!(body["checks"]["storage"]["status"], "degraded");
+        assert!(body["checks"]["storage"]["message"].as_str().unwrap().contains("uploads"));
+        assert_eq!(body["checks"]["config"]["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_upstream_probe_is_skipped() {
+        let mut config = config("http://127.0.0.1:9");
+        config.health.upstream_probe = false;
+        let (status, body) = send(&app(config), "GET", "/api/health/ready", &[], None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["checks"]["upstream"]["status"], "skipped");
+    }
+
+    #[test]
+    fn test_config_problems() {
+        let mut config = config("https://api.z.ai/v1");
+        assert!(config_problems(&config).is_empty());
+
+        config.chatglm.api_key = "${CHATGLM_API_KEY}".into();
+        config.chatglm.api_url = "api.z.ai".to_string();
+        config.chatglm.top_p = 1.5;
+        let problems = config_problems(&config);
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems[0].contains("api_key"));
+    }
+}
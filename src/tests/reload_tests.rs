@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::{GlmClient, GlmModel};
+    use crate::config::{restart_required, AppConfig, ConfigHandle};
+    use crate::functions::{ApprovalManager, FunctionRegistry, RiskLevel};
+    use crate::usage::UsageStore;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const BASE: &str = r#"
+[chatglm]
+api_key = "test-key"
+model = "glm-4.5"
+temperature = 0.7
+
+[usage]
+currency = "USD"
+prices = [{ model = "default", input = 1.0, output = 2.0 }]
+"#;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn handle(content: &str) -> (Arc<ConfigHandle>, PathBuf) {
+        let path = std::env::temp_dir().join(format!("chatglm-reload-{}.toml", uuid::Uuid::new_v4()));
+        write(&path, content);
+        let loader_path = path.clone();
+        let loaded = AppConfig::load_file(&path).unwrap();
+        (Arc::new(ConfigHandle::new(loaded, move || AppConfig::load_file(&loader_path))), path)
+    }
+
+    #[test]
+    fn test_reload_applies_valid_changes() {
+        let (handle, path) = handle(BASE);
+        let config = handle.current();
+        let client = GlmClient::new(config.glm_config()).unwrap();
+        let clone = client.clone();
+        let usage = Arc::new(UsageStore::open(&crate::config::UsageConfig {
+            file: path.with_extension("jsonl").display().to_string(),
+            ..config.usage.clone()
+        }));
+        {
+            let usage = usage.clone();
+            handle.on_reload(move |config| {
+                client.update_config(config.glm_config()).unwrap();
+                usage.set_pricing(&config.usage);
+                Ok(())
+            });
+        }
+        assert!(!handle.reload_if_changed());
+
+        write(&path, &BASE
+            .replace("glm-4.5\"", "glm-4.5-turbo\"")
+            .replace("0.7", "0.2")
+            .replace("USD", "EUR"));
+        assert!(handle.reload_if_changed());
+
+        assert_eq!(handle.current().chatglm.model, "glm-4.5-turbo");
+        // Klone teilen die Einstellungen
+        assert_eq!(clone.config().model.to_string(), GlmModel::Glm45Turbo.to_string());
+        assert_eq!(clone.config().temperature, 0.2);
+        assert_eq!(usage.currency(), "EUR");
+        assert_eq!(usage.cost("glm-4.5-turbo", 1_000_000, 0), 1.0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_invalid_reload_keeps_previous_config() {
+        let (handle, path) = handle(BASE);
+        let calls = Arc::new(AtomicUsize::new(0));
+        {
+            let calls = calls.clone();
+            handle.on_reload(move |_| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            });
+        }
+
+        write(&path, &BASE.replace("glm-4.5\"", "glm-9\"").replace("0.7", "3.0"));
+        let problems = handle.reload().unwrap_err();
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(!handle.reload_if_changed());
+
+        write(&path, &BASE.replace("test-key", ""));
+        assert!(handle.reload().unwrap_err().iter().any(|problem| problem.contains("api_key")));
+
+        write(&path, "[chatglm\n");
+        assert!(handle.reload().is_err());
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(handle.current().chatglm.model, "glm-4.5");
+        assert_eq!(handle.current().chatglm.temperature, 0.7);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_failing_listener_keeps_previous_config() {
+        let (handle, path) = handle(BASE);
+        let applied = Arc::new(std::sync::Mutex::new(Vec::new()));
+        {
+            let applied = applied.clone();
+            handle.on_reload(move |config| {
+                applied.lock().unwrap().push(config.chatglm.temperature);
+                Ok(())
+            });
+        }
+        handle.on_reload(|config| match config.chatglm.temperature {
+            temperature if temperature < 0.5 => Err(anyhow::anyhow!("nicht übernehmbar")),
+            _ => Ok(()),
+        });
+
+        write(&path, &BASE.replace("0.7", "0.2"));
+        let problems = handle.reload().unwrap_err();
+        assert!(problems[0].contains("nicht übernehmbar"));
+        assert_eq!(handle.current().chatglm.temperature, 0.7);
+        // Der erste Listener erhält wieder die bisherige Konfiguration
+        assert_eq!(*applied.lock().unwrap(), [0.2, 0.7]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_tool_limits_are_updated_in_place() {
+        let config = AppConfig::default();
+        let approvals = Arc::new(ApprovalManager::new(&config.tools.approval));
+        let registry = FunctionRegistry::new()
+            .with_approvals(approvals.clone())
+            .with_execution_limits(config.tools.execution.clone());
+        let handler = registry.get("calculate").unwrap();
+        assert_eq!(registry.timeout_for("calculate", handler.as_ref()).as_secs(), 60);
+        assert!(approvals.requires_approval(RiskLevel::High));
+
+        let mut next = config.clone();
+        next.tools.execution.timeouts.insert("calculate".to_string(), 5);
+        next.tools.approval.threshold = RiskLevel::High;
+        registry.set_execution_limits(next.tools.execution.clone());
+        approvals.update(&next.tools.approval);
+
+        assert_eq!(registry.timeout_for("calculate", handler.as_ref()).as_secs(), 5);
+        assert!(!approvals.requires_approval(RiskLevel::High));
+    }
+
+    #[test]
+    fn test_restart_required_lists_static_settings() {
+        let previous = AppConfig::default();
+        let mut next = previous.clone();
+        next.chatglm.model = "glm-4.5-turbo".to_string();
+        next.cors.allowed_origins.push("https://chat.example.com".to_string());
+        assert!(restart_required(&previous, &next).is_empty());
+
+        next.server.port = 8080;
+        next.tools.plugins.enabled = true;
+        assert_eq!(restart_required(&previous, &next), vec!["server.port", "tools.plugins"]);
+    }
+}
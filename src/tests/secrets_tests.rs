@@ -0,0 +1,176 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::*;
+    use crate::config::AppConfig;
+    use crate::secrets::{read_secret_file, Secret};
+    use serde_json::json;
+    use std::path::PathBuf;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn temp_file(extension: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("chatglm-secrets-{}.{}", uuid::Uuid::new_v4(), extension));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn completion() -> serde_json::Value {
+        json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "glm-4.5",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "ok" },
+                "finish_reason": "stop"
+            }]
+        })
+    }
+
+    async fn mount(server: &MockServer, key: &str, response: ResponseTemplate) {
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("authorization", format!("Bearer {}", key).as_str()))
+            .respond_with(response)
+            .mount(server)
+            .await;
+    }
+
+    fn client(server: &MockServer, keys: &[&str]) -> GlmClient {
+        GlmClient::new(GlmConfig {
+            api_key: keys[0].into(),
+            api_keys: keys[1..].iter().map(|key| Secret::from(*key)).collect(),
+            api_url: server.uri(),
+            ..GlmConfig::default()
+        })
+        .unwrap()
+    }
+
+    /// Verwendete Schlüssel in der Reihenfolge der Anfragen
+    async fn used_keys(server: &MockServer) -> Vec<String> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter_map(|request| request.headers.iter().find(|(name, _)| name.as_str() == "authorization"))
+            .map(|(_, values)| values.last().as_str().replace("Bearer ", ""))
+            .collect()
+    }
+
+    #[test]
+    fn test_secret_is_masked_in_debug_display_and_json() {
+        let secret = Secret::from("sk-abcdefghijklmnop");
+        assert_eq!(secret.expose(), "sk-abcdefghijklmnop");
+        assert_eq!(secret.to_string(), "sk-a***");
+        assert_eq!(format!("{:?}", secret), "Secret(\"sk-a***\")");
+        assert_eq!(serde_json::to_value(&secret).unwrap(), json!("sk-a***"));
+        assert_eq!(Secret::default().to_string(), "");
+
+        let config = GlmConfig::default().with_api_keys(vec![secret.clone()]);
+        assert!(!format!("{:?}", config).contains("abcdefghijklmnop"));
+        let mut app = AppConfig::default();
+        app.chatglm.api_keys = vec![secret];
+        assert!(!format!("{:?}", app).contains("abcdefghijklmnop"));
+        assert_eq!(app.redacted()["chatglm"]["api_keys"], json!(["sk-a***"]));
+    }
+
+    #[test]
+    fn test_keys_are_read_from_file() {
+        let key_file = temp_file("txt", "# Produktion\nkey-from-file-1\n\n  key-from-file-2  \nkey-main-0000000\n");
+        assert_eq!(read_secret_file(&key_file).unwrap().len(), 3);
+
+        let config_file = temp_file(
+            "toml",
+            &format!("[chatglm]\napi_key = \"key-main-0000000\"\napi_key_file = \"{}\"\n", key_file.display()),
+        );
+        let loaded = AppConfig::load_file(&config_file).unwrap();
+        let keys: Vec<String> = loaded.config.chatglm.keys().iter().map(|key| key.expose().to_string()).collect();
+        assert_eq!(keys, vec!["key-main-0000000", "key-from-file-1", "key-from-file-2"]);
+        // Änderungen an der Schlüsseldatei lösen ein Neuladen aus
+        assert!(loaded.sources.contains(&key_file));
+        assert_eq!(loaded.config.glm_config().keys().len(), 3);
+
+        let missing = temp_file("toml", "[chatglm]\napi_key_file = \"/nicht/vorhanden/key\"\n");
+        let err = AppConfig::load_file(&missing).unwrap_err().to_string();
+        assert!(err.contains("chatglm.api_key_file"));
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_used_round_robin() {
+        let server = MockServer::start().await;
+        for key in ["key-a", "key-b", "key-c"] {
+            mount(&server, key, ResponseTemplate::new(200).set_body_json(completion())).await;
+        }
+        let client = client(&server, &["key-a", "key-b", "key-c", "key-a"]);
+
+        for _ in 0..6 {
+            client.chat_completions(vec![Message::user("Hallo")]).await.unwrap();
+        }
+        assert_eq!(used_keys(&server).await, ["key-a", "key-b", "key-c", "key-a", "key-b", "key-c"]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_key_is_disabled_until_reload() {
+        let server = MockServer::start().await;
+        mount(&server, "key-bad", ResponseTemplate::new(401).set_body_json(json!({
+            "error": { "message": "Invalid API key", "code": "invalid_api_key" }
+        }))).await;
+        mount(&server, "key-good", ResponseTemplate::new(200).set_body_json(completion())).await;
+        let client = client(&server, &["key-bad", "key-good"]);
+
+        for _ in 0..3 {
+            client.chat_completions(vec![Message::user("Hallo")]).await.unwrap();
+        }
+        assert_eq!(used_keys(&server).await, ["key-bad", "key-good", "key-good", "key-good"]);
+
+        let status = client.key_status();
+        assert!(!status[0].available);
+        assert_eq!(status[0].reason, Some("rejected"));
+        assert!(status[1].available);
+
+        // Eine neue Konfiguration gibt abgelehnte Schlüssel wieder frei
+        client.update_config(client.config()).unwrap();
+        assert!(client.key_status().iter().all(|status| status.available));
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_quota_pauses_key() {
+        let server = MockServer::start().await;
+        mount(&server, "key-empty", ResponseTemplate::new(429).set_body_json(json!({
+            "error": { "message": "余额不足", "code": "1113" }
+        }))).await;
+        mount(&server, "key-paid", ResponseTemplate::new(402).set_body_string("Payment Required")).await;
+        let client = client(&server, &["key-empty", "key-paid"]);
+
+        let err = client.chat_completions(vec![Message::user("Hallo")]).await.unwrap_err();
+        assert!(matches!(err, GlmError::QuotaExceeded { .. }));
+        assert_eq!(err.variant_name(), "quota_exceeded");
+        assert!(!err.is_retryable());
+        assert_eq!(used_keys(&server).await, ["key-empty", "key-paid"]);
+
+        // Ohne verfügbaren Schlüssel wird die API nicht mehr angefragt
+        let err = client.chat_completions(vec![Message::user("Hallo")]).await.unwrap_err();
+        assert!(matches!(err, GlmError::QuotaExceeded { .. }));
+        assert_eq!(used_keys(&server).await.len(), 2);
+        let status = client.key_status();
+        assert!(status.iter().all(|status| status.reason == Some("quota_exceeded")));
+        assert!(status[0].retry_in.unwrap() > 3500);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_do_not_disable_keys() {
+        let server = MockServer::start().await;
+        mount(&server, "key-a", ResponseTemplate::new(429).set_body_json(json!({
+            "error": { "message": "Rate limit exceeded", "type": "rate_limit_error" }
+        }))).await;
+        let client = client(&server, &["key-a"]);
+
+        for _ in 0..2 {
+            let err = client.chat_completions(vec![Message::user("Hallo")]).await.unwrap_err();
+            assert!(matches!(err, GlmError::RateLimitError { .. }));
+        }
+        assert!(client.key_status()[0].available);
+    }
+}
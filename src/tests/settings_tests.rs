@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::{chat_routes, settings_routes, ChatSettings, SettingsStore, UploadStore, SETTINGS_VERSION};
+    use crate::client::*;
+    use crate::config::{SettingsConfig, UploadsConfig};
+    use crate::tests::send;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use serde_json::{json, Value};
+    use sha2::{Digest, Sha256};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn settings_config() -> SettingsConfig {
+        let dir = std::env::temp_dir().join(format!("chatglm-settings-{}", uuid::Uuid::new_v4()));
+        SettingsConfig { dir: dir.to_string_lossy().to_string() }
+    }
+
+    /// Datei, in der die Einstellungen des Benutzers liegen
+    fn settings_file(config: &SettingsConfig, user: &str) -> PathBuf {
+        let digest = Sha256::digest(user.as_bytes());
+        let name: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        PathBuf::from(&config.dir).join(format!("{}.json", name))
+    }
+
+    #[tokio::test]
+    async fn test_settings_are_stored_per_user_and_survive_restart() {
+        let config = settings_config();
+        let app = settings_routes(Arc::new(SettingsStore::new(&config)));
+
+        let settings = ChatSettings { temperature: 0.2, model: "glm-4.5-turbo".to_string(), ..ChatSettings::default() };
+        let (status, body) = send(&app, "PUT", "/api/settings", &[("x-user-id", "anna")], Some(json!(settings))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["version"], SETTINGS_VERSION);
+
+        let (_, body) = send(&app, "GET", "/api/settings", &[("x-user-id", "ben")], None).await;
+        assert_eq!(body["settings"], json!(ChatSettings::default()));
+        assert_eq!(body["stored"], false);
+
+        // Neue Instanz auf demselben Verzeichnis, wie nach einem Neustart
+        let app = settings_routes(Arc::new(SettingsStore::new(&config)));
+        let (_, body) = send(&app, "GET", "/api/settings", &[("x-user-id", "anna")], None).await;
+        assert_eq!(body["settings"], json!(settings));
+
+        let stored: Value = serde_json::from_slice(&std::fs::read(settings_file(&config, "anna")).unwrap()).unwrap();
+        assert_eq!(stored["version"], SETTINGS_VERSION);
+        assert_eq!(stored["user"], "anna");
+
+        let reset = Request::post("/api/settings/reset").header("x-user-id", "anna").body(Body::empty()).unwrap();
+        assert_eq!(app.clone().oneshot(reset).await.unwrap().status(), StatusCode::OK);
+        assert!(!settings_file(&config, "anna").exists());
+        let (_, body) = send(&app, "GET", "/api/settings", &[("x-user-id", "anna")], None).await;
+        assert_eq!(body["settings"], json!(ChatSettings::default()));
+    }
+
+    #[tokio::test]
+    async fn test_patch_updates_single_fields_and_validates() {
+        let config = settings_config();
+        let store = Arc::new(SettingsStore::new(&config));
+        let app = settings_routes(store.clone());
+
+        let (status, body) = send(&app, "PATCH", "/api/settings", &[], Some(json!({ "temperature": 0.3, "max_tokens": 1024 }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["settings"]["max_tokens"], 1024);
+        assert_eq!(body["settings"]["model"], "glm-4.5");
+
+        for (patch, message) in [
+            (json!({ "temperature": 1.5 }), "Temperature"),
+            (json!({ "model": "gpt-4" }), "Ungültiges Modell"),
+            (json!({ "colour": "blau" }), "Unbekannte Einstellung 'colour'"),
+            (json!({ "max_tokens": "viele" }), "Ungültige Einstellungen"),
+            (json!([1, 2]), "JSON-Objekt"),
+        ] {
+            let (status, body) = send(&app, "PATCH", "/api/settings", &[], Some(patch)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(body["error"].as_str().unwrap().contains(message), "{}", body["error"]);
+        }
+
+        let (status, _) = send(&app, "PUT", "/api/settings", &[], Some(json!({ "top_p": -1.0 }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Abgelehnte Änderungen lassen die gespeicherten Einstellungen unverändert
+        let settings = store.get("anonymous").await.unwrap();
+        assert_eq!(settings.max_tokens, 1024);
+        assert!((settings.temperature - 0.3).abs() < f32::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_stored_settings_are_migrated() {
+        let config = settings_config();
+        std::fs::create_dir_all(&config.dir).unwrap();
+
+        // Ohne `version`: nur die Einstellungen, fehlende Felder mit Standardwerten
+        std::fs::write(settings_file(&config, "alt"), json!({ "temperature": 0.1, "model": "glm-4.5-32k" }).to_string()).unwrap();
+        std::fs::write(
+            settings_file(&config, "zukunft"),
+            json!({ "version": SETTINGS_VERSION + 1, "settings": { "temperature": 0.1 } }).to_string(),
+        ).unwrap();
+        std::fs::write(settings_file(&config, "kaputt"), json!({ "temperature": 7.0 }).to_string()).unwrap();
+
+        let store = SettingsStore::new(&config);
+        let legacy = store.get("alt").await.unwrap();
+        assert_eq!(legacy.model, "glm-4.5-32k");
+        assert_eq!(legacy.max_tokens, ChatSettings::default().max_tokens);
+        assert_eq!(store.get("zukunft").await, None);
+        assert_eq!(store.get("kaputt").await, None);
+        assert_eq!(store.get("neu").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_settings_are_defaults_for_chat_requests() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5-turbo",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hallo" }, "finish_reason": "stop" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let store = Arc::new(SettingsStore::new(&settings_config()));
+        store
+            .update("anna", |settings| Ok(ChatSettings { model: "glm-4.5-turbo".to_string(), temperature: 0.1, max_tokens: 512, ..settings }))
+            .await
+            .unwrap();
+
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: mock_server.uri(),
+            max_tokens: 2048,
+            ..GlmConfig::default()
+        }).unwrap();
+        let app = chat_routes(client, Arc::new(UploadStore::new(&UploadsConfig::default())), None, Some(store), None, None, None);
+
+        for (user, body) in [
+            ("anna", json!({ "messages": [{ "role": "user", "content": "Hi" }] })),
+            ("anna", json!({ "messages": [{ "role": "user", "content": "Hi" }], "temperature": 0.9 })),
+            ("ben", json!({ "messages": [{ "role": "user", "content": "Hi" }] })),
+        ] {
+            let request = Request::post("/api/chat")
+                .header("content-type", "application/json")
+                .header("x-user-id", user)
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+        }
+
+        let requests: Vec<Value> = mock_server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .collect();
+        assert_eq!(requests[0]["model"], "glm-4.5-turbo");
+        assert_eq!(requests[0]["max_tokens"], 512);
+        assert!((requests[0]["temperature"].as_f64().unwrap() - 0.1).abs() < 1e-6);
+        // Werte aus der Anfrage haben Vorrang
+        assert!((requests[1]["temperature"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+        assert_eq!(requests[1]["model"], "glm-4.5-turbo");
+        // Ohne gespeicherte Einstellungen gilt die Serverkonfiguration
+        assert_eq!(requests[2]["max_tokens"], 2048);
+    }
+}
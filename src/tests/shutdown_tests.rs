@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::*;
+    use crate::shutdown::{Phase, Shutdown, ShutdownLayer, StreamContext, SHUTDOWN_FINISH_REASON};
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::{routing::get, Router};
+    use futures::{stream, StreamExt};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn chunk(content: &str, finish_reason: Option<&str>) -> StreamingChatCompletionResponse {
+        StreamingChatCompletionResponse {
+            id: "1".to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created: 1,
+            model: "glm-4.5".to_string(),
+            usage: None,
+            choices: vec![StreamChoice {
+                index: 0,
+                delta: Delta { content: Some(content.to_string()), ..Delta::default() },
+                finish_reason: finish_reason.map(str::to_string),
+            }],
+        }
+    }
+
+    fn partial_file() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("chatglm-partials-{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_requests_rejected_while_draining() {
+        let shutdown = Arc::new(Shutdown::new(Duration::from_secs(7)));
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(ShutdownLayer::new(shutdown.clone()));
+
+        let response = app.clone().oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(shutdown.in_flight(), 0);
+
+        let drain = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.drain().await }
+        });
+        drain.await.unwrap();
+        assert_eq!(shutdown.phase(), Phase::Closing);
+
+        let response = app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+        assert_eq!(response.headers()[header::CONNECTION], "close");
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_in_flight_requests() {
+        let shutdown = Arc::new(Shutdown::new(Duration::from_secs(30)));
+        let request = shutdown.track();
+
+        let drain = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.drain().await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(shutdown.phase(), Phase::Draining);
+        assert!(!drain.is_finished());
+
+        drop(request);
+        tokio::time::timeout(Duration::from_secs(1), drain).await.unwrap().unwrap();
+        assert_eq!(shutdown.phase(), Phase::Closing);
+    }
+
+    #[tokio::test]
+    async fn test_open_stream_is_cut_off_and_persisted() {
+        let file = partial_file();
+        let shutdown = Arc::new(Shutdown::new(Duration::from_millis(50)).with_partial_store(&file));
+
+        // Ein Chunk, danach keine weiteren Daten vom Upstream
+        let upstream = stream::iter(vec![Ok(chunk("Hallo", None))]).chain(stream::pending());
+        let guarded = shutdown.guard_stream(StreamingResponse::new(upstream), StreamContext {
+            user: Some("alice".to_string()),
+            conversation_id: Some("conv-1".to_string()),
+        });
+        assert_eq!(shutdown.in_flight(), 1);
+
+        let drain = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.drain().await }
+        });
+        let chunks: Vec<_> = tokio::time::timeout(Duration::from_secs(1), guarded.collect::<Vec<_>>())
+            .await
+            .unwrap();
+        drain.await.unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        let last = chunks.last().unwrap().as_ref().unwrap();
+        assert_eq!(last.choices[0].finish_reason.as_deref(), Some(SHUTDOWN_FINISH_REASON));
+        assert_eq!(last.model, "glm-4.5");
+        assert_eq!(shutdown.in_flight(), 0);
+
+        let partials = shutdown.partial_messages();
+        assert_eq!(partials.len(), 1);
+        assert_eq!(partials[0].user.as_deref(), Some("alice"));
+        assert_eq!(partials[0].conversation_id.as_deref(), Some("conv-1"));
+        assert_eq!(partials[0].message.content.as_ref().unwrap().text(), "Hallo");
+        let _ = std::fs::remove_file(file);
+    }
+
+    #[tokio::test]
+    async fn test_finished_stream_is_not_persisted() {
+        let file = partial_file();
+        let shutdown = Arc::new(Shutdown::new(Duration::from_secs(1)).with_partial_store(&file));
+        let upstream = stream::iter(vec![Ok(chunk("Hallo", None)), Ok(chunk(" Welt", Some("stop")))]);
+        let guarded = shutdown.guard_stream(StreamingResponse::new(upstream), StreamContext::default());
+
+        assert_eq!(guarded.collect_content().await.unwrap(), "Hallo Welt");
+        shutdown.drain().await;
+        assert!(shutdown.partial_messages().is_empty());
+    }
+}
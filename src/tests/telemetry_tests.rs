@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::*;
+    use crate::config::TelemetryConfig;
+    use crate::functions::FunctionRegistry;
+    use crate::logging::RequestIdLayer;
+    use crate::telemetry::{trace_headers, Telemetry};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+    fn header(request: &wiremock::Request, name: &str) -> Option<String> {
+        request
+            .headers
+            .iter()
+            .find(|(key, _)| key.as_str() == name)
+            .map(|(_, values)| values.last().as_str().to_string())
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    /// Collector-Attrappe und GLM-API; der Collector nimmt OTLP/HTTP entgegen
+    async fn servers() -> (MockServer, MockServer) {
+        let collector = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/traces"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&collector)
+            .await;
+
+        let glm = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hallo" }, "finish_reason": "stop" }],
+                "usage": { "prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15 }
+            })))
+            .mount(&glm)
+            .await;
+        (collector, glm)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_traceparent_is_continued_and_exported() {
+        let (collector, glm) = servers().await;
+        let telemetry = Telemetry::init(&TelemetryConfig {
+            enabled: true,
+            endpoint: collector.uri(),
+            ..TelemetryConfig::default()
+        })
+        .unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(telemetry.tracer()));
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let client = Arc::new(
+            GlmClient::new(GlmConfig {
+                api_key: "test-key".into(),
+                api_url: glm.uri(),
+                ..GlmConfig::default()
+            })
+            .unwrap(),
+        );
+        let app = Router::new()
+            .route("/chat", get(move || async move {
+                client.chat_completions(vec![Message::user("Hi")]).await.unwrap();
+                "ok"
+            }))
+            .layer(RequestIdLayer::new());
+
+        let request = Request::get("/chat")
+            .header("traceparent", format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Der Upstream-Aufruf gehört zum Trace des Clients, mit eigenem Span als Eltern
+        let upstream = glm.received_requests().await.unwrap();
+        let traceparent = header(&upstream[0], "traceparent").expect("traceparent an die GLM-API");
+        assert!(traceparent.starts_with(&format!("00-{}-", TRACE_ID)));
+        assert!(!traceparent.contains(PARENT_SPAN_ID));
+
+        telemetry.flush();
+        let exported = collector.received_requests().await.unwrap();
+        assert!(!exported.is_empty());
+        let body: Vec<u8> = exported.iter().flat_map(|request| request.body.clone()).collect();
+        let trace_id: Vec<u8> = (0..TRACE_ID.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&TRACE_ID[i..i + 2], 16).unwrap())
+            .collect();
+        assert!(contains(&body, &trace_id));
+        for attribute in ["http_request", "glm_request", "model", "glm-4.5", "prompt_tokens", "completion_tokens", "chatglm-web"] {
+            assert!(contains(&body, attribute.as_bytes()), "{} fehlt im Export", attribute);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_tool_spans_are_exported() {
+        let (collector, _glm) = servers().await;
+        let telemetry = Telemetry::init(&TelemetryConfig {
+            enabled: true,
+            endpoint: collector.uri(),
+            ..TelemetryConfig::default()
+        })
+        .unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(telemetry.tracer()));
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let registry = FunctionRegistry::new();
+        assert!(registry.execute_function("generate_uuid", HashMap::new()).await.success);
+
+        telemetry.flush();
+        let body: Vec<u8> = collector
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .flat_map(|request| request.body.clone())
+            .collect();
+        assert!(contains(&body, b"generate_uuid"));
+        assert!(contains(&body, b"success"));
+    }
+
+    #[test]
+    fn test_no_trace_headers_without_export() {
+        assert!(trace_headers().is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::templates_routes;
+    use crate::client::*;
+    use crate::config::TemplatesConfig;
+    use crate::templates::{TemplateError, TemplateInput, TemplateMessage, TemplateStore, TemplateVariable, VariableType};
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::Router;
+    use serde_json::{json, Map, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn templates_config() -> TemplatesConfig {
+        let dir = std::env::temp_dir().join(format!("chatglm-templates-{}", uuid::Uuid::new_v4()));
+        TemplatesConfig { enabled: true, dir: dir.to_string_lossy().to_string() }
+    }
+
+    fn variable(name: &str, kind: VariableType) -> TemplateVariable {
+        TemplateVariable {
+            name: name.to_string(),
+            kind,
+            description: String::new(),
+            required: true,
+            default: None,
+            options: Vec::new(),
+        }
+    }
+
+    /// Übersetzung mit Pflichttext, Zielsprache als Auswahl und optionalem Stil
+    fn translation() -> TemplateInput {
+        TemplateInput {
+            id: Some("translate".to_string()),
+            name: "Übersetzung".to_string(),
+            variables: vec![
+                variable("text", VariableType::String),
+                TemplateVariable {
+                    options: vec!["Deutsch".to_string(), "Englisch".to_string()],
+                    default: Some(json!("Englisch")),
+                    ..variable("language", VariableType::Enum)
+                },
+                TemplateVariable { required: false, ..variable("formal", VariableType::Boolean) },
+                TemplateVariable { required: false, ..variable("max_words", VariableType::Integer) },
+            ],
+            messages: vec![
+                TemplateMessage { role: Role::System, content: "Du übersetzt für {{user_name}} nach {{language}}.".to_string() },
+                TemplateMessage { role: Role::User, content: "Formell: {{formal}}, höchstens {{max_words}} Wörter.\n\n{{ text }}".to_string() },
+            ],
+            model: Some("glm-4.5-turbo".to_string()),
+            temperature: Some(0.2),
+            ..TemplateInput::default()
+        }
+    }
+
+    fn values(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    fn builtins() -> HashMap<String, String> {
+        HashMap::from([("user_name".to_string(), "Anna".to_string())])
+    }
+
+    async fn app(server: &MockServer) -> Router {
+        let store = TemplateStore::open(&templates_config());
+        store.create(translation()).await.unwrap();
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: server.uri(),
+            ..GlmConfig::default()
+        }).unwrap();
+        templates_routes(Arc::new(store), Arc::new(client), None, None)
+    }
+
+    async fn post(app: &Router, uri: &str, body: Value) -> (StatusCode, String) {
+        let request = Request::post(uri)
+            .header("content-type", "application/json")
+            .header("x-user-name", "Anna")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn test_template_renders_typed_variables() {
+        let template = crate::templates::Template {
+            id: "translate".to_string(),
+            name: "Übersetzung".to_string(),
+            description: String::new(),
+            variables: translation().variables,
+            messages: translation().messages,
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+
+        let messages = template.render(&values(json!({ "text": "Hallo", "formal": true, "max_words": 20 })), builtins()).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(messages[0].role, Role::System));
+        assert_eq!(messages[0].text(), Some("Du übersetzt für Anna nach Englisch."));
+        assert_eq!(messages[1].text(), Some("Formell: true, höchstens 20 Wörter.\n\nHallo"));
+
+        // Optionale Variablen ohne Wert werden leer eingesetzt
+        let messages = template.render(&values(json!({ "text": "Hallo", "language": "Deutsch" })), builtins()).unwrap();
+        assert_eq!(messages[1].text(), Some("Formell: , höchstens  Wörter.\n\nHallo"));
+
+        let err = template.render(&Map::new(), builtins()).unwrap_err();
+        assert!(matches!(&err, TemplateError::MissingVariables(names) if names == &["text"]));
+
+        for (invalid, name) in [
+            (json!({ "text": 5 }), "text"),
+            (json!({ "text": "x", "language": "Französisch" }), "language"),
+            (json!({ "text": "x", "max_words": 2.5 }), "max_words"),
+            (json!({ "text": "x", "formal": "ja" }), "formal"),
+            (json!({ "text": "x", "tone": "locker" }), "tone"),
+        ] {
+            let err = template.render(&values(invalid), builtins()).unwrap_err();
+            assert!(matches!(&err, TemplateError::InvalidVariable(variable, _) if variable == name), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_template_definitions_are_validated() {
+        assert!(translation().validate().is_ok());
+
+        let mut undeclared = translation();
+        undeclared.messages[1].content.push_str(" {{tone}}");
+        let err = undeclared.validate().unwrap_err().to_string();
+        assert!(err.contains("'tone'"), "{}", err);
+
+        let mut enum_without_options = translation();
+        enum_without_options.variables[1].options.clear();
+        assert!(enum_without_options.validate().is_err());
+
+        let mut bad_default = translation();
+        bad_default.variables[3].default = Some(json!("viele"));
+        assert!(bad_default.validate().is_err());
+
+        let mut duplicate = translation();
+        duplicate.variables.push(variable("text", VariableType::String));
+        assert!(duplicate.validate().is_err());
+
+        let mut no_messages = translation();
+        no_messages.messages.clear();
+        assert!(no_messages.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_templates_crud_and_render_endpoint() {
+        let server = MockServer::start().await;
+        let app = app(&server).await;
+
+        let (status, _) = post(&app, "/api/templates", json!(translation())).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+
+        let (status, body) = post(&app, "/api/templates/translate/render", json!({ "variables": { "text": "Guten Morgen" } })).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["messages"][0]["content"], "Du übersetzt für Anna nach Englisch.");
+
+        let (status, body) = post(&app, "/api/templates/translate/render", json!({})).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["missing"], json!(["text"]));
+
+        let (status, _) = post(&app, "/api/templates/fehlt/render", json!({})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let delete = Request::delete("/api/templates/translate").body(Body::empty()).unwrap();
+        assert_eq!(app.clone().oneshot(delete).await.unwrap().status(), StatusCode::OK);
+        let list = Request::get("/api/templates").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(list).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&bytes).unwrap()["count"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_streams_completion_with_template_options() {
+        let server = MockServer::start().await;
+        let body = [
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5-turbo","choices":[{"index":0,"delta":{"content":"Good "},"finish_reason":null}]}"#,
+            "",
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5-turbo","choices":[{"index":0,"delta":{"content":"morning"},"finish_reason":"stop"}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+        ].join("\n");
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let app = app(&server).await;
+
+        let (status, events) = post(&app, "/api/templates/translate/run", json!({ "variables": { "text": "Guten Morgen" }, "temperature": 0.5 })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(events.contains("event: content"), "{}", events);
+        assert!(events.contains("morning"));
+        assert!(events.contains("event: done"));
+
+        // Ungültige Variablen erreichen die API nicht
+        let (status, _) = post(&app, "/api/templates/translate/run", json!({ "variables": { "language": "Deutsch" } })).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let request: Value = serde_json::from_slice(&server.received_requests().await.unwrap()[0].body).unwrap();
+        assert_eq!(request["model"], "glm-4.5-turbo");
+        assert!((request["temperature"].as_f64().unwrap() - 0.5).abs() < 1e-6);
+        assert_eq!(request["messages"][1]["content"], "Formell: , höchstens  Wörter.\n\nGuten Morgen");
+    }
+}
@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use crate::client::*;
+    use futures::{stream, StreamExt};
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn chunk(content: Option<&str>, reasoning: Option<&str>, finish_reason: Option<&str>) -> StreamingChatCompletionResponse {
+        StreamingChatCompletionResponse {
+            id: "test".to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created: 1677652288,
+            model: "glm-4.5".to_string(),
+            usage: None,
+            choices: vec![StreamChoice {
+                index: 0,
+                delta: Delta {
+                    role: None,
+                    content: content.map(str::to_string),
+                    reasoning_content: reasoning.map(str::to_string),
+                },
+                finish_reason: finish_reason.map(str::to_string),
+            }],
+        }
+    }
+
+    fn client(api_url: &str) -> GlmClient {
+        GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: api_url.to_string(),
+            thinking_budget: Some(512),
+            ..GlmConfig::default()
+        }).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_events_separate_reasoning_content() {
+        let stream = stream::iter(vec![
+            Ok(chunk(None, Some("Schritt 1. "), None)),
+            Ok(chunk(None, Some("Schritt 2."), None)),
+            Ok(chunk(Some("Ergebnis"), None, None)),
+            Ok(chunk(None, None, Some("stop"))),
+        ]);
+
+        let events: Vec<StreamEvent> = StreamingResponse::new(stream)
+            .events()
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+
+        assert!(matches!(&events[0], StreamEvent::Thinking { content } if content == "Schritt 1. "));
+        assert!(matches!(&events[2], StreamEvent::Content { content } if content == "Ergebnis"));
+        match events.last().unwrap() {
+            StreamEvent::Done { message, finish_reason } => {
+                assert_eq!(message.thinking.as_deref(), Some("Schritt 1. Schritt 2."));
+                assert_eq!(message.text(), Some("Ergebnis"));
+                assert_eq!(finish_reason.as_deref(), Some("stop"));
+            }
+            other => panic!("Unerwartetes Ereignis: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_request_thinking_budget_only_when_enabled() {
+        let request = ChatCompletionRequest::new("glm-4.5".to_string(), vec![])
+            .with_thinking(true)
+            .with_thinking_budget(Some(1024));
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(value["thinking"], json!({ "type": "enabled", "budget_tokens": 1024 }));
+
+        let request = ChatCompletionRequest::new("glm-4.5".to_string(), vec![])
+            .with_thinking(false)
+            .with_thinking_budget(Some(1024));
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(value["thinking"], json!({ "type": "disabled" }));
+    }
+
+    #[tokio::test]
+    async fn test_full_response_reasoning_is_parsed() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "thinking": { "type": "enabled", "budget_tokens": 512 } })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1677652288,
+                "model": "glm-4.5",
+                "choices": [
+                    {
+                        "index": 0,
+                        "message": { "role": "assistant", "content": "Antwort", "reasoning_content": "Überlegung" },
+                        "finish_reason": "stop"
+                    },
+                    {
+                        "index": 1,
+                        "message": { "role": "assistant", "content": "Zweite" },
+                        "finish_reason": "stop"
+                    }
+                ]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let response = client(&mock_server.uri()).chat_completions(vec![Message::user("Hallo")]).await.unwrap();
+        assert_eq!(response.choices[0].message.thinking.as_deref(), Some("Überlegung"));
+        assert_eq!(response.choices[0].message.text(), Some("Antwort"));
+        assert!(response.choices[1].message.thinking.is_none());
+        assert_eq!(response.choices[1].message.text(), Some("Zweite"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_request_toggle_and_sse_parsing() {
+        let mock_server = MockServer::start().await;
+        let body = [
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"reasoning_content":"Denke"},"finish_reason":null}]}"#,
+            "",
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"content":"Fertig"},"finish_reason":"stop"}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+        ].join("\n");
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true, "thinking": { "type": "disabled" } })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&mock_server)
+            .await;
+
+        let options = ChatOptions { thinking: Some(false), ..ChatOptions::default() };
+        let stream = client(&mock_server.uri())
+            .chat_completions_stream_with(vec![Message::user("Hallo")], &options)
+            .await
+            .unwrap();
+        let message = stream.collect_message().await.unwrap();
+        assert_eq!(message.thinking.as_deref(), Some("Denke"));
+        assert_eq!(message.text(), Some("Fertig"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_sse_stream_splits_lines_across_packets() {
+        let line = r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"content":"Grüße"},"finish_reason":null}]}"#;
+        let bytes = format!("{}\n\n", line).into_bytes();
+        // Mitten im Umlaut trennen
+        let split = bytes.iter().position(|&b| b == 0xC3).unwrap() + 1;
+        let packets: Vec<Result<Vec<u8>, GlmError>> = vec![Ok(bytes[..split].to_vec()), Ok(bytes[split..].to_vec())];
+
+        let chunks: Vec<_> = streaming::parse_sse_stream(stream::iter(packets)).collect().await;
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].as_ref().unwrap().choices[0].delta.content.as_deref(), Some("Grüße"));
+    }
+}
@@ -0,0 +1,193 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::{chat_routes, sniff_image_type, uploads_routes, UploadError, UploadStore};
+    use crate::client::*;
+    use crate::config::UploadsConfig;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use base64::Engine;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const PNG: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+
+    fn store(max_file_size: usize) -> UploadStore {
+        UploadStore::new(&UploadsConfig {
+            dir: std::env::temp_dir()
+                .join(format!("chatglm-uploads-{}", uuid::Uuid::new_v4()))
+                .to_string_lossy()
+                .to_string(),
+            max_file_size,
+            ..UploadsConfig::default()
+        })
+    }
+
+    fn data_url(data: &[u8], mime_type: &str) -> String {
+        format!("data:{};base64,{}", mime_type, base64::engine::general_purpose::STANDARD.encode(data))
+    }
+
+    #[test]
+    fn test_content_parts_use_vision_format() {
+        let message = Message::user_with_parts(vec![
+            ContentPart::text("Was ist auf dem Bild?"),
+            ContentPart::image_url("https://example.com/bild.png"),
+        ]);
+
+        assert_eq!(serde_json::to_value(&message).unwrap(), json!({
+            "role": "user",
+            "content": [
+                { "type": "text", "text": "Was ist auf dem Bild?" },
+                { "type": "image_url", "image_url": { "url": "https://example.com/bild.png" } }
+            ]
+        }));
+
+        let text: Message = serde_json::from_value(json!({ "role": "user", "content": "Hallo" })).unwrap();
+        assert_eq!(text.text(), Some("Hallo"));
+        let parts: Message = serde_json::from_value(json!({
+            "role": "user",
+            "content": [{ "type": "file", "file": { "file_id": "abc" } }]
+        })).unwrap();
+        assert_eq!(parts.content, Some(MessageContent::Parts(vec![ContentPart::file("abc")])));
+    }
+
+    #[test]
+    fn test_sniff_image_type() {
+        assert_eq!(sniff_image_type(PNG), Some("image/png"));
+        assert_eq!(sniff_image_type(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("image/jpeg"));
+        assert_eq!(sniff_image_type(b"GIF89a..."), Some("image/gif"));
+        assert_eq!(sniff_image_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_image_type(b"%PDF-1.7"), None);
+    }
+
+    #[tokio::test]
+    async fn test_store_validates_size_and_type() {
+        let store = store(64);
+
+        let upload = store.store(PNG, Some("image/png"), Some("bild.png".to_string())).await.unwrap();
+        let (loaded, data) = store.load(&upload.id).await.unwrap();
+        assert_eq!(loaded.content_type, "image/png");
+        assert_eq!(loaded.filename.as_deref(), Some("bild.png"));
+        assert_eq!(data, PNG);
+
+        assert!(matches!(store.store(&[0x89; 65], None, None).await, Err(UploadError::TooLarge { .. })));
+        assert!(matches!(store.store(b"%PDF-1.7", None, None).await, Err(UploadError::UnsupportedType(_))));
+        assert!(matches!(store.store(PNG, Some("image/jpeg"), None).await, Err(UploadError::UnsupportedType(_))));
+        assert!(matches!(store.load("../../etc/passwd").await, Err(UploadError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_messages_inlines_files_and_checks_images() {
+        let store = store(64);
+        let upload = store.store(PNG, None, None).await.unwrap();
+
+        let mut messages = vec![Message::user_with_parts(vec![
+            ContentPart::text("Beschreibe"),
+            ContentPart::file(&upload.id),
+            ContentPart::image_url(data_url(PNG, "image/png")),
+        ])];
+        store.resolve_messages(&mut messages).await.unwrap();
+        let Some(MessageContent::Parts(parts)) = &messages[0].content else { panic!("Teile erwartet") };
+        assert_eq!(parts[1], ContentPart::image_url(data_url(PNG, "image/png")));
+
+        let mut invalid = vec![Message::user_with_parts(vec![ContentPart::image_url("file:///etc/passwd")])];
+        assert!(matches!(store.resolve_messages(&mut invalid).await, Err(UploadError::Invalid(_))));
+
+        let mut too_large = vec![Message::user_with_parts(vec![ContentPart::image_url(data_url(&[0x89; 200], "image/png"))])];
+        assert!(matches!(store.resolve_messages(&mut too_large).await, Err(UploadError::TooLarge { .. })));
+
+        let mut missing = vec![Message::user_with_parts(vec![ContentPart::file(uuid::Uuid::new_v4().to_string())])];
+        assert!(matches!(store.resolve_messages(&mut missing).await, Err(UploadError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_upload_endpoint() {
+        let app = uploads_routes(Arc::new(store(1024)));
+
+        let boundary = "grenze";
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"bild.png\"\r\nContent-Type: image/png\r\n\r\n",
+            b = boundary
+        ).into_bytes();
+        body.extend_from_slice(PNG);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        let response = app.clone().oneshot(
+            Request::post("/api/uploads")
+                .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+                .body(Body::from(body))
+                .unwrap()
+        ).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let id = value["upload"]["id"].as_str().unwrap();
+        assert_eq!(value["content_part"], json!({ "type": "file", "file": { "file_id": id } }));
+
+        let response = app.oneshot(
+            Request::get(format!("/api/uploads/{}", id)).body(Body::empty()).unwrap()
+        ).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "image/png");
+    }
+
+    #[tokio::test]
+    async fn test_chat_sends_resolved_images_upstream() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({
+                "messages": [{
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "Was siehst du?" },
+                        { "type": "image_url", "image_url": { "url": data_url(PNG, "image/png") } }
+                    ]
+                }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "glm-4.5",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "Ein Bild" },
+                    "finish_reason": "stop"
+                }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let store = Arc::new(store(1024));
+        let upload = store.store(PNG, None, None).await.unwrap();
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: mock_server.uri(),
+            ..GlmConfig::default()
+        }).unwrap();
+
+        let payload = json!({
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "Was siehst du?" },
+                    { "type": "file", "file": { "file_id": upload.id } }
+                ]
+            }]
+        });
+        let response = chat_routes(client, store, None, None, None, None, None).oneshot(
+            Request::post("/api/chat")
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap()
+        ).await.unwrap();
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value["response"]["choices"][0]["message"]["content"], "Ein Bild");
+    }
+}
@@ -0,0 +1,318 @@
+#[cfg(test)]
+mod tests {
+    use crate::api::{chat_routes, usage_routes, UploadStore};
+    use crate::client::*;
+    use crate::config::{AppConfig, ModelPrice, UploadsConfig, UsageConfig};
+    use crate::usage::*;
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn usage_config() -> UsageConfig {
+        let file = std::env::temp_dir().join(format!("chatglm-usage-{}", uuid::Uuid::new_v4())).join("usage.jsonl");
+        UsageConfig {
+            enabled: true,
+            file: file.to_string_lossy().to_string(),
+            currency: "USD".to_string(),
+            prices: vec![
+                ModelPrice { model: "glm-4.5".to_string(), input: 1.0, output: 2.0 },
+                ModelPrice { model: "default".to_string(), input: 10.0, output: 10.0 },
+            ],
+        }
+    }
+
+    fn record(day: u32, month: u32, model: &str, user: &str, prompt_tokens: u32, cost: f64) -> UsageRecord {
+        UsageRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, month, day, 12, 0, 0).unwrap(),
+            user: Some(user.to_string()),
+            conversation_id: None,
+            model: model.to_string(),
+            prompt_tokens,
+            completion_tokens: 10,
+            total_tokens: prompt_tokens + 10,
+            estimated: false,
+            cached: false,
+            streamed: false,
+            cost,
+        }
+    }
+
+    fn client_with_usage(uri: &str, store: Arc<UsageStore>) -> GlmClient {
+        GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: uri.to_string(),
+            stream: false,
+            ..GlmConfig::default()
+        })
+        .unwrap()
+        .with_usage_sink(store)
+    }
+
+    #[test]
+    fn test_cost_uses_model_price_or_default() {
+        let store = UsageStore::open(&usage_config());
+        assert!((store.cost("glm-4.5", 1_000_000, 500_000) - 2.0).abs() < 1e-9);
+        assert!((store.cost("glm-4.5-turbo", 100_000, 0) - 1.0).abs() < 1e-9);
+
+        let store = UsageStore::open(&UsageConfig { prices: Vec::new(), ..usage_config() });
+        assert_eq!(store.cost("glm-4.5", 1000, 1000), 0.0);
+    }
+
+    #[test]
+    fn test_summarize_by_day_month_and_group() {
+        let store = UsageStore::open(&usage_config());
+        store.add(record(1, 3, "glm-4.5", "anna", 100, 0.5));
+        store.add(record(1, 3, "glm-4.5-turbo", "ben", 50, 0.25));
+        store.add(record(2, 3, "glm-4.5", "anna", 20, 0.1));
+        store.add(record(5, 4, "glm-4.5", "anna", 10, 0.05));
+
+        let days = store.summarize(&UsageFilter::default(), UsagePeriod::Day, UsageGroupBy::None);
+        assert_eq!(days.iter().map(|b| b.period.as_str()).collect::<Vec<_>>(), vec!["2025-03-01", "2025-03-02", "2025-04-05"]);
+        assert_eq!(days[0].requests, 2);
+        assert_eq!(days[0].prompt_tokens, 150);
+
+        let months = store.summarize(&UsageFilter::default(), UsagePeriod::Month, UsageGroupBy::Model);
+        assert_eq!(months.len(), 3);
+        assert_eq!((months[0].period.as_str(), months[0].group.as_deref()), ("2025-03", Some("glm-4.5")));
+        assert_eq!(months[0].total_tokens, 140);
+
+        let filter = UsageFilter {
+            user: Some("anna".to_string()),
+            from: chrono::NaiveDate::from_ymd_opt(2025, 3, 2),
+            ..UsageFilter::default()
+        };
+        let filtered = store.summarize(&filter, UsagePeriod::Month, UsageGroupBy::None);
+        let totals = total(&filtered);
+        assert_eq!(totals.requests, 2);
+        assert!((totals.cost - 0.15).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_records_persist_and_export_as_csv() {
+        let config = usage_config();
+        let store = UsageStore::open(&config);
+        let mut entry = record(1, 3, "glm-4.5", "Müller, Anna", 5, 0.0);
+        entry.conversation_id = Some("c\"1".to_string());
+        store.add(entry.clone());
+
+        let reopened = UsageStore::open(&config);
+        assert_eq!(reopened.records(&UsageFilter::default()), vec![entry]);
+
+        let csv = records_to_csv(&reopened.records(&UsageFilter::default()));
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.contains(",\"Müller, Anna\",\"c\"\"1\",glm-4.5,5,10,15,false,false,false,0.000000"));
+
+        let buckets = reopened.summarize(&UsageFilter::default(), UsagePeriod::Day, UsageGroupBy::User);
+        assert_eq!(buckets_to_csv(&buckets).lines().nth(1), Some("2025-03-01,\"Müller, Anna\",1,5,10,15,0,0,0.000000"));
+
+        // Vom Client gewählte Werte werden nicht als Formel exportiert
+        let mut formula = record(2, 3, "glm-4.5", "=HYPERLINK(\"http://x\")", 5, 0.0);
+        formula.conversation_id = Some("@SUM(A1)".to_string());
+        let csv = records_to_csv(&[formula]);
+        assert!(csv.lines().nth(1).unwrap().contains(",\"'=HYPERLINK(\"\"http://x\"\")\",'@SUM(A1),"));
+
+        std::fs::remove_dir_all(std::path::Path::new(&config.file).parent().unwrap()).ok();
+    }
+
+    #[tokio::test]
+    async fn test_client_records_reported_and_estimated_usage() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hallo" }, "finish_reason": "stop" }],
+                "usage": { "prompt_tokens": 1000, "completion_tokens": 500, "total_tokens": 1500 }
+            })))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "2", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "abcdefgh" }, "finish_reason": "stop" }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let config = usage_config();
+        let store = Arc::new(UsageStore::open(&config));
+        let client = client_with_usage(&mock_server.uri(), store.clone());
+        let options = ChatOptions {
+            user: Some("anna".to_string()),
+            conversation_id: Some("conv-1".to_string()),
+            ..ChatOptions::default()
+        };
+        client.chat_completions_with(vec![Message::user("Hi")], &options).await.unwrap();
+        client.chat_completions(vec![Message::user("Hi")]).await.unwrap();
+
+        let records = store.records(&UsageFilter::default());
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].user.as_deref(), Some("anna"));
+        assert_eq!(records[0].conversation_id.as_deref(), Some("conv-1"));
+        assert_eq!((records[0].prompt_tokens, records[0].completion_tokens), (1000, 500));
+        assert!(!records[0].estimated);
+        assert!((records[0].cost - 0.002).abs() < 1e-9);
+
+        assert!(records[1].estimated);
+        assert_eq!(records[1].completion_tokens, estimate_tokens("abcdefgh"));
+        assert!(records[1].prompt_tokens > 0);
+
+        // Die Datei wird im Hintergrund geschrieben
+        let mut persisted = Vec::new();
+        for _ in 0..50 {
+            persisted = UsageStore::open(&config).records(&UsageFilter::default());
+            if persisted.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(persisted.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_usage_from_final_chunk_or_estimate() {
+        let mock_server = MockServer::start().await;
+        let with_usage = [
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"content":"Hallo"},"finish_reason":null}]}"#,
+            "",
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+            "",
+            r#"data: {"id":"1","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[],"usage":{"prompt_tokens":7,"completion_tokens":3,"total_tokens":10}}"#,
+            "",
+            "data: [DONE]",
+            "",
+        ].join("\n");
+        let without_usage = [
+            r#"data: {"id":"2","object":"chat.completion.chunk","created":1,"model":"glm-4.5","choices":[{"index":0,"delta":{"content":"12345678"},"finish_reason":"stop"}]}"#,
+            "",
+            "data: [DONE]",
+            "",
+        ].join("\n");
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true, "stream_options": { "include_usage": true } })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(with_usage, "text/event-stream"))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(without_usage, "text/event-stream"))
+            .mount(&mock_server)
+            .await;
+
+        let store = Arc::new(UsageStore::open(&usage_config()));
+        let client = client_with_usage(&mock_server.uri(), store.clone());
+        let stream = client.chat_completions_stream(vec![Message::user("Hi")]).await.unwrap();
+        assert_eq!(stream.collect_content().await.unwrap(), "Hallo");
+        let stream = client.chat_completions_stream(vec![Message::user("Hi")]).await.unwrap();
+        assert_eq!(stream.collect_content().await.unwrap(), "12345678");
+
+        let records = store.records(&UsageFilter::default());
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|record| record.streamed));
+        assert_eq!((records[0].prompt_tokens, records[0].completion_tokens, records[0].estimated), (7, 3, false));
+        assert_eq!((records[1].completion_tokens, records[1].estimated), (2, true));
+    }
+
+    #[tokio::test]
+    async fn test_cached_responses_are_recorded_without_cost() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hallo" }, "finish_reason": "stop" }],
+                "usage": { "prompt_tokens": 100, "completion_tokens": 100, "total_tokens": 200 }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let store = Arc::new(UsageStore::open(&usage_config()));
+        let client = GlmClient::new(GlmConfig {
+            api_key: "test-key".into(),
+            api_url: mock_server.uri(),
+            temperature: 0.0,
+            stream: false,
+            ..GlmConfig::default()
+        })
+        .unwrap()
+        .with_cache(Arc::new(ResponseCache::memory(4)))
+        .with_usage_sink(store.clone());
+
+        client.chat_completions(vec![Message::user("Hi")]).await.unwrap();
+        client.chat_completions_stream(vec![Message::user("Hi")]).await.unwrap().collect_content().await.unwrap();
+
+        let records = store.records(&UsageFilter::default());
+        assert_eq!(records.len(), 2);
+        assert!(!records[0].cached && records[0].cost > 0.0);
+        assert!(records[1].cached && records[1].streamed);
+        assert_eq!(records[1].cost, 0.0);
+        assert_eq!(records[1].total_tokens, 200);
+    }
+
+    #[tokio::test]
+    async fn test_usage_api_and_user_header() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "1", "object": "chat.completion", "created": 1, "model": "glm-4.5",
+                "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Hallo" }, "finish_reason": "stop" }],
+                "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let store = Arc::new(UsageStore::open(&usage_config()));
+        let uploads = Arc::new(UploadStore::new(&UploadsConfig::default()));
+        let chat = chat_routes(client_with_usage(&mock_server.uri(), store.clone()), uploads, None, None, None, None, None);
+        let request = Request::post("/api/chat")
+            .header("content-type", "application/json")
+            .header("x-user-id", "anna")
+            .body(Body::from(json!({ "messages": [{ "role": "user", "content": "Hi" }], "conversation_id": "c1" }).to_string()))
+            .unwrap();
+        assert_eq!(chat.oneshot(request).await.unwrap().status(), StatusCode::OK);
+
+        let app = usage_routes(store);
+        let response = app.clone()
+            .oneshot(Request::get("/api/usage?period=month&group_by=user").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["buckets"][0]["group"], "anna");
+        assert_eq!(body["total"]["total_tokens"], 15);
+        assert_eq!(body["currency"], "USD");
+
+        let response = app.clone()
+            .oneshot(Request::get("/api/usage/records?conversation_id=c1&format=csv").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert!(response.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("text/csv"));
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let csv = String::from_utf8(bytes.to_vec()).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().contains(",anna,c1,glm-4.5,10,5,15,"));
+    }
+
+    #[test]
+    fn test_price_table_in_config_file() {
+        let config: AppConfig = config::Config::builder()
+            .add_source(config::File::new("config.toml", config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert!(config.usage.prices.iter().any(|price| price.model == "glm-4.5" && price.output > 0.0));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod store;
+
+pub use store::{
+    buckets_to_csv, records_to_csv, total, UsageBucket, UsageFilter, UsageGroupBy, UsagePeriod, UsageRecord,
+    UsageStore,
+};
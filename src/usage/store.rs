@@ -0,0 +1,325 @@
+use crate::client::{UsageEvent, UsageSink};
+use crate::config::{ModelPrice, UsageConfig};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tracing::warn;
+
+/// Verbrauch einer einzelnen Anfrage mit berechneten Kosten
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub user: Option<String>,
+    pub conversation_id: Option<String>,
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+    pub estimated: bool,
+    pub cached: bool,
+    pub streamed: bool,
+    pub cost: f64,
+}
+
+/// Filter für Auswertungen; Datumsgrenzen sind inklusive (UTC)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UsageFilter {
+    pub user: Option<String>,
+    pub model: Option<String>,
+    pub conversation_id: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl UsageFilter {
+    fn matches(&self, record: &UsageRecord) -> bool {
+        let date = record.timestamp.date_naive();
+        self.user.as_ref().is_none_or(|user| record.user.as_ref() == Some(user))
+            && self.model.as_ref().is_none_or(|model| &record.model == model)
+            && self.conversation_id.as_ref().is_none_or(|id| record.conversation_id.as_ref() == Some(id))
+            && self.from.is_none_or(|from| date >= from)
+            && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+/// Zeitraum für die Aggregation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsagePeriod {
+    #[default]
+    Day,
+    Month,
+}
+
+impl UsagePeriod {
+    fn label(self, timestamp: &DateTime<Utc>) -> String {
+        match self {
+            UsagePeriod::Day => timestamp.format("%Y-%m-%d").to_string(),
+            UsagePeriod::Month => timestamp.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// Zusätzliche Gruppierung innerhalb eines Zeitraums
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageGroupBy {
+    #[default]
+    None,
+    Model,
+    User,
+    Conversation,
+}
+
+impl UsageGroupBy {
+    fn key(self, record: &UsageRecord) -> Option<String> {
+        match self {
+            UsageGroupBy::None => None,
+            UsageGroupBy::Model => Some(record.model.clone()),
+            UsageGroupBy::User => Some(record.user.clone().unwrap_or_default()),
+            UsageGroupBy::Conversation => Some(record.conversation_id.clone().unwrap_or_default()),
+        }
+    }
+}
+
+/// Summen für einen Zeitraum (und optional eine Gruppe)
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageBucket {
+    pub period: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    pub estimated_requests: u64,
+    pub cached_requests: u64,
+    pub cost: f64,
+}
+
+impl UsageBucket {
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.prompt_tokens += record.prompt_tokens as u64;
+        self.completion_tokens += record.completion_tokens as u64;
+        self.total_tokens += record.total_tokens as u64;
+        self.estimated_requests += record.estimated as u64;
+        self.cached_requests += record.cached as u64;
+        self.cost += record.cost;
+    }
+}
+
+/// Verbrauchsprotokoll als JSONL-Datei, das beim Start vollständig geladen wird
+#[derive(Debug)]
+pub struct UsageStore {
+    path: PathBuf,
+    currency: RwLock<String>,
+    prices: RwLock<Vec<ModelPrice>>,
+    records: RwLock<Vec<UsageRecord>>,
+}
+
+impl UsageStore {
+    pub fn open(config: &UsageConfig) -> Self {
+        let store = Self {
+            path: PathBuf::from(&config.file),
+            currency: RwLock::new(config.currency.clone()),
+            prices: RwLock::new(config.prices.clone()),
+            records: RwLock::new(Vec::new()),
+        };
+        store.load_existing();
+        store
+    }
+
+    pub fn currency(&self) -> String {
+        self.currency.read().unwrap().clone()
+    }
+
+    /// Übernimmt Währung und Preise einer neuen Konfiguration für künftige
+    /// Einträge; bereits erfasste Kosten bleiben unverändert
+    pub fn set_pricing(&self, config: &UsageConfig) {
+        *self.currency.write().unwrap() = config.currency.clone();
+        *self.prices.write().unwrap() = config.prices.clone();
+    }
+
+    fn load_existing(&self) {
+        let Ok(content) = std::fs::read_to_string(&self.path) else { return };
+
+        let mut records = self.records.write().unwrap();
+        for (number, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(record) => records.push(record),
+                Err(err) => warn!("Verbrauchseintrag {}:{} ungültig: {}", self.path.display(), number + 1, err),
+            }
+        }
+    }
+
+    /// Kosten nach dem Preis des Modells (oder `default`); ohne Preis 0
+    pub fn cost(&self, model: &str, prompt_tokens: u32, completion_tokens: u32) -> f64 {
+        let prices = self.prices.read().unwrap();
+        let price = prices
+            .iter()
+            .find(|price| price.model == model)
+            .or_else(|| prices.iter().find(|price| price.model == "default"));
+        match price {
+            Some(price) => (prompt_tokens as f64 * price.input + completion_tokens as f64 * price.output) / 1_000_000.0,
+            None => 0.0,
+        }
+    }
+
+    /// Hängt einen Eintrag an die Datei an und übernimmt ihn in den Speicher
+    pub fn add(&self, record: UsageRecord) {
+        match serde_json::to_string(&record) {
+            Ok(line) => append_line(&self.path, line),
+            Err(err) => warn!("Verbrauch konnte nicht gespeichert werden: {}", err),
+        }
+        self.records.write().unwrap().push(record);
+    }
+
+    /// Wie [`add`](Self::add), schreibt aber in einem Hintergrund-Thread,
+    /// damit Anfragen nicht auf die Datei warten
+    fn add_in_background(&self, record: UsageRecord) {
+        let (Ok(runtime), Ok(line)) = (tokio::runtime::Handle::try_current(), serde_json::to_string(&record)) else {
+            return self.add(record);
+        };
+        let path = self.path.clone();
+        runtime.spawn_blocking(move || append_line(&path, line));
+        self.records.write().unwrap().push(record);
+    }
+
+    pub fn records(&self, filter: &UsageFilter) -> Vec<UsageRecord> {
+        self.records
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|record| filter.matches(record))
+            .cloned()
+            .collect()
+    }
+
+    /// Summiert die gefilterten Einträge je Zeitraum und Gruppe (chronologisch sortiert)
+    pub fn summarize(&self, filter: &UsageFilter, period: UsagePeriod, group_by: UsageGroupBy) -> Vec<UsageBucket> {
+        let mut buckets: BTreeMap<(String, Option<String>), UsageBucket> = BTreeMap::new();
+        for record in self.records.read().unwrap().iter().filter(|record| filter.matches(record)) {
+            let key = (period.label(&record.timestamp), group_by.key(record));
+            buckets
+                .entry(key.clone())
+                .or_insert_with(|| UsageBucket { period: key.0, group: key.1, ..UsageBucket::default() })
+                .add(record);
+        }
+        buckets.into_values().collect()
+    }
+}
+
+impl UsageSink for UsageStore {
+    fn record(&self, event: UsageEvent) {
+        let cost = if event.cached {
+            0.0
+        } else {
+            self.cost(&event.model, event.usage.prompt_tokens, event.usage.completion_tokens)
+        };
+        self.add_in_background(UsageRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            user: event.user,
+            conversation_id: event.conversation_id,
+            model: event.model,
+            prompt_tokens: event.usage.prompt_tokens,
+            completion_tokens: event.usage.completion_tokens,
+            total_tokens: event.usage.total_tokens,
+            estimated: event.estimated,
+            cached: event.cached,
+            streamed: event.streamed,
+            cost,
+        });
+    }
+}
+
+/// Hängt eine Zeile in einem Schreibvorgang an, damit sich gleichzeitige
+/// Einträge nicht vermischen
+fn append_line(path: &Path, mut line: String) {
+    line.push('\n');
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::OpenOptions::new().create(true).append(true).open(path))
+        .and_then(|mut file| file.write_all(line.as_bytes()));
+    if let Err(err) = result {
+        warn!("Verbrauch konnte nicht gespeichert werden: {}", err);
+    }
+}
+
+/// Gesamtsumme über alle Buckets
+pub fn total(buckets: &[UsageBucket]) -> UsageBucket {
+    let mut total = UsageBucket { period: "total".to_string(), ..UsageBucket::default() };
+    for bucket in buckets {
+        total.requests += bucket.requests;
+        total.prompt_tokens += bucket.prompt_tokens;
+        total.completion_tokens += bucket.completion_tokens;
+        total.total_tokens += bucket.total_tokens;
+        total.estimated_requests += bucket.estimated_requests;
+        total.cached_requests += bucket.cached_requests;
+        total.cost += bucket.cost;
+    }
+    total
+}
+
+pub fn buckets_to_csv(buckets: &[UsageBucket]) -> String {
+    let mut csv = String::from("period,group,requests,prompt_tokens,completion_tokens,total_tokens,estimated_requests,cached_requests,cost\n");
+    for bucket in buckets {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{:.6}\n",
+            csv_field(&bucket.period),
+            csv_field(bucket.group.as_deref().unwrap_or_default()),
+            bucket.requests,
+            bucket.prompt_tokens,
+            bucket.completion_tokens,
+            bucket.total_tokens,
+            bucket.estimated_requests,
+            bucket.cached_requests,
+            bucket.cost,
+        ));
+    }
+    csv
+}
+
+pub fn records_to_csv(records: &[UsageRecord]) -> String {
+    let mut csv = String::from("id,timestamp,user,conversation_id,model,prompt_tokens,completion_tokens,total_tokens,estimated,cached,streamed,cost\n");
+    for record in records {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{:.6}\n",
+            record.id,
+            record.timestamp.to_rfc3339(),
+            csv_field(record.user.as_deref().unwrap_or_default()),
+            csv_field(record.conversation_id.as_deref().unwrap_or_default()),
+            csv_field(&record.model),
+            record.prompt_tokens,
+            record.completion_tokens,
+            record.total_tokens,
+            record.estimated,
+            record.cached,
+            record.streamed,
+            record.cost,
+        ));
+    }
+    csv
+}
+
+/// Setzt Felder mit Trennzeichen, Anführungszeichen oder Zeilenumbrüchen in
+/// Anführungszeichen. Werte, die eine Tabellenkalkulation als Formel lesen
+/// würde, erhalten ein `'` vorangestellt.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}